@@ -0,0 +1,112 @@
+//! Commit/s của WAL với 1, 8, 64 thread cùng commit.
+//!
+//! - `serial`: fsync ngay trong write lock (`Pager::flush`), mỗi commit 1 lần fsync.
+//! - `group`: append trong write lock, chờ fsync sau khi nhả lock (`WalPager::commit`),
+//!   các commit đến trong lúc đang sync được gom vào lần sync kế tiếp.
+//!
+//! Chạy: `cargo bench --bench group_commit` (NOVADB_BENCH_SECS đổi thời gian mỗi case).
+
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use novadb_lite::constants::PAGE_SIZE;
+use novadb_lite::pager::pager::Pager;
+use novadb_lite::wal::{wal_path, WalPager};
+use novadb_lite::PageId;
+
+const THREADS: [usize; 3] = [1, 8, 64];
+const DEFAULT_SECS: u64 = 2;
+
+fn bench_path(name: &str) -> String {
+    let mut p = std::env::temp_dir();
+    p.push(format!("novadb-bench-{}-{}.db", name, std::process::id()));
+    let path = p.to_string_lossy().into_owned();
+    remove(&path);
+    path
+}
+
+fn remove(path: &str) {
+    let _ = std::fs::remove_file(path);
+    let _ = std::fs::remove_file(wal_path(path));
+}
+
+/// Trả về (commit/s, số commit, số fsync).
+fn run(threads: usize, group: bool, secs: u64) -> (f64, u64, u64) {
+    let path = bench_path(&format!("{}-{}", threads, group));
+    let pager = Arc::new(Mutex::new(WalPager::open(path.clone()).unwrap()));
+    // mỗi thread ghi page riêng
+    let pids: Vec<PageId> = {
+        let mut p = pager.lock().unwrap();
+        let pids = (0..threads).map(|_| p.alloc_page().unwrap()).collect();
+        p.flush().unwrap();
+        pids
+    };
+    let base_syncs = pager.lock().unwrap().commit_syncs();
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let commits = Arc::new(AtomicU64::new(0));
+    let start = Instant::now();
+    let handles: Vec<_> = pids
+        .into_iter()
+        .map(|pid| {
+            let (pager, stop, commits) = (pager.clone(), stop.clone(), commits.clone());
+            std::thread::spawn(move || {
+                let page = vec![pid.as_u32() as u8; PAGE_SIZE];
+                while !stop.load(Ordering::Relaxed) {
+                    if group {
+                        let ticket = {
+                            let mut p = pager.lock().unwrap();
+                            p.write_page(pid, &page).unwrap();
+                            p.commit().unwrap()
+                        };
+                        ticket.wait().unwrap();
+                    } else {
+                        let mut p = pager.lock().unwrap();
+                        p.write_page(pid, &page).unwrap();
+                        p.flush().unwrap();
+                    }
+                    commits.fetch_add(1, Ordering::Relaxed);
+                }
+            })
+        })
+        .collect();
+    std::thread::sleep(Duration::from_secs(secs));
+    stop.store(true, Ordering::Relaxed);
+    for h in handles {
+        h.join().unwrap();
+    }
+    let elapsed = start.elapsed().as_secs_f64();
+
+    let syncs = pager.lock().unwrap().commit_syncs() - base_syncs;
+    drop(pager);
+    remove(&path);
+    let n = commits.load(Ordering::Relaxed);
+    (n as f64 / elapsed, n, syncs)
+}
+
+fn main() {
+    let secs = std::env::var("NOVADB_BENCH_SECS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(DEFAULT_SECS);
+
+    println!(
+        "{:>8} {:>8} {:>12} {:>10} {:>10} {:>12}",
+        "threads", "mode", "commits/s", "commits", "fsyncs", "commits/sync"
+    );
+    for threads in THREADS {
+        for group in [false, true] {
+            let (rate, n, syncs) = run(threads, group, secs);
+            println!(
+                "{:>8} {:>8} {:>12.0} {:>10} {:>10} {:>12.2}",
+                threads,
+                if group { "group" } else { "serial" },
+                rate,
+                n,
+                syncs,
+                n as f64 / syncs.max(1) as f64
+            );
+        }
+    }
+}
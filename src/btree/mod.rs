@@ -1 +1,357 @@
+//! Clustered B+tree: key i64 (rowid), leaf chứa cả row.
+//!
+//! - Node là slotted page `PAGE_TYPE_BTREE_LEAF` / `PAGE_TYPE_BTREE_INTERNAL`, xem
+//!   `node.rs`. Leaf nối với nhau theo thứ tự key để scan.
+//! - Node tràn sau insert/update bị chia đôi, separator được đẩy lên cha; cha tràn thì
+//!   chia tiếp, lan tới root.
+//! - Root page id không bao giờ đổi (catalog lưu nó): root tràn thì nội dung của nó được
+//!   chia sang 2 page mới, root thành internal node trỏ tới 2 page đó.
+//! - Delete chỉ xoá cell khỏi leaf, không gộp node (leaf rỗng vẫn nằm trong cây).
 
+mod node;
+
+pub use node::MAX_ROW_SIZE;
+
+use crate::pager::pager::Pager;
+use crate::{DbError, DbResult, PageId};
+use node::{leaf_split_point, Node};
+
+/// Node bị chia: (key nhỏ nhất của nửa phải, page của nửa phải).
+type Split = Option<(i64, PageId)>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WriteMode {
+    /// Key chưa có.
+    Insert,
+    /// Key đã có.
+    Update,
+}
+
+/// Handle của 1 B+tree, chỉ gồm root page id.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BTree {
+    root: PageId,
+}
+
+impl BTree {
+    /// Tạo cây rỗng: root là 1 leaf rỗng.
+    pub fn create(pager: &mut dyn Pager) -> DbResult<Self> {
+        let root = pager.alloc_page()?;
+        Node::empty_leaf().write(pager, root)?;
+        Ok(BTree { root })
+    }
+
+    /// Mở cây đã có từ root page id (lưu trong catalog).
+    pub fn open(root: PageId) -> Self {
+        BTree { root }
+    }
+
+    pub fn root_page(&self) -> PageId {
+        self.root
+    }
+
+    pub fn get(&self, pager: &mut dyn Pager, key: i64) -> DbResult<Option<Vec<u8>>> {
+        let (_, leaf) = self.find_leaf(pager, key)?;
+        let Node::Leaf { cells, .. } = leaf else {
+            unreachable!("find_leaf returns a leaf");
+        };
+        Ok(cells
+            .binary_search_by_key(&key, |(k, _)| *k)
+            .ok()
+            .map(|i| cells[i].1.clone()))
+    }
+
+    /// Insert row mới; key đã có -> `InvalidArgument`.
+    pub fn insert(&self, pager: &mut dyn Pager, key: i64, row: &[u8]) -> DbResult<()> {
+        if !self.write(pager, key, row, WriteMode::Insert)? {
+            return Err(DbError::InvalidArgument("duplicate key"));
+        }
+        Ok(())
+    }
+
+    /// Thay row của key, trả về false nếu key không có.
+    pub fn update(&self, pager: &mut dyn Pager, key: i64, row: &[u8]) -> DbResult<bool> {
+        self.write(pager, key, row, WriteMode::Update)
+    }
+
+    /// Xoá key, trả về false nếu key không có.
+    pub fn delete(&self, pager: &mut dyn Pager, key: i64) -> DbResult<bool> {
+        let (pid, mut leaf) = self.find_leaf(pager, key)?;
+        let Node::Leaf { cells, .. } = &mut leaf else {
+            unreachable!("find_leaf returns a leaf");
+        };
+        match cells.binary_search_by_key(&key, |(k, _)| *k) {
+            Ok(i) => {
+                cells.remove(i);
+                leaf.write(pager, pid)?;
+                Ok(true)
+            }
+            Err(_) => Ok(false),
+        }
+    }
+
+    /// Mọi (key, row) theo thứ tự key, đi theo chuỗi leaf từ leaf trái nhất.
+    pub fn scan(&self, pager: &mut dyn Pager) -> DbResult<Vec<(i64, Vec<u8>)>> {
+        let (_, mut node) = self.find_leaf(pager, i64::MIN)?;
+        let mut out = Vec::new();
+        loop {
+            let Node::Leaf { cells, next } = node else {
+                return Err(DbError::Corruption("leaf chain points to an internal node"));
+            };
+            out.extend(cells);
+            if next == PageId::INVALID {
+                return Ok(out);
+            }
+            node = Node::read(pager, next)?;
+        }
+    }
+
+    /// Độ cao của cây (1 = root là leaf).
+    pub fn depth(&self, pager: &mut dyn Pager) -> DbResult<usize> {
+        let mut depth = 1;
+        let mut node = Node::read(pager, self.root)?;
+        while let Node::Internal { cells, rightmost } = node {
+            let child = cells.first().map_or(rightmost, |(_, c)| *c);
+            node = Node::read(pager, child)?;
+            depth += 1;
+        }
+        Ok(depth)
+    }
+
+    fn find_leaf(&self, pager: &mut dyn Pager, key: i64) -> DbResult<(PageId, Node)> {
+        let mut pid = self.root;
+        loop {
+            let node = Node::read(pager, pid)?;
+            match &node {
+                Node::Leaf { .. } => return Ok((pid, node)),
+                Node::Internal { cells, rightmost } => pid = child_for(cells, *rightmost, key).1,
+            }
+        }
+    }
+
+    fn write(
+        &self,
+        pager: &mut dyn Pager,
+        key: i64,
+        row: &[u8],
+        mode: WriteMode,
+    ) -> DbResult<bool> {
+        if row.len() > MAX_ROW_SIZE {
+            return Err(DbError::NoSpace("row does not fit in a btree leaf"));
+        }
+        let (applied, split) = write_rec(pager, self.root, key, row, mode)?;
+        if let Some((sep, right)) = split {
+            self.split_root(pager, sep, right)?;
+        }
+        Ok(applied)
+    }
+
+    /// Root đã giữ nửa trái sau khi chia: chuyển nửa trái sang page mới, root thành
+    /// internal node với 2 con. Root page id giữ nguyên.
+    fn split_root(&self, pager: &mut dyn Pager, sep: i64, right: PageId) -> DbResult<()> {
+        let left = pager.alloc_page()?;
+        Node::read(pager, self.root)?.write(pager, left)?;
+        Node::Internal {
+            cells: vec![(sep, left)],
+            rightmost: right,
+        }
+        .write(pager, self.root)
+    }
+}
+
+/// (vị trí cell, child) chứa `key` trong internal node; vị trí = `cells.len()` là
+/// rightmost.
+fn child_for(cells: &[(i64, PageId)], rightmost: PageId, key: i64) -> (usize, PageId) {
+    let i = cells.partition_point(|(k, _)| *k <= key);
+    (i, cells.get(i).map_or(rightmost, |(_, c)| *c))
+}
+
+/// Ghi key vào cây con `pid`. Trả về (đã ghi chưa, split của `pid` nếu có): node bị
+/// chia giữ nửa trái tại `pid`, nửa phải nằm ở page mới.
+fn write_rec(
+    pager: &mut dyn Pager,
+    pid: PageId,
+    key: i64,
+    row: &[u8],
+    mode: WriteMode,
+) -> DbResult<(bool, Split)> {
+    let mut node = Node::read(pager, pid)?;
+    match &mut node {
+        Node::Leaf { cells, .. } => match (cells.binary_search_by_key(&key, |(k, _)| *k), mode) {
+            (Ok(i), WriteMode::Update) => cells[i].1 = row.to_vec(),
+            (Err(i), WriteMode::Insert) => cells.insert(i, (key, row.to_vec())),
+            _ => return Ok((false, None)),
+        },
+        Node::Internal { cells, rightmost } => {
+            let (i, child) = child_for(cells, *rightmost, key);
+            let (applied, split) = write_rec(pager, child, key, row, mode)?;
+            let Some((sep, right)) = split else {
+                return Ok((applied, None));
+            };
+            // child giữ [.., sep), nửa phải [sep, ..) nhận chỗ cũ của child
+            match cells.get_mut(i) {
+                Some(cell) => cell.1 = right,
+                None => *rightmost = right,
+            }
+            cells.insert(i, (sep, child));
+        }
+    }
+    let split = split_if_full(pager, pid, node)?;
+    Ok((true, split))
+}
+
+/// Ghi node về `pid`, chia đôi nếu tràn.
+fn split_if_full(pager: &mut dyn Pager, pid: PageId, node: Node) -> DbResult<Split> {
+    if node.fits() {
+        node.write(pager, pid)?;
+        return Ok(None);
+    }
+    let right_pid = pager.alloc_page()?;
+    let (left, right, sep) = match node {
+        Node::Leaf { mut cells, next } => {
+            let right_cells = cells.split_off(leaf_split_point(&cells));
+            let sep = right_cells[0].0;
+            let left = Node::Leaf {
+                cells,
+                next: right_pid,
+            };
+            let right = Node::Leaf {
+                cells: right_cells,
+                next,
+            };
+            (left, right, sep)
+        }
+        Node::Internal {
+            mut cells,
+            rightmost,
+        } => {
+            // cell giữa được đẩy lên cha, child của nó thành rightmost của nửa trái
+            let mut right_cells = cells.split_off(cells.len() / 2);
+            let (sep, mid_child) = right_cells.remove(0);
+            let left = Node::Internal {
+                cells,
+                rightmost: mid_child,
+            };
+            let right = Node::Internal {
+                cells: right_cells,
+                rightmost,
+            };
+            (left, right, sep)
+        }
+    };
+    right.write(pager, right_pid)?;
+    left.write(pager, pid)?;
+    Ok(Some((sep, right_pid)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pager::mem::MemPager;
+
+    fn row(key: i64, len: usize) -> Vec<u8> {
+        vec![key as u8; len]
+    }
+
+    /// Thứ tự key xáo trộn cố định (bước nhảy nguyên tố cùng nhau với n).
+    fn shuffled(n: i64) -> Vec<i64> {
+        (0..n).map(|i| (i * 7919) % n - n / 2).collect()
+    }
+
+    #[test]
+    fn test_insert_get_with_splits() {
+        let mut pager = MemPager::new();
+        let tree = BTree::create(&mut pager).unwrap();
+        let root = tree.root_page();
+
+        let keys = shuffled(2000);
+        for &k in &keys {
+            tree.insert(&mut pager, k, &row(k, 600)).unwrap();
+        }
+        // leaf chứa ~6 row 600 bytes, internal ~226 con -> cây phải có 3 tầng
+        assert_eq!(tree.root_page(), root);
+        assert_eq!(tree.depth(&mut pager).unwrap(), 3);
+
+        for &k in &keys {
+            assert_eq!(tree.get(&mut pager, k).unwrap().unwrap(), row(k, 600));
+        }
+        assert!(tree.get(&mut pager, 5000).unwrap().is_none());
+
+        let scanned: Vec<i64> = tree
+            .scan(&mut pager)
+            .unwrap()
+            .into_iter()
+            .map(|(k, _)| k)
+            .collect();
+        let mut sorted = keys.clone();
+        sorted.sort();
+        assert_eq!(scanned, sorted);
+
+        // mở lại từ root page id
+        let reopened = BTree::open(root);
+        assert_eq!(
+            reopened.get(&mut pager, -1000).unwrap().unwrap(),
+            row(-1000, 600)
+        );
+    }
+
+    #[test]
+    fn test_update_delete_and_errors() {
+        let mut pager = MemPager::new();
+        let tree = BTree::create(&mut pager).unwrap();
+        for k in 0..50 {
+            tree.insert(&mut pager, k, b"small").unwrap();
+        }
+        assert!(matches!(
+            tree.insert(&mut pager, 7, b"dup").unwrap_err(),
+            DbError::InvalidArgument(_)
+        ));
+        assert!(matches!(
+            tree.insert(&mut pager, 100, &vec![0u8; MAX_ROW_SIZE + 1])
+                .unwrap_err(),
+            DbError::NoSpace(_)
+        ));
+        assert!(!tree.update(&mut pager, 100, b"x").unwrap());
+        assert_eq!(tree.depth(&mut pager).unwrap(), 1);
+
+        // row lớn lên làm leaf tràn -> update cũng phải chia node
+        for k in 0..50 {
+            assert!(tree.update(&mut pager, k, &row(k, MAX_ROW_SIZE)).unwrap());
+        }
+        assert!(tree.depth(&mut pager).unwrap() > 1);
+        for k in 0..50 {
+            assert_eq!(
+                tree.get(&mut pager, k).unwrap().unwrap(),
+                row(k, MAX_ROW_SIZE)
+            );
+        }
+
+        for k in (0..50).step_by(2) {
+            assert!(tree.delete(&mut pager, k).unwrap());
+        }
+        assert!(!tree.delete(&mut pager, 0).unwrap());
+        assert!(tree.get(&mut pager, 4).unwrap().is_none());
+        let keys: Vec<i64> = tree
+            .scan(&mut pager)
+            .unwrap()
+            .into_iter()
+            .map(|(k, _)| k)
+            .collect();
+        assert_eq!(keys, (1..50).step_by(2).collect::<Vec<_>>());
+
+        // key đã xoá insert lại được
+        tree.insert(&mut pager, 4, b"back").unwrap();
+        assert_eq!(tree.get(&mut pager, 4).unwrap().unwrap(), b"back");
+    }
+
+    #[test]
+    fn test_rejects_non_btree_page() {
+        let mut pager = MemPager::new();
+        let pid = pager.alloc_page().unwrap();
+        let tree = BTree::open(pid);
+        assert!(matches!(
+            tree.get(&mut pager, 1).unwrap_err(),
+            DbError::Corruption(_)
+        ));
+    }
+}
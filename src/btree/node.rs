@@ -0,0 +1,205 @@
+//! Node B+tree: slotted page `PAGE_TYPE_BTREE_LEAF` / `PAGE_TYPE_BTREE_INTERNAL`.
+//!
+//! - Cell nằm trong slot theo thứ tự key tăng dần (node được ghi lại toàn bộ mỗi lần sửa,
+//!   nên slot directory không có tombstone).
+//! - Leaf cell: `[key i64][row]`; special area: next leaf page id (`INVALID` = leaf cuối).
+//! - Internal cell: `[key i64][child u32]`, child chứa key < key của cell; special area:
+//!   rightmost child chứa key >= key của cell cuối.
+
+use crate::constants::PAGE_SIZE;
+use crate::page::header::{self, PAGE_TYPE_BTREE_INTERNAL, PAGE_TYPE_BTREE_LEAF};
+use crate::page::raw::{read_i64_le, read_u32_le, write_i64_le, write_u32_le};
+use crate::page::slotted_page::SlottedPage;
+use crate::page::{SLOTTED_HEADER_SIZE, SLOTTED_SLOT_SIZE};
+use crate::pager::pager::Pager;
+use crate::{DbError, DbResult, PageId};
+
+/// Special area của node: 1 page id (next leaf hoặc rightmost child).
+pub(super) const NODE_SPECIAL_SIZE: usize = 4;
+const OFF_KEY: usize = 0;
+const KEY_SIZE: usize = 8;
+const CHILD_SIZE: usize = 4;
+
+/// Số bytes cho slot + cell trong 1 node.
+const NODE_CAPACITY: usize = PAGE_SIZE - SLOTTED_HEADER_SIZE - NODE_SPECIAL_SIZE;
+
+/// Row lớn nhất: mỗi leaf chứa được ít nhất 4 cell, nên chia đôi 1 leaf tràn
+/// theo số bytes luôn cho 2 nửa vừa page.
+pub const MAX_ROW_SIZE: usize = NODE_CAPACITY / 4 - SLOTTED_SLOT_SIZE - KEY_SIZE;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) enum Node {
+    Leaf {
+        cells: Vec<(i64, Vec<u8>)>,
+        next: PageId,
+    },
+    Internal {
+        cells: Vec<(i64, PageId)>,
+        rightmost: PageId,
+    },
+}
+
+impl Node {
+    pub(super) fn empty_leaf() -> Self {
+        Node::Leaf {
+            cells: Vec::new(),
+            next: PageId::INVALID,
+        }
+    }
+
+    pub(super) fn read(pager: &mut dyn Pager, pid: PageId) -> DbResult<Self> {
+        let mut buf = vec![0u8; PAGE_SIZE];
+        pager.read_page(pid, &mut buf)?;
+        Self::decode(&mut buf)
+    }
+
+    pub(super) fn write(&self, pager: &mut dyn Pager, pid: PageId) -> DbResult<()> {
+        pager.write_page(pid, &self.encode()?)
+    }
+
+    /// Tổng bytes slot + cell của node.
+    pub(super) fn size(&self) -> usize {
+        match self {
+            Node::Leaf { cells, .. } => {
+                cells.iter().map(|(_, row)| leaf_cell_size(row.len())).sum()
+            }
+            Node::Internal { cells, .. } => cells.len() * INTERNAL_CELL_SIZE,
+        }
+    }
+
+    pub(super) fn fits(&self) -> bool {
+        self.size() <= NODE_CAPACITY
+    }
+
+    fn decode(buf: &mut [u8]) -> DbResult<Self> {
+        let flags = header::flags(buf)?;
+        if header::special_size(flags) != NODE_SPECIAL_SIZE {
+            return Err(DbError::Corruption("not a btree page"));
+        }
+        let page = SlottedPage::new(buf)?;
+        let link = PageId(read_u32_le(page.special()?, 0)?);
+        let cells = (0..page.slot_count()?)
+            .map(|slot_id| {
+                page.get(slot_id)?
+                    .ok_or(DbError::Corruption("btree cell is dead"))
+            })
+            .collect::<DbResult<Vec<&[u8]>>>()?;
+
+        if header::is_page_type(flags, PAGE_TYPE_BTREE_LEAF) {
+            let cells = cells
+                .into_iter()
+                .map(|c| Ok((read_i64_le(c, OFF_KEY)?, c[KEY_SIZE..].to_vec())))
+                .collect::<DbResult<_>>()?;
+            Ok(Node::Leaf { cells, next: link })
+        } else if header::is_page_type(flags, PAGE_TYPE_BTREE_INTERNAL) {
+            let cells = cells
+                .into_iter()
+                .map(|c| {
+                    if c.len() != KEY_SIZE + CHILD_SIZE {
+                        return Err(DbError::Corruption("bad internal cell size"));
+                    }
+                    Ok((read_i64_le(c, OFF_KEY)?, PageId(read_u32_le(c, KEY_SIZE)?)))
+                })
+                .collect::<DbResult<_>>()?;
+            Ok(Node::Internal {
+                cells,
+                rightmost: link,
+            })
+        } else {
+            Err(DbError::Corruption("not a btree page"))
+        }
+    }
+
+    fn encode(&self) -> DbResult<Vec<u8>> {
+        if !self.fits() {
+            return Err(DbError::NoSpace("btree node overflow"));
+        }
+        let mut buf = vec![0u8; PAGE_SIZE];
+        let (page_type, link) = match self {
+            Node::Leaf { next, .. } => (PAGE_TYPE_BTREE_LEAF, *next),
+            Node::Internal { rightmost, .. } => (PAGE_TYPE_BTREE_INTERNAL, *rightmost),
+        };
+        let mut page =
+            SlottedPage::new(&mut buf)?.init_with_special(page_type, NODE_SPECIAL_SIZE)?;
+        write_u32_le(page.special_mut()?, 0, link.as_u32())?;
+        match self {
+            Node::Leaf { cells, .. } => {
+                for (key, row) in cells {
+                    let mut cell = vec![0u8; KEY_SIZE + row.len()];
+                    write_i64_le(&mut cell, OFF_KEY, *key)?;
+                    cell[KEY_SIZE..].copy_from_slice(row);
+                    page.insert(&cell)?;
+                }
+            }
+            Node::Internal { cells, .. } => {
+                for (key, child) in cells {
+                    let mut cell = [0u8; KEY_SIZE + CHILD_SIZE];
+                    write_i64_le(&mut cell, OFF_KEY, *key)?;
+                    write_u32_le(&mut cell, KEY_SIZE, child.as_u32())?;
+                    page.insert(&cell)?;
+                }
+            }
+        }
+        Ok(buf)
+    }
+}
+
+const INTERNAL_CELL_SIZE: usize = SLOTTED_SLOT_SIZE + KEY_SIZE + CHILD_SIZE;
+
+fn leaf_cell_size(row_len: usize) -> usize {
+    SLOTTED_SLOT_SIZE + KEY_SIZE + row_len
+}
+
+/// Vị trí chia leaf tràn: nửa trái nhận cell tới khi vượt nửa tổng số bytes.
+pub(super) fn leaf_split_point(cells: &[(i64, Vec<u8>)]) -> usize {
+    let total: usize = cells.iter().map(|(_, r)| leaf_cell_size(r.len())).sum();
+    let mut left = 0;
+    for (i, (_, row)) in cells.iter().enumerate() {
+        left += leaf_cell_size(row.len());
+        if left * 2 >= total {
+            // mỗi nửa giữ ít nhất 1 cell
+            return (i + 1).clamp(1, cells.len() - 1);
+        }
+    }
+    cells.len() - 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pager::mem::MemPager;
+
+    #[test]
+    fn test_node_roundtrip() {
+        let mut pager = MemPager::new();
+        let pid = pager.alloc_page().unwrap();
+
+        let leaf = Node::Leaf {
+            cells: vec![(-5, b"neg".to_vec()), (0, Vec::new()), (7, b"row".to_vec())],
+            next: PageId(9),
+        };
+        leaf.write(&mut pager, pid).unwrap();
+        assert_eq!(Node::read(&mut pager, pid).unwrap(), leaf);
+
+        let internal = Node::Internal {
+            cells: vec![(10, PageId(3)), (20, PageId(4))],
+            rightmost: PageId(5),
+        };
+        internal.write(&mut pager, pid).unwrap();
+        assert_eq!(Node::read(&mut pager, pid).unwrap(), internal);
+
+        let big = Node::Leaf {
+            cells: (0..5).map(|k| (k, vec![0u8; MAX_ROW_SIZE])).collect(),
+            next: PageId::INVALID,
+        };
+        assert!(!big.fits());
+        assert!(matches!(
+            big.write(&mut pager, pid).unwrap_err(),
+            DbError::NoSpace(_)
+        ));
+        let Node::Leaf { cells, .. } = big else {
+            unreachable!()
+        };
+        assert_eq!(leaf_split_point(&cells), 3);
+    }
+}
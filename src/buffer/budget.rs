@@ -0,0 +1,96 @@
+//! Memory budget dùng chung giữa nhiều buffer pool trong cùng process.
+//!
+//! Mỗi frame của pool giữ `PAGE_SIZE` byte của budget từ lúc được cấp tới lúc bị
+//! bỏ (shrink hoặc drop pool). Pool không grow được khi budget đã hết.
+
+use std::sync::{Arc, Mutex};
+
+use super::lock;
+
+/// Snapshot của budget.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MemoryBudgetStats {
+    pub limit_bytes: usize,
+    /// Tổng byte các pool đang giữ.
+    pub used_bytes: usize,
+    /// High-water mark của `used_bytes`.
+    pub peak_bytes: usize,
+}
+
+#[derive(Debug)]
+pub struct MemoryBudget {
+    state: Mutex<MemoryBudgetStats>,
+}
+
+impl MemoryBudget {
+    pub fn new(limit_bytes: usize) -> Arc<Self> {
+        Arc::new(MemoryBudget {
+            state: Mutex::new(MemoryBudgetStats {
+                limit_bytes,
+                ..MemoryBudgetStats::default()
+            }),
+        })
+    }
+
+    pub fn stats(&self) -> MemoryBudgetStats {
+        *lock(&self.state)
+    }
+
+    /// Đổi limit lúc đang chạy. Hạ limit xuống dưới mức đang dùng không ép pool
+    /// shrink, chỉ chặn các lần grow tiếp theo.
+    pub fn set_limit_bytes(&self, limit_bytes: usize) {
+        lock(&self.state).limit_bytes = limit_bytes;
+    }
+
+    /// Đưa peak về mức đang dùng.
+    pub fn reset_peak(&self) {
+        let mut s = lock(&self.state);
+        s.peak_bytes = s.used_bytes;
+    }
+
+    /// Giữ `bytes` nếu còn đủ budget (tất cả hoặc không gì cả).
+    pub(super) fn try_reserve(&self, bytes: usize) -> bool {
+        let mut s = lock(&self.state);
+        match s.used_bytes.checked_add(bytes) {
+            Some(used) if used <= s.limit_bytes => {
+                s.used_bytes = used;
+                s.peak_bytes = s.peak_bytes.max(used);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub(super) fn release(&self, bytes: usize) {
+        let mut s = lock(&self.state);
+        debug_assert!(s.used_bytes >= bytes, "release more than reserved");
+        s.used_bytes = s.used_bytes.saturating_sub(bytes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reserve_release_and_peak() {
+        let budget = MemoryBudget::new(100);
+        assert!(budget.try_reserve(60));
+        assert!(!budget.try_reserve(50));
+        assert!(budget.try_reserve(40));
+        budget.release(70);
+        assert_eq!(
+            budget.stats(),
+            MemoryBudgetStats {
+                limit_bytes: 100,
+                used_bytes: 30,
+                peak_bytes: 100,
+            }
+        );
+
+        budget.reset_peak();
+        budget.set_limit_bytes(20);
+        assert!(!budget.try_reserve(1));
+        assert_eq!(budget.stats().peak_bytes, 30);
+    }
+}
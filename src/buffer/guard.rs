@@ -0,0 +1,150 @@
+//! Guard của page đang được pin + latch. Drop: nhả latch rồi unpin.
+
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use crate::page::slotted_page::{SlottedPage, SlottedPageRef};
+use crate::{DbResult, PageId};
+
+use super::{BufferPool, Frame, FrameId};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LatchMode {
+    Shared,
+    Exclusive,
+}
+
+/// Pin + latch trên 1 frame, dùng chung cho read/write guard để
+/// upgrade/downgrade chỉ đổi `mode` mà không nhả pin.
+struct FrameHold<'a> {
+    pool: &'a BufferPool,
+    pid: PageId,
+    frame_id: FrameId,
+    frame: Arc<Frame>,
+    mode: LatchMode,
+}
+
+impl Drop for FrameHold<'_> {
+    fn drop(&mut self) {
+        match self.mode {
+            LatchMode::Shared => self.frame.latch.unlock_shared(),
+            LatchMode::Exclusive => self.frame.latch.unlock_exclusive(),
+        }
+        self.pool.unpin(self.frame_id);
+    }
+}
+
+/// Page được pin với shared latch: chỉ đọc.
+pub struct PageReadGuard<'a> {
+    hold: FrameHold<'a>,
+}
+
+impl<'a> PageReadGuard<'a> {
+    /// Frame đã được pin; chờ shared latch.
+    pub(super) fn new(
+        pool: &'a BufferPool,
+        pid: PageId,
+        frame_id: FrameId,
+        frame: Arc<Frame>,
+    ) -> Self {
+        frame.latch.lock_shared();
+        PageReadGuard {
+            hold: FrameHold {
+                pool,
+                pid,
+                frame_id,
+                frame,
+                mode: LatchMode::Shared,
+            },
+        }
+    }
+
+    pub(super) fn frame(&self) -> &Frame {
+        &self.hold.frame
+    }
+
+    pub fn page_id(&self) -> PageId {
+        self.hold.pid
+    }
+
+    pub fn data(&self) -> &[u8] {
+        // SAFETY: đang giữ shared latch
+        unsafe { self.hold.frame.bytes() }
+    }
+
+    /// View slotted page chỉ đọc.
+    pub fn page(&self) -> DbResult<SlottedPageRef<'_>> {
+        SlottedPageRef::new(self.data())
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.hold.frame.dirty.load(Ordering::Acquire)
+    }
+
+    /// Shared -> exclusive, chờ các reader khác nhả latch (page vẫn pin suốt).
+    /// Trả lại read guard nếu đang có reader khác chờ upgrade: caller phải nhả
+    /// guard rồi fetch_write lại, tránh 2 upgrader chờ nhau.
+    pub fn try_upgrade(self) -> Result<PageWriteGuard<'a>, Self> {
+        if !self.hold.frame.latch.try_upgrade() {
+            return Err(self);
+        }
+        let mut hold = self.hold;
+        hold.mode = LatchMode::Exclusive;
+        hold.frame.dirty.store(true, Ordering::Release);
+        Ok(PageWriteGuard { hold })
+    }
+}
+
+/// Page được pin với exclusive latch; frame bị đánh dấu dirty ngay khi lấy guard.
+pub struct PageWriteGuard<'a> {
+    hold: FrameHold<'a>,
+}
+
+impl<'a> PageWriteGuard<'a> {
+    /// Frame đã được pin; chờ exclusive latch.
+    pub(super) fn new(
+        pool: &'a BufferPool,
+        pid: PageId,
+        frame_id: FrameId,
+        frame: Arc<Frame>,
+    ) -> Self {
+        frame.latch.lock_exclusive();
+        frame.dirty.store(true, Ordering::Release);
+        PageWriteGuard {
+            hold: FrameHold {
+                pool,
+                pid,
+                frame_id,
+                frame,
+                mode: LatchMode::Exclusive,
+            },
+        }
+    }
+
+    pub fn page_id(&self) -> PageId {
+        self.hold.pid
+    }
+
+    pub fn data(&self) -> &[u8] {
+        // SAFETY: đang giữ exclusive latch
+        unsafe { self.hold.frame.bytes() }
+    }
+
+    pub fn data_mut(&mut self) -> &mut [u8] {
+        // SAFETY: đang giữ exclusive latch, &mut self nên chỉ có 1 borrow
+        unsafe { self.hold.frame.bytes_mut() }
+    }
+
+    /// `SlottedPage` trên data của page.
+    pub fn page(&mut self) -> DbResult<SlottedPage<'_>> {
+        SlottedPage::new(self.data_mut())
+    }
+
+    /// Exclusive -> shared, không nhả pin và không cho writer khác chen vào giữa.
+    pub fn downgrade(self) -> PageReadGuard<'a> {
+        self.hold.frame.latch.downgrade();
+        let mut hold = self.hold;
+        hold.mode = LatchMode::Shared;
+        PageReadGuard { hold }
+    }
+}
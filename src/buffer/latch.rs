@@ -0,0 +1,175 @@
+//! Reader-writer latch của 1 frame, có upgrade (shared -> exclusive) và downgrade.
+//!
+//! `std::sync::RwLock` không hỗ trợ upgrade/downgrade nên tự cài bằng Mutex + Condvar.
+//! Writer được ưu tiên: khi có writer (hoặc upgrader) đang chờ, reader mới phải chờ,
+//! tránh writer bị starve bởi dòng reader liên tục.
+//!
+//! Latch không re-entrant: thread đang giữ latch mà lock lại cùng latch sẽ deadlock.
+
+use std::sync::{Condvar, Mutex, MutexGuard, PoisonError};
+
+#[derive(Debug, Default)]
+struct LatchState {
+    readers: u32,
+    writer: bool,
+    /// 1 reader đang chờ upgrade lên exclusive.
+    upgrading: bool,
+    writers_waiting: u32,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Latch {
+    state: Mutex<LatchState>,
+    cond: Condvar,
+}
+
+impl Latch {
+    fn lock_state(&self) -> MutexGuard<'_, LatchState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn wait<'a>(&self, guard: MutexGuard<'a, LatchState>) -> MutexGuard<'a, LatchState> {
+        self.cond
+            .wait(guard)
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub(crate) fn lock_shared(&self) {
+        let mut s = self.lock_state();
+        while s.writer || s.upgrading || s.writers_waiting > 0 {
+            s = self.wait(s);
+        }
+        s.readers += 1;
+    }
+
+    pub(crate) fn unlock_shared(&self) {
+        let mut s = self.lock_state();
+        debug_assert!(s.readers > 0, "unlock_shared without shared latch");
+        s.readers -= 1;
+        // 0: writer vào được, 1: upgrader (reader cuối) vào được
+        if s.readers <= 1 {
+            self.cond.notify_all();
+        }
+    }
+
+    pub(crate) fn lock_exclusive(&self) {
+        let mut s = self.lock_state();
+        s.writers_waiting += 1;
+        while s.writer || s.readers > 0 || s.upgrading {
+            s = self.wait(s);
+        }
+        s.writers_waiting -= 1;
+        s.writer = true;
+    }
+
+    pub(crate) fn unlock_exclusive(&self) {
+        let mut s = self.lock_state();
+        debug_assert!(s.writer, "unlock_exclusive without exclusive latch");
+        s.writer = false;
+        self.cond.notify_all();
+    }
+
+    /// Shared -> exclusive, chờ các reader khác nhả latch.
+    /// Trả về false (vẫn giữ shared) nếu đã có reader khác đang upgrade:
+    /// 2 reader cùng chờ nhau upgrade sẽ deadlock, 1 trong 2 phải nhả latch.
+    pub(crate) fn try_upgrade(&self) -> bool {
+        let mut s = self.lock_state();
+        debug_assert!(s.readers > 0, "upgrade without shared latch");
+        if s.upgrading {
+            return false;
+        }
+        s.upgrading = true;
+        while s.readers > 1 {
+            s = self.wait(s);
+        }
+        s.readers = 0;
+        s.upgrading = false;
+        s.writer = true;
+        true
+    }
+
+    /// Exclusive -> shared, không có khoảng hở nào cho writer khác chen vào.
+    pub(crate) fn downgrade(&self) {
+        let mut s = self.lock_state();
+        debug_assert!(s.writer, "downgrade without exclusive latch");
+        s.writer = false;
+        s.readers = 1;
+        self.cond.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_shared_latches_coexist() {
+        let latch = Latch::default();
+        latch.lock_shared();
+        latch.lock_shared();
+        latch.unlock_shared();
+        assert!(latch.try_upgrade());
+        latch.downgrade();
+        latch.unlock_shared();
+        latch.lock_exclusive();
+        latch.unlock_exclusive();
+    }
+
+    #[test]
+    fn test_exclusive_is_exclusive() {
+        let latch = Arc::new(Latch::default());
+        let inside = Arc::new(AtomicU32::new(0));
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let latch = Arc::clone(&latch);
+                let inside = Arc::clone(&inside);
+                thread::spawn(move || {
+                    for _ in 0..200 {
+                        latch.lock_exclusive();
+                        assert_eq!(inside.fetch_add(1, Ordering::SeqCst), 0);
+                        inside.fetch_sub(1, Ordering::SeqCst);
+                        latch.unlock_exclusive();
+
+                        latch.lock_shared();
+                        assert_eq!(inside.load(Ordering::SeqCst), 0);
+                        latch.unlock_shared();
+                    }
+                })
+            })
+            .collect();
+        for h in handles {
+            h.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_upgrade_waits_for_other_readers() {
+        let latch = Arc::new(Latch::default());
+        latch.lock_shared();
+
+        let other = Arc::clone(&latch);
+        let upgraded = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&upgraded);
+        other.lock_shared();
+        let h = thread::spawn(move || {
+            assert!(other.try_upgrade());
+            flag.store(true, Ordering::SeqCst);
+            other.unlock_exclusive();
+        });
+
+        while !latch.lock_state().upgrading {
+            thread::yield_now();
+        }
+        thread::sleep(Duration::from_millis(10));
+        assert!(!upgraded.load(Ordering::SeqCst));
+        // upgrade thứ 2 trong lúc đang có upgrader -> từ chối
+        assert!(!latch.try_upgrade());
+        latch.unlock_shared();
+        h.join().unwrap();
+        assert!(upgraded.load(Ordering::SeqCst));
+    }
+}
@@ -0,0 +1,1759 @@
+//! Buffer pool: cache cố định N frame `PAGE_SIZE` nằm giữa access method và `Pager`.
+//!
+//! - Page table `PageId -> FrameId` cho biết page nào đang nằm trong frame nào.
+//! - Mỗi lần fetch tăng pin_count, guard drop thì unpin. Frame đang pin không bị evict.
+//! - Mỗi frame có 1 reader-writer latch: `fetch_read` giữ shared latch, `fetch_write`
+//!   giữ exclusive latch (và đánh dấu frame dirty) suốt đời guard.
+//! - Dirty page được ghi xuống pager khi bị evict, khi flush (`flush_page`/`flush_all`),
+//!   hoặc dần dần bởi `BackgroundWriter` khi tỉ lệ dirty vượt ngưỡng, để evict ít
+//!   phải ghi đồng bộ.
+//! - Hết frame trống thì evict 1 frame có pin_count = 0, chọn theo `Replacer`
+//!   (Clock, LRU, LRU-K, 2Q, FIFO) cấu hình lúc mở pool.
+//! - Số frame đổi được lúc đang chạy (`resize`/`resize_bytes`); bộ nhớ của frame có
+//!   thể lấy từ 1 `MemoryBudget` dùng chung giữa nhiều pool.
+//!
+//! Lock order: `resize` -> `state` -> `pager`. Latch của frame chỉ được chờ khi KHÔNG giữ `state`
+//! (thread giữ latch page cha có thể đang fetch page con, cần `state`).
+//! Frame không bị pin thì không ai giữ latch của nó, nên lấy latch của frame đó dưới
+//! `state` không phải chờ.
+//!
+//! I/O của pager khi fetch (đọc page bị miss, ghi victim dirty) chạy khi đã nhả
+//! `state`, để fetch page khác (kể cả hit) không phải chờ disk của thread khác:
+//! - Miss: dưới `state`, frame được gắn vào page table, pin, và loader giữ exclusive
+//!   latch; đọc xong mới đánh dấu `loaded`. Thread khác fetch cùng page pin frame đó
+//!   rồi chờ latch; load lỗi thì frame rời page table, thread chờ fetch lại từ đầu.
+//!   Page readahead được reserve y như vậy trước khi đọc, nên không ai đọc/ghi page
+//!   đó trong lúc đọc và dữ liệu đọc được không bao giờ cũ hơn bản trong pool.
+//! - Victim dirty được pin trong lúc ghi (không ai evict hay load đè lên nó); ghi xong
+//!   mà page lại bị pin hoặc dirty thì victim được trả lại, chọn victim khác.
+
+mod budget;
+mod guard;
+mod latch;
+pub mod replacer;
+mod scan;
+mod writer;
+
+use std::cell::UnsafeCell;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+
+use crate::constants::PAGE_SIZE;
+use crate::pager::pager::{nth_page, Pager};
+use crate::{DbError, DbResult, PageId};
+
+pub use budget::{MemoryBudget, MemoryBudgetStats};
+pub use guard::{PageReadGuard, PageWriteGuard};
+pub use replacer::{Replacer, ReplacerKind};
+pub use scan::AccessHint;
+pub use writer::{BackgroundWriter, BackgroundWriterConfig};
+
+use latch::Latch;
+use scan::{ScanRing, SeqDetector};
+
+/// Index của frame trong pool.
+pub type FrameId = usize;
+
+/// 1 frame: data của page + latch bảo vệ data + dirty bit.
+struct Frame {
+    latch: Latch,
+    data: UnsafeCell<Box<[u8]>>,
+    dirty: AtomicBool,
+    /// Data là page đang gắn với frame (false khi đang đọc từ pager hoặc đọc lỗi).
+    loaded: AtomicBool,
+}
+
+// SAFETY: `data` chỉ được đọc khi giữ latch (shared hoặc exclusive), chỉ được ghi khi
+// giữ exclusive latch, hoặc khi frame không bị pin và đang giữ `state` (xem doc module).
+unsafe impl Sync for Frame {}
+
+impl Frame {
+    fn new() -> Self {
+        Self::with_len(PAGE_SIZE)
+    }
+
+    /// Slot của frame đã bị bỏ khi shrink: không giữ data.
+    fn retired() -> Self {
+        Self::with_len(0)
+    }
+
+    fn with_len(len: usize) -> Self {
+        Frame {
+            latch: Latch::default(),
+            data: UnsafeCell::new(vec![0u8; len].into_boxed_slice()),
+            dirty: AtomicBool::new(false),
+            loaded: AtomicBool::new(false),
+        }
+    }
+
+    /// SAFETY: caller giữ latch của frame, hoặc frame không bị pin và caller giữ `state`.
+    unsafe fn bytes(&self) -> &[u8] {
+        &*self.data.get()
+    }
+
+    /// SAFETY: caller giữ exclusive latch, hoặc frame không bị pin và caller giữ `state`.
+    #[allow(clippy::mut_from_ref)]
+    unsafe fn bytes_mut(&self) -> &mut [u8] {
+        &mut *self.data.get()
+    }
+}
+
+/// Cấu hình buffer pool.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BufferPoolConfig {
+    /// Số frame `PAGE_SIZE`.
+    pub frames: usize,
+    pub replacer: ReplacerKind,
+    /// Số page đọc 1 lần khi scan tuần tự (tính cả page được fetch), 0/1 = tắt readahead.
+    /// Bị giới hạn bởi kích thước scan ring.
+    pub readahead_pages: usize,
+    /// Số frame tối đa dành cho page của scan; bị giới hạn ở 1/4 pool.
+    pub scan_ring_frames: usize,
+}
+
+impl Default for BufferPoolConfig {
+    fn default() -> Self {
+        BufferPoolConfig {
+            frames: DEFAULT_POOL_FRAMES,
+            replacer: ReplacerKind::default(),
+            readahead_pages: DEFAULT_READAHEAD_PAGES,
+            scan_ring_frames: DEFAULT_SCAN_RING_FRAMES,
+        }
+    }
+}
+
+impl BufferPoolConfig {
+    /// Cấu hình mặc định với pool `bytes` byte (làm tròn xuống bội của `PAGE_SIZE`).
+    pub fn with_bytes(bytes: usize) -> Self {
+        BufferPoolConfig {
+            frames: frames_for_bytes(bytes),
+            ..BufferPoolConfig::default()
+        }
+    }
+
+    /// Kích thước scan ring ứng với số frame hiện tại.
+    fn ring_capacity(&self) -> usize {
+        self.scan_ring_frames
+            .min(self.frames / SCAN_RING_MAX_DIVISOR)
+    }
+}
+
+/// Số frame vừa `bytes` byte.
+pub fn frames_for_bytes(bytes: usize) -> usize {
+    bytes / PAGE_SIZE
+}
+
+/// 1024 frame = 4 MiB.
+pub const DEFAULT_POOL_FRAMES: usize = 1024;
+/// 8 page = 32 KiB mỗi lần đọc.
+pub const DEFAULT_READAHEAD_PAGES: usize = 8;
+pub const DEFAULT_SCAN_RING_FRAMES: usize = 32;
+/// Scan ring chiếm tối đa 1/SCAN_RING_MAX_DIVISOR pool.
+const SCAN_RING_MAX_DIVISOR: usize = 4;
+
+/// Counter của pool, để so sánh các replacement policy trên cùng 1 trace.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BufferPoolStats {
+    /// Tên policy đang dùng.
+    pub policy: &'static str,
+    /// Fetch thấy page đã nằm trong pool.
+    pub hits: u64,
+    /// Fetch phải đọc page từ pager.
+    pub misses: u64,
+    pub evictions: u64,
+    /// Số lần ghi dirty page xuống pager (evict + flush + background writer).
+    pub writebacks: u64,
+    /// Số page do background writer ghi.
+    pub background_writes: u64,
+    /// Số page được load trước bởi readahead.
+    pub prefetched: u64,
+    /// Số frame hiện tại.
+    pub frames: usize,
+    /// Bộ nhớ data của các frame (`frames * PAGE_SIZE`).
+    pub memory_bytes: usize,
+    /// High-water mark của `memory_bytes`.
+    pub peak_memory_bytes: usize,
+    /// Số page đang nằm trong pool.
+    pub resident_pages: usize,
+    /// High-water mark của `resident_pages`.
+    pub peak_resident_pages: usize,
+}
+
+impl BufferPoolStats {
+    /// hits / (hits + misses), 0 nếu chưa có fetch nào.
+    pub fn hit_ratio(&self) -> f64 {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            0.0
+        } else {
+            self.hits as f64 / total as f64
+        }
+    }
+}
+
+/// Metadata của frame, chỉ đọc/ghi khi giữ `state`.
+#[derive(Debug, Clone, Copy, Default)]
+struct FrameMeta {
+    pid: Option<PageId>,
+    pin_count: u32,
+    /// Frame đang thuộc scan ring.
+    in_ring: bool,
+}
+
+struct PoolState {
+    /// `config.frames` luôn bằng số frame hiện tại.
+    config: BufferPoolConfig,
+    /// Gồm cả slot đã retire; FrameId ổn định qua các lần resize.
+    frames: Vec<Arc<Frame>>,
+    meta: Vec<FrameMeta>,
+    page_table: HashMap<PageId, FrameId>,
+    free: Vec<FrameId>,
+    /// Slot bị bỏ khi shrink, được dùng lại trước khi grow thêm slot mới.
+    retired: Vec<FrameId>,
+    replacer: Box<dyn Replacer>,
+    stats: BufferPoolStats,
+    ring: ScanRing,
+    seq: SeqDetector,
+}
+
+impl PoolState {
+    fn readahead_pages(&self) -> usize {
+        self.config.readahead_pages.min(self.ring.capacity())
+    }
+
+    fn note_resident(&mut self) {
+        let resident = self.page_table.len();
+        let peak = &mut self.stats.peak_resident_pages;
+        *peak = (*peak).max(resident);
+    }
+
+    fn note_memory(&mut self) {
+        let bytes = self.config.frames * PAGE_SIZE;
+        let peak = &mut self.stats.peak_memory_bytes;
+        *peak = (*peak).max(bytes);
+    }
+
+    /// Lập lại replacer cho số slot hiện tại (lịch sử truy cập bị reset),
+    /// scan ring và readahead theo kích thước mới.
+    fn rebuild_policy(&mut self) -> DbResult<()> {
+        let mut replacer = self.config.replacer.build(self.frames.len())?;
+        for (frame_id, meta) in self.meta.iter().enumerate() {
+            if let Some(pid) = meta.pid {
+                replacer.record_access(frame_id, pid);
+                replacer.set_evictable(frame_id, meta.pin_count == 0);
+            }
+        }
+        self.replacer = replacer;
+        for frame_id in self.ring.set_capacity(self.config.ring_capacity()) {
+            self.meta[frame_id].in_ring = false;
+        }
+        Ok(())
+    }
+}
+
+/// Lock bị poison (thread khác panic khi giữ lock) vẫn dùng tiếp được:
+/// state luôn nhất quán giữa các câu lệnh.
+fn lock<T>(m: &Mutex<T>) -> MutexGuard<'_, T> {
+    m.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+pub struct BufferPool {
+    state: Mutex<PoolState>,
+    /// Báo khi có frame hết bị pin hoặc được trả về free list (resize đang chờ).
+    unpinned: Condvar,
+    /// Chỉ 1 resize chạy tại 1 thời điểm.
+    resize: Mutex<()>,
+    pager: Mutex<Box<dyn Pager + Send>>,
+    budget: Option<Arc<MemoryBudget>>,
+}
+
+impl BufferPool {
+    /// Tạo pool gồm `frames` frame trên `pager`, policy mặc định (LRU).
+    pub fn new(pager: Box<dyn Pager + Send>, frames: usize) -> DbResult<Self> {
+        Self::with_config(
+            pager,
+            BufferPoolConfig {
+                frames,
+                ..BufferPoolConfig::default()
+            },
+        )
+    }
+
+    pub fn with_config(pager: Box<dyn Pager + Send>, config: BufferPoolConfig) -> DbResult<Self> {
+        Self::build(pager, config, None)
+    }
+
+    /// Như `with_config`, bộ nhớ của frame lấy từ `budget` dùng chung.
+    /// Lỗi `NoSpace` nếu budget không còn đủ cho `config.frames` frame.
+    pub fn with_budget(
+        pager: Box<dyn Pager + Send>,
+        config: BufferPoolConfig,
+        budget: Arc<MemoryBudget>,
+    ) -> DbResult<Self> {
+        Self::build(pager, config, Some(budget))
+    }
+
+    fn build(
+        pager: Box<dyn Pager + Send>,
+        config: BufferPoolConfig,
+        budget: Option<Arc<MemoryBudget>>,
+    ) -> DbResult<Self> {
+        let frames = config.frames;
+        if frames == 0 {
+            return Err(DbError::InvalidArgument(
+                "buffer pool needs at least one frame",
+            ));
+        }
+        let replacer = config.replacer.build(frames)?;
+        if let Some(budget) = &budget {
+            if !budget.try_reserve(frames * PAGE_SIZE) {
+                return Err(DbError::NoSpace("memory budget exhausted"));
+            }
+        }
+        let mut state = PoolState {
+            config,
+            frames: (0..frames).map(|_| Arc::new(Frame::new())).collect(),
+            meta: vec![FrameMeta::default(); frames],
+            page_table: HashMap::new(),
+            // pop() lấy frame 0 trước
+            free: (0..frames).rev().collect(),
+            retired: Vec::new(),
+            replacer,
+            stats: BufferPoolStats::default(),
+            ring: ScanRing::new(config.ring_capacity()),
+            seq: SeqDetector::default(),
+        };
+        state.note_memory();
+        Ok(BufferPool {
+            state: Mutex::new(state),
+            unpinned: Condvar::new(),
+            resize: Mutex::new(()),
+            pager: Mutex::new(pager),
+            budget,
+        })
+    }
+
+    /// Số frame của pool.
+    pub fn capacity(&self) -> usize {
+        lock(&self.state).config.frames
+    }
+
+    pub fn stats(&self) -> BufferPoolStats {
+        let state = lock(&self.state);
+        BufferPoolStats {
+            policy: state.replacer.name(),
+            frames: state.config.frames,
+            memory_bytes: state.config.frames * PAGE_SIZE,
+            resident_pages: state.page_table.len(),
+            ..state.stats
+        }
+    }
+
+    /// Reset các counter; high-water mark về mức hiện tại.
+    pub fn reset_stats(&self) {
+        let mut state = lock(&self.state);
+        state.stats = BufferPoolStats::default();
+        state.note_memory();
+        state.note_resident();
+    }
+
+    /// Đổi kích thước pool theo byte (làm tròn xuống bội của `PAGE_SIZE`).
+    pub fn resize_bytes(&self, bytes: usize) -> DbResult<()> {
+        self.resize(frames_for_bytes(bytes))
+    }
+
+    /// Đổi số frame lúc đang chạy.
+    ///
+    /// Grow lấy thêm bộ nhớ từ budget (lỗi `NoSpace` nếu không đủ, pool giữ nguyên).
+    /// Shrink bỏ frame trống trước, rồi tới page clean, cuối cùng mới ghi và bỏ page
+    /// dirty; nếu mọi frame còn lại đều bị pin thì chờ tới khi có page được unpin.
+    /// Không gọi khi chính thread này đang giữ guard của pool (có thể chờ mãi).
+    /// Replacer được lập lại nên lịch sử truy cập bị reset.
+    pub fn resize(&self, frames: usize) -> DbResult<()> {
+        if frames == 0 {
+            return Err(DbError::InvalidArgument(
+                "buffer pool needs at least one frame",
+            ));
+        }
+        let _resize = lock(&self.resize);
+        let mut state = lock(&self.state);
+        let current = state.config.frames;
+        if frames > current {
+            self.grow(&mut state, frames - current)?;
+        } else {
+            while state.config.frames > frames {
+                state = self.shrink_one(state)?;
+            }
+        }
+        state.rebuild_policy()
+    }
+
+    fn grow(&self, state: &mut PoolState, add: usize) -> DbResult<()> {
+        if let Some(budget) = &self.budget {
+            if !budget.try_reserve(add * PAGE_SIZE) {
+                return Err(DbError::NoSpace("memory budget exhausted"));
+            }
+        }
+        for _ in 0..add {
+            let frame_id = match state.retired.pop() {
+                Some(f) => f,
+                None => {
+                    state.frames.push(Arc::new(Frame::retired()));
+                    state.meta.push(FrameMeta::default());
+                    state.frames.len() - 1
+                }
+            };
+            state.frames[frame_id] = Arc::new(Frame::new());
+            state.free.push(frame_id);
+        }
+        state.config.frames += add;
+        state.note_memory();
+        Ok(())
+    }
+
+    /// Bỏ 1 frame: frame trống, rồi page clean, rồi page dirty không bị pin;
+    /// không có thì chờ unpin (nhả `state` trong lúc chờ).
+    fn shrink_one<'a>(
+        &'a self,
+        mut state: MutexGuard<'a, PoolState>,
+    ) -> DbResult<MutexGuard<'a, PoolState>> {
+        loop {
+            if let Some(frame_id) = state.free.pop() {
+                self.retire_frame(&mut state, frame_id);
+                return Ok(state);
+            }
+            let clean = state.meta.iter().enumerate().find_map(|(f, m)| {
+                let unpinned = m.pid.is_some() && m.pin_count == 0;
+                (unpinned && !state.frames[f].dirty.load(Ordering::Acquire)).then_some(f)
+            });
+            let victim = match clean {
+                Some(frame_id) => {
+                    state.replacer.remove(frame_id);
+                    Some(frame_id)
+                }
+                None => state.replacer.evict(),
+            };
+            if let Some(frame_id) = victim {
+                let (s, evicted) = self.evict_frame(state, frame_id)?;
+                state = s;
+                if evicted {
+                    self.retire_frame(&mut state, frame_id);
+                    return Ok(state);
+                }
+                continue;
+            }
+            state = self
+                .unpinned
+                .wait(state)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+    }
+
+    /// Trả bộ nhớ của frame (đã trống, không bị pin) về budget.
+    fn retire_frame(&self, state: &mut PoolState, frame_id: FrameId) {
+        // frame không bị pin: không guard nào còn dùng data; Arc cũ (nếu guard vừa
+        // unpin chưa drop xong) tự giải phóng sau
+        state.frames[frame_id] = Arc::new(Frame::retired());
+        state.retired.push(frame_id);
+        state.config.frames -= 1;
+        if let Some(budget) = &self.budget {
+            budget.release(PAGE_SIZE);
+        }
+    }
+
+    /// Page có đang nằm trong pool không.
+    pub fn is_resident(&self, pid: PageId) -> bool {
+        lock(&self.state).page_table.contains_key(&pid)
+    }
+
+    /// pin_count của page, `None` nếu page không nằm trong pool.
+    pub fn pin_count(&self, pid: PageId) -> Option<u32> {
+        let state = lock(&self.state);
+        state.page_table.get(&pid).map(|&f| state.meta[f].pin_count)
+    }
+
+    /// Pin page và giữ shared latch: nhiều reader cùng đọc được 1 page.
+    pub fn fetch_read(&self, pid: PageId) -> DbResult<PageReadGuard<'_>> {
+        self.fetch_read_with(pid, AccessHint::Normal)
+    }
+
+    /// Như `fetch_read`, kèm gợi ý pattern truy cập: scan tuần tự được readahead
+    /// và dùng scan ring.
+    pub fn fetch_read_with(&self, pid: PageId, hint: AccessHint) -> DbResult<PageReadGuard<'_>> {
+        let (frame_id, frame) = self.pin_page(pid, hint)?;
+        Ok(PageReadGuard::new(self, pid, frame_id, frame))
+    }
+
+    /// Pin page và giữ exclusive latch; frame được đánh dấu dirty.
+    pub fn fetch_write(&self, pid: PageId) -> DbResult<PageWriteGuard<'_>> {
+        let (frame_id, frame) = self.pin_page(pid, AccessHint::Normal)?;
+        Ok(PageWriteGuard::new(self, pid, frame_id, frame))
+    }
+
+    /// Cấp phát page mới qua pager, trả về write guard trên page đã zero.
+    pub fn new_page(&self) -> DbResult<PageWriteGuard<'_>> {
+        // frame lấy ra không nằm trong free list, replacer hay page table: không thread
+        // nào khác thấy nó trong lúc pager cấp phát
+        let (state, frame_id) = self.take_frame(lock(&self.state))?;
+        drop(state);
+        let alloc = lock(&self.pager).alloc_page();
+
+        let mut state = lock(&self.state);
+        let pid = match alloc {
+            Ok(pid) => pid,
+            Err(e) => {
+                state.free.push(frame_id);
+                self.unpinned.notify_all();
+                return Err(e);
+            }
+        };
+        let frame = Arc::clone(&state.frames[frame_id]);
+        // SAFETY: frame chưa gắn page, chưa bị pin, đang giữ state
+        unsafe { frame.bytes_mut() }.fill(0);
+        frame.loaded.store(true, Ordering::Release);
+        self.install(&mut state, pid, frame_id);
+        drop(state);
+        Ok(PageWriteGuard::new(self, pid, frame_id, frame))
+    }
+
+    /// Ghi page xuống pager nếu đang dirty. Trả về true nếu có ghi.
+    /// Chờ writer đang giữ page (nếu có) xong rồi mới ghi: không gọi khi chính thread
+    /// này đang giữ write guard của page.
+    pub fn flush_page(&self, pid: PageId) -> DbResult<bool> {
+        self.flush_resident(pid, false)
+    }
+
+    /// Ghi mọi dirty page theo thứ tự `PageId` rồi sync pager; dùng cho checkpoint
+    /// và shutdown sạch. Trả về số page đã ghi.
+    pub fn flush_all(&self) -> DbResult<usize> {
+        let mut written = 0;
+        for pid in self.dirty_page_ids() {
+            if self.flush_resident(pid, false)? {
+                written += 1;
+            }
+        }
+        lock(&self.pager).flush()?;
+        Ok(written)
+    }
+
+    /// Số frame đang dirty.
+    pub fn dirty_count(&self) -> usize {
+        let state = lock(&self.state);
+        state
+            .frames
+            .iter()
+            .filter(|f| f.dirty.load(Ordering::Acquire))
+            .count()
+    }
+
+    /// 1 vòng của background writer: nếu tỉ lệ dirty >= `dirty_ratio`, ghi tối đa
+    /// `max_pages` dirty page không bị pin theo thứ tự `PageId`.
+    /// Page đang bị pin bị bỏ qua (không chờ latch), để vòng sau.
+    pub fn write_dirty_round(&self, dirty_ratio: f64, max_pages: usize) -> DbResult<usize> {
+        let dirty = self.dirty_count();
+        if dirty == 0 || (dirty as f64) < dirty_ratio * self.capacity() as f64 {
+            return Ok(0);
+        }
+        let mut written = 0;
+        for pid in self.dirty_page_ids() {
+            if written == max_pages {
+                break;
+            }
+            if self.flush_resident(pid, true)? {
+                written += 1;
+            }
+        }
+        lock(&self.state).stats.background_writes += written as u64;
+        Ok(written)
+    }
+
+    /// PageId của các dirty page, tăng dần (ghi tuần tự trên file).
+    fn dirty_page_ids(&self) -> Vec<PageId> {
+        let state = lock(&self.state);
+        let mut pids: Vec<PageId> = state
+            .page_table
+            .iter()
+            .filter(|(_, &f)| state.frames[f].dirty.load(Ordering::Acquire))
+            .map(|(&pid, _)| pid)
+            .collect();
+        pids.sort_unstable();
+        pids
+    }
+
+    /// Ghi page nếu đang nằm trong pool và dirty.
+    /// `skip_pinned`: page đang bị pin thì bỏ qua thay vì chờ latch.
+    fn flush_resident(&self, pid: PageId, skip_pinned: bool) -> DbResult<bool> {
+        let (frame_id, frame) = {
+            let mut state = lock(&self.state);
+            let frame_id = match state.page_table.get(&pid) {
+                Some(&f) => f,
+                None => return Ok(false),
+            };
+            if skip_pinned && state.meta[frame_id].pin_count > 0 {
+                return Ok(false);
+            }
+            // pin để frame không bị evict, không tính là 1 lần truy cập
+            self.pin(&mut state, frame_id);
+            (frame_id, Arc::clone(&state.frames[frame_id]))
+        };
+        let guard = PageReadGuard::new(self, pid, frame_id, frame);
+        let written = self.write_frame(guard.frame(), pid)?;
+        if written {
+            lock(&self.state).stats.writebacks += 1;
+        }
+        Ok(written)
+    }
+
+    /// Bỏ page khỏi pool (không ghi) và trả page về pager.
+    pub fn delete_page(&self, pid: PageId) -> DbResult<()> {
+        self.drop_resident(pid)?;
+        lock(&self.pager).free_page(pid)
+    }
+
+    /// Bỏ page khỏi pool (không ghi), nếu page đang nằm trong pool và không bị pin.
+    fn drop_resident(&self, pid: PageId) -> DbResult<()> {
+        let mut state = lock(&self.state);
+        if let Some(&frame_id) = state.page_table.get(&pid) {
+            if state.meta[frame_id].pin_count > 0 {
+                return Err(DbError::InvalidArgument("cannot delete a pinned page"));
+            }
+            state.page_table.remove(&pid);
+            state.replacer.remove(frame_id);
+            state.ring.remove(frame_id);
+            state.meta[frame_id] = FrameMeta::default();
+            state.frames[frame_id].dirty.store(false, Ordering::Release);
+            state.free.push(frame_id);
+            self.unpinned.notify_all();
+        }
+        Ok(())
+    }
+
+    /// Pin page (load từ pager nếu cần). Không lấy latch: caller lấy sau khi
+    /// đã nhả `state`. Trả về khi data của frame đã được load.
+    fn pin_page(&self, pid: PageId, hint: AccessHint) -> DbResult<(FrameId, Arc<Frame>)> {
+        let mut state = lock(&self.state);
+        let detected = state.seq.observe(pid);
+        let sequential = hint == AccessHint::Sequential || detected;
+
+        loop {
+            if let Some(&frame_id) = state.page_table.get(&pid) {
+                state.stats.hits += 1;
+                self.pin(&mut state, frame_id);
+                state.replacer.record_access(frame_id, pid);
+                // page của scan được truy cập kiểu thường -> thành page thường
+                if !sequential && state.meta[frame_id].in_ring {
+                    state.ring.remove(frame_id);
+                    state.meta[frame_id].in_ring = false;
+                }
+                let frame = Arc::clone(&state.frames[frame_id]);
+                drop(state);
+                if self.wait_loaded(&frame) {
+                    return Ok((frame_id, frame));
+                }
+                // load của thread khác lỗi: frame đã rời page table, fetch lại
+                self.unpin(frame_id);
+                state = lock(&self.state);
+                continue;
+            }
+
+            let use_ring = sequential && state.ring.capacity() > 0;
+            let (s, frame_id) = if use_ring {
+                self.take_ring_frame(state)?
+            } else {
+                self.take_frame(state)?
+            };
+            state = s;
+            // lấy frame có thể đã nhả state (ghi victim): page có thể vừa được load
+            if state.page_table.contains_key(&pid) {
+                state.free.push(frame_id);
+                continue;
+            }
+            state.stats.misses += 1;
+            return self.load_page(state, pid, frame_id, use_ring);
+        }
+    }
+
+    /// Chờ loader (đang giữ exclusive latch) đọc xong page của frame đã pin.
+    /// false: load lỗi, frame không còn chứa page.
+    fn wait_loaded(&self, frame: &Frame) -> bool {
+        if frame.loaded.load(Ordering::Acquire) {
+            return true;
+        }
+        frame.latch.lock_shared();
+        frame.latch.unlock_shared();
+        frame.loaded.load(Ordering::Acquire)
+    }
+
+    /// Gắn `frame_id` (trống) với `pid` rồi đọc page từ pager khi đã nhả `state`.
+    /// Page đọc trước (scan) cũng được gắn vào frame riêng, pin và giữ exclusive latch
+    /// như page chính trước khi đọc: thread khác fetch page đó chờ latch thay vì đọc
+    /// lại, và không thể ghi/evict page đó trong lúc đọc rồi bị ảnh cũ đè lên.
+    fn load_page(
+        &self,
+        mut state: MutexGuard<'_, PoolState>,
+        pid: PageId,
+        frame_id: FrameId,
+        use_ring: bool,
+    ) -> DbResult<(FrameId, Arc<Frame>)> {
+        let frame = self.reserve_frame(&mut state, pid, frame_id, use_ring);
+        let readahead = if use_ring { state.readahead_pages() } else { 1 };
+        drop(state);
+
+        // readahead_window lấy pager lock: không giữ `state` khi chờ pager
+        let mut prefetch = Vec::new();
+        let read = self.readahead_window(pid, readahead).and_then(|window| {
+            if window > 1 {
+                prefetch = self.reserve_prefetch(pid, window - 1);
+            }
+            if prefetch.is_empty() {
+                // SAFETY: đang giữ exclusive latch
+                return lock(&self.pager).read_page(pid, unsafe { frame.bytes_mut() });
+            }
+            // đọc page được fetch + các page sau trong 1 lần
+            let mut buf = vec![0u8; (1 + prefetch.len()) * PAGE_SIZE];
+            lock(&self.pager).read_pages(pid, &mut buf)?;
+            let frames = std::iter::once(&frame).chain(prefetch.iter().map(|(_, f)| f));
+            for (f, page) in frames.zip(buf.chunks_exact(PAGE_SIZE)) {
+                // SAFETY: đang giữ exclusive latch của mọi frame đã reserve
+                unsafe { f.bytes_mut() }.copy_from_slice(page);
+            }
+            Ok(())
+        });
+
+        if let Err(e) = read {
+            for (id, f) in
+                std::iter::once((frame_id, &frame)).chain(prefetch.iter().map(|(id, f)| (*id, f)))
+            {
+                self.abandon_load(id, f);
+            }
+            return Err(e);
+        }
+        frame.loaded.store(true, Ordering::Release);
+        frame.latch.unlock_exclusive();
+        if !prefetch.is_empty() {
+            for (_, f) in &prefetch {
+                f.loaded.store(true, Ordering::Release);
+                f.latch.unlock_exclusive();
+            }
+            lock(&self.state).stats.prefetched += prefetch.len() as u64;
+            for (id, _) in prefetch {
+                self.unpin(id);
+            }
+        }
+        Ok((frame_id, frame))
+    }
+
+    /// Gắn frame trống với `pid` cho loader: pin, exclusive latch, chưa `loaded`.
+    fn reserve_frame(
+        &self,
+        state: &mut PoolState,
+        pid: PageId,
+        frame_id: FrameId,
+        use_ring: bool,
+    ) -> Arc<Frame> {
+        let frame = Arc::clone(&state.frames[frame_id]);
+        frame.dirty.store(false, Ordering::Release);
+        frame.loaded.store(false, Ordering::Release);
+        // frame trống không bị pin nên không ai giữ latch: không phải chờ
+        frame.latch.lock_exclusive();
+        self.install(state, pid, frame_id);
+        if use_ring {
+            let in_ring = state.ring.push(frame_id);
+            state.meta[frame_id].in_ring = in_ring;
+        }
+        frame
+    }
+
+    /// Reserve frame trong ring cho tối đa `pages` page ngay sau `first`. Dừng ở page
+    /// đã có trong pool (có thể đang dirty) hoặc khi chỉ còn cách ghi victim: readahead
+    /// chỉ là tối ưu.
+    fn reserve_prefetch(&self, first: PageId, pages: usize) -> Vec<(FrameId, Arc<Frame>)> {
+        let mut state = lock(&self.state);
+        let mut reserved = Vec::new();
+        for i in 1..=pages {
+            let Ok(pid) = nth_page(first, i) else {
+                break;
+            };
+            if state.page_table.contains_key(&pid) {
+                break;
+            }
+            let Some(frame_id) = self.take_clean_ring_frame(&mut state) else {
+                break;
+            };
+            let frame = self.reserve_frame(&mut state, pid, frame_id, true);
+            reserved.push((frame_id, frame));
+        }
+        reserved
+    }
+
+    /// Load của frame đã reserve bị lỗi: gỡ page khỏi pool, nhả latch và pin của loader.
+    fn abandon_load(&self, frame_id: FrameId, frame: &Frame) {
+        {
+            let mut state = lock(&self.state);
+            if let Some(pid) = state.meta[frame_id].pid {
+                state.page_table.remove(&pid);
+            }
+            state.replacer.remove(frame_id);
+            if state.meta[frame_id].in_ring {
+                state.ring.remove(frame_id);
+            }
+            // pin còn lại (của loader và thread đang chờ) vẫn giữ frame; unpin cuối
+            // trả frame về free list
+            state.meta[frame_id].pid = None;
+            state.meta[frame_id].in_ring = false;
+        }
+        frame.latch.unlock_exclusive();
+        self.unpin(frame_id);
+    }
+
+    /// Số page đọc 1 lần bắt đầu từ `pid` (tối đa `pages`): không vượt quá cuối file.
+    fn readahead_window(&self, pid: PageId, pages: usize) -> DbResult<usize> {
+        if pages <= 1 {
+            return Ok(1);
+        }
+        let total = lock(&self.pager).num_pages()?;
+        let left = total.saturating_sub(pid.as_u32() as u64);
+        Ok((pages as u64).min(left).max(1) as usize)
+    }
+
+    /// Frame cho page của scan: ring đầy thì tái dùng frame cũ nhất của ring,
+    /// không động tới các frame khác của pool.
+    fn take_ring_frame<'a>(
+        &'a self,
+        mut state: MutexGuard<'a, PoolState>,
+    ) -> DbResult<(MutexGuard<'a, PoolState>, FrameId)> {
+        if state.ring.is_full() {
+            if let Some(frame_id) = state.ring.oldest_unpinned(&state.meta) {
+                state.replacer.remove(frame_id);
+                let (s, evicted) = self.evict_frame(state, frame_id)?;
+                state = s;
+                if evicted {
+                    return Ok((state, frame_id));
+                }
+            }
+        }
+        self.take_frame(state)
+    }
+
+    /// Như `take_ring_frame` nhưng không ghi gì xuống pager (giữ `state` suốt):
+    /// `None` nếu chỉ còn cách evict page dirty.
+    fn take_clean_ring_frame(&self, state: &mut PoolState) -> Option<FrameId> {
+        if state.ring.is_full() {
+            let frame_id = state.ring.oldest_unpinned(&state.meta)?;
+            if state.frames[frame_id].dirty.load(Ordering::Acquire) {
+                return None;
+            }
+            state.replacer.remove(frame_id);
+            self.unmap_frame(state, frame_id);
+            return Some(frame_id);
+        }
+        if let Some(frame_id) = state.free.pop() {
+            return Some(frame_id);
+        }
+        let frame_id = state.replacer.evict()?;
+        if state.frames[frame_id].dirty.load(Ordering::Acquire) {
+            self.release_victim(state, frame_id);
+            return None;
+        }
+        self.unmap_frame(state, frame_id);
+        Some(frame_id)
+    }
+
+    fn pin(&self, state: &mut PoolState, frame_id: FrameId) {
+        let meta = &mut state.meta[frame_id];
+        meta.pin_count += 1;
+        if meta.pin_count == 1 {
+            state.replacer.set_evictable(frame_id, false);
+        }
+    }
+
+    fn unpin(&self, frame_id: FrameId) {
+        let mut state = lock(&self.state);
+        let meta = &mut state.meta[frame_id];
+        debug_assert!(meta.pin_count > 0, "unpin of an unpinned frame");
+        meta.pin_count -= 1;
+        if meta.pin_count == 0 {
+            if meta.pid.is_some() {
+                state.replacer.set_evictable(frame_id, true);
+            } else {
+                // load lỗi: frame không còn page nào
+                state.free.push(frame_id);
+            }
+            self.unpinned.notify_all();
+        }
+    }
+
+    /// Gắn frame (đã có data) với `pid` và pin lần đầu.
+    fn install(&self, state: &mut PoolState, pid: PageId, frame_id: FrameId) {
+        state.meta[frame_id] = FrameMeta {
+            pid: Some(pid),
+            pin_count: 1,
+            in_ring: false,
+        };
+        state.page_table.insert(pid, frame_id);
+        state.replacer.record_access(frame_id, pid);
+        state.replacer.set_evictable(frame_id, false);
+        state.note_resident();
+    }
+
+    /// Lấy 1 frame trống: free list trước, không có thì evict victim do replacer chọn
+    /// (ghi xuống pager nếu dirty, khi đã nhả `state`).
+    fn take_frame<'a>(
+        &'a self,
+        mut state: MutexGuard<'a, PoolState>,
+    ) -> DbResult<(MutexGuard<'a, PoolState>, FrameId)> {
+        loop {
+            if let Some(frame_id) = state.free.pop() {
+                return Ok((state, frame_id));
+            }
+            let frame_id = state
+                .replacer
+                .evict()
+                .ok_or(DbError::NoSpace("all buffer frames are pinned"))?;
+            let (s, evicted) = self.evict_frame(state, frame_id)?;
+            state = s;
+            if evicted {
+                return Ok((state, frame_id));
+            }
+        }
+    }
+
+    /// Bỏ page khỏi frame victim (đã được lấy ra khỏi replacer, không bị pin). Victim
+    /// dirty được pin và ghi xuống khi đã nhả `state`; ghi xong mà page bị pin lại
+    /// hoặc dirty lại thì victim được trả về replacer và trả về false (chọn victim
+    /// khác). Ghi lỗi thì page giữ nguyên trong pool, lần sau evict lại.
+    fn evict_frame<'a>(
+        &'a self,
+        mut state: MutexGuard<'a, PoolState>,
+        frame_id: FrameId,
+    ) -> DbResult<(MutexGuard<'a, PoolState>, bool)> {
+        let old = state.meta[frame_id]
+            .pid
+            .ok_or(DbError::Corruption("evictable frame has no page"))?;
+
+        if state.frames[frame_id].dirty.load(Ordering::Acquire) {
+            // pin không qua replacer: victim đã rời replacer
+            state.meta[frame_id].pin_count += 1;
+            let frame = Arc::clone(&state.frames[frame_id]);
+            drop(state);
+            frame.latch.lock_shared();
+            let written = self.write_frame(&frame, old);
+            frame.latch.unlock_shared();
+            state = lock(&self.state);
+            state.meta[frame_id].pin_count -= 1;
+
+            match written {
+                Ok(true) => state.stats.writebacks += 1,
+                Ok(false) => {}
+                Err(e) => {
+                    self.release_victim(&mut state, frame_id);
+                    return Err(e);
+                }
+            }
+            if state.meta[frame_id].pin_count > 0
+                || state.frames[frame_id].dirty.load(Ordering::Acquire)
+            {
+                self.release_victim(&mut state, frame_id);
+                return Ok((state, false));
+            }
+            // thread fetch page trong lúc ghi đã ghi lại lịch sử truy cập
+            state.replacer.remove(frame_id);
+        }
+        self.unmap_frame(&mut state, frame_id);
+        Ok((state, true))
+    }
+
+    /// Gỡ page khỏi frame victim (clean, không bị pin, đã rời replacer).
+    fn unmap_frame(&self, state: &mut PoolState, frame_id: FrameId) {
+        if let Some(old) = state.meta[frame_id].pid {
+            state.page_table.remove(&old);
+        }
+        if state.meta[frame_id].in_ring {
+            state.ring.remove(frame_id);
+        }
+        state.meta[frame_id] = FrameMeta::default();
+        state.stats.evictions += 1;
+    }
+
+    /// Victim không bị evict: trả lại replacer như vừa được truy cập.
+    fn release_victim(&self, state: &mut PoolState, frame_id: FrameId) {
+        let Some(pid) = state.meta[frame_id].pid else {
+            return;
+        };
+        state.replacer.record_access(frame_id, pid);
+        if state.meta[frame_id].pin_count == 0 {
+            state.replacer.set_evictable(frame_id, true);
+            self.unpinned.notify_all();
+        }
+    }
+
+    /// Ghi frame xuống pager nếu dirty. Trả về true nếu có ghi.
+    /// Caller giữ latch của frame.
+    fn write_frame(&self, frame: &Frame, pid: PageId) -> DbResult<bool> {
+        if !frame.dirty.swap(false, Ordering::AcqRel) {
+            return Ok(false);
+        }
+        // SAFETY: xem điều kiện của hàm, không ai đang ghi data
+        let data = unsafe { frame.bytes() };
+        if let Err(e) = lock(&self.pager).write_page(pid, data) {
+            frame.dirty.store(true, Ordering::Release);
+            return Err(e);
+        }
+        Ok(true)
+    }
+}
+
+impl Drop for BufferPool {
+    /// Ghi dirty page còn lại như `flush_all`, bỏ qua lỗi: cần biết lỗi thì gọi
+    /// `flush_all` trước khi drop.
+    fn drop(&mut self) {
+        let _ = self.flush_all();
+        if let Some(budget) = &self.budget {
+            budget.release(lock(&self.state).config.frames * PAGE_SIZE);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::page::header::PAGE_TYPE_HEAP;
+    use crate::pager::mem::MemPager;
+    use std::sync::atomic::{AtomicBool, AtomicUsize};
+    use std::time::{Duration, Instant};
+
+    /// MemPager đếm số lần read/write để kiểm tra cache hit và write-back.
+    struct CountingPager {
+        inner: MemPager,
+        reads: Arc<AtomicUsize>,
+        writes: Arc<AtomicUsize>,
+    }
+
+    impl Pager for CountingPager {
+        fn read_page(&mut self, pid: PageId, out: &mut [u8]) -> DbResult<()> {
+            self.reads.fetch_add(1, Ordering::Relaxed);
+            self.inner.read_page(pid, out)
+        }
+        /// 1 lần đọc nhiều page tính là 1 read.
+        fn read_pages(&mut self, start: PageId, out: &mut [u8]) -> DbResult<()> {
+            self.reads.fetch_add(1, Ordering::Relaxed);
+            self.inner.read_pages(start, out)
+        }
+        fn write_page(&mut self, pid: PageId, buf: &[u8]) -> DbResult<()> {
+            self.writes.fetch_add(1, Ordering::Relaxed);
+            self.inner.write_page(pid, buf)
+        }
+        fn alloc_page(&mut self) -> DbResult<PageId> {
+            self.inner.alloc_page()
+        }
+        fn free_page(&mut self, pid: PageId) -> DbResult<()> {
+            self.inner.free_page(pid)
+        }
+        fn flush(&mut self) -> DbResult<()> {
+            self.inner.flush()
+        }
+        fn num_pages(&mut self) -> DbResult<u64> {
+            self.inner.num_pages()
+        }
+    }
+
+    fn counting_pool(frames: usize) -> (BufferPool, Arc<AtomicUsize>, Arc<AtomicUsize>) {
+        let reads = Arc::new(AtomicUsize::new(0));
+        let writes = Arc::new(AtomicUsize::new(0));
+        let pager = CountingPager {
+            inner: MemPager::new(),
+            reads: Arc::clone(&reads),
+            writes: Arc::clone(&writes),
+        };
+        (
+            BufferPool::new(Box::new(pager), frames).unwrap(),
+            reads,
+            writes,
+        )
+    }
+
+    /// Pool trên pager có sẵn `pages` page (byte 0 = số thứ tự page).
+    fn prefilled_pool(config: BufferPoolConfig, pages: usize) -> (BufferPool, Arc<AtomicUsize>) {
+        let mut inner = MemPager::new();
+        for i in 0..pages {
+            let pid = inner.alloc_page().unwrap();
+            let mut buf = vec![0u8; PAGE_SIZE];
+            buf[0] = i as u8;
+            inner.write_page(pid, &buf).unwrap();
+        }
+        let reads = Arc::new(AtomicUsize::new(0));
+        let pager = CountingPager {
+            inner,
+            reads: Arc::clone(&reads),
+            writes: Arc::new(AtomicUsize::new(0)),
+        };
+        (
+            BufferPool::with_config(Box::new(pager), config).unwrap(),
+            reads,
+        )
+    }
+
+    fn new_pages(pool: &BufferPool, n: usize) -> Vec<PageId> {
+        (0..n)
+            .map(|i| {
+                let mut g = pool.new_page().unwrap();
+                g.data_mut()[0] = i as u8;
+                g.page_id()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_zero_frames_rejected() {
+        assert!(BufferPool::new(Box::new(MemPager::new()), 0).is_err());
+    }
+
+    #[test]
+    fn test_pin_unpin() {
+        let pool = BufferPool::new(Box::new(MemPager::new()), 2).unwrap();
+        let pid = new_pages(&pool, 1)[0];
+        assert_eq!(pool.pin_count(pid), Some(0));
+
+        let g1 = pool.fetch_read(pid).unwrap();
+        let g2 = pool.fetch_read(pid).unwrap();
+        assert_eq!(pool.pin_count(pid), Some(2));
+        assert_eq!(g2.data()[0], 0);
+        drop(g1);
+        assert_eq!(pool.pin_count(pid), Some(1));
+        drop(g2);
+        assert_eq!(pool.pin_count(pid), Some(0));
+    }
+
+    #[test]
+    fn test_cache_hit_does_not_read_pager() {
+        let (pool, reads, _) = counting_pool(2);
+        let pid = new_pages(&pool, 1)[0];
+        for _ in 0..5 {
+            pool.fetch_read(pid).unwrap();
+        }
+        assert_eq!(reads.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn test_lru_eviction_writes_back_dirty() {
+        let (pool, reads, writes) = counting_pool(3);
+        let pids = new_pages(&pool, 3);
+
+        // dùng lại page 0 -> page 1 thành LRU
+        pool.fetch_read(pids[0]).unwrap();
+        let p3 = new_pages(&pool, 1)[0];
+        assert!(!pool.is_resident(pids[1]));
+        assert!(pool.is_resident(pids[0]) && pool.is_resident(pids[2]) && pool.is_resident(p3));
+        assert_eq!(writes.load(Ordering::Relaxed), 1);
+
+        // đọc lại page 1 từ pager: data đã được write-back
+        let g = pool.fetch_read(pids[1]).unwrap();
+        assert_eq!(g.data()[0], 1);
+        assert!(!g.is_dirty());
+        assert_eq!(reads.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn test_clean_victim_not_written() {
+        let (pool, _, writes) = counting_pool(1);
+        let pids = new_pages(&pool, 2);
+        assert_eq!(writes.load(Ordering::Relaxed), 1);
+
+        // page 0 được đọc lại (clean) rồi bị evict -> không ghi thêm
+        pool.fetch_read(pids[0]).unwrap();
+        assert_eq!(writes.load(Ordering::Relaxed), 2);
+        pool.fetch_read(pids[1]).unwrap();
+        assert_eq!(writes.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn test_stats_count_hits_and_misses() {
+        let (pool, _, _) = counting_pool(2);
+        let pids = new_pages(&pool, 3);
+        assert_eq!(pool.stats().policy, "lru");
+
+        pool.fetch_read(pids[2]).unwrap(); // hit
+        pool.fetch_read(pids[0]).unwrap(); // miss, evict pids[1]
+        let stats = pool.stats();
+        assert_eq!((stats.hits, stats.misses), (1, 1));
+        assert_eq!(stats.evictions, 2);
+        assert_eq!(stats.writebacks, 2);
+        assert_eq!(stats.hit_ratio(), 0.5);
+
+        pool.reset_stats();
+        assert_eq!(pool.stats().hits, 0);
+    }
+
+    #[test]
+    fn test_replacer_selected_by_config() {
+        let config = BufferPoolConfig {
+            frames: 2,
+            replacer: ReplacerKind::Fifo,
+            ..BufferPoolConfig::default()
+        };
+        let pool = BufferPool::with_config(Box::new(MemPager::new()), config).unwrap();
+        assert_eq!(pool.stats().policy, "fifo");
+
+        let pids = new_pages(&pool, 2);
+        // FIFO: hit không đổi thứ tự, page load đầu tiên vẫn bị evict
+        pool.fetch_read(pids[0]).unwrap();
+        new_pages(&pool, 1);
+        assert!(!pool.is_resident(pids[0]));
+        assert!(pool.is_resident(pids[1]));
+    }
+
+    #[test]
+    fn test_write_guard_slotted_page() {
+        let pool = BufferPool::new(Box::new(MemPager::new()), 2).unwrap();
+        let pid = {
+            let mut g = pool.new_page().unwrap();
+            let mut page = g.page().unwrap().init(PAGE_TYPE_HEAP).unwrap();
+            page.insert(b"row").unwrap();
+            g.page_id()
+        };
+
+        let r = pool.fetch_read(pid).unwrap();
+        assert_eq!(r.page().unwrap().get(0).unwrap(), Some(&b"row"[..]));
+    }
+
+    #[test]
+    fn test_upgrade_and_downgrade() {
+        let (pool, _, _) = counting_pool(2);
+        let pid = new_pages(&pool, 1)[0];
+        pool.flush_page(pid).unwrap();
+
+        let r = pool.fetch_read(pid).unwrap();
+        assert!(!r.is_dirty());
+        let mut w = r.try_upgrade().ok().unwrap();
+        w.data_mut()[1] = 7;
+        let r = w.downgrade();
+        assert!(r.is_dirty());
+        assert_eq!(r.data()[1], 7);
+        assert_eq!(pool.pin_count(pid), Some(1));
+        drop(r);
+        assert_eq!(pool.pin_count(pid), Some(0));
+
+        // latch đã nhả hết: writer mới lấy được
+        pool.fetch_write(pid).unwrap();
+    }
+
+    #[test]
+    fn test_concurrent_writers_are_serialized() {
+        let pool = BufferPool::new(Box::new(MemPager::new()), 4).unwrap();
+        let pid = new_pages(&pool, 1)[0];
+        let threads = 4;
+        let rounds = 100;
+
+        std::thread::scope(|s| {
+            for _ in 0..threads {
+                s.spawn(|| {
+                    for _ in 0..rounds {
+                        let mut w = pool.fetch_write(pid).unwrap();
+                        let data = w.data_mut();
+                        let n = u32::from_le_bytes(data[4..8].try_into().unwrap());
+                        data[4..8].copy_from_slice(&(n + 1).to_le_bytes());
+                        drop(w);
+
+                        let r = pool.fetch_read(pid).unwrap();
+                        assert!(u32::from_le_bytes(r.data()[4..8].try_into().unwrap()) > 0);
+                    }
+                });
+            }
+        });
+
+        let r = pool.fetch_read(pid).unwrap();
+        let n = u32::from_le_bytes(r.data()[4..8].try_into().unwrap());
+        assert_eq!(n, threads * rounds);
+        drop(r);
+        assert_eq!(pool.pin_count(pid), Some(0));
+    }
+
+    /// Chặn 1 lần I/O (read hoặc write) của 1 page tới khi test mở cổng.
+    #[derive(Default)]
+    struct Gate {
+        pid: Mutex<Option<PageId>>,
+        entered: AtomicBool,
+        open: AtomicBool,
+        /// I/O bị chặn trả lỗi khi cổng mở.
+        fail: AtomicBool,
+    }
+
+    impl Gate {
+        fn arm(&self, pid: PageId, fail: bool) {
+            self.fail.store(fail, Ordering::SeqCst);
+            *lock(&self.pid) = Some(pid);
+        }
+
+        fn pass(&self, pid: PageId) -> DbResult<()> {
+            {
+                let mut armed = lock(&self.pid);
+                if *armed != Some(pid) {
+                    return Ok(());
+                }
+                *armed = None;
+            }
+            self.entered.store(true, Ordering::SeqCst);
+            while !self.open.load(Ordering::SeqCst) {
+                std::thread::sleep(Duration::from_millis(1));
+            }
+            if self.fail.load(Ordering::SeqCst) {
+                return Err(DbError::Corruption("gated read failed"));
+            }
+            Ok(())
+        }
+
+        fn wait_entered(&self) {
+            while !self.entered.load(Ordering::SeqCst) {
+                std::thread::sleep(Duration::from_millis(1));
+            }
+        }
+    }
+
+    struct GatedPager {
+        inner: MemPager,
+        gate: Arc<Gate>,
+    }
+
+    impl Pager for GatedPager {
+        fn read_page(&mut self, pid: PageId, out: &mut [u8]) -> DbResult<()> {
+            self.gate.pass(pid)?;
+            self.inner.read_page(pid, out)
+        }
+        fn write_page(&mut self, pid: PageId, buf: &[u8]) -> DbResult<()> {
+            self.gate.pass(pid)?;
+            self.inner.write_page(pid, buf)
+        }
+        fn alloc_page(&mut self) -> DbResult<PageId> {
+            self.inner.alloc_page()
+        }
+        fn free_page(&mut self, pid: PageId) -> DbResult<()> {
+            self.inner.free_page(pid)
+        }
+        fn flush(&mut self) -> DbResult<()> {
+            self.inner.flush()
+        }
+        fn num_pages(&mut self) -> DbResult<u64> {
+            self.inner.num_pages()
+        }
+    }
+
+    /// Pool 2 frame trên pager có sẵn 3 page (byte 0 = số thứ tự page).
+    fn gated_pool() -> (BufferPool, Arc<Gate>, Vec<PageId>) {
+        let mut inner = MemPager::new();
+        let pids: Vec<PageId> = (0..3)
+            .map(|i| {
+                let pid = inner.alloc_page().unwrap();
+                inner.write_page(pid, &vec![i as u8; PAGE_SIZE]).unwrap();
+                pid
+            })
+            .collect();
+        let gate = Arc::new(Gate::default());
+        let pager = GatedPager {
+            inner,
+            gate: Arc::clone(&gate),
+        };
+        (BufferPool::new(Box::new(pager), 2).unwrap(), gate, pids)
+    }
+
+    #[test]
+    fn test_miss_does_not_block_other_fetches() {
+        let (pool, gate, pids) = gated_pool();
+        drop(pool.fetch_read(pids[1]).unwrap());
+        gate.arm(pids[0], false);
+
+        std::thread::scope(|s| {
+            let loader = s.spawn(|| pool.fetch_read(pids[0]).unwrap().data()[0]);
+            gate.wait_entered();
+            // hit trên page khác không chờ I/O của loader
+            assert_eq!(pool.fetch_read(pids[1]).unwrap().data()[0], 1);
+            // fetch cùng page chờ load xong, không đọc lại
+            let waiter = s.spawn(|| pool.fetch_read(pids[0]).unwrap().data()[0]);
+            while pool.pin_count(pids[0]) != Some(2) {
+                std::thread::yield_now();
+            }
+            gate.open.store(true, Ordering::SeqCst);
+            assert_eq!(loader.join().unwrap(), 0);
+            assert_eq!(waiter.join().unwrap(), 0);
+        });
+        assert_eq!(pool.stats().misses, 2);
+        assert_eq!(pool.pin_count(pids[0]), Some(0));
+    }
+
+    #[test]
+    fn test_dirty_victim_written_without_state_lock() {
+        let (pool, gate, pids) = gated_pool();
+        pool.fetch_write(pids[0]).unwrap().data_mut()[0] = 9;
+        drop(pool.fetch_read(pids[1]).unwrap());
+        gate.arm(pids[0], false);
+
+        std::thread::scope(|s| {
+            // evict page 0 (LRU, dirty) để load page 2
+            let loader = s.spawn(|| pool.fetch_read(pids[2]).unwrap().data()[0]);
+            gate.wait_entered();
+            assert_eq!(pool.fetch_read(pids[1]).unwrap().data()[0], 1);
+            gate.open.store(true, Ordering::SeqCst);
+            assert_eq!(loader.join().unwrap(), 2);
+        });
+        assert!(!pool.is_resident(pids[0]));
+        assert_eq!(pool.fetch_read(pids[0]).unwrap().data()[0], 9);
+        assert_eq!(pool.stats().writebacks, 1);
+    }
+
+    #[test]
+    fn test_prefetched_page_waits_for_readahead() {
+        let mut inner = MemPager::new();
+        let pids: Vec<PageId> = (0..4)
+            .map(|i| {
+                let pid = inner.alloc_page().unwrap();
+                inner.write_page(pid, &vec![i as u8; PAGE_SIZE]).unwrap();
+                pid
+            })
+            .collect();
+        let gate = Arc::new(Gate::default());
+        let pager = GatedPager {
+            inner,
+            gate: Arc::clone(&gate),
+        };
+        let config = BufferPoolConfig {
+            frames: 16,
+            readahead_pages: 4,
+            scan_ring_frames: 4,
+            ..BufferPoolConfig::default()
+        };
+        let pool = BufferPool::with_config(Box::new(pager), config).unwrap();
+        gate.arm(pids[0], false);
+
+        std::thread::scope(|s| {
+            let scan = s.spawn(|| {
+                pool.fetch_read_with(pids[0], AccessHint::Sequential)
+                    .unwrap()
+                    .data()[0]
+            });
+            gate.wait_entered();
+            // page trong cửa sổ readahead đã được reserve trước khi đọc
+            assert!(pool.is_resident(pids[1]));
+            let writer = s.spawn(|| {
+                pool.fetch_write(pids[1]).unwrap().data_mut()[0] = 9;
+                pool.flush_page(pids[1]).unwrap();
+            });
+            // writer chờ readahead xong chứ không tự đọc page rồi bị ghi đè bản cũ
+            while pool.pin_count(pids[1]) != Some(2) {
+                std::thread::yield_now();
+            }
+            gate.open.store(true, Ordering::SeqCst);
+            assert_eq!(scan.join().unwrap(), 0);
+            writer.join().unwrap();
+        });
+        assert_eq!(pool.stats().prefetched, 3);
+        assert_eq!(pool.fetch_read(pids[1]).unwrap().data()[0], 9);
+        for pid in &pids {
+            assert_eq!(pool.pin_count(*pid), Some(0));
+        }
+    }
+
+    #[test]
+    fn test_failed_load_is_retried_by_waiter() {
+        let (pool, gate, pids) = gated_pool();
+        gate.arm(pids[0], true);
+
+        std::thread::scope(|s| {
+            let loader = s.spawn(|| pool.fetch_read(pids[0]).map(|g| g.data()[0]));
+            gate.wait_entered();
+            let waiter = s.spawn(|| pool.fetch_read(pids[0]).map(|g| g.data()[0]));
+            while pool.pin_count(pids[0]) != Some(2) {
+                std::thread::yield_now();
+            }
+            gate.open.store(true, Ordering::SeqCst);
+            assert!(loader.join().unwrap().is_err());
+            // load lỗi không để lại frame hỏng: waiter tự load lại
+            assert_eq!(waiter.join().unwrap().unwrap(), 0);
+        });
+        assert_eq!(pool.pin_count(pids[0]), Some(0));
+        // frame của load lỗi đã về free list: cả 2 frame dùng được
+        drop(pool.fetch_read(pids[1]).unwrap());
+        drop(pool.fetch_read(pids[2]).unwrap());
+        assert_eq!(pool.stats().evictions, 1);
+    }
+
+    /// Pager ghi lại thứ tự page được write.
+    struct RecordingPager {
+        inner: MemPager,
+        written: Arc<Mutex<Vec<PageId>>>,
+    }
+
+    impl Pager for RecordingPager {
+        fn read_page(&mut self, pid: PageId, out: &mut [u8]) -> DbResult<()> {
+            self.inner.read_page(pid, out)
+        }
+        fn write_page(&mut self, pid: PageId, buf: &[u8]) -> DbResult<()> {
+            lock(&self.written).push(pid);
+            self.inner.write_page(pid, buf)
+        }
+        fn alloc_page(&mut self) -> DbResult<PageId> {
+            self.inner.alloc_page()
+        }
+        fn free_page(&mut self, pid: PageId) -> DbResult<()> {
+            self.inner.free_page(pid)
+        }
+        fn flush(&mut self) -> DbResult<()> {
+            self.inner.flush()
+        }
+        fn num_pages(&mut self) -> DbResult<u64> {
+            self.inner.num_pages()
+        }
+    }
+
+    fn recording_pool(frames: usize) -> (BufferPool, Arc<Mutex<Vec<PageId>>>) {
+        let written = Arc::new(Mutex::new(Vec::new()));
+        let pager = RecordingPager {
+            inner: MemPager::new(),
+            written: Arc::clone(&written),
+        };
+        (BufferPool::new(Box::new(pager), frames).unwrap(), written)
+    }
+
+    #[test]
+    fn test_flush_all_in_page_id_order() {
+        let (pool, written) = recording_pool(8);
+        let pids = new_pages(&pool, 5);
+        // làm dirty theo thứ tự ngược
+        for pid in pids.iter().rev() {
+            pool.fetch_write(*pid).unwrap().data_mut()[2] = 1;
+        }
+        assert_eq!(pool.dirty_count(), 5);
+
+        assert_eq!(pool.flush_all().unwrap(), 5);
+        assert_eq!(*lock(&written), pids);
+        assert_eq!(pool.dirty_count(), 0);
+        assert_eq!(pool.flush_all().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_drop_flushes_dirty_pages() {
+        let (pool, written) = recording_pool(8);
+        let pids = new_pages(&pool, 3);
+        pool.flush_all().unwrap();
+        lock(&written).clear();
+        pool.fetch_write(pids[2]).unwrap().data_mut()[2] = 1;
+        pool.fetch_write(pids[0]).unwrap().data_mut()[2] = 1;
+
+        drop(pool);
+        assert_eq!(*lock(&written), vec![pids[0], pids[2]]);
+    }
+
+    #[test]
+    fn test_dirty_round_threshold_and_pinned_pages() {
+        let (pool, written) = recording_pool(4);
+        let pids = new_pages(&pool, 2);
+
+        // 2/4 dirty < 0.75 -> không ghi
+        assert_eq!(pool.write_dirty_round(0.75, 10).unwrap(), 0);
+
+        // page đang bị pin bị bỏ qua
+        let w = pool.fetch_write(pids[0]).unwrap();
+        assert_eq!(pool.write_dirty_round(0.5, 10).unwrap(), 1);
+        assert_eq!(*lock(&written), vec![pids[1]]);
+        drop(w);
+
+        assert_eq!(pool.write_dirty_round(0.0, 10).unwrap(), 1);
+        assert_eq!(pool.stats().background_writes, 2);
+    }
+
+    #[test]
+    fn test_background_writer_cleans_pool() {
+        let (pool, _) = recording_pool(8);
+        let pool = Arc::new(pool);
+        new_pages(&pool, 6);
+
+        let writer = BackgroundWriter::start(
+            &pool,
+            BackgroundWriterConfig {
+                interval: Duration::from_millis(1),
+                dirty_ratio: 0.5,
+                max_pages_per_round: 2,
+            },
+        );
+        let deadline = Instant::now() + Duration::from_secs(5);
+        // 6/8 dirty -> ghi dần tới khi dưới ngưỡng 4/8
+        while pool.dirty_count() >= 4 {
+            assert!(
+                Instant::now() < deadline,
+                "background writer made no progress"
+            );
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        writer.stop();
+        assert!(pool.stats().background_writes >= 2);
+    }
+
+    #[test]
+    fn test_sequential_hint_reads_ahead_in_ring() {
+        let config = BufferPoolConfig {
+            frames: 16,
+            readahead_pages: 4,
+            scan_ring_frames: 4,
+            ..BufferPoolConfig::default()
+        };
+        let (pool, reads) = prefilled_pool(config, 40);
+
+        // working set nóng, truy cập không tuần tự
+        let hot: Vec<PageId> = [34, 31, 33, 32].into_iter().map(PageId).collect();
+        for pid in &hot {
+            pool.fetch_read(*pid).unwrap();
+        }
+        let before = reads.load(Ordering::Relaxed);
+
+        // scan page 1..=24: mỗi lần đọc 4 page
+        for i in 1..=24u32 {
+            let g = pool
+                .fetch_read_with(PageId(i), AccessHint::Sequential)
+                .unwrap();
+            assert_eq!(g.data()[0], (i - 1) as u8);
+        }
+        assert_eq!(reads.load(Ordering::Relaxed) - before, 6);
+        assert_eq!(pool.stats().prefetched, 18);
+
+        // scan chỉ dùng ring: working set vẫn nằm trong pool
+        for pid in &hot {
+            assert!(pool.is_resident(*pid));
+        }
+    }
+
+    #[test]
+    fn test_sequential_pattern_detected() {
+        let config = BufferPoolConfig {
+            frames: 16,
+            readahead_pages: 4,
+            scan_ring_frames: 4,
+            ..BufferPoolConfig::default()
+        };
+        let (pool, reads) = prefilled_pool(config, 20);
+
+        for i in 1..=3 {
+            pool.fetch_read(PageId(i)).unwrap();
+        }
+        // fetch thứ 3 liên tiếp kích hoạt readahead cho page 3..6
+        assert_eq!(pool.stats().prefetched, 3);
+        for i in 4..=6 {
+            pool.fetch_read(PageId(i)).unwrap();
+        }
+        assert_eq!(reads.load(Ordering::Relaxed), 3);
+
+        // readahead không đọc quá page cuối
+        for i in 17..=20 {
+            pool.fetch_read(PageId(i)).unwrap();
+        }
+        assert!(pool.fetch_read(PageId(21)).is_err());
+    }
+
+    #[test]
+    fn test_resize_shrinks_clean_pages_first() {
+        let (pool, _, writes) = counting_pool(4);
+        let pids = new_pages(&pool, 4);
+        pool.flush_page(pids[1]).unwrap();
+        pool.flush_page(pids[3]).unwrap();
+        let before = writes.load(Ordering::Relaxed);
+
+        pool.resize(2).unwrap();
+        assert_eq!(pool.capacity(), 2);
+        assert_eq!(writes.load(Ordering::Relaxed), before);
+        assert!(pool.is_resident(pids[0]) && pool.is_resident(pids[2]));
+        assert!(!pool.is_resident(pids[1]) && !pool.is_resident(pids[3]));
+
+        // hết page clean -> ghi page dirty rồi mới bỏ
+        pool.resize(1).unwrap();
+        assert_eq!(writes.load(Ordering::Relaxed), before + 1);
+
+        pool.resize_bytes(3 * PAGE_SIZE).unwrap();
+        assert_eq!(pool.capacity(), 3);
+        for pid in &pids[..3] {
+            pool.fetch_read(*pid).unwrap();
+        }
+        assert!(pool.resize(0).is_err());
+
+        let stats = pool.stats();
+        assert_eq!(stats.frames, 3);
+        assert_eq!(stats.memory_bytes, 3 * PAGE_SIZE);
+        assert_eq!(stats.peak_memory_bytes, 4 * PAGE_SIZE);
+        assert_eq!(stats.resident_pages, 3);
+        assert_eq!(stats.peak_resident_pages, 4);
+
+        pool.reset_stats();
+        assert_eq!(pool.stats().peak_memory_bytes, 3 * PAGE_SIZE);
+    }
+
+    #[test]
+    fn test_shrink_waits_for_pins() {
+        let pool = BufferPool::new(Box::new(MemPager::new()), 2).unwrap();
+        let a = pool.new_page().unwrap();
+        let b = pool.new_page().unwrap();
+        let done = AtomicBool::new(false);
+
+        std::thread::scope(|s| {
+            s.spawn(|| {
+                pool.resize(1).unwrap();
+                done.store(true, Ordering::SeqCst);
+            });
+            std::thread::sleep(Duration::from_millis(20));
+            assert!(!done.load(Ordering::SeqCst));
+            drop(a);
+        });
+        assert!(done.load(Ordering::SeqCst));
+        assert_eq!(pool.capacity(), 1);
+        assert!(pool.is_resident(b.page_id()));
+    }
+
+    #[test]
+    fn test_pools_share_memory_budget() {
+        let budget = MemoryBudget::new(6 * PAGE_SIZE);
+        let config = BufferPoolConfig::with_bytes(4 * PAGE_SIZE);
+        let a = BufferPool::with_budget(Box::new(MemPager::new()), config, Arc::clone(&budget))
+            .unwrap();
+        assert!(matches!(
+            BufferPool::with_budget(Box::new(MemPager::new()), config, Arc::clone(&budget)),
+            Err(DbError::NoSpace(_))
+        ));
+
+        a.resize(2).unwrap();
+        let b = BufferPool::with_budget(Box::new(MemPager::new()), config, Arc::clone(&budget))
+            .unwrap();
+        assert_eq!(budget.stats().used_bytes, 6 * PAGE_SIZE);
+        // grow thất bại thì pool giữ nguyên kích thước
+        assert!(a.resize(3).is_err());
+        assert_eq!(a.capacity(), 2);
+
+        drop(b);
+        a.resize(3).unwrap();
+        let stats = budget.stats();
+        assert_eq!(stats.used_bytes, 3 * PAGE_SIZE);
+        assert_eq!(stats.peak_bytes, 6 * PAGE_SIZE);
+        drop(a);
+        assert_eq!(budget.stats().used_bytes, 0);
+    }
+
+    #[test]
+    fn test_all_pinned() {
+        let pool = BufferPool::new(Box::new(MemPager::new()), 2).unwrap();
+        let _a = pool.new_page().unwrap();
+        let _b = pool.new_page().unwrap();
+        assert!(matches!(pool.new_page(), Err(DbError::NoSpace(_))));
+    }
+
+    #[test]
+    fn test_flush_and_delete() {
+        let (pool, _, writes) = counting_pool(2);
+        let pid = new_pages(&pool, 1)[0];
+        assert!(pool.flush_page(pid).unwrap());
+        assert!(!pool.flush_page(pid).unwrap());
+        assert_eq!(writes.load(Ordering::Relaxed), 1);
+
+        let g = pool.fetch_read(pid).unwrap();
+        assert!(pool.delete_page(pid).is_err());
+        drop(g);
+        pool.delete_page(pid).unwrap();
+        assert!(!pool.is_resident(pid));
+        // pager tái dùng page id đã free
+        assert_eq!(pool.new_page().unwrap().page_id(), pid);
+    }
+}
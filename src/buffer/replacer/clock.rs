@@ -0,0 +1,87 @@
+use crate::PageId;
+
+use super::{FrameId, Replacer};
+
+/// Clock (second chance): mỗi frame có reference bit, kim quét vòng tròn;
+/// frame có bit = 1 được tha 1 vòng (bit về 0), frame bit = 0 bị evict.
+pub struct ClockReplacer {
+    hand: usize,
+    present: Vec<bool>,
+    referenced: Vec<bool>,
+    evictable: Vec<bool>,
+}
+
+impl ClockReplacer {
+    pub fn new(frames: usize) -> Self {
+        ClockReplacer {
+            hand: 0,
+            present: vec![false; frames],
+            referenced: vec![false; frames],
+            evictable: vec![false; frames],
+        }
+    }
+
+    fn clear(&mut self, frame: FrameId) {
+        self.present[frame] = false;
+        self.referenced[frame] = false;
+        self.evictable[frame] = false;
+    }
+}
+
+impl Replacer for ClockReplacer {
+    fn name(&self) -> &'static str {
+        "clock"
+    }
+
+    fn record_access(&mut self, frame: FrameId, _pid: PageId) {
+        self.present[frame] = true;
+        self.referenced[frame] = true;
+    }
+
+    fn set_evictable(&mut self, frame: FrameId, evictable: bool) {
+        self.evictable[frame] = evictable;
+    }
+
+    fn evict(&mut self) -> Option<FrameId> {
+        let n = self.present.len();
+        // vòng 1 có thể chỉ xoá reference bit, vòng 2 chắc chắn tìm được victim
+        for _ in 0..2 * n {
+            let frame = self.hand;
+            self.hand = (self.hand + 1) % n;
+            if !self.present[frame] || !self.evictable[frame] {
+                continue;
+            }
+            if self.referenced[frame] {
+                self.referenced[frame] = false;
+                continue;
+            }
+            self.clear(frame);
+            return Some(frame);
+        }
+        None
+    }
+
+    fn remove(&mut self, frame: FrameId) {
+        self.clear(frame);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_second_chance() {
+        let mut r = ClockReplacer::new(3);
+        for f in 0..3 {
+            r.record_access(f, PageId(f as u32));
+            r.set_evictable(f, true);
+        }
+        // vòng 1 xoá hết bit, frame 0 là victim đầu tiên
+        assert_eq!(r.evict(), Some(0));
+        // frame 1 được truy cập lại -> được tha, frame 2 bị evict
+        r.record_access(1, PageId(1));
+        assert_eq!(r.evict(), Some(2));
+        assert_eq!(r.evict(), Some(1));
+    }
+}
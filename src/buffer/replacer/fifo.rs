@@ -0,0 +1,72 @@
+use std::collections::VecDeque;
+
+use crate::PageId;
+
+use super::{FrameId, Replacer};
+
+/// FIFO: victim là frame evictable được load sớm nhất, cache hit không đổi thứ tự.
+pub struct FifoReplacer {
+    queue: VecDeque<FrameId>,
+    queued: Vec<bool>,
+    evictable: Vec<bool>,
+}
+
+impl FifoReplacer {
+    pub fn new(frames: usize) -> Self {
+        FifoReplacer {
+            queue: VecDeque::with_capacity(frames),
+            queued: vec![false; frames],
+            evictable: vec![false; frames],
+        }
+    }
+}
+
+impl Replacer for FifoReplacer {
+    fn name(&self) -> &'static str {
+        "fifo"
+    }
+
+    fn record_access(&mut self, frame: FrameId, _pid: PageId) {
+        if !self.queued[frame] {
+            self.queued[frame] = true;
+            self.queue.push_back(frame);
+        }
+    }
+
+    fn set_evictable(&mut self, frame: FrameId, evictable: bool) {
+        self.evictable[frame] = evictable;
+    }
+
+    fn evict(&mut self) -> Option<FrameId> {
+        let pos = self.queue.iter().position(|&f| self.evictable[f])?;
+        let frame = self.queue.remove(pos)?;
+        self.queued[frame] = false;
+        self.evictable[frame] = false;
+        Some(frame)
+    }
+
+    fn remove(&mut self, frame: FrameId) {
+        if self.queued[frame] {
+            self.queue.retain(|&f| f != frame);
+        }
+        self.queued[frame] = false;
+        self.evictable[frame] = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hits_do_not_reorder() {
+        let mut r = FifoReplacer::new(3);
+        for f in 0..3 {
+            r.record_access(f, PageId(f as u32));
+            r.set_evictable(f, true);
+        }
+        r.record_access(0, PageId(0));
+        assert_eq!(r.evict(), Some(0));
+        assert_eq!(r.evict(), Some(1));
+    }
+}
@@ -0,0 +1,98 @@
+use std::collections::BTreeMap;
+
+use crate::PageId;
+
+use super::{FrameId, Replacer};
+
+/// LRU: victim là frame evictable có lần truy cập cuối cũ nhất.
+///
+/// Mỗi frame mang tick của lần truy cập cuối; frame evictable nằm trong
+/// `BTreeMap<tick, frame>` nên evict và cập nhật đều O(log n).
+pub struct LruReplacer {
+    tick: u64,
+    stamp: Vec<Option<u64>>,
+    evictable: Vec<bool>,
+    order: BTreeMap<u64, FrameId>,
+}
+
+impl LruReplacer {
+    pub fn new(frames: usize) -> Self {
+        LruReplacer {
+            tick: 0,
+            stamp: vec![None; frames],
+            evictable: vec![false; frames],
+            order: BTreeMap::new(),
+        }
+    }
+
+    fn unlink(&mut self, frame: FrameId) {
+        if let Some(t) = self.stamp[frame] {
+            self.order.remove(&t);
+        }
+    }
+
+    fn link(&mut self, frame: FrameId) {
+        if let Some(t) = self.stamp[frame] {
+            self.order.insert(t, frame);
+        }
+    }
+}
+
+impl Replacer for LruReplacer {
+    fn name(&self) -> &'static str {
+        "lru"
+    }
+
+    fn record_access(&mut self, frame: FrameId, _pid: PageId) {
+        self.unlink(frame);
+        self.tick += 1;
+        self.stamp[frame] = Some(self.tick);
+        if self.evictable[frame] {
+            self.link(frame);
+        }
+    }
+
+    fn set_evictable(&mut self, frame: FrameId, evictable: bool) {
+        if self.evictable[frame] == evictable {
+            return;
+        }
+        self.evictable[frame] = evictable;
+        if evictable {
+            self.link(frame);
+        } else {
+            self.unlink(frame);
+        }
+    }
+
+    fn evict(&mut self) -> Option<FrameId> {
+        let (_, frame) = self.order.pop_first()?;
+        self.stamp[frame] = None;
+        self.evictable[frame] = false;
+        Some(frame)
+    }
+
+    fn remove(&mut self, frame: FrameId) {
+        self.unlink(frame);
+        self.stamp[frame] = None;
+        self.evictable[frame] = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lru_order() {
+        let mut lru = LruReplacer::new(4);
+        for f in 0..3 {
+            lru.record_access(f, PageId(f as u32));
+            lru.set_evictable(f, true);
+        }
+        lru.record_access(0, PageId(0)); // 0 thành MRU
+        lru.set_evictable(1, false);
+        assert_eq!(lru.evict(), Some(2));
+        assert_eq!(lru.evict(), Some(0));
+        assert_eq!(lru.evict(), None);
+    }
+}
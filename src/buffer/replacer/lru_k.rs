@@ -0,0 +1,99 @@
+use std::collections::VecDeque;
+
+use crate::PageId;
+
+use super::{FrameId, Replacer};
+
+/// LRU-K: victim là frame có backward K-distance lớn nhất
+/// (lần truy cập thứ K gần nhất cũ nhất).
+///
+/// Frame chưa đủ K lần truy cập có K-distance = +inf, bị evict trước; giữa chúng
+/// chọn frame có lần truy cập cũ nhất. Nhờ vậy page chỉ được scan qua 1 lần không
+/// đẩy được page hay dùng ra khỏi pool.
+pub struct LruKReplacer {
+    k: usize,
+    tick: u64,
+    history: Vec<VecDeque<u64>>,
+    evictable: Vec<bool>,
+}
+
+impl LruKReplacer {
+    pub fn new(frames: usize, k: usize) -> Self {
+        LruKReplacer {
+            k,
+            tick: 0,
+            history: vec![VecDeque::new(); frames],
+            evictable: vec![false; frames],
+        }
+    }
+}
+
+impl Replacer for LruKReplacer {
+    fn name(&self) -> &'static str {
+        "lru-k"
+    }
+
+    fn record_access(&mut self, frame: FrameId, _pid: PageId) {
+        self.tick += 1;
+        let h = &mut self.history[frame];
+        if h.len() == self.k {
+            h.pop_front();
+        }
+        h.push_back(self.tick);
+    }
+
+    fn set_evictable(&mut self, frame: FrameId, evictable: bool) {
+        self.evictable[frame] = evictable;
+    }
+
+    fn evict(&mut self) -> Option<FrameId> {
+        // key nhỏ hơn = nên evict trước: (đã đủ K lần?, timestamp cũ nhất trong history)
+        let frame = (0..self.history.len())
+            .filter(|&f| self.evictable[f] && !self.history[f].is_empty())
+            .min_by_key(|&f| {
+                let h = &self.history[f];
+                (h.len() == self.k, h.front().copied())
+            })?;
+        self.remove(frame);
+        Some(frame)
+    }
+
+    fn remove(&mut self, frame: FrameId) {
+        self.history[frame].clear();
+        self.evictable[frame] = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_infinite_distance_first() {
+        let mut r = LruKReplacer::new(3, 2);
+        // frame 0 được truy cập 2 lần, frame 1 và 2 mỗi frame 1 lần
+        r.record_access(0, PageId(0));
+        r.record_access(1, PageId(1));
+        r.record_access(0, PageId(0));
+        r.record_access(2, PageId(2));
+        for f in 0..3 {
+            r.set_evictable(f, true);
+        }
+        assert_eq!(r.evict(), Some(1));
+        assert_eq!(r.evict(), Some(2));
+        assert_eq!(r.evict(), Some(0));
+    }
+
+    #[test]
+    fn test_k_distance_order() {
+        let mut r = LruKReplacer::new(2, 2);
+        // history: frame 0 = [1, 4], frame 1 = [2, 3] -> frame 0 có K-distance lớn hơn
+        r.record_access(0, PageId(0));
+        r.record_access(1, PageId(1));
+        r.record_access(1, PageId(1));
+        r.record_access(0, PageId(0));
+        r.set_evictable(0, true);
+        r.set_evictable(1, true);
+        assert_eq!(r.evict(), Some(0));
+    }
+}
@@ -0,0 +1,111 @@
+//! Page replacement policy của buffer pool.
+//!
+//! Pool báo cho replacer mỗi lần frame được truy cập và mỗi lần frame đổi trạng thái
+//! evictable (pin_count về 0 / rời 0). Replacer chỉ được chọn victim trong các frame
+//! evictable.
+
+mod clock;
+mod fifo;
+mod lru;
+mod lru_k;
+mod two_q;
+
+pub use clock::ClockReplacer;
+pub use fifo::FifoReplacer;
+pub use lru::LruReplacer;
+pub use lru_k::LruKReplacer;
+pub use two_q::TwoQReplacer;
+
+use crate::{DbError, DbResult, PageId};
+
+use super::FrameId;
+
+pub trait Replacer: Send {
+    /// Tên policy (dùng trong stats).
+    fn name(&self) -> &'static str;
+
+    /// Frame `frame` (đang chứa `pid`) vừa được truy cập: load mới hoặc cache hit.
+    fn record_access(&mut self, frame: FrameId, pid: PageId);
+
+    /// Frame có được phép evict hay không (pin_count == 0).
+    fn set_evictable(&mut self, frame: FrameId, evictable: bool);
+
+    /// Chọn và bỏ 1 victim trong các frame evictable.
+    fn evict(&mut self) -> Option<FrameId>;
+
+    /// Frame bị bỏ khỏi pool mà không qua evict (page bị delete): xoá lịch sử.
+    fn remove(&mut self, frame: FrameId);
+}
+
+/// Policy chọn lúc mở pool.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReplacerKind {
+    Clock,
+    #[default]
+    Lru,
+    /// LRU-K với K lần truy cập gần nhất (K >= 1).
+    LruK(usize),
+    TwoQ,
+    /// FIFO theo thứ tự load, deterministic (dùng cho test).
+    Fifo,
+}
+
+impl ReplacerKind {
+    pub fn build(self, frames: usize) -> DbResult<Box<dyn Replacer>> {
+        Ok(match self {
+            ReplacerKind::Clock => Box::new(ClockReplacer::new(frames)),
+            ReplacerKind::Lru => Box::new(LruReplacer::new(frames)),
+            ReplacerKind::LruK(0) => return Err(DbError::InvalidArgument("lru-k needs k >= 1")),
+            ReplacerKind::LruK(k) => Box::new(LruKReplacer::new(frames, k)),
+            ReplacerKind::TwoQ => Box::new(TwoQReplacer::new(frames)),
+            ReplacerKind::Fifo => Box::new(FifoReplacer::new(frames)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: [ReplacerKind; 5] = [
+        ReplacerKind::Clock,
+        ReplacerKind::Lru,
+        ReplacerKind::LruK(2),
+        ReplacerKind::TwoQ,
+        ReplacerKind::Fifo,
+    ];
+
+    #[test]
+    fn test_only_evictable_frames_are_victims() {
+        for kind in ALL {
+            let mut r = kind.build(3).unwrap();
+            for f in 0..3 {
+                r.record_access(f, PageId(f as u32 + 1));
+            }
+            r.set_evictable(1, true);
+            assert_eq!(r.evict(), Some(1), "{}", r.name());
+            assert_eq!(r.evict(), None, "{}", r.name());
+
+            r.set_evictable(0, true);
+            r.set_evictable(2, true);
+            r.set_evictable(2, false);
+            assert_eq!(r.evict(), Some(0), "{}", r.name());
+        }
+    }
+
+    #[test]
+    fn test_removed_frame_is_forgotten() {
+        for kind in ALL {
+            let mut r = kind.build(2).unwrap();
+            r.record_access(0, PageId(1));
+            r.set_evictable(0, true);
+            r.remove(0);
+            assert_eq!(r.evict(), None, "{}", r.name());
+        }
+    }
+
+    #[test]
+    fn test_lru_k_zero_rejected() {
+        assert!(ReplacerKind::LruK(0).build(4).is_err());
+    }
+}
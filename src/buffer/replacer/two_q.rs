@@ -0,0 +1,145 @@
+use std::collections::VecDeque;
+
+use crate::PageId;
+
+use super::{FrameId, Replacer};
+
+/// Queue mà frame đang thuộc về.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Queue {
+    /// Page mới load, mới được truy cập 1 lần (FIFO).
+    A1in,
+    /// Page hot: được truy cập lại sau khi rời A1in (LRU).
+    Am,
+}
+
+/// 2Q (Johnson & Shasha, bản full): page mới vào A1in (FIFO), bị đẩy khỏi A1in thì
+/// chỉ giữ lại page id trong ghost queue A1out. Page được load lại khi còn trong
+/// A1out mới vào Am (LRU). Page chỉ scan qua 1 lần không bao giờ vào Am, nên scan
+/// lớn không đẩy được working set ra khỏi pool.
+pub struct TwoQReplacer {
+    tick: u64,
+    /// Số frame tối đa A1in giữ trước khi ưu tiên evict từ A1in.
+    kin: usize,
+    /// Số page id tối đa trong A1out.
+    kout: usize,
+    queue: Vec<Option<Queue>>,
+    /// A1in: tick lúc load; Am: tick lần truy cập cuối.
+    stamp: Vec<u64>,
+    pid: Vec<PageId>,
+    evictable: Vec<bool>,
+    a1out: VecDeque<PageId>,
+}
+
+impl TwoQReplacer {
+    pub fn new(frames: usize) -> Self {
+        TwoQReplacer {
+            tick: 0,
+            kin: (frames / 4).max(1),
+            kout: (frames / 2).max(1),
+            queue: vec![None; frames],
+            stamp: vec![0; frames],
+            pid: vec![PageId::INVALID; frames],
+            evictable: vec![false; frames],
+            a1out: VecDeque::new(),
+        }
+    }
+
+    /// Frame evictable cũ nhất trong queue `q`.
+    fn oldest(&self, q: Queue) -> Option<FrameId> {
+        (0..self.queue.len())
+            .filter(|&f| self.queue[f] == Some(q) && self.evictable[f])
+            .min_by_key(|&f| self.stamp[f])
+    }
+
+    fn remember(&mut self, pid: PageId) {
+        if self.a1out.len() == self.kout {
+            self.a1out.pop_front();
+        }
+        self.a1out.push_back(pid);
+    }
+}
+
+impl Replacer for TwoQReplacer {
+    fn name(&self) -> &'static str {
+        "2q"
+    }
+
+    fn record_access(&mut self, frame: FrameId, pid: PageId) {
+        self.tick += 1;
+        match self.queue[frame] {
+            None => {
+                self.pid[frame] = pid;
+                self.stamp[frame] = self.tick;
+                if let Some(pos) = self.a1out.iter().position(|&p| p == pid) {
+                    self.a1out.remove(pos);
+                    self.queue[frame] = Some(Queue::Am);
+                } else {
+                    self.queue[frame] = Some(Queue::A1in);
+                }
+            }
+            // truy cập lại trong A1in thường là correlated reference, không promote
+            Some(Queue::A1in) => {}
+            Some(Queue::Am) => self.stamp[frame] = self.tick,
+        }
+    }
+
+    fn set_evictable(&mut self, frame: FrameId, evictable: bool) {
+        self.evictable[frame] = evictable;
+    }
+
+    fn evict(&mut self) -> Option<FrameId> {
+        let a1in_len = self
+            .queue
+            .iter()
+            .filter(|q| **q == Some(Queue::A1in))
+            .count();
+        let frame = if a1in_len > self.kin {
+            self.oldest(Queue::A1in).or_else(|| self.oldest(Queue::Am))
+        } else {
+            self.oldest(Queue::Am).or_else(|| self.oldest(Queue::A1in))
+        }?;
+
+        if self.queue[frame] == Some(Queue::A1in) {
+            self.remember(self.pid[frame]);
+        }
+        self.remove(frame);
+        Some(frame)
+    }
+
+    fn remove(&mut self, frame: FrameId) {
+        self.queue[frame] = None;
+        self.pid[frame] = PageId::INVALID;
+        self.evictable[frame] = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(r: &mut TwoQReplacer, frame: FrameId, pid: u32) {
+        r.record_access(frame, PageId(pid));
+        r.set_evictable(frame, true);
+    }
+
+    #[test]
+    fn test_scan_does_not_evict_hot_page() {
+        let mut r = TwoQReplacer::new(4);
+        load(&mut r, 0, 100);
+        for f in 1..4 {
+            load(&mut r, f, f as u32);
+        }
+        // page 100 rời A1in vào A1out
+        assert_eq!(r.evict(), Some(0));
+        // load lại khi còn trong A1out -> vào Am
+        load(&mut r, 0, 100);
+
+        // scan 10 page: chỉ các frame A1in bị evict
+        for pid in 10..20 {
+            let victim = r.evict().unwrap();
+            assert_ne!(victim, 0);
+            load(&mut r, victim, pid);
+        }
+    }
+}
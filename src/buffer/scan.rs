@@ -0,0 +1,129 @@
+//! Hỗ trợ scan tuần tự: phát hiện pattern đọc liên tiếp (để readahead) và ring frame
+//! riêng cho page của scan, để scan lớn không đẩy working set ra khỏi pool.
+
+use std::collections::VecDeque;
+
+use crate::PageId;
+
+use super::{FrameId, FrameMeta};
+
+/// Gợi ý của caller về pattern truy cập.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AccessHint {
+    /// Pool tự phát hiện pattern tuần tự.
+    #[default]
+    Normal,
+    /// Caller biết chắc đang scan tuần tự (table/index scan): readahead ngay,
+    /// page đi vào scan ring.
+    Sequential,
+}
+
+/// Số lần fetch page liên tiếp (pid, pid+1, ...) để coi là đang scan tuần tự.
+pub(super) const SEQ_TRIGGER: usize = 3;
+
+/// Theo dõi page id được fetch gần nhất.
+#[derive(Debug, Default)]
+pub(super) struct SeqDetector {
+    last: Option<PageId>,
+    run: usize,
+}
+
+impl SeqDetector {
+    /// Ghi nhận 1 lần fetch, trả về true nếu đang trong chuỗi tuần tự.
+    pub(super) fn observe(&mut self, pid: PageId) -> bool {
+        match self.last {
+            // fetch lại đúng page vừa đọc (vd đọc nhiều tuple cùng page) không cắt chuỗi
+            Some(last) if last == pid => {}
+            Some(last) if last.as_u32().checked_add(1) == Some(pid.as_u32()) => self.run += 1,
+            _ => self.run = 1,
+        }
+        self.last = Some(pid);
+        self.run >= SEQ_TRIGGER
+    }
+}
+
+/// Tập frame đang chứa page của scan, theo thứ tự load (cũ nhất ở đầu).
+#[derive(Debug)]
+pub(super) struct ScanRing {
+    cap: usize,
+    frames: VecDeque<FrameId>,
+}
+
+impl ScanRing {
+    pub(super) fn new(cap: usize) -> Self {
+        ScanRing {
+            cap,
+            frames: VecDeque::with_capacity(cap),
+        }
+    }
+
+    pub(super) fn capacity(&self) -> usize {
+        self.cap
+    }
+
+    /// Đổi capacity (pool resize). Trả về các frame cũ nhất bị đẩy khỏi ring
+    /// khi ring đang giữ nhiều frame hơn capacity mới.
+    pub(super) fn set_capacity(&mut self, cap: usize) -> Vec<FrameId> {
+        self.cap = cap;
+        let excess = self.frames.len().saturating_sub(cap);
+        self.frames.drain(..excess).collect()
+    }
+
+    pub(super) fn is_full(&self) -> bool {
+        self.frames.len() >= self.cap
+    }
+
+    /// Thêm frame vào ring; ring đầy thì không thêm (frame thành frame thường).
+    pub(super) fn push(&mut self, frame: FrameId) -> bool {
+        if self.is_full() {
+            return false;
+        }
+        self.frames.push_back(frame);
+        true
+    }
+
+    pub(super) fn remove(&mut self, frame: FrameId) {
+        if let Some(pos) = self.frames.iter().position(|&f| f == frame) {
+            self.frames.remove(pos);
+        }
+    }
+
+    /// Frame cũ nhất của ring không bị pin (ứng viên tái dùng).
+    pub(super) fn oldest_unpinned(&self, meta: &[FrameMeta]) -> Option<FrameId> {
+        self.frames
+            .iter()
+            .copied()
+            .find(|&f| meta[f].pin_count == 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seq_detector() {
+        let mut d = SeqDetector::default();
+        assert!(!d.observe(PageId(5)));
+        assert!(!d.observe(PageId(6)));
+        assert!(!d.observe(PageId(6)));
+        assert!(d.observe(PageId(7)));
+        assert!(d.observe(PageId(8)));
+        assert!(!d.observe(PageId(2)));
+    }
+
+    #[test]
+    fn test_ring_reuses_oldest_unpinned() {
+        let mut ring = ScanRing::new(2);
+        let mut meta = vec![FrameMeta::default(); 3];
+        assert!(ring.push(0));
+        assert!(ring.push(1));
+        assert!(!ring.push(2));
+
+        meta[0].pin_count = 1;
+        assert_eq!(ring.oldest_unpinned(&meta), Some(1));
+        ring.remove(1);
+        assert_eq!(ring.oldest_unpinned(&meta), None);
+        assert!(!ring.is_full());
+    }
+}
@@ -0,0 +1,88 @@
+//! Background writer: thread định kỳ ghi bớt dirty page không bị pin, để lúc evict
+//! victim thường đã clean và fetch không phải chờ 1 lần ghi đồng bộ.
+
+use std::sync::{Arc, Condvar, Mutex, Weak};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use super::{lock, BufferPool};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BackgroundWriterConfig {
+    /// Khoảng nghỉ giữa 2 vòng.
+    pub interval: Duration,
+    /// Chỉ ghi khi số frame dirty >= `dirty_ratio * capacity`.
+    pub dirty_ratio: f64,
+    /// Số page ghi tối đa mỗi vòng (giới hạn I/O burst).
+    pub max_pages_per_round: usize,
+}
+
+impl Default for BackgroundWriterConfig {
+    fn default() -> Self {
+        BackgroundWriterConfig {
+            interval: Duration::from_millis(100),
+            dirty_ratio: 0.25,
+            max_pages_per_round: 64,
+        }
+    }
+}
+
+/// Handle của writer thread. Drop (hoặc `stop`) thì dừng thread và join.
+pub struct BackgroundWriter {
+    stop: Arc<(Mutex<bool>, Condvar)>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl BackgroundWriter {
+    /// Chạy writer cho `pool`. Thread chỉ giữ `Weak`: pool bị drop thì thread tự dừng.
+    pub fn start(pool: &Arc<BufferPool>, config: BackgroundWriterConfig) -> Self {
+        let stop = Arc::new((Mutex::new(false), Condvar::new()));
+        let weak = Arc::downgrade(pool);
+        let thread_stop = Arc::clone(&stop);
+        let handle = thread::spawn(move || run(weak, config, thread_stop));
+        BackgroundWriter {
+            stop,
+            handle: Some(handle),
+        }
+    }
+
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        let (flag, cond) = &*self.stop;
+        *lock(flag) = true;
+        cond.notify_all();
+        if let Some(h) = self.handle.take() {
+            // writer thread không panic trong điều kiện bình thường; nếu có thì bỏ qua
+            let _ = h.join();
+        }
+    }
+}
+
+impl Drop for BackgroundWriter {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+fn run(pool: Weak<BufferPool>, config: BackgroundWriterConfig, stop: Arc<(Mutex<bool>, Condvar)>) {
+    let (flag, cond) = &*stop;
+    loop {
+        {
+            let stopped = lock(flag);
+            let (stopped, _) = cond
+                .wait_timeout_while(stopped, config.interval, |s| !*s)
+                .unwrap_or_else(std::sync::PoisonError::into_inner);
+            if *stopped {
+                return;
+            }
+        }
+        let Some(pool) = pool.upgrade() else {
+            return;
+        };
+        // lỗi I/O: bỏ vòng này, page vẫn dirty và sẽ được ghi lại ở vòng sau / khi evict
+        let _ = pool.write_dirty_round(config.dirty_ratio, config.max_pages_per_round);
+    }
+}
@@ -6,4 +6,5 @@ pub const PAGE_SIZE: usize = 4096;
 // 12 bytes magic header
 pub const DB_MAGIC: [u8; 12] = *b"NOVADBLITE\0\0";
 
-pub const DB_VERSION: u16 = 1;
+// 2: bỏ next_pid khỏi page meta, thêm head của freelist
+pub const DB_VERSION: u16 = 2;
@@ -0,0 +1,205 @@
+//! Busy handler: quyết định làm gì khi lock của database đang bị giữ.
+
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard, TryLockError};
+use std::time::{Duration, Instant};
+
+use crate::{DbError, DbResult};
+
+/// Thời gian chờ lần đầu của `BusyHandler::Timeout`, gấp đôi sau mỗi lần thử lại.
+const INITIAL_BACKOFF: Duration = Duration::from_millis(1);
+/// Thời gian chờ tối đa giữa 2 lần thử.
+const MAX_BACKOFF: Duration = Duration::from_millis(50);
+
+/// Callback nhận số lần đã thử lấy lock (từ 1), trả `true` để thử lại.
+/// Callback tự sleep nếu muốn chờ giữa các lần thử.
+pub type BusyCallback = Arc<dyn Fn(u32) -> bool + Send + Sync>;
+
+#[derive(Clone, Default)]
+pub enum BusyHandler {
+    /// Chờ tới khi lấy được lock. Lock do chính thread đang chờ giữ thì `Database` trả
+    /// `DbError::Busy` ngay thay vì chờ mãi.
+    #[default]
+    Wait,
+    /// Thử lại với backoff tăng dần, quá `timeout` thì trả `DbError::Busy`
+    /// (`Duration::ZERO`: không chờ).
+    Timeout(Duration),
+    /// Hỏi callback sau mỗi lần lock đang bị giữ.
+    Callback(BusyCallback),
+}
+
+impl fmt::Debug for BusyHandler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BusyHandler::Wait => write!(f, "Wait"),
+            BusyHandler::Timeout(t) => write!(f, "Timeout({:?})", t),
+            BusyHandler::Callback(_) => write!(f, "Callback(..)"),
+        }
+    }
+}
+
+impl BusyHandler {
+    /// Lấy `m` theo handler; `what` là tên lock trong lỗi `Busy`.
+    /// Lock bị poison vẫn dùng tiếp được (xem `super::lock`).
+    pub(super) fn acquire<'a, T>(
+        &self,
+        m: &'a Mutex<T>,
+        what: &'static str,
+    ) -> DbResult<MutexGuard<'a, T>> {
+        let mut retry = Retry::new();
+        loop {
+            match m.try_lock() {
+                Ok(guard) => return Ok(guard),
+                Err(TryLockError::Poisoned(e)) => return Ok(e.into_inner()),
+                Err(TryLockError::WouldBlock) => {}
+            }
+            if let BusyHandler::Wait = self {
+                return Ok(super::lock(m));
+            }
+            if !retry.again(self) {
+                return Err(DbError::Busy(what));
+            }
+        }
+    }
+
+    /// Chạy `op` tới khi nó không còn trả `DbError::Busy` hoặc handler bỏ cuộc (trả lỗi
+    /// `Busy` cuối cùng). `Wait` thử lại mãi với backoff.
+    pub(super) fn retry<T>(&self, mut op: impl FnMut() -> DbResult<T>) -> DbResult<T> {
+        let mut retry = Retry::new();
+        loop {
+            match op() {
+                Err(DbError::Busy(what)) => {
+                    if !retry.again(self) {
+                        return Err(DbError::Busy(what));
+                    }
+                }
+                res => return res,
+            }
+        }
+    }
+}
+
+/// Trạng thái backoff giữa các lần thử lại.
+struct Retry {
+    start: Instant,
+    backoff: Duration,
+    attempts: u32,
+}
+
+impl Retry {
+    fn new() -> Self {
+        Retry {
+            start: Instant::now(),
+            backoff: INITIAL_BACKOFF,
+            attempts: 0,
+        }
+    }
+
+    /// Ghi nhận 1 lần thử bị busy; chờ theo handler rồi trả `true` nếu thử lại.
+    fn again(&mut self, handler: &BusyHandler) -> bool {
+        self.attempts += 1;
+        let wait = match handler {
+            BusyHandler::Wait => self.backoff,
+            BusyHandler::Timeout(timeout) => {
+                let left = timeout.saturating_sub(self.start.elapsed());
+                if left.is_zero() {
+                    return false;
+                }
+                self.backoff.min(left)
+            }
+            BusyHandler::Callback(retry) => return retry(self.attempts),
+        };
+        std::thread::sleep(wait);
+        self.backoff = (self.backoff * 2).min(MAX_BACKOFF);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[test]
+    fn test_timeout_and_callback() {
+        let m = Mutex::new(());
+        let held = m.lock().unwrap();
+
+        let t = Duration::from_millis(20);
+        let start = Instant::now();
+        let err = BusyHandler::Timeout(t)
+            .acquire(&m, "test lock")
+            .unwrap_err();
+        assert!(matches!(err, DbError::Busy("test lock")));
+        assert!(start.elapsed() >= t);
+        assert!(BusyHandler::Timeout(Duration::ZERO)
+            .acquire(&m, "test lock")
+            .is_err());
+
+        let calls = Arc::new(AtomicU32::new(0));
+        let c = calls.clone();
+        let h = BusyHandler::Callback(Arc::new(move |n| {
+            c.store(n, Ordering::SeqCst);
+            n < 3
+        }));
+        assert!(h.acquire(&m, "test lock").is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        drop(held);
+        assert!(h.acquire(&m, "test lock").is_ok());
+    }
+
+    #[test]
+    fn test_retry_until_not_busy() {
+        let mut calls = 0;
+        let res = BusyHandler::Wait.retry(|| {
+            calls += 1;
+            if calls < 3 {
+                Err(DbError::Busy("op"))
+            } else {
+                Ok(calls)
+            }
+        });
+        assert_eq!(res.unwrap(), 3);
+
+        let h = BusyHandler::Callback(Arc::new(|n| n < 2));
+        let mut calls = 0;
+        let err = h
+            .retry(|| -> DbResult<()> {
+                calls += 1;
+                Err(DbError::Busy("op"))
+            })
+            .unwrap_err();
+        assert!(matches!(err, DbError::Busy("op")));
+        assert_eq!(calls, 2);
+
+        // lỗi khác Busy trả về ngay
+        let mut calls = 0;
+        assert!(BusyHandler::Wait
+            .retry(|| -> DbResult<()> {
+                calls += 1;
+                Err(DbError::InvalidArgument("op"))
+            })
+            .is_err());
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn test_timeout_gets_lock_released_while_waiting() {
+        let m = Mutex::new(0u32);
+        let (locked_tx, locked_rx) = std::sync::mpsc::channel();
+        std::thread::scope(|s| {
+            s.spawn(|| {
+                let _held = m.lock().unwrap();
+                locked_tx.send(()).unwrap();
+                std::thread::sleep(Duration::from_millis(10));
+            });
+            locked_rx.recv().unwrap();
+            let mut g = BusyHandler::Timeout(Duration::from_secs(5))
+                .acquire(&m, "test lock")
+                .unwrap();
+            *g += 1;
+        });
+        assert_eq!(*m.lock().unwrap(), 1);
+    }
+}
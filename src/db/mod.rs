@@ -0,0 +1,167 @@
+//! Database: pager + global write lock, điểm vào của transaction.
+//!
+//! - Tối đa 1 transaction ghi tại 1 thời điểm (global lock cấp database).
+//! - Transaction ghi giữ page đã sửa trong buffer riêng; `commit` ghi tất cả xuống
+//!   pager thành 1 commit WAL / journal, nên reader không bao giờ thấy commit dở. fsync
+//!   của commit chạy sau khi nhả lock, nên commit WAL đồng thời dùng chung 1 lần fsync.
+//! - Transaction chỉ đọc không lấy write lock, chạy song song với transaction ghi. Ở
+//!   chế độ WAL nó giữ snapshot: mọi page đọc ra thuộc cùng 1 commit, kể cả khi writer
+//!   commit tiếp trong lúc đọc. Ở chế độ rollback journal nó chỉ đọc page đã commit
+//!   (không thấy page writer đang ghi hay cấp phát dở).
+//! - Lấy lock để bắt đầu transaction, đọc/ghi page, commit và checkpoint đều đi qua
+//!   `BusyHandler` (mặc định chờ tới khi lấy được); handler bỏ cuộc -> `DbError::Busy`.
+//!   Rollback luôn chờ lock để không bỏ dở việc dọn dẹp.
+//! - Thread tự chờ chính nó thì không bao giờ xong: `begin`/`checkpoint` khi chính thread
+//!   đó đang giữ write lock, hoặc checkpoint bị reader chặn khi thread đó đang giữ
+//!   snapshot, trả `DbError::Busy` ngay, không qua handler. `Transaction` không `Send`
+//!   nên lock/snapshot luôn thuộc thread đã mở transaction.
+//! - Lock chỉ có trong process: không có file lock giữa các process, mở cùng 1
+//!   database từ nhiều process (hoặc nhiều `Database` trong 1 process) không được hỗ trợ.
+
+mod busy;
+mod transaction;
+
+pub use busy::{BusyCallback, BusyHandler};
+pub use transaction::Transaction;
+
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::thread::ThreadId;
+use std::time::Duration;
+
+use crate::pager::meta::JournalMode;
+use crate::pager::open::open_pager;
+use crate::pager::pager::Pager;
+use crate::pager::sync::SyncMode;
+use crate::wal::{CheckpointInfo, CheckpointMode};
+use crate::{DbError, DbResult};
+
+pub struct Database {
+    pager: Mutex<Box<dyn Pager + Send>>,
+    /// Global write lock, transaction ghi giữ guard tới khi commit/rollback.
+    write_lock: Mutex<()>,
+    /// Thread đang giữ write lock.
+    write_owner: Mutex<Option<ThreadId>>,
+    /// Số transaction đọc đang giữ snapshot của mỗi thread.
+    snapshots: Mutex<HashMap<ThreadId, usize>>,
+    /// Đổi được khi đang có transaction, lần lấy lock sau dùng handler mới.
+    busy: Mutex<BusyHandler>,
+}
+
+impl Database {
+    /// Mở database `path` theo journal mode lưu trong page meta (database mới dùng `mode`).
+    pub fn open(path: String, mode: JournalMode, sync: SyncMode) -> DbResult<Self> {
+        Ok(Self::with_pager(open_pager(path, mode, sync)?))
+    }
+
+    /// Database trên pager có sẵn; pager phải hỗ trợ `rollback` để transaction ghi
+    /// rollback được.
+    pub fn with_pager(pager: Box<dyn Pager + Send>) -> Self {
+        Database {
+            pager: Mutex::new(pager),
+            write_lock: Mutex::new(()),
+            write_owner: Mutex::new(None),
+            snapshots: Mutex::new(HashMap::new()),
+            busy: Mutex::new(BusyHandler::default()),
+        }
+    }
+
+    pub fn busy_handler(&self) -> BusyHandler {
+        lock(&self.busy).clone()
+    }
+
+    pub fn set_busy_handler(&self, handler: BusyHandler) {
+        *lock(&self.busy) = handler;
+    }
+
+    /// Bắt đầu transaction ghi; transaction ghi khác đang chạy thì xử lý theo busy handler.
+    pub fn begin(&self) -> DbResult<Transaction<'_>> {
+        Ok(Transaction::write(self, self.write_lock()?))
+    }
+
+    /// Bắt đầu transaction chỉ đọc (không lấy write lock).
+    pub fn begin_read(&self) -> DbResult<Transaction<'_>> {
+        Transaction::read(self)
+    }
+
+    /// Checkpoint log về file database (`None` nếu không ở chế độ WAL). Mode khác
+    /// `Passive` chờ transaction ghi đang chạy kết thúc để checkpoint cả commit của nó,
+    /// và chờ reader đang chặn frame kết thúc (cả 2 qua busy handler). Thread đang giữ
+    /// snapshot thì không chờ reader: snapshot đó có thể chính là reader đang chặn.
+    pub fn checkpoint(&self, mode: CheckpointMode) -> DbResult<Option<CheckpointInfo>> {
+        let _write = match mode {
+            CheckpointMode::Passive => None,
+            _ => Some(self.write_lock()?),
+        };
+        // reader đang mở chặn mode khác `Passive`: nhả pager lock giữa các lần thử để
+        // reader kết thúc được
+        let handler = if self.holds_snapshot() {
+            BusyHandler::Timeout(Duration::ZERO)
+        } else {
+            self.busy_handler()
+        };
+        handler.retry(|| self.pager()?.checkpoint(mode))
+    }
+
+    /// Lấy write lock qua busy handler; thread hiện tại đang giữ nó thì `Busy` ngay.
+    fn write_lock(&self) -> DbResult<WriteGuard<'_>> {
+        let me = std::thread::current().id();
+        if *lock(&self.write_owner) == Some(me) {
+            return Err(DbError::Busy("write lock held by this thread"));
+        }
+        let guard = self
+            .busy_handler()
+            .acquire(&self.write_lock, "write lock")?;
+        *lock(&self.write_owner) = Some(me);
+        Ok(WriteGuard {
+            db: self,
+            _guard: guard,
+        })
+    }
+
+    fn hold_snapshot(&self) {
+        let me = std::thread::current().id();
+        *lock(&self.snapshots).entry(me).or_default() += 1;
+    }
+
+    fn release_snapshot(&self) {
+        let me = std::thread::current().id();
+        let mut snapshots = lock(&self.snapshots);
+        if let Some(n) = snapshots.get_mut(&me) {
+            *n -= 1;
+            if *n == 0 {
+                snapshots.remove(&me);
+            }
+        }
+    }
+
+    fn holds_snapshot(&self) -> bool {
+        lock(&self.snapshots).contains_key(&std::thread::current().id())
+    }
+
+    fn pager(&self) -> DbResult<MutexGuard<'_, Box<dyn Pager + Send>>> {
+        self.busy_handler().acquire(&self.pager, "pager lock")
+    }
+
+    /// Lấy pager lock bỏ qua busy handler, cho việc dọn dẹp không được bỏ dở.
+    fn pager_wait(&self) -> MutexGuard<'_, Box<dyn Pager + Send>> {
+        lock(&self.pager)
+    }
+}
+
+/// Write lock đang được giữ; drop thì xoá thread giữ rồi nhả lock.
+struct WriteGuard<'db> {
+    db: &'db Database,
+    _guard: MutexGuard<'db, ()>,
+}
+
+impl Drop for WriteGuard<'_> {
+    fn drop(&mut self) {
+        *lock(&self.db.write_owner) = None;
+    }
+}
+
+/// Lock bị poison vẫn dùng tiếp được: transaction ghi bị panic đã rollback khi drop.
+fn lock<T>(m: &Mutex<T>) -> MutexGuard<'_, T> {
+    m.lock().unwrap_or_else(PoisonError::into_inner)
+}
@@ -0,0 +1,801 @@
+use std::collections::BTreeMap;
+
+use crate::constants::PAGE_SIZE;
+use crate::pager::pager::{Pager, Snapshot};
+use crate::wal::CommitTicket;
+use crate::{DbError, DbResult, PageId};
+
+use super::{Database, WriteGuard};
+
+/// Savepoint: đủ thông tin để đưa transaction về đúng trạng thái lúc tạo savepoint.
+struct Savepoint {
+    name: String,
+    /// Trạng thái trong buffer transaction của mỗi page trước lần sửa đầu tiên sau
+    /// savepoint (`None`: page chưa nằm trong buffer, đọc từ pager).
+    before: BTreeMap<PageId, Option<Box<[u8]>>>,
+    /// Page được cấp phát sau savepoint.
+    allocated: Vec<PageId>,
+    /// Độ dài `freed` lúc tạo savepoint.
+    freed_len: usize,
+}
+
+/// Transaction trên `Database` (BEGIN ... COMMIT/ROLLBACK).
+///
+/// Transaction ghi giữ global write lock tới khi `commit`/`rollback`; drop mà chưa
+/// commit = rollback. Dùng được như 1 `Pager` để chạy heap/btree bên trong transaction
+/// (`flush` không commit, chỉ `commit` mới commit).
+///
+/// Savepoint (`savepoint`/`release`/`rollback_to`) lồng nhau được: mỗi savepoint giữ
+/// ảnh trước của page bị sửa sau nó, rollback tới savepoint chỉ khôi phục các page đó.
+/// `atomic` chạy 1 câu lệnh trong savepoint ẩn danh (lỗi = không để lại thay đổi dở).
+pub struct Transaction<'db> {
+    db: &'db Database,
+    /// `None`: transaction chỉ đọc.
+    write_guard: Option<WriteGuard<'db>>,
+    /// Page đã sửa trong transaction, chưa ai khác thấy.
+    pages: BTreeMap<PageId, Box<[u8]>>,
+    /// Page free trong transaction, chỉ free trên pager lúc commit.
+    freed: Vec<PageId>,
+    /// Savepoint đang mở, trong cùng ở cuối.
+    savepoints: Vec<Savepoint>,
+    /// Snapshot của transaction chỉ đọc (pager WAL): đọc nhất quán tại lúc bắt đầu.
+    snapshot: Option<Snapshot>,
+    /// Đã commit/rollback.
+    finished: bool,
+}
+
+impl<'db> Transaction<'db> {
+    pub(super) fn write(db: &'db Database, guard: WriteGuard<'db>) -> Self {
+        Transaction {
+            db,
+            write_guard: Some(guard),
+            pages: BTreeMap::new(),
+            freed: Vec::new(),
+            savepoints: Vec::new(),
+            snapshot: None,
+            finished: false,
+        }
+    }
+
+    pub(super) fn read(db: &'db Database) -> DbResult<Self> {
+        let snapshot = db.pager()?.begin_snapshot()?;
+        if snapshot.is_some() {
+            db.hold_snapshot();
+        }
+        Ok(Transaction {
+            db,
+            write_guard: None,
+            pages: BTreeMap::new(),
+            freed: Vec::new(),
+            savepoints: Vec::new(),
+            snapshot,
+            finished: false,
+        })
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.write_guard.is_none()
+    }
+
+    /// Ghi mọi page đã sửa xuống pager thành 1 commit atomic (WAL hoặc journal) và
+    /// chờ commit bền vững. Commit lỗi (kể cả `Busy` khi không lấy được pager lock)
+    /// thì thay đổi bị bỏ như rollback.
+    pub fn commit(self) -> DbResult<()> {
+        self.commit_deferred()?.wait()
+    }
+
+    /// Như `commit` nhưng trả về ngay khi commit đã nằm trong log và lock đã được nhả;
+    /// commit bền vững khi `wait` ticket. Ở chế độ WAL, các commit đang chờ dùng chung
+    /// 1 lần fsync.
+    pub fn commit_deferred(mut self) -> DbResult<CommitTicket> {
+        self.finished = true;
+        if self.is_read_only() {
+            self.end_snapshot();
+            return Ok(CommitTicket::done());
+        }
+        let mut pager = match self.db.pager() {
+            Ok(pager) => pager,
+            Err(e) => {
+                self.discard();
+                let _ = self.db.pager_wait().rollback();
+                return Err(e);
+            }
+        };
+        let res = write_all(pager.as_mut(), &self.pages, &self.freed)
+            .and_then(|_| pager.flush_deferred());
+        if res.is_err() {
+            // pager có thể đã giữ 1 phần page: bỏ hết, lỗi rollback không che lỗi commit
+            let _ = pager.rollback();
+        }
+        // nhả pager lock và write lock trước khi caller chờ fsync
+        drop(pager);
+        self.write_guard = None;
+        res
+    }
+
+    /// Bỏ mọi thay đổi của transaction (kể cả page đã cấp phát/free).
+    pub fn rollback(mut self) -> DbResult<()> {
+        self.finish_rollback()
+    }
+
+    fn finish_rollback(&mut self) -> DbResult<()> {
+        self.finished = true;
+        self.discard();
+        if self.is_read_only() {
+            self.end_snapshot();
+            return Ok(());
+        }
+        self.db.pager_wait().rollback()
+    }
+
+    fn end_snapshot(&mut self) {
+        if let Some(snap) = self.snapshot.take() {
+            self.db.pager_wait().end_snapshot(snap);
+            self.db.release_snapshot();
+        }
+    }
+
+    fn discard(&mut self) {
+        self.pages.clear();
+        self.freed.clear();
+        self.savepoints.clear();
+    }
+
+    /// SAVEPOINT `name`. Trùng tên thì savepoint mới che savepoint cũ.
+    pub fn savepoint(&mut self, name: &str) -> DbResult<()> {
+        self.check_writable()?;
+        self.savepoints.push(Savepoint {
+            name: name.to_string(),
+            before: BTreeMap::new(),
+            allocated: Vec::new(),
+            freed_len: self.freed.len(),
+        });
+        Ok(())
+    }
+
+    /// RELEASE `name`: bỏ savepoint `name` và mọi savepoint mở sau nó, giữ thay đổi.
+    pub fn release(&mut self, name: &str) -> DbResult<()> {
+        let idx = self.find_savepoint(name)?;
+        self.release_at(idx);
+        Ok(())
+    }
+
+    /// ROLLBACK TO `name`: khôi phục trạng thái lúc tạo savepoint `name`; savepoint
+    /// `name` vẫn mở, các savepoint mở sau nó bị bỏ.
+    pub fn rollback_to(&mut self, name: &str) -> DbResult<()> {
+        let idx = self.find_savepoint(name)?;
+        self.rollback_to_at(idx)
+    }
+
+    /// Chạy `f` như 1 câu lệnh atomic: `f` lỗi thì mọi thay đổi của `f` bị bỏ, phần
+    /// còn lại của transaction giữ nguyên.
+    pub fn atomic<T>(&mut self, f: impl FnOnce(&mut Self) -> DbResult<T>) -> DbResult<T> {
+        self.savepoint("")?;
+        let idx = self.savepoints.len() - 1;
+        match f(self) {
+            Ok(v) => {
+                self.release_at(idx);
+                Ok(v)
+            }
+            Err(e) => {
+                // lỗi khi undo che mất lỗi gốc thì khó debug: trả lỗi gốc
+                let _ = self.rollback_to_at(idx);
+                self.release_at(idx);
+                Err(e)
+            }
+        }
+    }
+
+    fn find_savepoint(&self, name: &str) -> DbResult<usize> {
+        self.savepoints
+            .iter()
+            .rposition(|sp| sp.name == name)
+            .ok_or(DbError::InvalidArgument("no such savepoint"))
+    }
+
+    /// Gộp savepoint `idx` (và các savepoint sau) vào savepoint ngay trước nó.
+    fn release_at(&mut self, idx: usize) {
+        let released: Vec<Savepoint> = self.savepoints.drain(idx..).collect();
+        let Some(parent) = self.savepoints.last_mut() else {
+            return;
+        };
+        for sp in released {
+            for (pid, img) in sp.before {
+                // parent đã có ảnh sớm hơn thì giữ ảnh của parent
+                parent.before.entry(pid).or_insert(img);
+            }
+            parent.allocated.extend(sp.allocated);
+        }
+    }
+
+    fn rollback_to_at(&mut self, idx: usize) -> DbResult<()> {
+        // undo từ savepoint trong cùng ra: mỗi savepoint giữ trạng thái lúc nó được tạo
+        let mut undone: Vec<Savepoint> = self.savepoints.drain(idx..).collect();
+        let mut res = Ok(());
+        for sp in undone.iter_mut().rev() {
+            for (pid, img) in std::mem::take(&mut sp.before) {
+                match img {
+                    Some(page) => self.pages.insert(pid, page),
+                    None => self.pages.remove(&pid),
+                };
+            }
+            // page cấp phát sau savepoint trả lại pager
+            for pid in sp.allocated.drain(..).rev() {
+                res = res.and(self.db.pager_wait().free_page(pid));
+            }
+            self.freed.truncate(sp.freed_len);
+        }
+        // savepoint đích vẫn mở (đã trống)
+        self.savepoints.push(undone.swap_remove(0));
+        res
+    }
+
+    /// Ghi nhận ảnh trước của `pid` cho savepoint trong cùng.
+    fn record_before(&mut self, pid: PageId) {
+        if let Some(sp) = self.savepoints.last_mut() {
+            sp.before
+                .entry(pid)
+                .or_insert_with(|| self.pages.get(&pid).cloned());
+        }
+    }
+
+    fn check_writable(&self) -> DbResult<()> {
+        if self.is_read_only() {
+            return Err(DbError::InvalidArgument("transaction is read-only"));
+        }
+        Ok(())
+    }
+}
+
+fn write_all(
+    pager: &mut dyn Pager,
+    pages: &BTreeMap<PageId, Box<[u8]>>,
+    freed: &[PageId],
+) -> DbResult<()> {
+    for (&pid, page) in pages {
+        pager.write_page(pid, page)?;
+    }
+    for &pid in freed {
+        pager.free_page(pid)?;
+    }
+    Ok(())
+}
+
+impl Drop for Transaction<'_> {
+    fn drop(&mut self) {
+        if !self.finished {
+            let _ = self.finish_rollback();
+        }
+    }
+}
+
+impl Pager for Transaction<'_> {
+    fn read_page(&mut self, pid: PageId, out: &mut [u8]) -> DbResult<()> {
+        if out.len() != PAGE_SIZE {
+            return Err(DbError::InvalidArgument(
+                "buffer length must equal PAGE_SIZE",
+            ));
+        }
+        if let Some(page) = self.pages.get(&pid) {
+            out.copy_from_slice(page);
+            return Ok(());
+        }
+        match &self.snapshot {
+            Some(snap) => self.db.pager()?.read_page_at(snap, pid, out),
+            None => self.db.pager()?.read_page(pid, out),
+        }
+    }
+
+    fn write_page(&mut self, pid: PageId, buf: &[u8]) -> DbResult<()> {
+        self.check_writable()?;
+        if buf.len() != PAGE_SIZE {
+            return Err(DbError::InvalidArgument(
+                "buffer length must equal PAGE_SIZE",
+            ));
+        }
+        if pid.as_u64() >= self.db.pager()?.num_pages()? {
+            return Err(DbError::InvalidArgument("page id out of range"));
+        }
+        self.record_before(pid);
+        self.pages.insert(pid, buf.into());
+        Ok(())
+    }
+
+    /// Cấp phát thẳng trên pager (chưa commit); rollback trả lại page.
+    fn alloc_page(&mut self) -> DbResult<PageId> {
+        self.check_writable()?;
+        let pid = self.db.pager()?.alloc_page()?;
+        if let Some(sp) = self.savepoints.last_mut() {
+            sp.allocated.push(pid);
+        }
+        Ok(pid)
+    }
+
+    /// Free được hoãn tới commit để rollback tới savepoint lấy lại được page.
+    fn free_page(&mut self, pid: PageId) -> DbResult<()> {
+        self.check_writable()?;
+        if pid == PageId(0) {
+            return Err(DbError::InvalidArgument("cannot free meta page"));
+        }
+        if pid.as_u64() >= self.db.pager()?.num_pages()? {
+            return Err(DbError::InvalidArgument("page id out of range"));
+        }
+        if self.freed.contains(&pid) {
+            return Err(DbError::InvalidArgument("page is already free"));
+        }
+        self.freed.push(pid);
+        Ok(())
+    }
+
+    /// Không commit: page đã sửa nằm trong transaction tới `commit`.
+    fn flush(&mut self) -> DbResult<()> {
+        Ok(())
+    }
+
+    fn num_pages(&mut self) -> DbResult<u64> {
+        match &self.snapshot {
+            Some(snap) => Ok(snap.db_pages() as u64),
+            None => self.db.pager()?.num_pages(),
+        }
+    }
+
+    /// Bỏ page đã sửa, transaction vẫn mở.
+    fn rollback(&mut self) -> DbResult<()> {
+        self.check_writable()?;
+        self.discard();
+        self.db.pager_wait().rollback()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::BusyHandler;
+    use crate::journal::{remove_db_files, temp_db_path};
+    use crate::pager::meta::JournalMode;
+    use crate::pager::sync::SyncMode;
+    use crate::wal::{CheckpointMode, WalPager};
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn open(name: &str, mode: JournalMode) -> (String, Database) {
+        let path = temp_db_path(name);
+        let db = Database::open(path.clone(), mode, SyncMode::Off).unwrap();
+        (path, db)
+    }
+
+    fn read_fill(p: &mut dyn Pager, pid: PageId) -> u8 {
+        let mut out = vec![0u8; PAGE_SIZE];
+        p.read_page(pid, &mut out).unwrap();
+        out[0]
+    }
+
+    #[test]
+    fn test_commit_and_rollback() {
+        for mode in [JournalMode::Wal, JournalMode::Delete] {
+            let (path, db) = open("txn-commit", mode);
+            let mut tx = db.begin().unwrap();
+            let pid = tx.alloc_page().unwrap();
+            tx.write_page(pid, &vec![1u8; PAGE_SIZE]).unwrap();
+            assert_eq!(read_fill(&mut tx, pid), 1);
+            // reader không thấy page cấp phát chưa commit
+            let mut out = vec![0u8; PAGE_SIZE];
+            let mut r = db.begin_read().unwrap();
+            assert_eq!(r.num_pages().unwrap(), pid.as_u64());
+            assert!(matches!(
+                r.read_page(pid, &mut out),
+                Err(DbError::InvalidArgument(_))
+            ));
+            drop(r);
+            tx.commit().unwrap();
+            assert_eq!(read_fill(&mut db.begin_read().unwrap(), pid), 1);
+
+            let mut tx = db.begin().unwrap();
+            tx.write_page(pid, &vec![2u8; PAGE_SIZE]).unwrap();
+            let extra = tx.alloc_page().unwrap();
+            tx.rollback().unwrap();
+            let mut r = db.begin_read().unwrap();
+            assert_eq!(read_fill(&mut r, pid), 1);
+            assert_eq!(r.num_pages().unwrap(), extra.as_u64());
+            assert!(r.read_page(extra, &mut out).is_err());
+            drop(r);
+
+            // drop không commit = rollback
+            {
+                let mut tx = db.begin().unwrap();
+                tx.write_page(pid, &vec![3u8; PAGE_SIZE]).unwrap();
+            }
+            drop(db);
+            let db = Database::open(path.clone(), mode, SyncMode::Off).unwrap();
+            assert_eq!(read_fill(&mut db.begin_read().unwrap(), pid), 1);
+            drop(db);
+            remove_db_files(&path);
+        }
+    }
+
+    #[test]
+    fn test_read_only_does_not_take_write_lock() {
+        let (path, db) = open("txn-readonly", JournalMode::Wal);
+        let mut tx = db.begin().unwrap();
+        let pid = tx.alloc_page().unwrap();
+        tx.write_page(pid, &vec![5u8; PAGE_SIZE]).unwrap();
+
+        let mut r = db.begin_read().unwrap();
+        assert!(r.is_read_only());
+        assert!(r.write_page(pid, &vec![0u8; PAGE_SIZE]).is_err());
+        assert!(r.alloc_page().is_err());
+        tx.commit().unwrap();
+        // snapshot lấy trước commit không thấy page mới
+        let mut out = vec![0u8; PAGE_SIZE];
+        assert!(r.read_page(pid, &mut out).is_err());
+        r.commit().unwrap();
+        assert_eq!(read_fill(&mut db.begin_read().unwrap(), pid), 5);
+
+        remove_db_files(&path);
+    }
+
+    #[test]
+    fn test_read_only_sees_consistent_snapshot() {
+        let (path, db) = open("txn-snapshot", JournalMode::Wal);
+        let mut tx = db.begin().unwrap();
+        let (a, b) = (tx.alloc_page().unwrap(), tx.alloc_page().unwrap());
+        tx.write_page(a, &vec![1u8; PAGE_SIZE]).unwrap();
+        tx.write_page(b, &vec![1u8; PAGE_SIZE]).unwrap();
+        tx.commit().unwrap();
+
+        let mut r = db.begin_read().unwrap();
+        assert_eq!(read_fill(&mut r, a), 1);
+        // writer sửa cả 2 page qua 2 commit trong lúc reader đang đọc
+        for pid in [a, b] {
+            let mut tx = db.begin().unwrap();
+            tx.write_page(pid, &vec![2u8; PAGE_SIZE]).unwrap();
+            tx.commit().unwrap();
+        }
+        assert_eq!(read_fill(&mut r, a), 1);
+        assert_eq!(read_fill(&mut r, b), 1);
+        r.commit().unwrap();
+        assert_eq!(read_fill(&mut db.begin_read().unwrap(), b), 2);
+
+        remove_db_files(&path);
+    }
+
+    #[test]
+    fn test_busy_handler_gives_up() {
+        let (path, db) = open("txn-busy", JournalMode::Wal);
+        db.set_busy_handler(BusyHandler::Timeout(Duration::from_millis(10)));
+        let (ready_tx, ready_rx) = std::sync::mpsc::channel();
+        let (go_tx, go_rx) = std::sync::mpsc::channel();
+        let pid = std::thread::scope(|s| {
+            let db = &db;
+            // transaction ghi của thread khác giữ write lock
+            let writer = s.spawn(move || {
+                let mut tx = db.begin().unwrap();
+                let pid = tx.alloc_page().unwrap();
+                tx.write_page(pid, &vec![1u8; PAGE_SIZE]).unwrap();
+                ready_tx.send(()).unwrap();
+                go_rx.recv().unwrap();
+                tx.commit().unwrap();
+                pid
+            });
+            ready_rx.recv().unwrap();
+
+            assert!(matches!(db.begin().err(), Some(DbError::Busy(_))));
+            assert!(matches!(
+                db.checkpoint(CheckpointMode::Restart),
+                Err(DbError::Busy(_))
+            ));
+            // passive checkpoint và reader không cần write lock
+            assert!(db.checkpoint(CheckpointMode::Passive).unwrap().is_some());
+            db.begin_read().unwrap().commit().unwrap();
+
+            // callback quyết định số lần thử lại
+            let tries = Arc::new(AtomicU32::new(0));
+            let t = tries.clone();
+            db.set_busy_handler(BusyHandler::Callback(Arc::new(move |n| {
+                t.store(n, Ordering::SeqCst);
+                n < 5
+            })));
+            assert!(matches!(db.begin().err(), Some(DbError::Busy(_))));
+            assert_eq!(tries.load(Ordering::SeqCst), 5);
+
+            go_tx.send(()).unwrap();
+            writer.join().unwrap()
+        });
+        let info = db.checkpoint(CheckpointMode::Restart).unwrap().unwrap();
+        assert_eq!(info.backfilled, info.log_frames);
+        assert_eq!(read_fill(&mut db.begin().unwrap(), pid), 1);
+
+        remove_db_files(&path);
+    }
+
+    #[test]
+    fn test_own_locks_are_busy_without_waiting() {
+        let (path, db) = open("txn-busy-self", JournalMode::Wal);
+        // handler mặc định chờ mãi: lock của chính thread này phải trả Busy ngay
+        let mut tx = db.begin().unwrap();
+        let pid = tx.alloc_page().unwrap();
+        tx.write_page(pid, &vec![1u8; PAGE_SIZE]).unwrap();
+        assert!(matches!(db.begin().err(), Some(DbError::Busy(_))));
+        assert!(matches!(
+            db.checkpoint(CheckpointMode::Full),
+            Err(DbError::Busy(_))
+        ));
+        tx.commit().unwrap();
+
+        // reader của thread này chặn commit sau nó
+        let mut r = db.begin_read().unwrap();
+        let mut tx = db.begin().unwrap();
+        tx.write_page(pid, &vec![2u8; PAGE_SIZE]).unwrap();
+        tx.commit().unwrap();
+        assert!(matches!(
+            db.checkpoint(CheckpointMode::Restart),
+            Err(DbError::Busy(_))
+        ));
+        assert_eq!(read_fill(&mut r, pid), 1);
+        r.commit().unwrap();
+
+        let info = db.checkpoint(CheckpointMode::Restart).unwrap().unwrap();
+        assert_eq!(info.backfilled, info.log_frames);
+        remove_db_files(&path);
+    }
+
+    #[test]
+    fn test_checkpoint_waits_for_readers() {
+        let (path, db) = open("txn-checkpoint-reader", JournalMode::Wal);
+        let mut tx = db.begin().unwrap();
+        let pid = tx.alloc_page().unwrap();
+        tx.write_page(pid, &vec![1u8; PAGE_SIZE]).unwrap();
+        tx.commit().unwrap();
+
+        let (ready_tx, ready_rx) = std::sync::mpsc::channel();
+        let (go_tx, go_rx) = std::sync::mpsc::channel();
+        let info = std::thread::scope(|s| {
+            let db = &db;
+            s.spawn(move || {
+                let mut r = db.begin_read().unwrap();
+                ready_tx.send(()).unwrap();
+                go_rx.recv().unwrap();
+                // reader kết thúc trong lúc checkpoint đang thử lại
+                std::thread::sleep(Duration::from_millis(20));
+                assert_eq!(read_fill(&mut r, pid), 1);
+                r.commit().unwrap();
+            });
+            ready_rx.recv().unwrap();
+            let mut tx = db.begin().unwrap();
+            tx.write_page(pid, &vec![2u8; PAGE_SIZE]).unwrap();
+            tx.commit().unwrap();
+
+            // reader chặn commit thứ 2, Restart không xong được trong timeout
+            db.set_busy_handler(BusyHandler::Timeout(Duration::from_millis(10)));
+            assert!(matches!(
+                db.checkpoint(CheckpointMode::Restart),
+                Err(DbError::Busy(_))
+            ));
+
+            db.set_busy_handler(BusyHandler::Timeout(Duration::from_secs(5)));
+            go_tx.send(()).unwrap();
+            db.checkpoint(CheckpointMode::Restart).unwrap().unwrap()
+        });
+        assert_eq!(info.backfilled, info.log_frames);
+        assert_eq!(read_fill(&mut db.begin_read().unwrap(), pid), 2);
+
+        remove_db_files(&path);
+    }
+
+    #[test]
+    fn test_nested_savepoints() {
+        let (path, db) = open("txn-savepoint", JournalMode::Wal);
+        let mut tx = db.begin().unwrap();
+        let a = tx.alloc_page().unwrap();
+        tx.write_page(a, &vec![1u8; PAGE_SIZE]).unwrap();
+
+        tx.savepoint("s1").unwrap();
+        tx.write_page(a, &vec![2u8; PAGE_SIZE]).unwrap();
+        let b = tx.alloc_page().unwrap();
+        tx.write_page(b, &vec![2u8; PAGE_SIZE]).unwrap();
+
+        tx.savepoint("s2").unwrap();
+        tx.write_page(a, &vec![3u8; PAGE_SIZE]).unwrap();
+        tx.free_page(b).unwrap();
+        tx.rollback_to("s2").unwrap();
+        assert_eq!(read_fill(&mut tx, a), 2);
+        // free sau s2 đã bị bỏ
+        tx.free_page(b).unwrap();
+
+        // release s2 gộp vào s1: rollback tới s1 vẫn bỏ được thay đổi của s2
+        tx.write_page(a, &vec![4u8; PAGE_SIZE]).unwrap();
+        tx.release("s2").unwrap();
+        assert!(tx.rollback_to("s2").is_err());
+        tx.rollback_to("s1").unwrap();
+        assert_eq!(read_fill(&mut tx, a), 1);
+        // page cấp phát sau s1 được trả lại pager
+        assert_eq!(tx.alloc_page().unwrap(), b);
+        tx.release("s1").unwrap();
+        tx.commit().unwrap();
+
+        assert_eq!(read_fill(&mut db.begin_read().unwrap(), a), 1);
+        remove_db_files(&path);
+    }
+
+    #[test]
+    fn test_savepoint_allocations_survive_full_rollback() {
+        for mode in [JournalMode::Wal, JournalMode::Delete] {
+            let (path, db) = open("txn-savepoint-alloc", mode);
+            let mut tx = db.begin().unwrap();
+            let a = tx.alloc_page().unwrap();
+            let keep = tx.alloc_page().unwrap();
+            tx.write_page(a, &vec![1u8; PAGE_SIZE]).unwrap();
+            tx.write_page(keep, &vec![1u8; PAGE_SIZE]).unwrap();
+            tx.commit().unwrap();
+            let mut tx = db.begin().unwrap();
+            tx.free_page(a).unwrap();
+            tx.commit().unwrap();
+            let pages = db.begin_read().unwrap().num_pages().unwrap();
+
+            // page lấy từ freelist và page mới, trả lại pager khi rollback tới savepoint
+            let mut tx = db.begin().unwrap();
+            tx.savepoint("s").unwrap();
+            assert_eq!(tx.alloc_page().unwrap(), a);
+            let b = tx.alloc_page().unwrap();
+            assert_eq!(b.as_u64(), pages);
+            tx.write_page(a, &vec![2u8; PAGE_SIZE]).unwrap();
+            tx.write_page(b, &vec![2u8; PAGE_SIZE]).unwrap();
+            tx.rollback_to("s").unwrap();
+            assert_eq!(tx.alloc_page().unwrap(), a);
+            assert_eq!(tx.alloc_page().unwrap(), b);
+            assert_eq!(read_fill(&mut tx, a), 0);
+            assert_eq!(read_fill(&mut tx, b), 0);
+            tx.rollback().unwrap();
+
+            // full rollback: freelist và số page như trước transaction
+            let mut tx = db.begin().unwrap();
+            assert_eq!(tx.num_pages().unwrap(), pages);
+            assert_eq!(tx.alloc_page().unwrap(), a);
+            assert_eq!(read_fill(&mut tx, a), 0);
+            assert_eq!(tx.alloc_page().unwrap(), b);
+            assert_eq!(read_fill(&mut tx, keep), 1);
+            drop(tx);
+            drop(db);
+            remove_db_files(&path);
+        }
+    }
+
+    #[test]
+    fn test_failed_statement_leaves_no_partial_rows() {
+        use crate::heap::{HeapFile, MAX_RECORD_SIZE};
+
+        const ROWS: usize = 40;
+        const ROW_SIZE: usize = 300;
+        let (path, db) = open("txn-statement", JournalMode::Delete);
+        let mut tx = db.begin().unwrap();
+        let heap = HeapFile::create(&mut tx).unwrap();
+        heap.insert(&mut tx, b"first").unwrap();
+
+        // multi-row insert, dòng cuối quá lớn -> cả câu lệnh bị bỏ (kể cả page mới)
+        let pages_before = tx.num_pages().unwrap();
+        let res = tx.atomic(|tx| {
+            for i in 0..ROWS {
+                heap.insert(tx, &[i as u8; ROW_SIZE])?;
+            }
+            heap.insert(tx, &vec![0u8; MAX_RECORD_SIZE + 1])
+        });
+        assert!(res.is_err());
+        let rows: Vec<_> = heap.scan(&mut tx).map(|r| r.unwrap().1).collect();
+        assert_eq!(rows, vec![b"first".to_vec()]);
+
+        // câu lệnh thành công giữ lại, và dùng lại được page đã trả
+        tx.atomic(|tx| heap.insert(tx, &[7u8; ROW_SIZE])).unwrap();
+        tx.commit().unwrap();
+        let mut r = db.begin_read().unwrap();
+        assert_eq!(heap.scan(&mut r).count(), 2);
+        assert!(r.num_pages().unwrap() > pages_before);
+        drop(r);
+
+        remove_db_files(&path);
+    }
+
+    #[test]
+    fn test_writers_are_serialized() {
+        const WRITERS: u8 = 4;
+        let (path, db) = open("txn-serial", JournalMode::Wal);
+        let pid = {
+            let mut tx = db.begin().unwrap();
+            let pid = tx.alloc_page().unwrap();
+            tx.write_page(pid, &vec![0u8; PAGE_SIZE]).unwrap();
+            tx.commit().unwrap();
+            pid
+        };
+
+        // read-modify-write: mất update nếu 2 writer chạy chồng nhau
+        std::thread::scope(|s| {
+            for _ in 0..WRITERS {
+                s.spawn(|| {
+                    let mut tx = db.begin().unwrap();
+                    let v = read_fill(&mut tx, pid);
+                    std::thread::yield_now();
+                    tx.write_page(pid, &vec![v + 1; PAGE_SIZE]).unwrap();
+                    tx.commit().unwrap();
+                });
+            }
+        });
+        assert_eq!(read_fill(&mut db.begin_read().unwrap(), pid), WRITERS);
+
+        remove_db_files(&path);
+    }
+
+    /// WalPager dùng chung với test để đếm số lần fsync commit.
+    struct SharedWal(Arc<std::sync::Mutex<WalPager>>);
+
+    impl Pager for SharedWal {
+        fn read_page(&mut self, pid: PageId, out: &mut [u8]) -> DbResult<()> {
+            self.0.lock().unwrap().read_page(pid, out)
+        }
+        fn write_page(&mut self, pid: PageId, buf: &[u8]) -> DbResult<()> {
+            self.0.lock().unwrap().write_page(pid, buf)
+        }
+        fn alloc_page(&mut self) -> DbResult<PageId> {
+            self.0.lock().unwrap().alloc_page()
+        }
+        fn free_page(&mut self, pid: PageId) -> DbResult<()> {
+            self.0.lock().unwrap().free_page(pid)
+        }
+        fn flush(&mut self) -> DbResult<()> {
+            self.0.lock().unwrap().flush()
+        }
+        fn num_pages(&mut self) -> DbResult<u64> {
+            self.0.lock().unwrap().num_pages()
+        }
+        fn flush_deferred(&mut self) -> DbResult<CommitTicket> {
+            self.0.lock().unwrap().flush_deferred()
+        }
+        fn rollback(&mut self) -> DbResult<()> {
+            self.0.lock().unwrap().rollback()
+        }
+    }
+
+    #[test]
+    fn test_concurrent_commits_share_syncs() {
+        use std::sync::Barrier;
+
+        const THREADS: u8 = 4;
+        const COMMITS: u8 = 10;
+        let path = temp_db_path("txn-group-commit");
+        let wal = Arc::new(std::sync::Mutex::new(WalPager::open(path.clone()).unwrap()));
+        let db = Database::with_pager(Box::new(SharedWal(wal.clone())));
+        let pids: Vec<PageId> = {
+            let mut tx = db.begin().unwrap();
+            let pids = (0..THREADS).map(|_| tx.alloc_page().unwrap()).collect();
+            tx.commit().unwrap();
+            pids
+        };
+        let syncs_before = wal.lock().unwrap().commit_syncs();
+
+        // mỗi vòng mọi thread commit xong (lock đã nhả) mới chờ fsync: cả vòng chung
+        // 1 lần fsync
+        let committed = Barrier::new(THREADS as usize);
+        std::thread::scope(|s| {
+            for &pid in &pids {
+                let (db, committed) = (&db, &committed);
+                s.spawn(move || {
+                    for i in 0..COMMITS {
+                        let mut tx = db.begin().unwrap();
+                        tx.write_page(pid, &vec![i; PAGE_SIZE]).unwrap();
+                        let ticket = tx.commit_deferred().unwrap();
+                        committed.wait();
+                        ticket.wait().unwrap();
+                    }
+                });
+            }
+        });
+        let syncs = wal.lock().unwrap().commit_syncs() - syncs_before;
+        assert_eq!(syncs, COMMITS as u64);
+
+        let mut r = db.begin_read().unwrap();
+        for &pid in &pids {
+            assert_eq!(read_fill(&mut r, pid), COMMITS - 1);
+        }
+        drop(r);
+        remove_db_files(&path);
+    }
+}
@@ -14,6 +14,10 @@ pub enum DbError {
     Corruption(&'static str),
     NoSpace(&'static str),
     InvalidArgument(&'static str),
+    /// Write-write conflict giữa 2 transaction, transaction phát hiện conflict bị abort.
+    Conflict(&'static str),
+    /// Lock đang bị giữ và busy handler đã bỏ cuộc.
+    Busy(&'static str),
 }
 
 impl From<std::io::Error> for DbError {
@@ -32,6 +36,8 @@ impl fmt::Display for DbError {
             DbError::Corruption(msg) => write!(f, "corruption: {}", msg),
             DbError::NoSpace(msg) => write!(f, "no space: {}", msg),
             DbError::InvalidArgument(msg) => write!(f, "invalid args: {}", msg),
+            DbError::Conflict(msg) => write!(f, "write conflict: {}", msg),
+            DbError::Busy(msg) => write!(f, "database is busy: {}", msg),
         }
     }
 }
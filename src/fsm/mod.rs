@@ -0,0 +1,495 @@
+//! Free-space map (FSM): mỗi heap table có 1 cây FSM page ghi lại free space
+//! (dạng category thô) của từng heap page, để insert chọn page mà không phải đọc
+//! từng heap page.
+//!
+//! Heap page được đánh số thứ tự (ordinal) 0, 1, 2... theo thứ tự nối vào chuỗi, cây
+//! FSM địa chỉ hoá entry theo ordinal:
+//!
+//! - page lá (level 0): entry i là (heap pid, category) của 1 heap page
+//! - page trong (level > 0): entry i là (page con, category lớn nhất trong cây con)
+//!
+//! FSM page (`PAGE_TYPE_FSM`) không dùng slot directory:
+//!
+//! ```text
+//! +-------------------+----------+-----------+------------------------------+
+//! | page header (16B) | level u8 | count u16 | entry[0..count]              |
+//! |                   |          |           | (pid u32, category u8)       |
+//! +-------------------+----------+-----------+------------------------------+
+//! ```
+//!
+//! Root không đổi suốt đời table: cây đầy thì nội dung root được chuyển xuống 1 page
+//! con mới và root lên 1 level. Update và tìm page chỉ đi theo đường từ root xuống lá
+//! (O(log n) page).
+//!
+//! Category c nghĩa là page có ít nhất `c * FSM_CATEGORY_BYTES` bytes free.
+//! FSM chỉ là hint: caller vẫn phải xử lý NoSpace khi insert và cập nhật lại FSM.
+
+use crate::constants::PAGE_SIZE;
+use crate::page::header::{self, PAGE_TYPE_FSM};
+use crate::page::raw::{read_u16_le, read_u32_le, read_u8, write_u16_le, write_u32_le, write_u8};
+use crate::page::SLOTTED_HEADER_SIZE;
+use crate::pager::pager::Pager;
+use crate::{DbError, DbResult, PageId};
+
+const OFF_LEVEL: usize = SLOTTED_HEADER_SIZE;
+const OFF_COUNT: usize = OFF_LEVEL + 1;
+const OFF_ENTRIES: usize = OFF_COUNT + 2;
+
+const ENTRY_PID: usize = 0;
+const ENTRY_CATEGORY: usize = 4;
+const ENTRY_SIZE: usize = 5;
+
+/// Số entry tối đa trong 1 FSM page.
+pub const FSM_ENTRIES_PER_PAGE: usize = (PAGE_SIZE - OFF_ENTRIES) / ENTRY_SIZE;
+
+/// Cây có root ở level này đã phủ mọi ordinal u32.
+const MAX_LEVEL: u8 = 3;
+
+/// 256 category chia đều PAGE_SIZE.
+pub const FSM_CATEGORY_BYTES: usize = PAGE_SIZE / 256;
+
+/// Category của page có `free` bytes trống (làm tròn xuống).
+pub fn category_for_free(free: usize) -> u8 {
+    (free / FSM_CATEGORY_BYTES).min(u8::MAX as usize) as u8
+}
+
+/// Category nhỏ nhất đảm bảo có ít nhất `bytes` bytes trống (làm tròn lên).
+/// `None` nếu không category nào đảm bảo được.
+fn category_needed(bytes: usize) -> Option<u8> {
+    let c = bytes.div_ceil(FSM_CATEGORY_BYTES);
+    u8::try_from(c).ok()
+}
+
+fn entry_off(i: usize) -> usize {
+    OFF_ENTRIES + i * ENTRY_SIZE
+}
+
+/// Số heap page mà 1 entry của FSM page ở `level` phủ.
+fn entry_span(level: u8) -> u64 {
+    (FSM_ENTRIES_PER_PAGE as u64).pow(level as u32)
+}
+
+/// Vị trí entry chứa heap page `ordinal` trong FSM page ở `level`.
+fn index_at(ordinal: u32, level: u8) -> usize {
+    (ordinal as u64 / entry_span(level) % FSM_ENTRIES_PER_PAGE as u64) as usize
+}
+
+/// FSM page đã đọc lên; `dirty` khi có entry đổi mà chưa ghi xuống pager.
+struct FsmPage {
+    pid: PageId,
+    buf: Vec<u8>,
+    level: u8,
+    dirty: bool,
+}
+
+impl FsmPage {
+    fn read(pager: &mut dyn Pager, pid: PageId) -> DbResult<Self> {
+        let mut buf = vec![0u8; PAGE_SIZE];
+        pager.read_page(pid, &mut buf)?;
+        if !header::is_page_type(header::flags(&buf)?, PAGE_TYPE_FSM) {
+            return Err(DbError::Corruption("not a fsm page"));
+        }
+        let level = read_u8(&buf, OFF_LEVEL)?;
+        if level > MAX_LEVEL {
+            return Err(DbError::Corruption("fsm level out of range"));
+        }
+        if read_u16_le(&buf, OFF_COUNT)? as usize > FSM_ENTRIES_PER_PAGE {
+            return Err(DbError::Corruption("fsm entry count out of range"));
+        }
+        Ok(FsmPage {
+            pid,
+            buf,
+            level,
+            dirty: false,
+        })
+    }
+
+    /// Đọc page con của page ở `parent_level`.
+    fn read_child(pager: &mut dyn Pager, pid: PageId, parent_level: u8) -> DbResult<Self> {
+        let page = Self::read(pager, pid)?;
+        if page.level + 1 != parent_level {
+            return Err(DbError::Corruption("fsm child has wrong level"));
+        }
+        Ok(page)
+    }
+
+    /// Page rỗng ở `level` (chưa ghi).
+    fn empty(pid: PageId, level: u8) -> DbResult<Self> {
+        let mut buf = vec![0u8; PAGE_SIZE];
+        header::init_empty(&mut buf, PAGE_TYPE_FSM)?;
+        write_u8(&mut buf, OFF_LEVEL, level)?;
+        write_u16_le(&mut buf, OFF_COUNT, 0)?;
+        Ok(FsmPage {
+            pid,
+            buf,
+            level,
+            dirty: true,
+        })
+    }
+
+    fn alloc(pager: &mut dyn Pager, level: u8) -> DbResult<Self> {
+        let mut page = Self::empty(pager.alloc_page()?, level)?;
+        page.write(pager)?;
+        Ok(page)
+    }
+
+    fn count(&self) -> DbResult<usize> {
+        Ok(read_u16_le(&self.buf, OFF_COUNT)? as usize)
+    }
+
+    fn entry(&self, i: usize) -> DbResult<(PageId, u8)> {
+        Ok((
+            PageId(read_u32_le(&self.buf, entry_off(i) + ENTRY_PID)?),
+            read_u8(&self.buf, entry_off(i) + ENTRY_CATEGORY)?,
+        ))
+    }
+
+    /// Ghi entry `i`; `i == count` thì thêm entry mới vào cuối.
+    fn set_entry(&mut self, i: usize, pid: PageId, category: u8) -> DbResult<()> {
+        let count = self.count()?;
+        if i > count || i >= FSM_ENTRIES_PER_PAGE {
+            return Err(DbError::Corruption("fsm entry index out of range"));
+        }
+        if i < count && self.entry(i)? == (pid, category) {
+            return Ok(());
+        }
+        write_u32_le(&mut self.buf, entry_off(i) + ENTRY_PID, pid.as_u32())?;
+        write_u8(&mut self.buf, entry_off(i) + ENTRY_CATEGORY, category)?;
+        if i == count {
+            write_u16_le(&mut self.buf, OFF_COUNT, (count + 1) as u16)?;
+        }
+        self.dirty = true;
+        Ok(())
+    }
+
+    fn max_category(&self) -> DbResult<u8> {
+        let mut max = 0;
+        for i in 0..self.count()? {
+            max = max.max(self.entry(i)?.1);
+        }
+        Ok(max)
+    }
+
+    fn write(&mut self, pager: &mut dyn Pager) -> DbResult<()> {
+        if self.dirty {
+            pager.write_page(self.pid, &self.buf)?;
+            self.dirty = false;
+        }
+        Ok(())
+    }
+}
+
+/// Handle FSM của 1 table; catalog lưu `root_page`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FreeSpaceMap {
+    root: PageId,
+}
+
+impl FreeSpaceMap {
+    pub fn create(pager: &mut dyn Pager) -> DbResult<Self> {
+        Ok(FreeSpaceMap {
+            root: FsmPage::alloc(pager, 0)?.pid,
+        })
+    }
+
+    pub fn open(root: PageId) -> Self {
+        FreeSpaceMap { root }
+    }
+
+    pub fn root_page(&self) -> PageId {
+        self.root
+    }
+
+    /// Ghi nhận heap page `heap_pid` (thứ `ordinal` trong chuỗi heap) hiện có `free`
+    /// bytes trống. Ordinal phải được cấp liên tục: page mới là `ordinal` = số page đã
+    /// có. Chỉ ghi FSM page khi category (hoặc max category của cây con) đổi.
+    pub fn update(
+        &self,
+        pager: &mut dyn Pager,
+        heap_pid: PageId,
+        ordinal: u32,
+        free: usize,
+    ) -> DbResult<()> {
+        let category = category_for_free(free);
+        let mut page = FsmPage::read(pager, self.root)?;
+        while ordinal as u64 >= entry_span(page.level) * FSM_ENTRIES_PER_PAGE as u64 {
+            page = self.grow(pager, page)?;
+        }
+
+        // đi từ root xuống lá, nhớ đường đi để sửa max category của các page trong
+        let mut path: Vec<(FsmPage, usize)> = Vec::new();
+        while page.level > 0 {
+            let idx = index_at(ordinal, page.level);
+            let child = match idx.cmp(&page.count()?) {
+                std::cmp::Ordering::Less => {
+                    FsmPage::read_child(pager, page.entry(idx)?.0, page.level)?
+                }
+                std::cmp::Ordering::Equal => {
+                    let child = FsmPage::alloc(pager, page.level - 1)?;
+                    page.set_entry(idx, child.pid, 0)?;
+                    child
+                }
+                std::cmp::Ordering::Greater => {
+                    return Err(DbError::Corruption("fsm ordinal skips a heap page"))
+                }
+            };
+            path.push((page, idx));
+            page = child;
+        }
+
+        let idx = index_at(ordinal, 0);
+        let count = page.count()?;
+        if idx > count {
+            return Err(DbError::Corruption("fsm ordinal skips a heap page"));
+        }
+        if idx < count && page.entry(idx)?.0 != heap_pid {
+            return Err(DbError::Corruption(
+                "fsm entry belongs to another heap page",
+            ));
+        }
+        page.set_entry(idx, heap_pid, category)?;
+
+        while let Some((mut parent, idx)) = path.pop() {
+            let max = page.max_category()?;
+            page.write(pager)?;
+            parent.set_entry(idx, page.pid, max)?;
+            page = parent;
+        }
+        page.write(pager)
+    }
+
+    /// Cây đầy: chuyển nội dung root xuống 1 page con mới, root lên 1 level.
+    fn grow(&self, pager: &mut dyn Pager, mut root: FsmPage) -> DbResult<FsmPage> {
+        if root.level >= MAX_LEVEL {
+            return Err(DbError::Corruption("fsm tree is too deep"));
+        }
+        let max = root.max_category()?;
+        root.pid = pager.alloc_page()?;
+        root.dirty = true;
+        root.write(pager)?;
+
+        let mut new_root = FsmPage::empty(self.root, root.level + 1)?;
+        new_root.set_entry(0, root.pid, max)?;
+        new_root.write(pager)?;
+        Ok(new_root)
+    }
+
+    /// Category đang ghi cho heap page thứ `ordinal` (None nếu chưa có entry).
+    pub fn category_of(&self, pager: &mut dyn Pager, ordinal: u32) -> DbResult<Option<u8>> {
+        let mut page = FsmPage::read(pager, self.root)?;
+        if ordinal as u64 >= entry_span(page.level) * FSM_ENTRIES_PER_PAGE as u64 {
+            return Ok(None);
+        }
+        loop {
+            let idx = index_at(ordinal, page.level);
+            if idx >= page.count()? {
+                return Ok(None);
+            }
+            let (pid, category) = page.entry(idx)?;
+            if page.level == 0 {
+                return Ok(Some(category));
+            }
+            page = FsmPage::read_child(pager, pid, page.level)?;
+        }
+    }
+
+    /// Tìm heap page có ít nhất `bytes` bytes trống.
+    pub fn find_page_with_space(
+        &self,
+        pager: &mut dyn Pager,
+        bytes: usize,
+    ) -> DbResult<Option<PageId>> {
+        self.find_page_with_space_except(pager, bytes, None)
+    }
+
+    /// Giống `find_page_with_space` nhưng bỏ qua page `skip`.
+    pub fn find_page_with_space_except(
+        &self,
+        pager: &mut dyn Pager,
+        bytes: usize,
+        skip: Option<PageId>,
+    ) -> DbResult<Option<PageId>> {
+        let Some(needed) = category_needed(bytes) else {
+            return Ok(None);
+        };
+        let root = FsmPage::read(pager, self.root)?;
+        search(pager, &root, needed, skip)
+    }
+}
+
+/// Heap page có ordinal nhỏ nhất trong cây con `page` có category >= `needed`.
+/// Chỉ xuống cây con có max category đủ lớn; `skip` làm lệch thêm nhiều nhất 1 đường.
+fn search(
+    pager: &mut dyn Pager,
+    page: &FsmPage,
+    needed: u8,
+    skip: Option<PageId>,
+) -> DbResult<Option<PageId>> {
+    for i in 0..page.count()? {
+        let (pid, category) = page.entry(i)?;
+        if category < needed {
+            continue;
+        }
+        if page.level == 0 {
+            if Some(pid) != skip {
+                return Ok(Some(pid));
+            }
+            continue;
+        }
+        let child = FsmPage::read_child(pager, pid, page.level)?;
+        if let Some(found) = search(pager, &child, needed, skip)? {
+            return Ok(Some(found));
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pager::mem::MemPager;
+
+    #[test]
+    fn test_category_rounding() {
+        assert_eq!(category_for_free(0), 0);
+        assert_eq!(category_for_free(FSM_CATEGORY_BYTES - 1), 0);
+        assert_eq!(category_for_free(FSM_CATEGORY_BYTES), 1);
+        assert_eq!(category_for_free(PAGE_SIZE), u8::MAX);
+
+        assert_eq!(category_needed(0), Some(0));
+        assert_eq!(category_needed(1), Some(1));
+        assert_eq!(category_needed(FSM_CATEGORY_BYTES), Some(1));
+        assert_eq!(category_needed(FSM_CATEGORY_BYTES + 1), Some(2));
+        assert_eq!(category_needed(PAGE_SIZE), None);
+    }
+
+    #[test]
+    fn test_update_and_find() {
+        let mut pager = MemPager::new();
+        let fsm = FreeSpaceMap::create(&mut pager).unwrap();
+
+        assert_eq!(fsm.find_page_with_space(&mut pager, 10).unwrap(), None);
+
+        fsm.update(&mut pager, PageId(10), 0, 100).unwrap();
+        fsm.update(&mut pager, PageId(11), 1, 2000).unwrap();
+
+        assert_eq!(
+            fsm.find_page_with_space(&mut pager, 50).unwrap(),
+            Some(PageId(10))
+        );
+        assert_eq!(
+            fsm.find_page_with_space(&mut pager, 500).unwrap(),
+            Some(PageId(11))
+        );
+        assert_eq!(
+            fsm.find_page_with_space_except(&mut pager, 50, Some(PageId(10)))
+                .unwrap(),
+            Some(PageId(11))
+        );
+        assert_eq!(fsm.find_page_with_space(&mut pager, 3000).unwrap(), None);
+
+        // update entry có sẵn (không thêm entry mới)
+        fsm.update(&mut pager, PageId(10), 0, 0).unwrap();
+        assert_eq!(fsm.category_of(&mut pager, 0).unwrap(), Some(0));
+        assert_eq!(fsm.category_of(&mut pager, 2).unwrap(), None);
+        assert_eq!(
+            fsm.find_page_with_space(&mut pager, 50).unwrap(),
+            Some(PageId(11))
+        );
+    }
+
+    #[test]
+    fn test_find_never_overestimates() {
+        let mut pager = MemPager::new();
+        let fsm = FreeSpaceMap::create(&mut pager).unwrap();
+
+        // 31 bytes free -> category 1 (>= 16 bytes), không được trả về cho yêu cầu 20 bytes
+        fsm.update(&mut pager, PageId(7), 0, 31).unwrap();
+        assert_eq!(fsm.find_page_with_space(&mut pager, 20).unwrap(), None);
+        assert_eq!(
+            fsm.find_page_with_space(&mut pager, 16).unwrap(),
+            Some(PageId(7))
+        );
+    }
+
+    #[test]
+    fn test_ordinals_must_be_dense() {
+        let mut pager = MemPager::new();
+        let fsm = FreeSpaceMap::create(&mut pager).unwrap();
+        fsm.update(&mut pager, PageId(5), 0, 100).unwrap();
+
+        assert!(matches!(
+            fsm.update(&mut pager, PageId(6), 2, 100).unwrap_err(),
+            DbError::Corruption(_)
+        ));
+        assert!(matches!(
+            fsm.update(&mut pager, PageId(6), 0, 100).unwrap_err(),
+            DbError::Corruption(_)
+        ));
+    }
+
+    #[test]
+    fn test_tree_grows_with_stable_root() {
+        let mut pager = MemPager::new();
+        let fsm = FreeSpaceMap::create(&mut pager).unwrap();
+        let pages = pager.num_pages().unwrap();
+
+        let n = FSM_ENTRIES_PER_PAGE as u32 + 10;
+        for i in 0..n {
+            fsm.update(&mut pager, PageId(1000 + i), i, 0).unwrap();
+        }
+        // root lên level 1 trỏ tới 2 page lá
+        assert_eq!(pager.num_pages().unwrap(), pages + 2);
+        let root = FsmPage::read(&mut pager, fsm.root_page()).unwrap();
+        assert_eq!((root.level, root.count().unwrap()), (1, 2));
+
+        // max category của page lá được đẩy lên root
+        let last = PageId(1000 + n - 1);
+        fsm.update(&mut pager, last, n - 1, 1000).unwrap();
+        assert_eq!(
+            fsm.find_page_with_space(&mut pager, 900).unwrap(),
+            Some(last)
+        );
+        assert_eq!(
+            fsm.find_page_with_space_except(&mut pager, 900, Some(last))
+                .unwrap(),
+            None
+        );
+        let first = PageId(1000);
+        fsm.update(&mut pager, first, 0, 2000).unwrap();
+        assert_eq!(
+            fsm.find_page_with_space(&mut pager, 900).unwrap(),
+            Some(first)
+        );
+        assert_eq!(
+            fsm.find_page_with_space_except(&mut pager, 900, Some(first))
+                .unwrap(),
+            Some(last)
+        );
+
+        // category giảm cũng được đẩy lên root
+        fsm.update(&mut pager, last, n - 1, 0).unwrap();
+        fsm.update(&mut pager, first, 0, 0).unwrap();
+        assert_eq!(fsm.find_page_with_space(&mut pager, 900).unwrap(), None);
+        let root = FsmPage::read(&mut pager, fsm.root_page()).unwrap();
+        assert_eq!(root.max_category().unwrap(), 0);
+
+        let reopened = FreeSpaceMap::open(fsm.root_page());
+        reopened.update(&mut pager, last, n - 1, 1000).unwrap();
+        assert_eq!(
+            reopened.category_of(&mut pager, n - 1).unwrap(),
+            Some(category_for_free(1000))
+        );
+    }
+
+    #[test]
+    fn test_not_a_fsm_page() {
+        let mut pager = MemPager::new();
+        let pid = pager.alloc_page().unwrap();
+        let fsm = FreeSpaceMap::open(pid);
+        assert!(matches!(
+            fsm.find_page_with_space(&mut pager, 1).unwrap_err(),
+            DbError::Corruption(_)
+        ));
+    }
+}
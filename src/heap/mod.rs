@@ -0,0 +1,849 @@
+//! Heap file: table không clustered, tuple nằm rải rác trong chuỗi page `PAGE_TYPE_HEAP`.
+//!
+//! - Mỗi heap page là slotted page có special area 12 bytes chứa next page id
+//!   (`PageId::INVALID` = page cuối chuỗi), last page id (chỉ dùng ở page đầu) và
+//!   ordinal (thứ tự của page trong chuỗi, page đầu = 0) dùng làm địa chỉ trong FSM.
+//! - Tuple được địa chỉ hoá bằng `RecordId { page, slot }`, RID không đổi suốt đời tuple.
+//! - Update làm tuple lớn hơn chỗ trống của page: tuple được chuyển sang page khác
+//!   (slot đích mang flag MOVED), slot gốc thành stub REDIRECTED chứa RID đích.
+//! - Tuple ngắn hơn stub được lưu pad tới `RecordId::ENCODED_SIZE` bytes (slot mang
+//!   flag PADDED, byte cuối là độ dài thật), nên slot gốc luôn ghi đè được bằng stub
+//!   kể cả khi page đã đầy.
+//! - Mọi lần ghi heap page đều cập nhật FSM, insert chọn page qua FSM.
+
+use std::borrow::Cow;
+
+use crate::constants::PAGE_SIZE;
+use crate::fsm::FreeSpaceMap;
+use crate::page::header::{self, PAGE_TYPE_HEAP};
+use crate::page::raw::{read_u32_le, write_u32_le};
+use crate::page::slot;
+use crate::page::slotted_page::SlottedPage;
+use crate::page::{SLOTTED_HEADER_SIZE, SLOTTED_SLOT_SIZE};
+use crate::pager::pager::Pager;
+use crate::{DbError, DbResult, PageId, RecordId};
+
+/// Special area của heap page: [next page id u32][last page id u32][ordinal u32]
+/// - next: page kế tiếp trong chuỗi
+/// - last: page cuối chuỗi, chỉ có nghĩa ở page đầu (để nối page mới O(1))
+/// - ordinal: thứ tự của page trong chuỗi
+pub const HEAP_SPECIAL_SIZE: usize = 12;
+const OFF_NEXT_PAGE: usize = 0;
+const OFF_LAST_PAGE: usize = 4;
+const OFF_ORDINAL: usize = 8;
+
+/// Tuple lớn nhất vừa 1 heap page rỗng.
+pub const MAX_RECORD_SIZE: usize =
+    PAGE_SIZE - SLOTTED_HEADER_SIZE - HEAP_SPECIAL_SIZE - SLOTTED_SLOT_SIZE;
+
+fn read_page(pager: &mut dyn Pager, pid: PageId) -> DbResult<Vec<u8>> {
+    let mut buf = vec![0u8; PAGE_SIZE];
+    pager.read_page(pid, &mut buf)?;
+    check_heap_page(&buf)?;
+    Ok(buf)
+}
+
+fn check_heap_page(buf: &[u8]) -> DbResult<()> {
+    let flags = header::flags(buf)?;
+    if !header::is_page_type(flags, PAGE_TYPE_HEAP)
+        || header::special_size(flags) != HEAP_SPECIAL_SIZE
+    {
+        return Err(DbError::Corruption("not a heap page"));
+    }
+    Ok(())
+}
+
+fn read_special_u32(buf: &mut [u8], off: usize) -> DbResult<u32> {
+    let page = SlottedPage::new(buf)?;
+    read_u32_le(page.special()?, off)
+}
+
+fn write_special_u32(buf: &mut [u8], off: usize, v: u32) -> DbResult<()> {
+    let mut page = SlottedPage::new(buf)?;
+    write_u32_le(page.special_mut()?, off, v)
+}
+
+fn read_special_pid(buf: &mut [u8], off: usize) -> DbResult<PageId> {
+    read_special_u32(buf, off).map(PageId)
+}
+
+fn write_special_pid(buf: &mut [u8], off: usize, pid: PageId) -> DbResult<()> {
+    write_special_u32(buf, off, pid.as_u32())
+}
+
+/// Đọc next page id từ special area.
+pub fn next_page(buf: &mut [u8]) -> DbResult<PageId> {
+    read_special_pid(buf, OFF_NEXT_PAGE)
+}
+
+pub fn set_next_page(buf: &mut [u8], next: PageId) -> DbResult<()> {
+    write_special_pid(buf, OFF_NEXT_PAGE, next)
+}
+
+/// Page cuối chuỗi (chỉ đọc trên page đầu); INVALID nghĩa là chuỗi chỉ có page đầu.
+fn last_page(buf: &mut [u8]) -> DbResult<PageId> {
+    read_special_pid(buf, OFF_LAST_PAGE)
+}
+
+fn set_last_page(buf: &mut [u8], last: PageId) -> DbResult<()> {
+    write_special_pid(buf, OFF_LAST_PAGE, last)
+}
+
+/// Thứ tự của page trong chuỗi heap (địa chỉ của page trong FSM).
+fn ordinal(buf: &mut [u8]) -> DbResult<u32> {
+    read_special_u32(buf, OFF_ORDINAL)
+}
+
+fn free_space(buf: &mut [u8]) -> DbResult<usize> {
+    Ok(SlottedPage::new(buf)?.free_space()? as usize)
+}
+
+/// Số bytes tối thiểu 1 tuple chiếm trong page: đủ để ghi đè bằng stub redirect.
+const MIN_STORED_SIZE: usize = RecordId::ENCODED_SIZE;
+
+/// Bytes lưu trong page của tuple `data`, và tuple có bị pad không.
+fn to_stored(data: &[u8]) -> (Cow<'_, [u8]>, bool) {
+    if data.len() >= MIN_STORED_SIZE {
+        return (Cow::Borrowed(data), false);
+    }
+    let mut stored = vec![0u8; MIN_STORED_SIZE];
+    stored[..data.len()].copy_from_slice(data);
+    stored[MIN_STORED_SIZE - 1] = data.len() as u8;
+    (Cow::Owned(stored), true)
+}
+
+/// Tuple thật từ bytes lưu trong slot có `flags`.
+fn from_stored(flags: u16, stored: &[u8]) -> DbResult<&[u8]> {
+    if !slot::is_padded(flags) {
+        return Ok(stored);
+    }
+    let len = match stored {
+        [.., len] if stored.len() == MIN_STORED_SIZE => *len as usize,
+        _ => return Err(DbError::Corruption("bad padded tuple size")),
+    };
+    if len >= MIN_STORED_SIZE {
+        return Err(DbError::Corruption("bad padded tuple length"));
+    }
+    Ok(&stored[..len])
+}
+
+/// Set/clear flag PADDED của slot theo bytes vừa ghi vào slot.
+fn set_padded(page: &mut SlottedPage<'_>, slot_id: u16, padded: bool) -> DbResult<()> {
+    let mut s = page.slot(slot_id)?;
+    if padded {
+        s.mark_flags_padded();
+    } else {
+        s.clear_flags_padded();
+    }
+    page.set_slot_flags(slot_id, s.flags())
+}
+
+fn encode_stub(target: RecordId) -> DbResult<[u8; RecordId::ENCODED_SIZE]> {
+    let mut stub = [0u8; RecordId::ENCODED_SIZE];
+    target.encode(&mut stub, 0)?;
+    Ok(stub)
+}
+
+/// Handle của 1 heap table: page đầu chuỗi + FSM của table.
+/// Catalog lưu `first_page` và `fsm_root`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeapFile {
+    first: PageId,
+    fsm: FreeSpaceMap,
+}
+
+impl HeapFile {
+    /// Tạo table mới gồm 1 heap page rỗng và FSM của nó.
+    pub fn create(pager: &mut dyn Pager) -> DbResult<Self> {
+        let fsm = FreeSpaceMap::create(pager)?;
+        let mut heap = HeapFile {
+            first: PageId::INVALID,
+            fsm,
+        };
+        heap.first = heap.alloc_heap_page(pager, 0)?;
+        Ok(heap)
+    }
+
+    /// Mở table đã có từ page đầu chuỗi và FSM root.
+    pub fn open(first: PageId, fsm_root: PageId) -> Self {
+        HeapFile {
+            first,
+            fsm: FreeSpaceMap::open(fsm_root),
+        }
+    }
+
+    pub fn first_page(&self) -> PageId {
+        self.first
+    }
+
+    pub fn fsm_root(&self) -> PageId {
+        self.fsm.root_page()
+    }
+
+    /// Tìm heap page (theo FSM) có ít nhất `bytes` bytes trống.
+    pub fn find_page_with_space(
+        &self,
+        pager: &mut dyn Pager,
+        bytes: usize,
+    ) -> DbResult<Option<PageId>> {
+        self.fsm.find_page_with_space(pager, bytes)
+    }
+
+    /// Ghi heap page và cập nhật free space của nó trong FSM.
+    fn write_page(&self, pager: &mut dyn Pager, pid: PageId, buf: &mut [u8]) -> DbResult<()> {
+        pager.write_page(pid, buf)?;
+        self.update_fsm(pager, pid, buf)
+    }
+
+    fn update_fsm(&self, pager: &mut dyn Pager, pid: PageId, buf: &mut [u8]) -> DbResult<()> {
+        let ordinal = ordinal(buf)?;
+        self.fsm.update(pager, pid, ordinal, free_space(buf)?)
+    }
+
+    /// Cấp phát + init 1 heap page rỗng thứ `ordinal` (chưa nối vào chuỗi).
+    fn alloc_heap_page(&self, pager: &mut dyn Pager, ordinal: u32) -> DbResult<PageId> {
+        let pid = pager.alloc_page()?;
+        let mut buf = vec![0u8; PAGE_SIZE];
+        SlottedPage::new(&mut buf)?.init_with_special(PAGE_TYPE_HEAP, HEAP_SPECIAL_SIZE)?;
+        set_next_page(&mut buf, PageId::INVALID)?;
+        set_last_page(&mut buf, PageId::INVALID)?;
+        write_special_u32(&mut buf, OFF_ORDINAL, ordinal)?;
+        self.write_page(pager, pid, &mut buf)?;
+        Ok(pid)
+    }
+
+    /// Nối 1 page mới vào cuối chuỗi (dùng last pointer ở page đầu).
+    fn append_page(&self, pager: &mut dyn Pager) -> DbResult<PageId> {
+        let mut first_buf = read_page(pager, self.first)?;
+        let last = match last_page(&mut first_buf)? {
+            p if p == PageId::INVALID => self.first,
+            p => p,
+        };
+
+        let new_pid = if last == self.first {
+            let new_pid = self.alloc_heap_page(pager, ordinal(&mut first_buf)? + 1)?;
+            set_next_page(&mut first_buf, new_pid)?;
+            new_pid
+        } else {
+            let mut last_buf = read_page(pager, last)?;
+            let new_pid = self.alloc_heap_page(pager, ordinal(&mut last_buf)? + 1)?;
+            set_next_page(&mut last_buf, new_pid)?;
+            pager.write_page(last, &last_buf)?;
+            new_pid
+        };
+        set_last_page(&mut first_buf, new_pid)?;
+        pager.write_page(self.first, &first_buf)?;
+        Ok(new_pid)
+    }
+
+    /// Insert vào 1 page cụ thể; page đầy -> Ok(None), page không bị ghi
+    /// (FSM được sửa lại theo free space thật).
+    fn try_insert_in_page(
+        &self,
+        pager: &mut dyn Pager,
+        pid: PageId,
+        data: &[u8],
+        slot_flags: u16,
+    ) -> DbResult<Option<u16>> {
+        let (stored, padded) = to_stored(data);
+        let mut buf = read_page(pager, pid)?;
+        let mut page = SlottedPage::new(&mut buf)?;
+        let slot_id = match page.insert(&stored) {
+            Ok(id) => id,
+            Err(DbError::NoSpace(_)) => {
+                self.update_fsm(pager, pid, &mut buf)?;
+                return Ok(None);
+            }
+            Err(e) => return Err(e),
+        };
+        if slot_flags != 0 {
+            page.set_slot_flags(slot_id, slot_flags)?;
+        }
+        set_padded(&mut page, slot_id, padded)?;
+        self.write_page(pager, pid, &mut buf)?;
+        Ok(Some(slot_id))
+    }
+
+    /// Update tuple trong chính page của nó, compact page nếu cần.
+    /// Không đủ chỗ kể cả sau compact -> Ok(false), data cũ giữ nguyên.
+    fn try_update_in_page(
+        &self,
+        pager: &mut dyn Pager,
+        pid: PageId,
+        slot_id: u16,
+        data: &[u8],
+    ) -> DbResult<bool> {
+        let (stored, padded) = to_stored(data);
+        let mut buf = read_page(pager, pid)?;
+        let mut page = SlottedPage::new(&mut buf)?;
+        let updated = match page.update(slot_id, &stored) {
+            Ok(_) => true,
+            Err(DbError::NoSpace(_)) => {
+                page.compact()?;
+                match page.update(slot_id, &stored) {
+                    Ok(_) => true,
+                    Err(DbError::NoSpace(_)) => false,
+                    Err(e) => return Err(e),
+                }
+            }
+            Err(e) => return Err(e),
+        };
+        if updated {
+            set_padded(&mut page, slot_id, padded)?;
+        }
+        // ghi cả khi chỉ compact được (free space đã tăng)
+        self.write_page(pager, pid, &mut buf)?;
+        Ok(updated)
+    }
+
+    /// Xoá 1 slot (không follow redirect).
+    fn delete_in_page(&self, pager: &mut dyn Pager, rid: RecordId) -> DbResult<()> {
+        let mut buf = read_page(pager, rid.page)?;
+        SlottedPage::new(&mut buf)?.delete(rid.slot)?;
+        self.write_page(pager, rid.page, &mut buf)
+    }
+
+    /// Compact 1 heap page (gom garbage), trả về số bytes free tăng thêm.
+    pub fn compact_page(&self, pager: &mut dyn Pager, pid: PageId) -> DbResult<u16> {
+        let mut buf = read_page(pager, pid)?;
+        let gained = SlottedPage::new(&mut buf)?.compact()?;
+        self.write_page(pager, pid, &mut buf)?;
+        Ok(gained)
+    }
+
+    /// Insert tuple, trả về RID.
+    pub fn insert(&self, pager: &mut dyn Pager, data: &[u8]) -> DbResult<RecordId> {
+        self.insert_with_flags(pager, data, 0, None)
+    }
+
+    /// Chọn page còn chỗ qua FSM, không có thì cấp page mới nối vào cuối chuỗi.
+    /// `skip`: page không được chọn (page gốc khi chuyển tuple đi chỗ khác).
+    fn insert_with_flags(
+        &self,
+        pager: &mut dyn Pager,
+        data: &[u8],
+        slot_flags: u16,
+        skip: Option<PageId>,
+    ) -> DbResult<RecordId> {
+        if data.len() > MAX_RECORD_SIZE {
+            return Err(DbError::NoSpace("record does not fit in a heap page"));
+        }
+
+        // cần chỗ cho data + 1 slot entry mới (trường hợp xấu nhất)
+        let needed = data.len().max(MIN_STORED_SIZE) + SLOTTED_SLOT_SIZE;
+        while let Some(pid) = self.fsm.find_page_with_space_except(pager, needed, skip)? {
+            if let Some(slot_id) = self.try_insert_in_page(pager, pid, data, slot_flags)? {
+                return Ok(RecordId::new(pid, slot_id));
+            }
+            // FSM stale: try_insert_in_page đã sửa lại entry, tìm tiếp
+        }
+
+        // không page nào đủ chỗ: nối page mới
+        let pid = self.append_page(pager)?;
+        match self.try_insert_in_page(pager, pid, data, slot_flags)? {
+            Some(slot_id) => Ok(RecordId::new(pid, slot_id)),
+            None => Err(DbError::Corruption(
+                "record does not fit in a new heap page",
+            )),
+        }
+    }
+
+    /// Đọc tuple theo RID. Trả None nếu tuple đã bị xoá.
+    pub fn get(&self, pager: &mut dyn Pager, rid: RecordId) -> DbResult<Option<Vec<u8>>> {
+        let mut buf = read_page(pager, rid.page)?;
+        let page = SlottedPage::new(&mut buf)?;
+        let s = page.slot(rid.slot)?;
+        if slot::is_dead(s.flags()) {
+            return Ok(None);
+        }
+        if slot::is_moved(s.flags()) {
+            return Err(DbError::InvalidArgument(
+                "record id points to a moved tuple",
+            ));
+        }
+
+        let data = page
+            .get(rid.slot)?
+            .ok_or(DbError::Corruption("live slot has no data"))?;
+        if !slot::is_redirected(s.flags()) {
+            return Ok(Some(from_stored(s.flags(), data)?.to_vec()));
+        }
+
+        let target = RecordId::decode(data, 0)?;
+        self.get_moved(pager, target).map(Some)
+    }
+
+    /// Đọc tuple đích của redirect stub.
+    fn get_moved(&self, pager: &mut dyn Pager, target: RecordId) -> DbResult<Vec<u8>> {
+        let mut buf = read_page(pager, target.page)?;
+        let page = SlottedPage::new(&mut buf)?;
+        let s = page.slot(target.slot)?;
+        if slot::is_dead(s.flags()) || !slot::is_moved(s.flags()) {
+            return Err(DbError::Corruption("redirect points to a non-moved tuple"));
+        }
+        let data = page
+            .get(target.slot)?
+            .ok_or(DbError::Corruption("live slot has no data"))?;
+        Ok(from_stored(s.flags(), data)?.to_vec())
+    }
+
+    /// Đọc slot entry của tuple gốc, reject RID không hợp lệ cho update/delete.
+    fn home_slot(&self, pager: &mut dyn Pager, rid: RecordId) -> DbResult<(slot::Slot, Vec<u8>)> {
+        let mut buf = read_page(pager, rid.page)?;
+        let page = SlottedPage::new(&mut buf)?;
+        let s = page.slot(rid.slot)?;
+        if slot::is_dead(s.flags()) {
+            return Err(DbError::InvalidArgument("record is deleted"));
+        }
+        if slot::is_moved(s.flags()) {
+            return Err(DbError::InvalidArgument(
+                "record id points to a moved tuple",
+            ));
+        }
+        let data = page
+            .get(rid.slot)?
+            .ok_or(DbError::Corruption("live slot has no data"))?
+            .to_vec();
+        Ok((s, data))
+    }
+
+    /// Update tuple, RID không đổi.
+    pub fn update(&self, pager: &mut dyn Pager, rid: RecordId, data: &[u8]) -> DbResult<()> {
+        let (s, stub) = self.home_slot(pager, rid)?;
+
+        if slot::is_redirected(s.flags()) {
+            let target = RecordId::decode(&stub, 0)?;
+            if self.try_update_in_page(pager, target.page, target.slot, data)? {
+                return Ok(());
+            }
+            // chuyển tiếp sang page khác, stub cùng size nên ghi đè in-place
+            let new_target =
+                self.insert_with_flags(pager, data, moved_flags(), Some(target.page))?;
+            self.delete_in_page(pager, target)?;
+            return self.write_stub(pager, rid, new_target);
+        }
+
+        if self.try_update_in_page(pager, rid.page, rid.slot, data)? {
+            return Ok(());
+        }
+
+        // page gốc không đủ chỗ: chuyển tuple đi, slot gốc thành stub
+        let target = self.insert_with_flags(pager, data, moved_flags(), Some(rid.page))?;
+        match self.write_stub(pager, rid, target) {
+            Ok(()) => Ok(()),
+            Err(e) => {
+                // không ghi được stub -> bỏ tuple vừa chuyển đi, tuple cũ giữ nguyên
+                self.delete_in_page(pager, target)?;
+                Err(e)
+            }
+        }
+    }
+
+    /// Ghi stub REDIRECTED -> target vào slot gốc.
+    fn write_stub(&self, pager: &mut dyn Pager, home: RecordId, target: RecordId) -> DbResult<()> {
+        let stub = encode_stub(target)?;
+        if !self.try_update_in_page(pager, home.page, home.slot, &stub)? {
+            return Err(DbError::NoSpace("no space for redirect stub"));
+        }
+        let mut buf = read_page(pager, home.page)?;
+        let mut page = SlottedPage::new(&mut buf)?;
+        let mut s = page.slot(home.slot)?;
+        s.mark_flags_redirected();
+        page.set_slot_flags(home.slot, s.flags())?;
+        self.write_page(pager, home.page, &mut buf)
+    }
+
+    /// Xoá tuple (idempotent). Stub REDIRECTED thì xoá luôn tuple đích.
+    pub fn delete(&self, pager: &mut dyn Pager, rid: RecordId) -> DbResult<()> {
+        let mut buf = read_page(pager, rid.page)?;
+        let page = SlottedPage::new(&mut buf)?;
+        let s = page.slot(rid.slot)?;
+        if slot::is_dead(s.flags()) {
+            return Ok(());
+        }
+        if slot::is_moved(s.flags()) {
+            return Err(DbError::InvalidArgument(
+                "record id points to a moved tuple",
+            ));
+        }
+        if slot::is_redirected(s.flags()) {
+            let stub = page
+                .get(rid.slot)?
+                .ok_or(DbError::Corruption("live slot has no data"))?;
+            let target = RecordId::decode(stub, 0)?;
+            self.delete_in_page(pager, target)?;
+        }
+        self.delete_in_page(pager, rid)
+    }
+
+    /// Full scan theo chuỗi page. Tuple bị chuyển đi vẫn trả về với RID gốc.
+    pub fn scan<'p>(&self, pager: &'p mut dyn Pager) -> HeapScan<'p> {
+        HeapScan {
+            heap: *self,
+            pager,
+            next_page: self.first,
+            cur: None,
+            done: false,
+        }
+    }
+}
+
+fn moved_flags() -> u16 {
+    let mut s = slot::Slot::new(0, 0, 0);
+    s.mark_flags_moved();
+    s.flags()
+}
+
+/// Page đang scan: (page id, bytes, slot kế tiếp).
+struct ScanPage {
+    pid: PageId,
+    buf: Vec<u8>,
+    slot: u16,
+}
+
+/// Iterator của `HeapFile::scan`, trả về `(RecordId, data)`.
+/// Gặp lỗi thì trả Err 1 lần rồi dừng.
+pub struct HeapScan<'p> {
+    heap: HeapFile,
+    pager: &'p mut dyn Pager,
+    next_page: PageId,
+    cur: Option<ScanPage>,
+    done: bool,
+}
+
+impl HeapScan<'_> {
+    fn advance(&mut self) -> DbResult<Option<(RecordId, Vec<u8>)>> {
+        loop {
+            let Some(cur) = self.cur.as_mut() else {
+                if self.next_page == PageId::INVALID {
+                    return Ok(None);
+                }
+                let pid = self.next_page;
+                let mut buf = read_page(self.pager, pid)?;
+                self.next_page = next_page(&mut buf)?;
+                self.cur = Some(ScanPage { pid, buf, slot: 0 });
+                continue;
+            };
+
+            let page = SlottedPage::new(&mut cur.buf)?;
+            if cur.slot >= page.slot_count()? {
+                self.cur = None;
+                continue;
+            }
+            let slot_id = cur.slot;
+            cur.slot += 1;
+
+            let s = page.slot(slot_id)?;
+            // tuple MOVED được trả về qua stub gốc
+            if slot::is_dead(s.flags()) || slot::is_moved(s.flags()) {
+                continue;
+            }
+            let rid = RecordId::new(cur.pid, slot_id);
+            let data = page
+                .get(slot_id)?
+                .ok_or(DbError::Corruption("live slot has no data"))?;
+            let data = from_stored(s.flags(), data)?.to_vec();
+            if !slot::is_redirected(s.flags()) {
+                return Ok(Some((rid, data)));
+            }
+            let target = RecordId::decode(&data, 0)?;
+            let moved = self.heap.get_moved(self.pager, target)?;
+            return Ok(Some((rid, moved)));
+        }
+    }
+}
+
+impl Iterator for HeapScan<'_> {
+    type Item = DbResult<(RecordId, Vec<u8>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match self.advance() {
+            Ok(Some(item)) => Some(Ok(item)),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pager::mem::MemPager;
+    use std::collections::BTreeMap;
+
+    fn scan_all(heap: &HeapFile, pager: &mut dyn Pager) -> BTreeMap<RecordId, Vec<u8>> {
+        heap.scan(pager).map(|r| r.unwrap()).collect()
+    }
+
+    #[test]
+    fn test_insert_get() {
+        let mut pager = MemPager::new();
+        let heap = HeapFile::create(&mut pager).unwrap();
+
+        let r1 = heap.insert(&mut pager, b"hello").unwrap();
+        let r2 = heap.insert(&mut pager, b"world!").unwrap();
+        assert_eq!(r1, RecordId::new(heap.first_page(), 0));
+        assert_eq!(r2, RecordId::new(heap.first_page(), 1));
+
+        assert_eq!(heap.get(&mut pager, r1).unwrap().unwrap(), b"hello");
+        assert_eq!(heap.get(&mut pager, r2).unwrap().unwrap(), b"world!");
+    }
+
+    #[test]
+    fn test_insert_spills_to_new_pages() {
+        let mut pager = MemPager::new();
+        let heap = HeapFile::create(&mut pager).unwrap();
+
+        let mut rids = Vec::new();
+        for i in 0..100u32 {
+            let data = vec![i as u8; 200];
+            rids.push(heap.insert(&mut pager, &data).unwrap());
+        }
+        let pages: std::collections::BTreeSet<PageId> = rids.iter().map(|r| r.page).collect();
+        assert!(pages.len() > 1, "100 x 200 bytes must span several pages");
+
+        for (i, rid) in rids.iter().enumerate() {
+            assert_eq!(
+                heap.get(&mut pager, *rid).unwrap().unwrap(),
+                vec![i as u8; 200]
+            );
+        }
+
+        // scan thấy đủ, theo thứ tự chuỗi page
+        let scanned: Vec<RecordId> = heap.scan(&mut pager).map(|r| r.unwrap().0).collect();
+        assert_eq!(scanned, rids);
+    }
+
+    #[test]
+    fn test_insert_reuses_space_via_fsm() {
+        let mut pager = MemPager::new();
+        let heap = HeapFile::create(&mut pager).unwrap();
+
+        let mut rids = Vec::new();
+        for i in 0..60u32 {
+            rids.push(heap.insert(&mut pager, &[i as u8; 200]).unwrap());
+        }
+        let first = heap.first_page();
+        assert!(rids.iter().any(|r| r.page != first));
+
+        // page đầu đầy -> FSM không chọn nó cho tuple lớn
+        let found = heap.find_page_with_space(&mut pager, 1000).unwrap();
+        assert_ne!(found, Some(first));
+
+        // xoá hết tuple ở page đầu + compact -> FSM thấy page đầu trống lại
+        for rid in rids.iter().filter(|r| r.page == first) {
+            heap.delete(&mut pager, *rid).unwrap();
+        }
+        assert!(heap.compact_page(&mut pager, first).unwrap() > 0);
+        assert_eq!(
+            heap.find_page_with_space(&mut pager, 3000).unwrap(),
+            Some(first)
+        );
+
+        let rid = heap.insert(&mut pager, &[0xEE; 3000]).unwrap();
+        assert_eq!(rid.page, first);
+        assert_eq!(
+            heap.get(&mut pager, rid).unwrap().unwrap(),
+            vec![0xEE; 3000]
+        );
+    }
+
+    #[test]
+    fn test_record_too_large() {
+        let mut pager = MemPager::new();
+        let heap = HeapFile::create(&mut pager).unwrap();
+        let err = heap.insert(&mut pager, &vec![0u8; PAGE_SIZE]).unwrap_err();
+        assert!(matches!(err, DbError::NoSpace(_)));
+    }
+
+    #[test]
+    fn test_delete() {
+        let mut pager = MemPager::new();
+        let heap = HeapFile::create(&mut pager).unwrap();
+        let r1 = heap.insert(&mut pager, b"a").unwrap();
+        let r2 = heap.insert(&mut pager, b"b").unwrap();
+
+        heap.delete(&mut pager, r1).unwrap();
+        heap.delete(&mut pager, r1).unwrap(); // idempotent
+        assert!(heap.get(&mut pager, r1).unwrap().is_none());
+        assert!(matches!(
+            heap.update(&mut pager, r1, b"x").unwrap_err(),
+            DbError::InvalidArgument(_)
+        ));
+
+        let all = scan_all(&heap, &mut pager);
+        assert_eq!(all.len(), 1);
+        assert_eq!(all[&r2], b"b");
+    }
+
+    #[test]
+    fn test_update_in_place_and_grow() {
+        let mut pager = MemPager::new();
+        let heap = HeapFile::create(&mut pager).unwrap();
+        let rid = heap.insert(&mut pager, b"short").unwrap();
+
+        heap.update(&mut pager, rid, b"s").unwrap();
+        assert_eq!(heap.get(&mut pager, rid).unwrap().unwrap(), b"s");
+
+        heap.update(&mut pager, rid, b"a bit longer than before")
+            .unwrap();
+        assert_eq!(
+            heap.get(&mut pager, rid).unwrap().unwrap(),
+            b"a bit longer than before"
+        );
+    }
+
+    /// Fill page đầu rồi grow 1 tuple -> phải chuyển sang page khác, RID giữ nguyên.
+    #[test]
+    fn test_update_moves_tuple_keeps_rid() {
+        let mut pager = MemPager::new();
+        let heap = HeapFile::create(&mut pager).unwrap();
+
+        let mut rids = Vec::new();
+        for i in 0..19u8 {
+            rids.push(heap.insert(&mut pager, &[i; 200]).unwrap());
+        }
+        assert!(rids.iter().all(|r| r.page == heap.first_page()));
+
+        let rid = rids[3];
+        let big = vec![0xAB; 1500];
+        heap.update(&mut pager, rid, &big).unwrap();
+        assert_eq!(heap.get(&mut pager, rid).unwrap().unwrap(), big);
+
+        // scan trả về tuple đã chuyển dưới RID gốc, không bị lặp
+        let all = scan_all(&heap, &mut pager);
+        assert_eq!(all.len(), 19);
+        assert_eq!(all[&rid], big);
+
+        // update tiếp (vẫn vừa page đích) rồi grow tiếp (phải chuyển lần 2)
+        heap.update(&mut pager, rid, b"tiny").unwrap();
+        assert_eq!(heap.get(&mut pager, rid).unwrap().unwrap(), b"tiny");
+
+        // lấp page đích để lần grow sau phải chuyển tiếp
+        for _ in 0..20 {
+            heap.insert(&mut pager, &[1u8; 200]).unwrap();
+        }
+        let bigger = vec![0xCD; 3000];
+        heap.update(&mut pager, rid, &bigger).unwrap();
+        assert_eq!(heap.get(&mut pager, rid).unwrap().unwrap(), bigger);
+        let all = scan_all(&heap, &mut pager);
+        assert_eq!(all.len(), 39);
+        assert_eq!(all[&rid], bigger);
+
+        // delete stub xoá luôn tuple đích
+        heap.delete(&mut pager, rid).unwrap();
+        assert!(heap.get(&mut pager, rid).unwrap().is_none());
+        assert_eq!(scan_all(&heap, &mut pager).len(), 38);
+    }
+
+    /// Tuple ngắn hơn stub trên page đầy vẫn chuyển đi được (slot gốc đủ chỗ cho stub).
+    #[test]
+    fn test_short_tuple_moves_from_full_page() {
+        let mut pager = MemPager::new();
+        let heap = HeapFile::create(&mut pager).unwrap();
+
+        let short = heap.insert(&mut pager, b"x").unwrap();
+        let empty = heap.insert(&mut pager, b"").unwrap();
+        assert_eq!(heap.get(&mut pager, short).unwrap().unwrap(), b"x");
+        assert_eq!(heap.get(&mut pager, empty).unwrap().unwrap(), b"");
+
+        // lấp page đầu tới 0 byte trống
+        let mut buf = read_page(&mut pager, heap.first_page()).unwrap();
+        let free = free_space(&mut buf).unwrap();
+        let filler = vec![7u8; free - SLOTTED_SLOT_SIZE];
+        heap.try_insert_in_page(&mut pager, heap.first_page(), &filler, 0)
+            .unwrap()
+            .unwrap();
+        let mut buf = read_page(&mut pager, heap.first_page()).unwrap();
+        assert_eq!(free_space(&mut buf).unwrap(), 0);
+
+        let big = vec![0xAB; 2000];
+        heap.update(&mut pager, short, &big).unwrap();
+        heap.update(&mut pager, empty, b"yz").unwrap();
+        assert_eq!(heap.get(&mut pager, short).unwrap().unwrap(), big);
+        assert_eq!(heap.get(&mut pager, empty).unwrap().unwrap(), b"yz");
+
+        // tuple đích co lại rồi scan: bytes pad không lộ ra
+        heap.update(&mut pager, short, b"ab").unwrap();
+        let all = scan_all(&heap, &mut pager);
+        assert_eq!(all.len(), 3);
+        assert_eq!(all[&short], b"ab");
+        assert_eq!(all[&empty], b"yz");
+    }
+
+    #[test]
+    fn test_update_compacts_before_moving() {
+        let mut pager = MemPager::new();
+        let heap = HeapFile::create(&mut pager).unwrap();
+
+        let mut rids = Vec::new();
+        for i in 0..19u8 {
+            rids.push(heap.insert(&mut pager, &[i; 200]).unwrap());
+        }
+        // xoá 2 tuple -> đủ garbage cho 1 tuple 400 bytes sau compact
+        heap.delete(&mut pager, rids[0]).unwrap();
+        heap.delete(&mut pager, rids[1]).unwrap();
+
+        heap.update(&mut pager, rids[5], &[9u8; 400]).unwrap();
+        let mut buf = read_page(&mut pager, heap.first_page()).unwrap();
+        let page = SlottedPage::new(&mut buf).unwrap();
+        let s = page.slot(rids[5].slot).unwrap();
+        assert!(
+            !slot::is_redirected(s.flags()),
+            "must update in place after compact"
+        );
+        assert_eq!(page.get(rids[5].slot).unwrap().unwrap(), &[9u8; 400][..]);
+    }
+
+    #[test]
+    fn test_moved_rid_is_rejected() {
+        let mut pager = MemPager::new();
+        let heap = HeapFile::create(&mut pager).unwrap();
+        for i in 0..19u8 {
+            heap.insert(&mut pager, &[i; 200]).unwrap();
+        }
+        let rid = RecordId::new(heap.first_page(), 0);
+        heap.update(&mut pager, rid, &[7u8; 1000]).unwrap();
+
+        let mut buf = read_page(&mut pager, rid.page).unwrap();
+        let stub = SlottedPage::new(&mut buf)
+            .unwrap()
+            .get(rid.slot)
+            .unwrap()
+            .unwrap()
+            .to_vec();
+        let target = RecordId::decode(&stub, 0).unwrap();
+        assert_ne!(target.page, rid.page);
+
+        assert!(heap.get(&mut pager, target).is_err());
+        assert!(heap.delete(&mut pager, target).is_err());
+        assert!(heap.update(&mut pager, target, b"x").is_err());
+    }
+
+    #[test]
+    fn test_open_existing() {
+        let mut pager = MemPager::new();
+        let heap = HeapFile::create(&mut pager).unwrap();
+        let rid = heap.insert(&mut pager, b"persist").unwrap();
+
+        let reopened = HeapFile::open(heap.first_page(), heap.fsm_root());
+        assert_eq!(reopened.get(&mut pager, rid).unwrap().unwrap(), b"persist");
+
+        // page không phải heap -> Corruption
+        let other = pager.alloc_page().unwrap();
+        let bad = HeapFile::open(other, heap.fsm_root());
+        assert!(matches!(
+            bad.get(&mut pager, RecordId::new(other, 0)).unwrap_err(),
+            DbError::Corruption(_)
+        ));
+    }
+}
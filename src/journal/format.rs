@@ -0,0 +1,190 @@
+//! Layout on-disk của file rollback journal (little-endian).
+//!
+//! ```text
+//! [header 32 bytes][record 0][record 1]...
+//! header: [magic u32][version u32][page_size u32][nonce u32]
+//!         [orig_pages u32][records u32][checksum1 u32][checksum2 u32]
+//! record: [page_id u32][nonce u32][page data PAGE_SIZE][checksum1 u32][checksum2 u32]
+//! ```
+//!
+//! - Record là ảnh gốc (trước transaction) của 1 page; `orig_pages` là số page của
+//!   database trước transaction, rollback cắt file về đúng số đó.
+//! - `nonce` đổi mỗi transaction: record cũ còn sót lại trong file (chế độ persist)
+//!   mang nonce khác nên không bao giờ được dùng nhầm.
+//! - Header bị zero (hoặc hỏng) = journal không còn hiệu lực.
+
+use crate::constants::PAGE_SIZE;
+use crate::page::raw::{read_u32_le, write_u32_le};
+use crate::wal::format::checksum;
+use crate::{DbError, DbResult, PageId};
+
+/// "NVJR"
+pub const JOURNAL_MAGIC: u32 = 0x524A_564E;
+pub const JOURNAL_VERSION: u32 = 1;
+
+pub const JOURNAL_HEADER_SIZE: usize = 32;
+pub const RECORD_HEADER_SIZE: usize = 8;
+pub const RECORD_TRAILER_SIZE: usize = 8;
+pub const RECORD_SIZE: usize = RECORD_HEADER_SIZE + PAGE_SIZE + RECORD_TRAILER_SIZE;
+
+// header
+const OFF_MAGIC: usize = 0;
+const OFF_VERSION: usize = 4;
+const OFF_PAGE_SIZE: usize = 8;
+const OFF_NONCE: usize = 12;
+const OFF_ORIG_PAGES: usize = 16;
+const OFF_RECORDS: usize = 20;
+const OFF_CHECKSUM1: usize = 24;
+const OFF_CHECKSUM2: usize = 28;
+/// Phần header được checksum (trước 2 field checksum).
+const HEADER_CHECKSUMMED: usize = OFF_CHECKSUM1;
+
+// record
+const OFF_RECORD_PID: usize = 0;
+const OFF_RECORD_NONCE: usize = 4;
+const OFF_RECORD_CHECKSUM1: usize = RECORD_HEADER_SIZE + PAGE_SIZE;
+const OFF_RECORD_CHECKSUM2: usize = OFF_RECORD_CHECKSUM1 + 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JournalHeader {
+    pub nonce: u32,
+    /// Số page của database trước transaction.
+    pub orig_pages: u32,
+    /// Số record ảnh gốc theo sau header.
+    pub records: u32,
+}
+
+impl JournalHeader {
+    pub fn encode(&self, buf: &mut [u8]) -> DbResult<()> {
+        if buf.len() != JOURNAL_HEADER_SIZE {
+            return Err(DbError::InvalidArgument(
+                "buffer length must equal JOURNAL_HEADER_SIZE",
+            ));
+        }
+        write_u32_le(buf, OFF_MAGIC, JOURNAL_MAGIC)?;
+        write_u32_le(buf, OFF_VERSION, JOURNAL_VERSION)?;
+        write_u32_le(buf, OFF_PAGE_SIZE, PAGE_SIZE as u32)?;
+        write_u32_le(buf, OFF_NONCE, self.nonce)?;
+        write_u32_le(buf, OFF_ORIG_PAGES, self.orig_pages)?;
+        write_u32_le(buf, OFF_RECORDS, self.records)?;
+        let sum = checksum(&buf[..HEADER_CHECKSUMMED], (0, 0));
+        write_u32_le(buf, OFF_CHECKSUM1, sum.0)?;
+        write_u32_le(buf, OFF_CHECKSUM2, sum.1)?;
+        Ok(())
+    }
+
+    /// Đọc + validate header. `None`: journal không còn hiệu lực (bị zero, ghi dở).
+    pub fn decode(buf: &[u8]) -> DbResult<Option<JournalHeader>> {
+        if buf.len() != JOURNAL_HEADER_SIZE {
+            return Err(DbError::InvalidArgument(
+                "buffer length must equal JOURNAL_HEADER_SIZE",
+            ));
+        }
+        if read_u32_le(buf, OFF_MAGIC)? != JOURNAL_MAGIC {
+            return Ok(None);
+        }
+        let sum = checksum(&buf[..HEADER_CHECKSUMMED], (0, 0));
+        if (
+            read_u32_le(buf, OFF_CHECKSUM1)?,
+            read_u32_le(buf, OFF_CHECKSUM2)?,
+        ) != sum
+        {
+            return Ok(None);
+        }
+        // header hợp lệ nhưng khác format: không được bỏ qua vì database có thể đang dở
+        if read_u32_le(buf, OFF_VERSION)? != JOURNAL_VERSION {
+            return Err(DbError::Corruption("unsupported journal version"));
+        }
+        if read_u32_le(buf, OFF_PAGE_SIZE)? as usize != PAGE_SIZE {
+            return Err(DbError::Corruption("journal page size mismatch"));
+        }
+        Ok(Some(JournalHeader {
+            nonce: read_u32_le(buf, OFF_NONCE)?,
+            orig_pages: read_u32_le(buf, OFF_ORIG_PAGES)?,
+            records: read_u32_le(buf, OFF_RECORDS)?,
+        }))
+    }
+}
+
+/// Ghi record ảnh gốc của `pid`; page data phải đã nằm sẵn trong `buf`.
+pub fn encode_record(buf: &mut [u8], pid: PageId, nonce: u32) -> DbResult<()> {
+    if buf.len() != RECORD_SIZE {
+        return Err(DbError::InvalidArgument(
+            "buffer length must equal RECORD_SIZE",
+        ));
+    }
+    write_u32_le(buf, OFF_RECORD_PID, pid.as_u32())?;
+    write_u32_le(buf, OFF_RECORD_NONCE, nonce)?;
+    let sum = checksum(&buf[..OFF_RECORD_CHECKSUM1], (0, 0));
+    write_u32_le(buf, OFF_RECORD_CHECKSUM1, sum.0)?;
+    write_u32_le(buf, OFF_RECORD_CHECKSUM2, sum.1)?;
+    Ok(())
+}
+
+/// Validate record theo nonce của journal, trả về page id + page data.
+/// `None`: record ghi dở hoặc thuộc journal cũ.
+pub fn decode_record(buf: &[u8], nonce: u32) -> DbResult<Option<(PageId, &[u8])>> {
+    if buf.len() != RECORD_SIZE {
+        return Err(DbError::InvalidArgument(
+            "buffer length must equal RECORD_SIZE",
+        ));
+    }
+    if read_u32_le(buf, OFF_RECORD_NONCE)? != nonce {
+        return Ok(None);
+    }
+    let sum = checksum(&buf[..OFF_RECORD_CHECKSUM1], (0, 0));
+    if (
+        read_u32_le(buf, OFF_RECORD_CHECKSUM1)?,
+        read_u32_le(buf, OFF_RECORD_CHECKSUM2)?,
+    ) != sum
+    {
+        return Ok(None);
+    }
+    let pid = PageId(read_u32_le(buf, OFF_RECORD_PID)?);
+    Ok(Some((pid, &buf[RECORD_HEADER_SIZE..OFF_RECORD_CHECKSUM1])))
+}
+
+/// Page data của record (để đọc ảnh gốc thẳng vào buffer record).
+pub fn record_page_mut(buf: &mut [u8]) -> &mut [u8] {
+    &mut buf[RECORD_HEADER_SIZE..OFF_RECORD_CHECKSUM1]
+}
+
+/// Offset của record thứ `idx` trong file journal.
+pub fn record_offset(idx: u32) -> u64 {
+    JOURNAL_HEADER_SIZE as u64 + idx as u64 * RECORD_SIZE as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_header_and_record_roundtrip() {
+        let header = JournalHeader {
+            nonce: 42,
+            orig_pages: 5,
+            records: 1,
+        };
+        let mut buf = [0u8; JOURNAL_HEADER_SIZE];
+        header.encode(&mut buf).unwrap();
+        assert_eq!(JournalHeader::decode(&buf).unwrap(), Some(header));
+        buf[OFF_ORIG_PAGES] ^= 1;
+        assert_eq!(JournalHeader::decode(&buf).unwrap(), None);
+        assert_eq!(
+            JournalHeader::decode(&[0u8; JOURNAL_HEADER_SIZE]).unwrap(),
+            None
+        );
+
+        let mut rec = vec![0u8; RECORD_SIZE];
+        record_page_mut(&mut rec).fill(0x3C);
+        encode_record(&mut rec, PageId(3), 42).unwrap();
+        let (pid, page) = decode_record(&rec, 42).unwrap().unwrap();
+        assert_eq!(pid, PageId(3));
+        assert!(page.iter().all(|&b| b == 0x3C));
+
+        // record của journal khác, record ghi dở
+        assert!(decode_record(&rec, 43).unwrap().is_none());
+        rec[RECORD_HEADER_SIZE + 1] = 0;
+        assert!(decode_record(&rec, 42).unwrap().is_none());
+    }
+}
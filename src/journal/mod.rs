@@ -0,0 +1,106 @@
+//! Rollback journal: chế độ ghi tại chỗ, thay thế cho WAL.
+//!
+//! Commit của 1 transaction:
+//! 1. ảnh gốc của mọi page sắp bị ghi đè được ghi vào `<db>-journal` và fsync;
+//! 2. page mới được ghi đè tại chỗ trong file database và fsync;
+//! 3. journal bị xoá (`JournalMode::Delete`) hoặc zero header (`JournalMode::Persist`).
+//!
+//! Crash giữa bước 1 và 3 để lại "hot journal": lần mở sau chép ảnh gốc về file
+//! database và cắt file về kích thước cũ trước khi cho đọc bất kỳ page nào. Bước 2/3
+//! lỗi (không crash) cũng để lại hot journal: pager rollback nó ngay, và không bắt
+//! đầu commit mới khi hot journal còn đó (ảnh gốc trong journal sẽ bị ghi đè bằng
+//! page đã ghi dở).
+//!
+//! - `format`: layout header/record của file journal.
+//! - `JournalPager`: `Pager` ghi qua rollback journal.
+
+pub mod format;
+mod pager;
+
+pub use pager::JournalPager;
+
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+
+use crate::pager::file::FilePager;
+use crate::pager::pager::Pager;
+use crate::DbResult;
+
+use format::{decode_record, record_offset, JournalHeader, JOURNAL_HEADER_SIZE, RECORD_SIZE};
+
+/// Hậu tố tên file rollback journal.
+pub const JOURNAL_SUFFIX: &str = "-journal";
+
+/// Đường dẫn file rollback journal của database `db_path`.
+pub fn journal_path(db_path: &str) -> String {
+    format!("{}{}", db_path, JOURNAL_SUFFIX)
+}
+
+/// Rollback hot journal `path` (nếu có) vào `db`: ghi lại các ảnh gốc hợp lệ, cắt file
+/// về số page trước transaction rồi fsync. Trả về số page được khôi phục, `None` nếu
+/// không có journal còn hiệu lực. File journal không bị động tới.
+pub(crate) fn rollback_hot_journal(db: &mut FilePager, path: &str) -> DbResult<Option<u32>> {
+    let Some((mut f, header, len)) = open_hot_journal(path)? else {
+        return Ok(None);
+    };
+
+    let mut restored = 0;
+    let mut rec = vec![0u8; RECORD_SIZE];
+    for idx in 0..header.records {
+        if record_offset(idx) + RECORD_SIZE as u64 > len {
+            break;
+        }
+        f.seek(SeekFrom::Start(record_offset(idx)))?;
+        f.read_exact(&mut rec)?;
+        // record ghi dở: transaction chưa kịp ghi page đó vào file database
+        let Some((pid, page)) = decode_record(&rec, header.nonce)? else {
+            break;
+        };
+        if pid.as_u32() >= header.orig_pages || pid.as_u64() >= db.num_pages()? {
+            continue;
+        }
+        db.write_page(pid, page)?;
+        restored += 1;
+    }
+    if db.num_pages()? != header.orig_pages as u64 {
+        db.truncate_to(header.orig_pages)?;
+    }
+    db.flush()?;
+    Ok(Some(restored))
+}
+
+/// Journal `path` còn hiệu lực (transaction chưa được rollback xong).
+pub(crate) fn is_hot_journal(path: &str) -> DbResult<bool> {
+    Ok(open_hot_journal(path)?.is_some())
+}
+
+/// Mở journal còn hiệu lực: (file, header, độ dài file); `None` nếu không có file hoặc
+/// header đã bị zero / không hợp lệ.
+fn open_hot_journal(path: &str) -> DbResult<Option<(File, JournalHeader, u64)>> {
+    let mut f = match File::open(path) {
+        Ok(f) => f,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let len = f.metadata()?.len();
+    if len < JOURNAL_HEADER_SIZE as u64 {
+        return Ok(None);
+    }
+    let mut head = [0u8; JOURNAL_HEADER_SIZE];
+    f.read_exact(&mut head)?;
+    Ok(JournalHeader::decode(&head)?.map(|header| (f, header, len)))
+}
+
+/// Path tạm cho test, đã xoá file database và journal cũ (nếu có).
+#[cfg(test)]
+pub(crate) fn temp_db_path(name: &str) -> String {
+    let path = crate::wal::temp_db_path(name);
+    let _ = std::fs::remove_file(journal_path(&path));
+    path
+}
+
+#[cfg(test)]
+pub(crate) fn remove_db_files(path: &str) {
+    crate::wal::remove_db_files(path);
+    let _ = std::fs::remove_file(journal_path(path));
+}
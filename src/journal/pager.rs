@@ -0,0 +1,548 @@
+use std::fs::OpenOptions;
+use std::io::{Seek, SeekFrom, Write};
+
+use crate::constants::PAGE_SIZE;
+use crate::pager::file::FilePager;
+use crate::pager::freelist;
+use crate::pager::meta::JournalMode;
+use crate::pager::pager::{Pager, Snapshot};
+use crate::pager::pending::PendingPages;
+use crate::pager::sync::{sync_data_if, sync_parent_dir, SyncMode};
+use crate::wal::format::new_salt;
+use crate::{DbError, DbResult, PageId};
+
+use super::format::{
+    encode_record, record_page_mut, JournalHeader, JOURNAL_HEADER_SIZE, RECORD_SIZE,
+};
+use super::{is_hot_journal, journal_path, rollback_hot_journal};
+
+/// Pager ở chế độ rollback journal: commit ghi đè page tại chỗ trong file database,
+/// ảnh gốc được giữ trong `<db>-journal` tới khi file database đã sync.
+///
+/// - `write_page`/`alloc_page` chỉ giữ page trong `pending` (transaction hiện tại).
+/// - `flush` = commit: journal ảnh gốc -> ghi tại chỗ -> xoá/zero journal. Lỗi sau khi
+///   journal đã bền thì ảnh gốc được ghi lại ngay, file database về trạng thái trước
+///   transaction; `rollback` cũng rollback hot journal còn sót.
+/// - `read_page`: pending -> file database.
+/// - Snapshot (`begin_snapshot`): chỉ đọc file database, không thấy page pending của
+///   transaction ghi đang chạy; số page cố định lúc lấy snapshot. File database bị ghi
+///   tại chỗ nên page có sẵn luôn đọc ra version đã commit mới nhất.
+pub struct JournalPager {
+    db: FilePager,
+    journal: String,
+    mode: JournalMode,
+    pending: PendingPages,
+    sync: SyncMode,
+    /// Số page được khôi phục từ hot journal lúc mở, `None` nếu không có hot journal.
+    recovered: Option<u32>,
+    /// Ghi tại chỗ page này khi commit trả lỗi I/O.
+    #[cfg(test)]
+    fail_in_place_at: Option<PageId>,
+}
+
+impl JournalPager {
+    /// Mở database `path` ở chế độ `mode` (`Delete` hoặc `Persist`); hot journal còn
+    /// sót lại từ lần crash trước được rollback trước khi trả về.
+    pub fn open(path: String, mode: JournalMode) -> DbResult<Self> {
+        if !mode.is_rollback() {
+            return Err(DbError::InvalidArgument(
+                "journal pager needs a rollback journal mode",
+            ));
+        }
+        let journal = journal_path(&path);
+        let mut db = FilePager::open_unjournaled(path)?;
+        let recovered = rollback_hot_journal(&mut db, &journal)?;
+        let pages = db.num_pages()? as u32;
+        let freelist = freelist::load(pages, |pid, out| db.read_page(pid, out))?;
+        let mut p = JournalPager {
+            db,
+            journal,
+            mode,
+            pending: PendingPages::new(pages, freelist),
+            sync: SyncMode::default(),
+            recovered,
+            #[cfg(test)]
+            fail_in_place_at: None,
+        };
+        if recovered.is_some() {
+            p.finish_journal()?;
+        }
+        Ok(p)
+    }
+
+    pub fn mode(&self) -> JournalMode {
+        self.mode
+    }
+
+    /// Mức fsync của journal, file database và thư mục khi commit.
+    /// Rollback hot journal lúc mở luôn fsync đầy đủ.
+    pub fn set_sync_mode(&mut self, mode: SyncMode) {
+        self.db.set_sync_mode(mode);
+        self.sync = mode;
+    }
+
+    /// Số page được khôi phục từ hot journal lúc mở.
+    pub fn recovered(&self) -> Option<u32> {
+        self.recovered
+    }
+
+    /// Bước 1 của commit: ghi header + ảnh gốc của các page pending đã có trong file
+    /// database vào journal rồi fsync (journal phải bền trước khi file database đổi).
+    fn write_journal(&mut self) -> DbResult<()> {
+        // ảnh gốc đọc từ file database ghi dở sẽ đè mất ảnh gốc thật trong journal
+        if is_hot_journal(&self.journal)? {
+            return Err(DbError::Corruption(
+                "hot journal must be rolled back before the next commit",
+            ));
+        }
+        let orig_pages = self.pending.committed_pages();
+        let originals: Vec<PageId> = self
+            .pending
+            .pages()
+            .iter()
+            .map(|&(pid, _)| pid)
+            .filter(|pid| pid.as_u32() < orig_pages)
+            .collect();
+        let header = JournalHeader {
+            nonce: new_salt().0,
+            orig_pages,
+            records: originals.len() as u32,
+        };
+
+        let mut buf = vec![0u8; JOURNAL_HEADER_SIZE + originals.len() * RECORD_SIZE];
+        let (head, records) = buf.split_at_mut(JOURNAL_HEADER_SIZE);
+        header.encode(head)?;
+        for (&pid, rec) in originals.iter().zip(records.chunks_exact_mut(RECORD_SIZE)) {
+            self.db.read_page(pid, record_page_mut(rec))?;
+            encode_record(rec, pid, header.nonce)?;
+        }
+
+        let created = !std::path::Path::new(&self.journal).exists();
+        let mut f = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&self.journal)?;
+        if self.sync.syncs_journal_header() {
+            // header zero (journal chưa hot) tới khi record đã bền
+            f.write_all(&[0u8; JOURNAL_HEADER_SIZE])?;
+            f.write_all(&buf[JOURNAL_HEADER_SIZE..])?;
+            f.set_len(buf.len() as u64)?;
+            f.sync_data()?;
+            f.seek(SeekFrom::Start(0))?;
+            f.write_all(&buf[..JOURNAL_HEADER_SIZE])?;
+        } else {
+            f.write_all(&buf)?;
+            // persist: bỏ record của journal cũ phía sau
+            f.set_len(buf.len() as u64)?;
+        }
+        sync_data_if(&f, self.sync.syncs_files())?;
+        if created && self.sync.syncs_dir_on_create() {
+            sync_parent_dir(&self.journal)?;
+        }
+        Ok(())
+    }
+
+    /// Bước 2: ghi page pending tại chỗ rồi fsync file database.
+    fn write_in_place(&mut self) -> DbResult<()> {
+        self.db.grow_to(self.pending.next_pid().as_u32())?;
+        for (pid, page) in self.pending.pages() {
+            #[cfg(test)]
+            if self.fail_in_place_at == Some(pid) {
+                return Err(std::io::Error::other("injected write failure").into());
+            }
+            self.db.write_page(pid, page)?;
+        }
+        self.db.flush()
+    }
+
+    /// Rollback hot journal còn sót (commit lỗi giữa chừng): ghi lại ảnh gốc, cắt file
+    /// database rồi vô hiệu journal. Trả về false nếu không có hot journal.
+    fn rollback_journal(&mut self) -> DbResult<bool> {
+        if rollback_hot_journal(&mut self.db, &self.journal)?.is_none() {
+            return Ok(false);
+        }
+        self.finish_journal()?;
+        Ok(true)
+    }
+
+    /// Bước 3: journal hết hiệu lực, commit hoàn tất.
+    fn finish_journal(&mut self) -> DbResult<()> {
+        match self.mode {
+            JournalMode::Delete => {
+                if let Err(e) = std::fs::remove_file(&self.journal) {
+                    if e.kind() != std::io::ErrorKind::NotFound {
+                        return Err(e.into());
+                    }
+                }
+                if self.sync.syncs_dir_on_delete() {
+                    sync_parent_dir(&self.journal)?;
+                }
+                Ok(())
+            }
+            JournalMode::Persist => {
+                let mut f = OpenOptions::new().write(true).open(&self.journal)?;
+                f.seek(SeekFrom::Start(0))?;
+                f.write_all(&[0u8; JOURNAL_HEADER_SIZE])?;
+                sync_data_if(&f, self.sync.syncs_commit())?;
+                Ok(())
+            }
+            JournalMode::Wal => unreachable!("journal pager opened in wal mode"),
+        }
+    }
+}
+
+impl Pager for JournalPager {
+    fn read_page(&mut self, pid: PageId, out: &mut [u8]) -> DbResult<()> {
+        if out.len() != PAGE_SIZE {
+            return Err(DbError::InvalidArgument(
+                "buffer length must equal PAGE_SIZE",
+            ));
+        }
+        self.pending.check_pid(pid)?;
+        if let Some(page) = self.pending.get(pid) {
+            out.copy_from_slice(page);
+            return Ok(());
+        }
+        if pid.as_u64() < self.db.num_pages()? {
+            return self.db.read_verified(pid, out);
+        }
+        // page đã cấp phát nhưng chưa từng được ghi
+        out.fill(0);
+        Ok(())
+    }
+
+    fn write_page(&mut self, pid: PageId, buf: &[u8]) -> DbResult<()> {
+        self.pending.write(pid, buf)
+    }
+
+    fn alloc_page(&mut self) -> DbResult<PageId> {
+        self.pending.alloc()
+    }
+
+    fn free_page(&mut self, pid: PageId) -> DbResult<()> {
+        self.pending.free(pid)
+    }
+
+    /// Commit các page pending tại chỗ, được bảo vệ bởi journal.
+    fn flush(&mut self) -> DbResult<()> {
+        let db = &mut self.db;
+        self.pending
+            .stage_freelist(|out| db.read_page(PageId(0), out))?;
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        self.write_journal()?;
+        if let Err(e) = self.write_in_place().and_then(|()| self.finish_journal()) {
+            // file database có thể đã bị ghi dở: về lại trạng thái trước transaction.
+            // Journal đã hết hiệu lực (lỗi sau khi xoá/zero) thì commit đã xong. Rollback
+            // lỗi thì journal vẫn hot: `rollback` / lần mở sau thử lại; trả lỗi gốc.
+            if let Ok(false) = self.rollback_journal() {
+                self.pending.committed();
+            }
+            return Err(e);
+        }
+        self.pending.committed();
+        Ok(())
+    }
+
+    fn num_pages(&mut self) -> DbResult<u64> {
+        Ok(self.pending.next_pid().as_u64())
+    }
+
+    fn rollback(&mut self) -> DbResult<()> {
+        self.pending.discard();
+        // commit lỗi trước đó chưa rollback được hot journal: thử lại
+        self.rollback_journal()?;
+        Ok(())
+    }
+
+    fn begin_snapshot(&mut self) -> DbResult<Option<Snapshot>> {
+        Ok(Some(Snapshot {
+            id: 0,
+            log_seq: 0,
+            mark: 0,
+            db_pages: self.pending.committed_pages(),
+        }))
+    }
+
+    fn read_page_at(&mut self, snap: &Snapshot, pid: PageId, out: &mut [u8]) -> DbResult<()> {
+        if out.len() != PAGE_SIZE {
+            return Err(DbError::InvalidArgument(
+                "buffer length must equal PAGE_SIZE",
+            ));
+        }
+        if pid.as_u32() >= snap.db_pages {
+            return Err(DbError::InvalidArgument("page id out of range"));
+        }
+        if pid.as_u64() < self.db.num_pages()? {
+            return self.db.read_verified(pid, out);
+        }
+        out.fill(0);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::journal::format::record_offset;
+    use crate::journal::{remove_db_files, temp_db_path};
+
+    fn commit_page(p: &mut JournalPager, pid: PageId, fill: u8) {
+        while p.num_pages().unwrap() <= pid.as_u64() {
+            p.alloc_page().unwrap();
+        }
+        p.write_page(pid, &vec![fill; PAGE_SIZE]).unwrap();
+        p.flush().unwrap();
+    }
+
+    fn db_file_page(path: &str, pid: PageId) -> Vec<u8> {
+        let bytes = std::fs::read(path).unwrap();
+        bytes[pid.as_usize() * PAGE_SIZE..(pid.as_usize() + 1) * PAGE_SIZE].to_vec()
+    }
+
+    #[test]
+    fn test_commit_writes_in_place() {
+        let path = temp_db_path("journal-commit");
+        let mut p = JournalPager::open(path.clone(), JournalMode::Delete).unwrap();
+        commit_page(&mut p, PageId(1), 1);
+        commit_page(&mut p, PageId(1), 2);
+        assert_eq!(db_file_page(&path, PageId(1))[0], 2);
+        assert!(std::fs::metadata(journal_path(&path)).is_err());
+
+        p.write_page(PageId(1), &vec![3u8; PAGE_SIZE]).unwrap();
+        p.rollback().unwrap();
+        let mut out = vec![0u8; PAGE_SIZE];
+        p.read_page(PageId(1), &mut out).unwrap();
+        assert_eq!(out[0], 2);
+        drop(p);
+
+        // persist giữ file journal nhưng header bị zero
+        let mut p = JournalPager::open(path.clone(), JournalMode::Persist).unwrap();
+        assert_eq!(p.recovered(), None);
+        commit_page(&mut p, PageId(2), 4);
+        let journal = std::fs::read(journal_path(&path)).unwrap();
+        assert!(journal[..JOURNAL_HEADER_SIZE].iter().all(|&b| b == 0));
+        drop(p);
+        let p = JournalPager::open(path.clone(), JournalMode::Persist).unwrap();
+        assert_eq!(p.recovered(), None);
+
+        remove_db_files(&path);
+    }
+
+    #[test]
+    fn test_freelist_committed_with_transaction() {
+        let path = temp_db_path("journal-freelist");
+        let mut p = JournalPager::open(path.clone(), JournalMode::Delete).unwrap();
+        commit_page(&mut p, PageId(1), 1);
+        commit_page(&mut p, PageId(2), 2);
+
+        p.free_page(PageId(1)).unwrap();
+        p.rollback().unwrap();
+        assert_eq!(p.alloc_page().unwrap(), PageId(3));
+        p.rollback().unwrap();
+
+        p.free_page(PageId(2)).unwrap();
+        p.flush().unwrap();
+        drop(p);
+
+        let mut p = JournalPager::open(path.clone(), JournalMode::Delete).unwrap();
+        assert!(p.free_page(PageId(2)).is_err(), "double free after reopen");
+        assert_eq!(p.alloc_page().unwrap(), PageId(2));
+        let mut out = vec![0u8; PAGE_SIZE];
+        p.read_page(PageId(2), &mut out).unwrap();
+        assert!(out.iter().all(|&b| b == 0));
+        p.rollback().unwrap();
+        assert_eq!(p.alloc_page().unwrap(), PageId(2));
+        assert_eq!(p.alloc_page().unwrap(), PageId(3));
+
+        remove_db_files(&path);
+    }
+
+    #[test]
+    fn test_snapshot_skips_pending_pages() {
+        let path = temp_db_path("journal-snapshot");
+        let mut p = JournalPager::open(path.clone(), JournalMode::Delete).unwrap();
+        commit_page(&mut p, PageId(1), 1);
+
+        p.write_page(PageId(1), &vec![2u8; PAGE_SIZE]).unwrap();
+        let extra = p.alloc_page().unwrap();
+        let snap = p.begin_snapshot().unwrap().unwrap();
+        assert_eq!(snap.db_pages(), extra.as_u32());
+        let mut out = vec![0u8; PAGE_SIZE];
+        p.read_page_at(&snap, PageId(1), &mut out).unwrap();
+        assert_eq!(out[0], 1);
+        assert!(p.read_page_at(&snap, extra, &mut out).is_err());
+
+        // page có sẵn đọc ra commit mới nhất
+        p.flush().unwrap();
+        p.read_page_at(&snap, PageId(1), &mut out).unwrap();
+        assert_eq!(out[0], 2);
+        assert!(p.read_page_at(&snap, extra, &mut out).is_err());
+        p.end_snapshot(snap);
+
+        remove_db_files(&path);
+    }
+
+    #[test]
+    fn test_commit_at_every_sync_mode() {
+        let path = temp_db_path("journal-sync");
+        let mut p = JournalPager::open(path.clone(), JournalMode::Delete).unwrap();
+        for (i, mode) in [
+            SyncMode::Off,
+            SyncMode::Normal,
+            SyncMode::Full,
+            SyncMode::Extra,
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            p.set_sync_mode(mode);
+            commit_page(&mut p, PageId(1), i as u8);
+            assert_eq!(db_file_page(&path, PageId(1))[0], i as u8);
+            assert!(std::fs::metadata(journal_path(&path)).is_err());
+        }
+        remove_db_files(&path);
+    }
+
+    #[test]
+    fn test_full_syncs_dir_after_journal_delete() {
+        use crate::pager::sync::DIR_SYNCS;
+
+        let path = temp_db_path("journal-dirsync");
+        let mut p = JournalPager::open(path.clone(), JournalMode::Delete).unwrap();
+        // commit đầu ở Full còn fsync thư mục cho file database vừa tạo
+        p.set_sync_mode(SyncMode::Full);
+        commit_page(&mut p, PageId(1), 7);
+        for (mode, expected) in [(SyncMode::Normal, 0), (SyncMode::Full, 2)] {
+            p.set_sync_mode(mode);
+            let before = DIR_SYNCS.with(|n| n.get());
+            commit_page(&mut p, PageId(1), 7);
+            // Full: 1 lần sau khi tạo journal, 1 lần sau khi xoá (điểm commit)
+            assert_eq!(DIR_SYNCS.with(|n| n.get()) - before, expected);
+        }
+        remove_db_files(&path);
+    }
+
+    #[test]
+    fn test_hot_journal_rolled_back_on_open() {
+        let path = temp_db_path("journal-hot");
+        let mut p = JournalPager::open(path.clone(), JournalMode::Delete).unwrap();
+        commit_page(&mut p, PageId(1), 1);
+        commit_page(&mut p, PageId(2), 2);
+
+        // crash sau khi đã ghi tại chỗ, trước khi xoá journal
+        p.write_page(PageId(1), &vec![9u8; PAGE_SIZE]).unwrap();
+        let pid = p.alloc_page().unwrap();
+        p.write_page(pid, &vec![9u8; PAGE_SIZE]).unwrap();
+        p.write_journal().unwrap();
+        p.write_in_place().unwrap();
+        drop(p);
+        assert_eq!(db_file_page(&path, PageId(1))[0], 9);
+
+        let mut p = JournalPager::open(path.clone(), JournalMode::Delete).unwrap();
+        assert_eq!(p.recovered(), Some(1));
+        assert_eq!(p.num_pages().unwrap(), 3);
+        let mut out = vec![0u8; PAGE_SIZE];
+        p.read_page(PageId(1), &mut out).unwrap();
+        assert!(out.iter().all(|&b| b == 1));
+        assert!(std::fs::metadata(journal_path(&path)).is_err());
+
+        remove_db_files(&path);
+    }
+
+    #[test]
+    fn test_failed_in_place_write_restores_originals() {
+        let path = temp_db_path("journal-fail-in-place");
+        let mut p = JournalPager::open(path.clone(), JournalMode::Persist).unwrap();
+        commit_page(&mut p, PageId(1), 1);
+        commit_page(&mut p, PageId(2), 1);
+
+        // page 1 đã ghi tại chỗ, page 2 lỗi
+        p.write_page(PageId(1), &vec![2u8; PAGE_SIZE]).unwrap();
+        p.write_page(PageId(2), &vec![2u8; PAGE_SIZE]).unwrap();
+        let extra = p.alloc_page().unwrap();
+        p.write_page(extra, &vec![2u8; PAGE_SIZE]).unwrap();
+        p.fail_in_place_at = Some(PageId(2));
+        assert!(p.flush().is_err());
+        p.fail_in_place_at = None;
+
+        // file database đã về trạng thái trước transaction, journal hết hiệu lực
+        assert_eq!(db_file_page(&path, PageId(1)), vec![1u8; PAGE_SIZE]);
+        assert_eq!(db_file_page(&path, PageId(2)), vec![1u8; PAGE_SIZE]);
+        assert_eq!(
+            std::fs::metadata(&path).unwrap().len(),
+            3 * PAGE_SIZE as u64
+        );
+        assert!(!is_hot_journal(&journal_path(&path)).unwrap());
+
+        p.rollback().unwrap();
+        assert_eq!(p.num_pages().unwrap(), 3);
+        commit_page(&mut p, PageId(2), 3);
+        drop(p);
+
+        let mut p = JournalPager::open(path.clone(), JournalMode::Persist).unwrap();
+        assert_eq!(p.recovered(), None);
+        let mut out = vec![0u8; PAGE_SIZE];
+        p.read_page(PageId(1), &mut out).unwrap();
+        assert_eq!(out, vec![1u8; PAGE_SIZE]);
+        p.read_page(PageId(2), &mut out).unwrap();
+        assert_eq!(out, vec![3u8; PAGE_SIZE]);
+
+        remove_db_files(&path);
+    }
+
+    #[test]
+    fn test_hot_journal_blocks_commit_until_rollback() {
+        let path = temp_db_path("journal-hot-blocks");
+        let mut p = JournalPager::open(path.clone(), JournalMode::Delete).unwrap();
+        commit_page(&mut p, PageId(1), 1);
+
+        // commit dừng sau khi ghi tại chỗ, journal còn hot
+        p.write_page(PageId(1), &vec![9u8; PAGE_SIZE]).unwrap();
+        p.write_journal().unwrap();
+        p.write_in_place().unwrap();
+
+        // commit mới không được đè ảnh gốc trong journal
+        assert!(matches!(p.flush(), Err(DbError::Corruption(_))));
+        assert_eq!(db_file_page(&path, PageId(1))[0], 9);
+
+        p.rollback().unwrap();
+        assert_eq!(db_file_page(&path, PageId(1)), vec![1u8; PAGE_SIZE]);
+        assert!(std::fs::metadata(journal_path(&path)).is_err());
+        commit_page(&mut p, PageId(1), 2);
+        assert_eq!(db_file_page(&path, PageId(1))[0], 2);
+
+        remove_db_files(&path);
+    }
+
+    #[test]
+    fn test_torn_journal_record_is_ignored() {
+        let path = temp_db_path("journal-torn");
+        let mut p = JournalPager::open(path.clone(), JournalMode::Persist).unwrap();
+        commit_page(&mut p, PageId(1), 1);
+        commit_page(&mut p, PageId(2), 2);
+
+        // crash lúc đang ghi journal: file database chưa bị động tới
+        p.write_page(PageId(1), &vec![9u8; PAGE_SIZE]).unwrap();
+        p.write_page(PageId(2), &vec![9u8; PAGE_SIZE]).unwrap();
+        p.write_journal().unwrap();
+        drop(p);
+        let f = OpenOptions::new()
+            .write(true)
+            .open(journal_path(&path))
+            .unwrap();
+        f.set_len(record_offset(1) + 10).unwrap();
+
+        let mut p = JournalPager::open(path.clone(), JournalMode::Persist).unwrap();
+        assert_eq!(p.recovered(), Some(1));
+        let mut out = vec![0u8; PAGE_SIZE];
+        p.read_page(PageId(1), &mut out).unwrap();
+        assert_eq!(out[0], 1);
+        p.read_page(PageId(2), &mut out).unwrap();
+        assert_eq!(out[0], 2);
+
+        remove_db_files(&path);
+    }
+}
@@ -1,10 +1,18 @@
 pub mod btree;
+pub mod buffer;
 
 pub mod constants;
+pub mod db;
 pub mod error;
+pub mod fsm;
+pub mod heap;
+pub mod journal;
+pub mod mvcc;
 pub mod page;
 pub mod pager;
+pub mod record;
 pub mod types;
+pub mod wal;
 
 pub use error::{DbError, DbResult};
-pub use types::PageId;
+pub use types::{Lsn, PageId, RecordId};
@@ -0,0 +1,560 @@
+//! MVCC ở mức tuple: key-value store trên heap file, mỗi lần ghi tạo version mới.
+//!
+//! - Mỗi version mang `xmin` (transaction tạo) và `xmax` (transaction xoá / thay thế),
+//!   xem `tuple.rs`. Update = set xmax của version cũ + insert version mới.
+//! - Transaction đọc theo snapshot chụp lúc begin: chỉ thấy version do transaction đã
+//!   commit trước đó (hoặc chính nó) tạo và chưa bị transaction như vậy xoá.
+//! - Ghi vào key mà version mới nhất do transaction khác tạo/xoá sau snapshot (đã commit
+//!   hoặc đang chạy) là write-write conflict: transaction ghi bị abort, trả
+//!   `DbError::Conflict`.
+//! - Abort undo ngay các thay đổi của transaction, nên xid gặp trong heap mà không còn
+//!   chạy luôn là xid đã commit.
+//! - `vacuum` xoá version không snapshot nào còn thấy: slot thành tombstone
+//!   (`FLAG_HAS_FREE_SLOTS`, insert sau dùng lại) rồi compact page, FSM được cập nhật.
+//!
+//! Index key -> version nằm trong memory, `open` dựng lại bằng 1 lần scan heap. Trạng
+//! thái transaction cũng chỉ nằm trong memory: sau khi mở lại, mọi version trong heap
+//! được coi là đã commit. Vì vậy pager chỉ được flush qua `MvccStore::flush`, hàm này
+//! trả `DbError::Busy` khi còn transaction ghi dở.
+
+mod tuple;
+mod txn;
+
+pub use txn::{MvccTxn, TxnSnapshot, Xid};
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::heap::HeapFile;
+use crate::pager::pager::Pager;
+use crate::{DbError, DbResult, RecordId};
+use tuple::TupleVersion;
+use txn::{TxnManager, Write};
+
+/// Kết quả 1 lần `vacuum`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct VacuumInfo {
+    /// Số version chết đã xoá khỏi heap.
+    pub versions_removed: usize,
+    /// Số heap page được compact.
+    pub pages_compacted: usize,
+    /// Số bytes free tăng thêm sau compact.
+    pub bytes_reclaimed: usize,
+}
+
+pub struct MvccStore {
+    heap: HeapFile,
+    /// key -> RID các version, cũ trước mới sau.
+    index: BTreeMap<Vec<u8>, Vec<RecordId>>,
+    txns: TxnManager,
+}
+
+impl MvccStore {
+    /// Tạo store mới trên 1 heap table rỗng.
+    pub fn create(pager: &mut dyn Pager) -> DbResult<Self> {
+        Ok(MvccStore {
+            heap: HeapFile::create(pager)?,
+            index: BTreeMap::new(),
+            txns: TxnManager::new(Xid::FIRST),
+        })
+    }
+
+    /// Mở store trên heap table đã có, dựng lại index từ heap.
+    pub fn open(pager: &mut dyn Pager, heap: HeapFile) -> DbResult<Self> {
+        let mut versions: BTreeMap<Vec<u8>, Vec<(Xid, bool, RecordId)>> = BTreeMap::new();
+        let mut max_xid = Xid::NONE;
+        for item in heap.scan(pager) {
+            let (rid, data) = item?;
+            let v = TupleVersion::decode(&data)?;
+            max_xid = max_xid.max(v.xmin).max(v.xmax);
+            let superseded_by_creator = v.xmax == v.xmin;
+            versions
+                .entry(v.key)
+                .or_default()
+                .push((v.xmin, !superseded_by_creator, rid));
+        }
+        // version sau luôn do transaction begin sau tạo. Cùng xmin (transaction xoá
+        // rồi put lại key của chính nó): mọi version trừ cái cuối đều bị chính
+        // transaction đó xoá, nên xếp trước; RID không phản ánh thứ tự vì slot có
+        // thể được dùng lại.
+        let index = versions
+            .into_iter()
+            .map(|(key, mut vs)| {
+                vs.sort();
+                (key, vs.into_iter().map(|(_, _, rid)| rid).collect())
+            })
+            .collect();
+        Ok(MvccStore {
+            heap,
+            index,
+            txns: TxnManager::new(Xid(max_xid.0 + 1)),
+        })
+    }
+
+    pub fn heap(&self) -> HeapFile {
+        self.heap
+    }
+
+    /// Số transaction đang chạy.
+    pub fn active_transactions(&self) -> usize {
+        self.txns.active_count()
+    }
+
+    /// Số transaction đang chạy đã ghi vào heap.
+    pub fn active_writers(&self) -> usize {
+        self.txns.writer_count()
+    }
+
+    /// Flush pager. Version của transaction ghi đang chạy mà xuống disk thì sau khi mở
+    /// lại bị coi là đã commit, nên flush bị từ chối khi còn transaction như vậy.
+    pub fn flush(&self, pager: &mut dyn Pager) -> DbResult<()> {
+        if self.txns.writer_count() > 0 {
+            return Err(DbError::Busy("mvcc write transaction still active"));
+        }
+        pager.flush()
+    }
+
+    pub fn begin(&mut self) -> MvccTxn {
+        self.txns.begin()
+    }
+
+    pub fn commit(&mut self, txn: MvccTxn) -> DbResult<()> {
+        self.txns.check_active(&txn)?;
+        self.txns.finish(txn.xid());
+        Ok(())
+    }
+
+    /// Undo mọi thay đổi của transaction. Transaction đã bị abort do conflict -> no-op.
+    pub fn abort(&mut self, pager: &mut dyn Pager, mut txn: MvccTxn) -> DbResult<()> {
+        if !self.txns.is_active(txn.xid()) {
+            return Ok(());
+        }
+        self.rollback(pager, &mut txn)
+    }
+
+    /// Giá trị của key theo snapshot của transaction.
+    pub fn get(
+        &self,
+        pager: &mut dyn Pager,
+        txn: &MvccTxn,
+        key: &[u8],
+    ) -> DbResult<Option<Vec<u8>>> {
+        self.txns.check_active(txn)?;
+        let Some(rids) = self.index.get(key) else {
+            return Ok(None);
+        };
+        for rid in rids.iter().rev() {
+            let v = self.read_version(pager, *rid)?;
+            if txn.sees_version(v.xmin, v.xmax) {
+                return Ok(Some(v.value));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Mọi cặp (key, value) transaction thấy, theo thứ tự key.
+    pub fn scan(&self, pager: &mut dyn Pager, txn: &MvccTxn) -> DbResult<Vec<(Vec<u8>, Vec<u8>)>> {
+        let mut out = Vec::new();
+        for key in self.index.keys() {
+            if let Some(value) = self.get(pager, txn, key)? {
+                out.push((key.clone(), value));
+            }
+        }
+        Ok(out)
+    }
+
+    /// Insert hoặc update key.
+    pub fn put(
+        &mut self,
+        pager: &mut dyn Pager,
+        txn: &mut MvccTxn,
+        key: &[u8],
+        value: &[u8],
+    ) -> DbResult<()> {
+        self.txns.check_active(txn)?;
+        let latest = self.latest_for_write(pager, txn, key)?;
+        let data = TupleVersion {
+            xmin: txn.xid(),
+            xmax: Xid::NONE,
+            key: key.to_vec(),
+            value: value.to_vec(),
+        }
+        .encode()?;
+
+        match latest {
+            // version do chính transaction tạo, chưa ai khác thấy: ghi đè luôn
+            Some((rid, v)) if v.xmin == txn.xid() => return self.heap.update(pager, rid, &data),
+            Some((rid, v)) => self.set_xmax(pager, txn, rid, v)?,
+            None => {}
+        }
+        let rid = self.heap.insert(pager, &data)?;
+        self.index.entry(key.to_vec()).or_default().push(rid);
+        txn.writes.push(Write::Inserted(rid));
+        self.txns.note_write(txn.xid());
+        Ok(())
+    }
+
+    /// Xoá key, trả về false nếu transaction không thấy key.
+    pub fn delete(
+        &mut self,
+        pager: &mut dyn Pager,
+        txn: &mut MvccTxn,
+        key: &[u8],
+    ) -> DbResult<bool> {
+        self.txns.check_active(txn)?;
+        match self.latest_for_write(pager, txn, key)? {
+            Some((rid, v)) => {
+                self.set_xmax(pager, txn, rid, v)?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Xoá các version mà mọi snapshot đang mở (và mọi snapshot sau này) đều thấy là
+    /// đã bị xoá, rồi compact các page chứa chúng.
+    pub fn vacuum(&mut self, pager: &mut dyn Pager) -> DbResult<VacuumInfo> {
+        let horizon = self.txns.horizon();
+        let mut info = VacuumInfo::default();
+        let mut pages = BTreeSet::new();
+        let mut dead = Vec::new();
+        for (key, rids) in &self.index {
+            for rid in rids {
+                let v = self.read_version(pager, *rid)?;
+                if v.xmax != Xid::NONE && v.xmax < horizon {
+                    dead.push((key.clone(), *rid));
+                }
+            }
+        }
+        for (key, rid) in dead {
+            self.heap.delete(pager, rid)?;
+            self.unindex(&key, rid);
+            pages.insert(rid.page);
+            info.versions_removed += 1;
+        }
+        for pid in pages {
+            info.bytes_reclaimed += self.heap.compact_page(pager, pid)? as usize;
+            info.pages_compacted += 1;
+        }
+        Ok(info)
+    }
+
+    fn read_version(&self, pager: &mut dyn Pager, rid: RecordId) -> DbResult<TupleVersion> {
+        let data = self
+            .heap
+            .get(pager, rid)?
+            .ok_or(DbError::Corruption("indexed version is deleted"))?;
+        TupleVersion::decode(&data)
+    }
+
+    /// Version mới nhất của key mà transaction đang thấy, để ghi đè / xoá.
+    /// Version mới nhất nằm ngoài snapshot -> conflict, transaction bị abort.
+    fn latest_for_write(
+        &mut self,
+        pager: &mut dyn Pager,
+        txn: &mut MvccTxn,
+        key: &[u8],
+    ) -> DbResult<Option<(RecordId, TupleVersion)>> {
+        let Some(&rid) = self.index.get(key).and_then(|rids| rids.last()) else {
+            return Ok(None);
+        };
+        let v = self.read_version(pager, rid)?;
+        let own = txn.xid();
+        if v.xmin != own && !txn.snapshot().sees(v.xmin) {
+            return Err(self.conflict(pager, txn, "key was written by a concurrent transaction"));
+        }
+        if v.xmax == Xid::NONE {
+            return Ok(Some((rid, v)));
+        }
+        if v.xmax == own || txn.snapshot().sees(v.xmax) {
+            return Ok(None);
+        }
+        Err(self.conflict(pager, txn, "key was deleted by a concurrent transaction"))
+    }
+
+    /// Abort transaction vì conflict, trả về lỗi cho caller.
+    fn conflict(&mut self, pager: &mut dyn Pager, txn: &mut MvccTxn, msg: &'static str) -> DbError {
+        match self.rollback(pager, txn) {
+            Ok(()) => DbError::Conflict(msg),
+            Err(e) => e,
+        }
+    }
+
+    fn set_xmax(
+        &mut self,
+        pager: &mut dyn Pager,
+        txn: &mut MvccTxn,
+        rid: RecordId,
+        mut v: TupleVersion,
+    ) -> DbResult<()> {
+        v.xmax = txn.xid();
+        // cùng size -> update in-place, RID không đổi
+        self.heap.update(pager, rid, &v.encode()?)?;
+        txn.writes.push(Write::Deleted(rid));
+        self.txns.note_write(txn.xid());
+        Ok(())
+    }
+
+    fn rollback(&mut self, pager: &mut dyn Pager, txn: &mut MvccTxn) -> DbResult<()> {
+        while let Some(w) = txn.writes.pop() {
+            match w {
+                Write::Inserted(rid) => {
+                    let v = self.read_version(pager, rid)?;
+                    self.heap.delete(pager, rid)?;
+                    self.unindex(&v.key, rid);
+                }
+                Write::Deleted(rid) => {
+                    let mut v = self.read_version(pager, rid)?;
+                    v.xmax = Xid::NONE;
+                    self.heap.update(pager, rid, &v.encode()?)?;
+                }
+            }
+        }
+        self.txns.finish(txn.xid());
+        Ok(())
+    }
+
+    fn unindex(&mut self, key: &[u8], rid: RecordId) {
+        if let Some(rids) = self.index.get_mut(key) {
+            rids.retain(|r| *r != rid);
+            if rids.is_empty() {
+                self.index.remove(key);
+            }
+        }
+    }
+
+    /// Số version của key còn trong heap.
+    #[cfg(test)]
+    fn version_count(&self, key: &[u8]) -> usize {
+        self.index.get(key).map_or(0, Vec::len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::PAGE_SIZE;
+    use crate::page::header::{self, FLAG_HAS_FREE_SLOTS};
+    use crate::pager::mem::MemPager;
+
+    fn put_committed(store: &mut MvccStore, pager: &mut MemPager, key: &[u8], value: &[u8]) {
+        let mut t = store.begin();
+        store.put(pager, &mut t, key, value).unwrap();
+        store.commit(t).unwrap();
+    }
+
+    #[test]
+    fn test_snapshot_isolation() {
+        let mut pager = MemPager::new();
+        let mut store = MvccStore::create(&mut pager).unwrap();
+        put_committed(&mut store, &mut pager, b"a", b"1");
+
+        let reader = store.begin();
+        let mut writer = store.begin();
+        store.put(&mut pager, &mut writer, b"a", b"2").unwrap();
+        store.put(&mut pager, &mut writer, b"b", b"new").unwrap();
+        // writer thấy thay đổi của nó, reader thì không
+        assert_eq!(store.get(&mut pager, &writer, b"a").unwrap().unwrap(), b"2");
+        assert_eq!(store.get(&mut pager, &reader, b"a").unwrap().unwrap(), b"1");
+        assert!(store.get(&mut pager, &reader, b"b").unwrap().is_none());
+
+        store.commit(writer).unwrap();
+        // reader vẫn đọc snapshot cũ sau khi writer commit
+        assert_eq!(
+            store.scan(&mut pager, &reader).unwrap(),
+            vec![(b"a".to_vec(), b"1".to_vec())]
+        );
+        store.commit(reader).unwrap();
+
+        let t = store.begin();
+        assert_eq!(
+            store.scan(&mut pager, &t).unwrap(),
+            vec![
+                (b"a".to_vec(), b"2".to_vec()),
+                (b"b".to_vec(), b"new".to_vec())
+            ]
+        );
+        store.commit(t).unwrap();
+    }
+
+    #[test]
+    fn test_write_write_conflict_aborts() {
+        let mut pager = MemPager::new();
+        let mut store = MvccStore::create(&mut pager).unwrap();
+        put_committed(&mut store, &mut pager, b"k", b"base");
+
+        let mut t1 = store.begin();
+        let mut t2 = store.begin();
+        store.put(&mut pager, &mut t1, b"other", b"x").unwrap();
+        store.put(&mut pager, &mut t1, b"k", b"t1").unwrap();
+        // t1 đang giữ version mới nhất -> t2 conflict và bị abort
+        let err = store.put(&mut pager, &mut t2, b"k", b"t2").unwrap_err();
+        assert!(matches!(err, DbError::Conflict(_)));
+        assert!(store.get(&mut pager, &t2, b"k").is_err());
+        assert!(store.commit(t2).is_err());
+        store.commit(t1).unwrap();
+
+        // t3 bắt đầu trước khi t4 commit xoá key -> ghi sau đó là conflict
+        let mut t3 = store.begin();
+        let mut t4 = store.begin();
+        assert!(store.delete(&mut pager, &mut t4, b"k").unwrap());
+        store.commit(t4).unwrap();
+        assert_eq!(store.get(&mut pager, &t3, b"k").unwrap().unwrap(), b"t1");
+        assert!(matches!(
+            store.delete(&mut pager, &mut t3, b"k").unwrap_err(),
+            DbError::Conflict(_)
+        ));
+        store.abort(&mut pager, t3).unwrap();
+
+        // transaction mới thấy key đã bị xoá, insert lại được
+        let mut t5 = store.begin();
+        assert!(store.get(&mut pager, &t5, b"k").unwrap().is_none());
+        assert!(!store.delete(&mut pager, &mut t5, b"k").unwrap());
+        store.put(&mut pager, &mut t5, b"k", b"again").unwrap();
+        store.commit(t5).unwrap();
+        assert_eq!(store.active_transactions(), 0);
+    }
+
+    #[test]
+    fn test_abort_undoes_writes() {
+        let mut pager = MemPager::new();
+        let mut store = MvccStore::create(&mut pager).unwrap();
+        put_committed(&mut store, &mut pager, b"a", b"1");
+        put_committed(&mut store, &mut pager, b"b", b"1");
+
+        let mut t = store.begin();
+        store.put(&mut pager, &mut t, b"a", b"2").unwrap();
+        store.put(&mut pager, &mut t, b"a", b"3").unwrap();
+        assert!(store.delete(&mut pager, &mut t, b"b").unwrap());
+        store.put(&mut pager, &mut t, b"c", b"1").unwrap();
+        store.abort(&mut pager, t).unwrap();
+
+        let mut t = store.begin();
+        assert_eq!(
+            store.scan(&mut pager, &t).unwrap(),
+            vec![
+                (b"a".to_vec(), b"1".to_vec()),
+                (b"b".to_vec(), b"1".to_vec())
+            ]
+        );
+        // version cũ không còn bị khoá bởi transaction đã abort
+        store.put(&mut pager, &mut t, b"a", b"4").unwrap();
+        store.commit(t).unwrap();
+        assert_eq!(store.version_count(b"a"), 2);
+    }
+
+    #[test]
+    fn test_vacuum_respects_snapshots_and_reuses_slots() {
+        let mut pager = MemPager::new();
+        let mut store = MvccStore::create(&mut pager).unwrap();
+        for i in 0..4u8 {
+            put_committed(&mut store, &mut pager, b"k", &[i; 200]);
+        }
+        assert_eq!(store.version_count(b"k"), 4);
+
+        // reader giữ snapshot thấy version 3: version đó chưa được dọn
+        let reader = store.begin();
+        put_committed(&mut store, &mut pager, b"k", &[9; 200]);
+        let info = store.vacuum(&mut pager).unwrap();
+        assert_eq!(info.versions_removed, 3);
+        assert_eq!(info.pages_compacted, 1);
+        assert!(info.bytes_reclaimed > 0);
+        assert_eq!(
+            store.get(&mut pager, &reader, b"k").unwrap().unwrap(),
+            vec![3; 200]
+        );
+        store.commit(reader).unwrap();
+
+        let info = store.vacuum(&mut pager).unwrap();
+        assert_eq!(info.versions_removed, 1);
+        assert_eq!(store.version_count(b"k"), 1);
+
+        // version bị dọn để lại slot tombstone, insert sau dùng lại thay vì cấp slot mới
+        let first = store.heap().first_page();
+        let mut buf = vec![0u8; PAGE_SIZE];
+        pager.read_page(first, &mut buf).unwrap();
+        assert_ne!(header::flags(&buf).unwrap() & FLAG_HAS_FREE_SLOTS, 0);
+        let slots = header::slot_count(&buf).unwrap();
+
+        put_committed(&mut store, &mut pager, b"z", b"reuse");
+        pager.read_page(first, &mut buf).unwrap();
+        assert_eq!(header::slot_count(&buf).unwrap(), slots);
+        let t = store.begin();
+        assert_eq!(store.get(&mut pager, &t, b"z").unwrap().unwrap(), b"reuse");
+        store.commit(t).unwrap();
+    }
+
+    #[test]
+    fn test_flush_refused_while_writer_active() {
+        let mut pager = MemPager::new();
+        let mut store = MvccStore::create(&mut pager).unwrap();
+        put_committed(&mut store, &mut pager, b"a", b"1");
+
+        // transaction chỉ đọc không chặn flush
+        let reader = store.begin();
+        store.flush(&mut pager).unwrap();
+
+        let mut writer = store.begin();
+        store.flush(&mut pager).unwrap();
+        store.put(&mut pager, &mut writer, b"a", b"2").unwrap();
+        assert_eq!(store.active_writers(), 1);
+        assert!(matches!(store.flush(&mut pager), Err(DbError::Busy(_))));
+        store.abort(&mut pager, writer).unwrap();
+        store.flush(&mut pager).unwrap();
+
+        let mut writer = store.begin();
+        assert!(store.delete(&mut pager, &mut writer, b"a").unwrap());
+        assert!(matches!(store.flush(&mut pager), Err(DbError::Busy(_))));
+        store.commit(writer).unwrap();
+        store.flush(&mut pager).unwrap();
+        store.commit(reader).unwrap();
+        assert_eq!(store.active_writers(), 0);
+    }
+
+    #[test]
+    fn test_open_rebuilds_index() {
+        let mut pager = MemPager::new();
+        let mut store = MvccStore::create(&mut pager).unwrap();
+        put_committed(&mut store, &mut pager, b"a", b"1");
+        put_committed(&mut store, &mut pager, b"a", b"2");
+        put_committed(&mut store, &mut pager, b"b", b"1");
+        let heap = store.heap();
+
+        let mut store = MvccStore::open(&mut pager, heap).unwrap();
+        let mut t = store.begin();
+        assert_eq!(store.get(&mut pager, &t, b"a").unwrap().unwrap(), b"2");
+        store.put(&mut pager, &mut t, b"b", b"2").unwrap();
+        store.commit(t).unwrap();
+        assert_eq!(store.vacuum(&mut pager).unwrap().versions_removed, 2);
+    }
+
+    #[test]
+    fn test_open_orders_versions_of_same_transaction() {
+        let mut pager = MemPager::new();
+        let mut store = MvccStore::create(&mut pager).unwrap();
+        put_committed(&mut store, &mut pager, b"x", b"1");
+        let mut t = store.begin();
+        assert!(store.delete(&mut pager, &mut t, b"x").unwrap());
+        store.commit(t).unwrap();
+
+        // version đầu của k nằm sau slot của x; vacuum xoá x để version thứ 2 dùng lại
+        // slot đó, RID nhỏ hơn version đầu
+        let mut t = store.begin();
+        store.put(&mut pager, &mut t, b"k", b"1").unwrap();
+        assert!(store.delete(&mut pager, &mut t, b"k").unwrap());
+        assert_eq!(store.vacuum(&mut pager).unwrap().versions_removed, 1);
+        store.put(&mut pager, &mut t, b"k", b"2").unwrap();
+        store.commit(t).unwrap();
+        let rids = store.index[b"k".as_slice()].clone();
+        assert!(rids[1] < rids[0]);
+
+        let heap = store.heap();
+        let mut store = MvccStore::open(&mut pager, heap).unwrap();
+        assert_eq!(store.index[b"k".as_slice()], rids);
+        put_committed(&mut store, &mut pager, b"k", b"3");
+        assert_eq!(store.vacuum(&mut pager).unwrap().versions_removed, 2);
+        assert_eq!(store.version_count(b"k"), 1);
+        let t = store.begin();
+        assert_eq!(store.get(&mut pager, &t, b"k").unwrap().unwrap(), b"3");
+        store.commit(t).unwrap();
+    }
+}
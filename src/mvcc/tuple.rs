@@ -0,0 +1,73 @@
+//! Layout 1 version của tuple trong heap (little-endian):
+//!
+//! ```text
+//! +----------+----------+-------------------+-------+
+//! | xmin u64 | xmax u64 | key (varint len)  | value |
+//! +----------+----------+-------------------+-------+
+//! ```
+//!
+//! - xmin: transaction tạo version
+//! - xmax: transaction xoá / thay version (`Xid::NONE` = còn sống)
+//! - value chiếm phần còn lại của tuple
+
+use crate::page::raw::{
+    len_prefixed_size, read_len_prefixed, read_u64_le, write_len_prefixed, write_u64_le,
+};
+use crate::DbResult;
+
+use super::txn::Xid;
+
+const OFF_XMIN: usize = 0;
+const OFF_XMAX: usize = 8;
+const OFF_KEY: usize = 16;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct TupleVersion {
+    pub(super) xmin: Xid,
+    pub(super) xmax: Xid,
+    pub(super) key: Vec<u8>,
+    pub(super) value: Vec<u8>,
+}
+
+impl TupleVersion {
+    pub(super) fn encode(&self) -> DbResult<Vec<u8>> {
+        let key_size = len_prefixed_size(self.key.len());
+        let mut buf = vec![0u8; OFF_KEY + key_size + self.value.len()];
+        write_u64_le(&mut buf, OFF_XMIN, self.xmin.0)?;
+        write_u64_le(&mut buf, OFF_XMAX, self.xmax.0)?;
+        let n = write_len_prefixed(&mut buf, OFF_KEY, &self.key)?;
+        buf[OFF_KEY + n..].copy_from_slice(&self.value);
+        Ok(buf)
+    }
+
+    pub(super) fn decode(buf: &[u8]) -> DbResult<Self> {
+        let xmin = Xid(read_u64_le(buf, OFF_XMIN)?);
+        let xmax = Xid(read_u64_le(buf, OFF_XMAX)?);
+        let (key, n) = read_len_prefixed(buf, OFF_KEY)?;
+        Ok(TupleVersion {
+            xmin,
+            xmax,
+            key: key.to_vec(),
+            value: buf[OFF_KEY + n..].to_vec(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_roundtrip() {
+        let v = TupleVersion {
+            xmin: Xid(3),
+            xmax: Xid::NONE,
+            key: b"k1".to_vec(),
+            value: b"hello".to_vec(),
+        };
+        let buf = v.encode().unwrap();
+        assert_eq!(buf.len(), 16 + 1 + 2 + 5);
+        assert_eq!(TupleVersion::decode(&buf).unwrap(), v);
+        assert!(TupleVersion::decode(&buf[..10]).is_err());
+    }
+}
@@ -0,0 +1,178 @@
+//! Transaction id, snapshot và bảng transaction đang chạy.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{DbError, DbResult, RecordId};
+
+/// Transaction id, tăng dần theo thứ tự begin.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Xid(pub u64);
+
+impl Xid {
+    /// Không có transaction (xmax của version còn sống).
+    pub const NONE: Xid = Xid(0);
+    /// Xid cấp cho transaction đầu tiên.
+    pub const FIRST: Xid = Xid(1);
+}
+
+/// Tập transaction đã commit mà 1 transaction nhìn thấy, chụp lúc begin.
+/// - xid < `xmin`: đã kết thúc trước khi chụp
+/// - xid >= `xmax`: begin sau khi chụp, không thấy
+/// - xid trong `active`: đang chạy lúc chụp, không thấy
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TxnSnapshot {
+    xmin: Xid,
+    xmax: Xid,
+    active: BTreeSet<Xid>,
+}
+
+impl TxnSnapshot {
+    /// Xid cũ nhất còn chạy lúc chụp.
+    pub fn xmin(&self) -> Xid {
+        self.xmin
+    }
+
+    /// Thay đổi của `xid` có thuộc snapshot không. Transaction abort đã tự undo
+    /// version của nó, nên xid đã kết thúc gặp trong heap luôn là xid đã commit.
+    pub fn sees(&self, xid: Xid) -> bool {
+        xid < self.xmax && !self.active.contains(&xid)
+    }
+}
+
+/// Thay đổi của transaction, undo theo thứ tự ngược khi abort.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Write {
+    /// Version mới được insert.
+    Inserted(RecordId),
+    /// xmax của version được set thành xid của transaction.
+    Deleted(RecordId),
+}
+
+/// Transaction MVCC, phải kết thúc bằng `MvccStore::commit` hoặc `abort`.
+#[must_use = "a transaction must be committed or aborted"]
+#[derive(Debug)]
+pub struct MvccTxn {
+    xid: Xid,
+    snapshot: TxnSnapshot,
+    pub(super) writes: Vec<Write>,
+}
+
+impl MvccTxn {
+    pub fn xid(&self) -> Xid {
+        self.xid
+    }
+
+    pub fn snapshot(&self) -> &TxnSnapshot {
+        &self.snapshot
+    }
+
+    /// Version tạo bởi `xmin` và xoá bởi `xmax` có hiện ra với transaction không.
+    pub(super) fn sees_version(&self, xmin: Xid, xmax: Xid) -> bool {
+        let created = xmin == self.xid || self.snapshot.sees(xmin);
+        let deleted = xmax != Xid::NONE && (xmax == self.xid || self.snapshot.sees(xmax));
+        created && !deleted
+    }
+}
+
+/// Cấp xid và theo dõi transaction đang chạy cùng snapshot của chúng.
+#[derive(Debug)]
+pub(super) struct TxnManager {
+    next: Xid,
+    /// xid đang chạy -> xmin snapshot của nó.
+    active: BTreeMap<Xid, Xid>,
+    /// Transaction đang chạy đã ghi vào heap.
+    writers: BTreeSet<Xid>,
+}
+
+impl TxnManager {
+    pub(super) fn new(next: Xid) -> Self {
+        TxnManager {
+            next,
+            active: BTreeMap::new(),
+            writers: BTreeSet::new(),
+        }
+    }
+
+    pub(super) fn begin(&mut self) -> MvccTxn {
+        let xid = self.next;
+        self.next = Xid(xid.0 + 1);
+        let active: BTreeSet<Xid> = self.active.keys().copied().collect();
+        let snapshot = TxnSnapshot {
+            xmin: active.first().copied().unwrap_or(xid),
+            xmax: xid,
+            active,
+        };
+        self.active.insert(xid, snapshot.xmin);
+        MvccTxn {
+            xid,
+            snapshot,
+            writes: Vec::new(),
+        }
+    }
+
+    pub(super) fn is_active(&self, xid: Xid) -> bool {
+        self.active.contains_key(&xid)
+    }
+
+    pub(super) fn check_active(&self, txn: &MvccTxn) -> DbResult<()> {
+        if !self.is_active(txn.xid) {
+            return Err(DbError::InvalidArgument("transaction is not active"));
+        }
+        Ok(())
+    }
+
+    pub(super) fn finish(&mut self, xid: Xid) {
+        self.active.remove(&xid);
+        self.writers.remove(&xid);
+    }
+
+    pub(super) fn note_write(&mut self, xid: Xid) {
+        self.writers.insert(xid);
+    }
+
+    /// Version bị xoá bởi xid < horizon là rác với mọi snapshot đang mở và mọi
+    /// snapshot chụp sau này.
+    pub(super) fn horizon(&self) -> Xid {
+        self.active.values().copied().min().unwrap_or(self.next)
+    }
+
+    pub(super) fn active_count(&self) -> usize {
+        self.active.len()
+    }
+
+    pub(super) fn writer_count(&self) -> usize {
+        self.writers.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_visibility() {
+        let mut m = TxnManager::new(Xid::FIRST);
+        let a = m.begin();
+        let b = m.begin();
+        m.finish(a.xid());
+        let c = m.begin();
+
+        // c thấy a (đã commit), không thấy b (đang chạy) và chính nó qua `sees`
+        assert!(c.snapshot().sees(a.xid()));
+        assert!(!c.snapshot().sees(b.xid()));
+        assert!(!b.snapshot().sees(a.xid()));
+        assert_eq!(c.snapshot().xmin(), b.xid());
+
+        // version của chính mình thấy được, version mình đã xoá thì không
+        assert!(c.sees_version(c.xid(), Xid::NONE));
+        assert!(!c.sees_version(a.xid(), c.xid()));
+        assert!(c.sees_version(a.xid(), b.xid()));
+
+        assert_eq!(m.horizon(), Xid(1));
+        m.finish(b.xid());
+        assert_eq!(m.horizon(), b.xid());
+        m.finish(c.xid());
+        assert_eq!(m.horizon(), Xid(4));
+        assert_eq!(m.active_count(), 0);
+    }
+}
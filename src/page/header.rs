@@ -1,18 +1,27 @@
 use crate::constants::PAGE_SIZE;
-use crate::page::raw::{read_u16_le, read_u64_le, write_u16_le, write_u64_le};
+use crate::page::raw::{read_u16_le, read_u32_le, write_u16_le, write_u32_le};
+use crate::page::slotted_page::SlottedPageRef;
 use crate::page::SLOTTED_HEADER_SIZE;
-use crate::{DbError, DbResult};
+use crate::{DbError, DbResult, Lsn};
 
 const OFF_LOWER: usize = 0;
 const OFF_UPPER: usize = 2;
 const OFF_SLOT_COUNT: usize = 4;
 const OFF_FLAGS: usize = 6;
-const OFF_RESERVED: usize = 8;
+// 8 bytes reserved cũ được chia cố định: [lsn u32][checksum u32]
+const OFF_LSN: usize = 8;
+const OFF_CHECKSUM: usize = 12;
+const CHECKSUM_SIZE: usize = 4;
+
+// FNV-1a 32-bit
+const FNV_OFFSET: u32 = 0x811C_9DC5;
+const FNV_PRIME: u32 = 0x0100_0193;
 
 pub const PAGE_TYPE_HEAP: u16 = 0;
 pub const PAGE_TYPE_BTREE_LEAF: u16 = 1;
 pub const PAGE_TYPE_BTREE_INTERNAL: u16 = 2;
 pub const PAGE_TYPE_BTREE_OVERFLOW: u16 = 3;
+pub const PAGE_TYPE_FSM: u16 = 4;
 
 pub const FLAG_HAS_FREE_SLOTS_BIT: u16 = 4;
 pub const FLAG_IS_COMPRESSED_BIT: u16 = 5;
@@ -22,7 +31,14 @@ pub const FLAG_HAS_FREE_SLOTS: u16 = 1u16 << FLAG_HAS_FREE_SLOTS_BIT;
 pub const FLAG_IS_COMPRESSED: u16 = 1u16 << FLAG_IS_COMPRESSED_BIT;
 pub const FLAG_IS_CHECKSUMMED: u16 = 1u16 << FLAG_IS_CHECKSUMMED_BIT;
 
-/// page header fixed 16 bytes, trong đó có 8 bytes là reserved
+/// Bits 8..15 của flags: kích thước special area (bytes) ở cuối page.
+/// Special area là vùng metadata riêng của từng loại page (vd heap: next page id),
+/// nằm ngoài vùng tuple: tuple data kết thúc tại `PAGE_SIZE - special_size`.
+const SPECIAL_SIZE_SHIFT: u16 = 8;
+const SPECIAL_SIZE_MASK: u16 = 0xFF00;
+pub const MAX_SPECIAL_SIZE: usize = 0xFF;
+
+/// page header fixed 16 bytes, 8 bytes cuối là page lsn (u32) + checksum (u32)
 /// PageHeader chỉ biểu diễn dữ liệu được lưu trong program, chứ k phải layout dưới disk
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct PageHeaderSnapshot {
@@ -38,16 +54,20 @@ pub struct PageHeaderSnapshot {
 
     /// flags: bitmask trạng thái ở cấp PAGE
     ///
-    /// - Bits 0..3  : page_type (0=heap, 1=btree_leaf, 2=btree_internal, 3=overflow, 4..15 reserved)
+    /// - Bits 0..3  : page_type (0=heap, 1=btree_leaf, 2=btree_internal, 3=overflow, 4=fsm, 5..15 reserved)
     /// - Bit  4     : HAS_FREE_SLOTS (trang có slot tombstone để reuse)
     /// - Bit  5     : IS_COMPRESSED (nếu sau này có nén)
     /// - Bit  6     : IS_CHECKSUMMED (nếu bật checksum)
     /// - Bit  7     : RESERVED
-    /// - Bits 8..15 : mở rộng sau
+    /// - Bits 8..15 : special area size (bytes, 0 = không có)
     flags: u16,
 
-    /// special field, mở rộng sau này (lsn, checksum, future metadata...)
-    reserved: u64,
+    /// LSN của log record cuối cùng đã apply lên page.
+    /// Recovery so sánh với LSN của record để biết record đã được apply chưa.
+    lsn: u32,
+
+    /// checksum của page (chỉ có ý nghĩa khi flag IS_CHECKSUMMED bật)
+    checksum: u32,
 }
 
 impl PageHeaderSnapshot {
@@ -67,8 +87,12 @@ impl PageHeaderSnapshot {
         self.slot_count
     }
 
-    pub fn reserved(&self) -> u64 {
-        self.reserved
+    pub fn lsn(&self) -> Lsn {
+        Lsn(self.lsn)
+    }
+
+    pub fn checksum(&self) -> u32 {
+        self.checksum
     }
 }
 
@@ -82,7 +106,8 @@ pub fn decode(buf: &[u8]) -> DbResult<PageHeaderSnapshot> {
         upper: read_u16_le(buf, OFF_UPPER)?,
         slot_count: read_u16_le(buf, OFF_SLOT_COUNT)?,
         flags: read_u16_le(buf, OFF_FLAGS)?,
-        reserved: read_u64_le(buf, OFF_RESERVED)?,
+        lsn: read_u32_le(buf, OFF_LSN)?,
+        checksum: read_u32_le(buf, OFF_CHECKSUM)?,
     })
 }
 
@@ -96,7 +121,25 @@ pub fn init_empty(buf: &mut [u8], page_type: u16) -> DbResult<()> {
     set_upper(buf, PAGE_SIZE as u16)?;
     set_slot_count(buf, 0)?;
     set_flags(buf, flags)?;
-    set_reserved(buf, 0)?;
+    set_page_lsn(buf, Lsn::ZERO)?;
+    set_checksum(buf, 0)?;
+    Ok(())
+}
+
+/// Giống `init_empty` nhưng chừa `special_size` bytes cuối page cho special area.
+/// - upper = PAGE_SIZE - special_size
+/// - special area được zero
+pub fn init_with_special(buf: &mut [u8], page_type: u16, special_size: usize) -> DbResult<()> {
+    debug_assert_eq!(buf.len(), PAGE_SIZE);
+    if special_size > MAX_SPECIAL_SIZE {
+        return Err(DbError::InvalidArgument("special area is too large"));
+    }
+
+    init_empty(buf, page_type)?;
+    let flags = (page_type & 0x000F) | ((special_size as u16) << SPECIAL_SIZE_SHIFT);
+    set_flags(buf, flags)?;
+    set_upper(buf, (PAGE_SIZE - special_size) as u16)?;
+    buf[PAGE_SIZE - special_size..].fill(0);
     Ok(())
 }
 
@@ -134,13 +177,57 @@ pub fn set_flags(buf: &mut [u8], v: u16) -> DbResult<()> {
     debug_assert_eq!(buf.len(), PAGE_SIZE);
     write_u16_le(buf, OFF_FLAGS, v)
 }
-pub fn reserved(buf: &[u8]) -> DbResult<u64> {
+pub fn page_lsn(buf: &[u8]) -> DbResult<Lsn> {
+    debug_assert_eq!(buf.len(), PAGE_SIZE);
+    Ok(Lsn(read_u32_le(buf, OFF_LSN)?))
+}
+pub fn set_page_lsn(buf: &mut [u8], v: Lsn) -> DbResult<()> {
+    debug_assert_eq!(buf.len(), PAGE_SIZE);
+    write_u32_le(buf, OFF_LSN, v.as_u32())
+}
+pub fn checksum(buf: &[u8]) -> DbResult<u32> {
+    debug_assert_eq!(buf.len(), PAGE_SIZE);
+    read_u32_le(buf, OFF_CHECKSUM)
+}
+pub fn set_checksum(buf: &mut [u8], v: u32) -> DbResult<()> {
     debug_assert_eq!(buf.len(), PAGE_SIZE);
-    read_u64_le(buf, OFF_RESERVED)
+    write_u32_le(buf, OFF_CHECKSUM, v)
 }
-pub fn set_reserved(buf: &mut [u8], v: u64) -> DbResult<()> {
+
+/// Checksum của cả page (FNV-1a), bỏ qua chính field checksum.
+pub fn compute_checksum(buf: &[u8]) -> u32 {
     debug_assert_eq!(buf.len(), PAGE_SIZE);
-    write_u64_le(buf, OFF_RESERVED, v)
+    buf[..OFF_CHECKSUM]
+        .iter()
+        .chain(&buf[OFF_CHECKSUM + CHECKSUM_SIZE..])
+        .fold(FNV_OFFSET, |h, &b| (h ^ b as u32).wrapping_mul(FNV_PRIME))
+}
+
+/// Page có header slotted hợp lệ: loại page đã biết và lower/upper/slot_count khớp
+/// layout. Pager chỉ stamp checksum các page này; page khác (meta, page free, buffer
+/// tuỳ ý của caller) được ghi và đọc lại nguyên từng byte.
+pub fn is_slotted(buf: &[u8]) -> DbResult<bool> {
+    if (flags(buf)? & 0x000F) > PAGE_TYPE_FSM {
+        return Ok(false);
+    }
+    Ok(SlottedPageRef::new(buf)?.validate_header().is_ok())
+}
+
+/// Bật IS_CHECKSUMMED và ghi checksum; pager gọi khi nhận page slotted được ghi (xem
+/// `PendingPages::write`).
+pub fn stamp_checksum(buf: &mut [u8]) -> DbResult<()> {
+    let flags = flags(buf)?;
+    set_flags(buf, set_flag(flags, FLAG_IS_CHECKSUMMED))?;
+    set_checksum(buf, compute_checksum(buf))
+}
+
+/// Page có IS_CHECKSUMMED thì checksum phải khớp (false = page bị ghi rách / hỏng);
+/// page không bật checksum luôn được coi là hợp lệ.
+pub fn verify_checksum(buf: &[u8]) -> DbResult<bool> {
+    if !has_flag(flags(buf)?, FLAG_IS_CHECKSUMMED) {
+        return Ok(true);
+    }
+    Ok(checksum(buf)? == compute_checksum(buf))
 }
 
 pub fn is_page_type(flags: u16, t: u16) -> bool {
@@ -165,6 +252,16 @@ pub fn has_flag(flags: u16, mask: u16) -> bool {
     (flags & mask) != 0
 }
 
+/// Kích thước special area lấy từ flags (bits 8..15).
+pub fn special_size(flags: u16) -> usize {
+    ((flags & SPECIAL_SIZE_MASK) >> SPECIAL_SIZE_SHIFT) as usize
+}
+
+/// Offset kết thúc vùng tuple data (= đầu special area).
+pub fn data_end(buf: &[u8]) -> DbResult<usize> {
+    Ok(PAGE_SIZE - special_size(flags(buf)?))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -187,6 +284,37 @@ mod tests {
         assert_eq!(h.lower(), expected_lower);
     }
 
+    #[test]
+    fn test_checksum_detects_torn_page() {
+        let mut buf = new_page_buf();
+        init_empty(&mut buf, PAGE_TYPE_HEAP).unwrap();
+        assert!(verify_checksum(&buf).unwrap());
+
+        buf[PAGE_SIZE - 1] = 0xAB;
+        stamp_checksum(&mut buf).unwrap();
+        assert!(has_flag(flags(&buf).unwrap(), FLAG_IS_CHECKSUMMED));
+        assert!(verify_checksum(&buf).unwrap());
+
+        // nửa sau page còn data cũ
+        buf[PAGE_SIZE / 2..].fill(0);
+        assert!(!verify_checksum(&buf).unwrap());
+    }
+
+    #[test]
+    fn test_is_slotted() {
+        let mut buf = new_page_buf();
+        assert!(!is_slotted(&buf).unwrap());
+        init_with_special(&mut buf, PAGE_TYPE_BTREE_LEAF, 8).unwrap();
+        assert!(is_slotted(&buf).unwrap());
+
+        // loại page lạ hoặc lower sai công thức -> không phải page slotted
+        set_flags(&mut buf, 9).unwrap();
+        assert!(!is_slotted(&buf).unwrap());
+        init_empty(&mut buf, PAGE_TYPE_HEAP).unwrap();
+        set_lower(&mut buf, SLOTTED_HEADER_SIZE as u16 + 1).unwrap();
+        assert!(!is_slotted(&buf).unwrap());
+    }
+
     #[test]
     fn test_flags_helpers_and_set_page_type() {
         let flags = set_flag(PAGE_TYPE_BTREE_INTERNAL, FLAG_HAS_FREE_SLOTS);
@@ -221,7 +349,8 @@ mod tests {
             upper: PAGE_SIZE as u16,
             slot_count,
             flags: 0,
-            reserved: 0,
+            lsn: 0,
+            checksum: 0,
         };
 
         check_invariants(&h);
@@ -235,7 +364,8 @@ mod tests {
             upper: PAGE_SIZE as u16,
             slot_count: 0,
             flags: 0,
-            reserved: 0,
+            lsn: 0,
+            checksum: 0,
         };
 
         check_invariants(&h);
@@ -250,7 +380,8 @@ mod tests {
         assert_eq!(upper(&buf).unwrap(), PAGE_SIZE as u16);
         assert_eq!(slot_count(&buf).unwrap(), 0);
         assert!(is_page_type(flags(&buf).unwrap(), PAGE_TYPE_BTREE_INTERNAL));
-        assert_eq!(reserved(&buf).unwrap(), 0);
+        assert_eq!(page_lsn(&buf).unwrap(), Lsn::ZERO);
+        assert_eq!(checksum(&buf).unwrap(), 0);
     }
 
     #[test]
@@ -262,20 +393,63 @@ mod tests {
         set_upper(&mut buf, 4000).unwrap();
         set_slot_count(&mut buf, 10).unwrap();
         set_flags(&mut buf, 0x00F2).unwrap();
-        set_reserved(&mut buf, 0x1122_3344_5566_7788).unwrap();
+        set_page_lsn(&mut buf, Lsn(0x1122_3344)).unwrap();
+        set_checksum(&mut buf, 0x5566_7788).unwrap();
 
         assert_eq!(lower(&buf).unwrap(), 123);
         assert_eq!(upper(&buf).unwrap(), 4000);
         assert_eq!(slot_count(&buf).unwrap(), 10);
         assert_eq!(flags(&buf).unwrap(), 0x00F2);
-        assert_eq!(reserved(&buf).unwrap(), 0x1122_3344_5566_7788);
+        assert_eq!(page_lsn(&buf).unwrap(), Lsn(0x1122_3344));
+        assert_eq!(checksum(&buf).unwrap(), 0x5566_7788);
+    }
+
+    #[test]
+    fn test_lsn_and_checksum_are_independent() {
+        let mut buf = new_page_buf();
+        init_empty(&mut buf, PAGE_TYPE_HEAP).unwrap();
+
+        set_checksum(&mut buf, u32::MAX).unwrap();
+        set_page_lsn(&mut buf, Lsn(42)).unwrap();
+        assert_eq!(checksum(&buf).unwrap(), u32::MAX);
+
+        set_page_lsn(&mut buf, Lsn(u32::MAX)).unwrap();
+        set_checksum(&mut buf, 0).unwrap();
+        assert_eq!(page_lsn(&buf).unwrap(), Lsn(u32::MAX));
+
+        // 2 field không đè lên lower/upper/slot_count/flags
+        assert_eq!(lower(&buf).unwrap(), SLOTTED_HEADER_SIZE as u16);
+        assert_eq!(upper(&buf).unwrap(), PAGE_SIZE as u16);
+    }
+
+    #[test]
+    fn test_init_with_special() {
+        let mut buf = vec![0xAAu8; PAGE_SIZE];
+        init_with_special(&mut buf, PAGE_TYPE_HEAP, 4).unwrap();
+
+        let f = flags(&buf).unwrap();
+        assert!(is_page_type(f, PAGE_TYPE_HEAP));
+        assert_eq!(special_size(f), 4);
+        assert_eq!(upper(&buf).unwrap() as usize, PAGE_SIZE - 4);
+        assert_eq!(data_end(&buf).unwrap(), PAGE_SIZE - 4);
+        assert_eq!(&buf[PAGE_SIZE - 4..], &[0, 0, 0, 0]);
+
+        // set flag khác không làm mất special size
+        set_flags(&mut buf, set_flag(f, FLAG_HAS_FREE_SLOTS)).unwrap();
+        assert_eq!(special_size(flags(&buf).unwrap()), 4);
+
+        // page init thường không có special area
+        init_empty(&mut buf, PAGE_TYPE_HEAP).unwrap();
+        assert_eq!(data_end(&buf).unwrap(), PAGE_SIZE);
+
+        assert!(init_with_special(&mut buf, PAGE_TYPE_HEAP, MAX_SPECIAL_SIZE + 1).is_err());
     }
 
     #[test]
     fn test_decode_invalid_size() {
         let buf = vec![0u8; 100];
         let e = decode(&buf).is_err();
-        assert_eq!(e, true)
+        assert!(e)
     }
 
     #[test]
@@ -284,7 +458,8 @@ mod tests {
         init_empty(&mut buf, PAGE_TYPE_BTREE_LEAF).unwrap();
         let cur = flags(&buf).unwrap();
         set_flags(&mut buf, set_flag(cur, FLAG_HAS_FREE_SLOTS)).unwrap();
-        set_reserved(&mut buf, 99).unwrap();
+        set_page_lsn(&mut buf, Lsn(99)).unwrap();
+        set_checksum(&mut buf, 7).unwrap();
 
         let h = decode(&buf).unwrap();
         assert_eq!(h.lower(), SLOTTED_HEADER_SIZE as u16);
@@ -292,7 +467,8 @@ mod tests {
         assert_eq!(h.slot_count(), 0);
         assert!(is_page_type(h.flags(), PAGE_TYPE_BTREE_LEAF));
         assert!(has_free_slots(h.flags()));
-        assert_eq!(h.reserved(), 99);
+        assert_eq!(h.lsn(), Lsn(99));
+        assert_eq!(h.checksum(), 7);
     }
 
     #[test]
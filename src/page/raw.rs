@@ -17,6 +17,33 @@ fn checked_range(len: usize, off: usize, size: usize) -> DbResult<std::ops::Rang
     Ok(off..off + size)
 }
 
+#[inline]
+pub fn read_u8(buf: &[u8], off: usize) -> DbResult<u8> {
+    let r = checked_range(buf.len(), off, 1)?;
+    Ok(buf[r.start])
+}
+
+#[inline]
+pub fn write_u8(buf: &mut [u8], off: usize, v: u8) -> DbResult<()> {
+    let r = checked_range(buf.len(), off, 1)?;
+    buf[r.start] = v;
+    Ok(())
+}
+
+/// Đọc `len` bytes tại `off` (borrow, không copy).
+#[inline]
+pub fn read_bytes(buf: &[u8], off: usize, len: usize) -> DbResult<&[u8]> {
+    let r = checked_range(buf.len(), off, len)?;
+    Ok(&buf[r])
+}
+
+#[inline]
+pub fn write_bytes(buf: &mut [u8], off: usize, data: &[u8]) -> DbResult<()> {
+    let r = checked_range(buf.len(), off, data.len())?;
+    buf[r].copy_from_slice(data);
+    Ok(())
+}
+
 #[inline]
 pub fn read_u16_le(buf: &[u8], off: usize) -> DbResult<u16> {
     let r = checked_range(buf.len(), off, 2)?;
@@ -89,6 +116,156 @@ pub fn write_u64_le(buf: &mut [u8], off: usize, v: u64) -> DbResult<()> {
     Ok(())
 }
 
+#[inline]
+pub fn read_i64_le(buf: &[u8], off: usize) -> DbResult<i64> {
+    Ok(read_u64_le(buf, off)? as i64)
+}
+
+#[inline]
+pub fn write_i64_le(buf: &mut [u8], off: usize, v: i64) -> DbResult<()> {
+    write_u64_le(buf, off, v as u64)
+}
+
+/// f64 lưu theo bit pattern IEEE-754 (giữ nguyên NaN/-0.0).
+#[inline]
+pub fn read_f64_le(buf: &[u8], off: usize) -> DbResult<f64> {
+    Ok(f64::from_bits(read_u64_le(buf, off)?))
+}
+
+#[inline]
+pub fn write_f64_le(buf: &mut [u8], off: usize, v: f64) -> DbResult<()> {
+    write_u64_le(buf, off, v.to_bits())
+}
+
+/// Varint kiểu SQLite: 1..9 bytes, big-endian.
+/// - byte 1..8: 7 bit data, bit cao nhất = 1 nghĩa là còn byte tiếp theo
+/// - byte 9 (nếu có): đủ 8 bit data
+///
+/// Giá trị nhỏ (< 128) chỉ tốn 1 byte.
+pub const VARINT_MAX_LEN: usize = 9;
+
+const VARINT_CONT_BIT: u8 = 0x80;
+const VARINT_DATA_MASK: u64 = 0x7F;
+/// 8 byte đầu chở 7*8 = 56 bit; giá trị cần > 56 bit thì phải dùng đủ 9 byte.
+const VARINT_56_BITS: u64 = (1u64 << 56) - 1;
+
+/// Số bytes cần để encode `v`.
+pub fn varint_len(v: u64) -> usize {
+    if v > VARINT_56_BITS {
+        return VARINT_MAX_LEN;
+    }
+    let mut n = 1;
+    let mut rest = v >> 7;
+    while rest != 0 {
+        n += 1;
+        rest >>= 7;
+    }
+    n
+}
+
+/// Ghi varint tại `off`, trả về số bytes đã ghi.
+pub fn write_varint(buf: &mut [u8], off: usize, v: u64) -> DbResult<usize> {
+    let n = varint_len(v);
+    let r = checked_range(buf.len(), off, n)?;
+    let out = &mut buf[r];
+
+    if n == VARINT_MAX_LEN {
+        // byte cuối giữ 8 bit thấp, 8 byte đầu giữ 56 bit cao
+        out[8] = v as u8;
+        let mut rest = v >> 8;
+        for i in (0..8).rev() {
+            out[i] = (rest & VARINT_DATA_MASK) as u8 | VARINT_CONT_BIT;
+            rest >>= 7;
+        }
+        return Ok(n);
+    }
+
+    let mut rest = v;
+    for i in (0..n).rev() {
+        out[i] = (rest & VARINT_DATA_MASK) as u8 | VARINT_CONT_BIT;
+        rest >>= 7;
+    }
+    // byte cuối không có continuation bit
+    out[n - 1] &= !VARINT_CONT_BIT;
+    Ok(n)
+}
+
+/// Đọc varint tại `off`, trả về (value, số bytes đã đọc).
+pub fn read_varint(buf: &[u8], off: usize) -> DbResult<(u64, usize)> {
+    // off gần usize::MAX (offset corrupt) -> off + i tràn, báo lỗi như đọc ngoài buf
+    let byte_at = |i: usize| match off.checked_add(i) {
+        Some(pos) => read_u8(buf, pos),
+        None => Err(DbError::OutOfBounds {
+            off,
+            size: i + 1,
+            len: buf.len(),
+        }),
+    };
+    let mut v: u64 = 0;
+    for i in 0..VARINT_MAX_LEN - 1 {
+        let b = byte_at(i)?;
+        v = (v << 7) | (b as u64 & VARINT_DATA_MASK);
+        if b & VARINT_CONT_BIT == 0 {
+            return Ok((v, i + 1));
+        }
+    }
+
+    // byte thứ 9 dùng đủ 8 bit
+    let b = byte_at(VARINT_MAX_LEN - 1)?;
+    v = (v << 8) | b as u64;
+    Ok((v, VARINT_MAX_LEN))
+}
+
+/// Zigzag: map số âm nhỏ thành số dương nhỏ (0,-1,1,-2.. -> 0,1,2,3..)
+/// để varint của số âm nhỏ vẫn ngắn.
+#[inline]
+pub fn zigzag_encode(v: i64) -> u64 {
+    ((v << 1) ^ (v >> 63)) as u64
+}
+
+#[inline]
+pub fn zigzag_decode(v: u64) -> i64 {
+    ((v >> 1) as i64) ^ -((v & 1) as i64)
+}
+
+/// Varint có dấu (zigzag + varint).
+pub fn write_varint_i64(buf: &mut [u8], off: usize, v: i64) -> DbResult<usize> {
+    write_varint(buf, off, zigzag_encode(v))
+}
+
+pub fn read_varint_i64(buf: &[u8], off: usize) -> DbResult<(i64, usize)> {
+    let (v, n) = read_varint(buf, off)?;
+    Ok((zigzag_decode(v), n))
+}
+
+/// Số bytes cần để ghi 1 byte string có prefix độ dài (varint len + data).
+pub fn len_prefixed_size(data_len: usize) -> usize {
+    varint_len(data_len as u64) + data_len
+}
+
+/// Ghi `[varint len][data]` tại `off`, trả về tổng số bytes đã ghi.
+/// Check đủ chỗ cho cả prefix và data trước khi ghi để fail thì buf không bị sửa.
+pub fn write_len_prefixed(buf: &mut [u8], off: usize, data: &[u8]) -> DbResult<usize> {
+    let total = len_prefixed_size(data.len());
+    checked_range(buf.len(), off, total)?;
+
+    let n = write_varint(buf, off, data.len() as u64)?;
+    write_bytes(buf, off + n, data)?;
+    Ok(total)
+}
+
+/// Đọc `[varint len][data]` tại `off`, trả về (data, tổng số bytes đã đọc).
+pub fn read_len_prefixed(buf: &[u8], off: usize) -> DbResult<(&[u8], usize)> {
+    let (len, n) = read_varint(buf, off)?;
+    let len: usize = len.try_into().map_err(|_| DbError::OutOfBounds {
+        off: off + n,
+        size: usize::MAX,
+        len: buf.len(),
+    })?;
+    let data = read_bytes(buf, off + n, len)?;
+    Ok((data, n + len))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -109,6 +286,147 @@ mod tests {
         assert_eq!(v, 0x1122_3344_5566_7788);
     }
 
+    #[test]
+    fn test_read_write_bytes() {
+        let mut buf = [0u8; 8];
+        write_u8(&mut buf, 0, 7).unwrap();
+        write_bytes(&mut buf, 1, b"abc").unwrap();
+        assert_eq!(read_u8(&buf, 0).unwrap(), 7);
+        assert_eq!(read_bytes(&buf, 1, 3).unwrap(), b"abc");
+
+        // len = 0 ở cuối buffer vẫn hợp lệ
+        assert_eq!(read_bytes(&buf, 8, 0).unwrap(), b"");
+        assert!(read_bytes(&buf, 6, 3).is_err());
+        assert!(write_bytes(&mut buf, 7, b"xy").is_err());
+    }
+
+    #[test]
+    fn test_read_write_i64_f64() {
+        let mut buf = [0u8; 16];
+        write_i64_le(&mut buf, 0, -5).unwrap();
+        write_f64_le(&mut buf, 8, -0.0).unwrap();
+        assert_eq!(read_i64_le(&buf, 0).unwrap(), -5);
+        let f = read_f64_le(&buf, 8).unwrap();
+        assert_eq!(f.to_bits(), (-0.0f64).to_bits());
+
+        write_f64_le(&mut buf, 8, f64::NAN).unwrap();
+        assert!(read_f64_le(&buf, 8).unwrap().is_nan());
+        assert!(read_i64_le(&buf, 9).is_err());
+    }
+
+    #[test]
+    fn test_varint_len_boundaries() {
+        assert_eq!(varint_len(0), 1);
+        assert_eq!(varint_len(0x7F), 1);
+        assert_eq!(varint_len(0x80), 2);
+        assert_eq!(varint_len(0x3FFF), 2);
+        assert_eq!(varint_len(0x4000), 3);
+        assert_eq!(varint_len((1 << 56) - 1), 8);
+        assert_eq!(varint_len(1 << 56), 9);
+        assert_eq!(varint_len(u64::MAX), 9);
+    }
+
+    #[test]
+    fn test_varint_roundtrip() {
+        let cases = [
+            0u64,
+            1,
+            127,
+            128,
+            240,
+            16_383,
+            16_384,
+            u32::MAX as u64,
+            (1 << 56) - 1,
+            1 << 56,
+            u64::MAX - 1,
+            u64::MAX,
+        ];
+        for v in cases {
+            let mut buf = [0u8; 12];
+            let n = write_varint(&mut buf, 1, v).unwrap();
+            assert_eq!(n, varint_len(v));
+            assert_eq!(read_varint(&buf, 1).unwrap(), (v, n), "v={}", v);
+        }
+    }
+
+    #[test]
+    fn test_varint_big_endian_bytes() {
+        let mut buf = [0u8; 9];
+        // 300 = 0b10_0101100 -> [0x82, 0x2C]
+        assert_eq!(write_varint(&mut buf, 0, 300).unwrap(), 2);
+        assert_eq!(&buf[..2], &[0x82, 0x2C]);
+
+        // 9 bytes: byte cuối giữ nguyên 8 bit
+        assert_eq!(write_varint(&mut buf, 0, u64::MAX).unwrap(), 9);
+        assert_eq!(buf, [0xFF; 9]);
+    }
+
+    #[test]
+    fn test_varint_out_of_bounds() {
+        let mut buf = [0u8; 2];
+        assert!(write_varint(&mut buf, 0, 1 << 20).is_err());
+        assert_eq!(buf, [0, 0], "failed write must not touch buf");
+
+        // continuation bit bật nhưng hết buffer
+        let buf = [0x80u8, 0x80];
+        match read_varint(&buf, 0).unwrap_err() {
+            crate::error::DbError::OutOfBounds { .. } => {}
+            other => panic!("expected OutOfBounds, got: {:?}", other),
+        }
+
+        // offset corrupt gần usize::MAX: không được tràn khi cộng off + i
+        let buf = [0xFFu8; 16];
+        for off in [usize::MAX, usize::MAX - 4] {
+            match read_varint(&buf, off).unwrap_err() {
+                crate::error::DbError::OutOfBounds { .. } => {}
+                other => panic!("expected OutOfBounds, got: {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_zigzag() {
+        assert_eq!(zigzag_encode(0), 0);
+        assert_eq!(zigzag_encode(-1), 1);
+        assert_eq!(zigzag_encode(1), 2);
+        assert_eq!(zigzag_encode(-2), 3);
+        for v in [0i64, 1, -1, 63, -64, i64::MIN, i64::MAX] {
+            assert_eq!(zigzag_decode(zigzag_encode(v)), v);
+
+            let mut buf = [0u8; 9];
+            let n = write_varint_i64(&mut buf, 0, v).unwrap();
+            assert_eq!(read_varint_i64(&buf, 0).unwrap(), (v, n));
+        }
+        // số âm nhỏ vẫn 1 byte
+        assert_eq!(varint_len(zigzag_encode(-64)), 1);
+    }
+
+    #[test]
+    fn test_len_prefixed() {
+        let mut buf = [0u8; 300];
+        let data = [7u8; 200];
+        let n = write_len_prefixed(&mut buf, 0, &data).unwrap();
+        assert_eq!(n, 2 + 200);
+        assert_eq!(len_prefixed_size(200), n);
+        let (got, used) = read_len_prefixed(&buf, 0).unwrap();
+        assert_eq!(got, &data[..]);
+        assert_eq!(used, n);
+
+        let n2 = write_len_prefixed(&mut buf, n, b"").unwrap();
+        assert_eq!(n2, 1);
+        assert_eq!(read_len_prefixed(&buf, n).unwrap(), (&b""[..], 1));
+
+        // không đủ chỗ cho data -> không ghi gì
+        let mut small = [0u8; 4];
+        assert!(write_len_prefixed(&mut small, 0, b"abcd").is_err());
+        assert_eq!(small, [0u8; 4]);
+
+        // prefix nói len lớn hơn phần còn lại
+        let bad = [10u8, 1, 2];
+        assert!(read_len_prefixed(&bad, 0).is_err());
+    }
+
     #[test]
     fn test_out_of_bounds() {
         let mut buf = [0u8; 8];
@@ -5,12 +5,16 @@ use super::{SLOTTED_HEADER_SIZE, SLOTTED_SLOT_SIZE};
 
 /// bitmask value
 /// 0: DELETED
-/// 1: REDIRECTED
+/// 1: REDIRECTED (data là địa chỉ tuple thật ở chỗ khác)
 /// 2: OVERFLOW
-/// 3..15 -> reserved - mở rộng nếu có thể
+/// 3: MOVED (tuple thật, đích của 1 slot REDIRECTED)
+/// 4: PADDED (data được pad thêm, byte cuối là độ dài thật)
+/// 5..15 -> reserved - mở rộng nếu có thể
 const SLOT_DEAD: u16 = 1 << 0;
 const SLOT_REDIRECTED: u16 = 1 << 1;
 const SLOT_OVERFLOW: u16 = 1 << 2;
+const SLOT_MOVED: u16 = 1 << 3;
+const SLOT_PADDED: u16 = 1 << 4;
 
 // fixed position cho mỗi slot
 const OFF_SLOT_OFFSET: usize = 0;
@@ -39,6 +43,10 @@ impl Slot {
         self.len
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
     pub fn flags(&self) -> u16 {
         self.flags
     }
@@ -54,6 +62,18 @@ impl Slot {
     pub fn mark_flags_overflow(&mut self) {
         self.flags |= SLOT_OVERFLOW;
     }
+
+    pub fn mark_flags_moved(&mut self) {
+        self.flags |= SLOT_MOVED;
+    }
+
+    pub fn mark_flags_padded(&mut self) {
+        self.flags |= SLOT_PADDED;
+    }
+
+    pub fn clear_flags_padded(&mut self) {
+        self.flags &= !SLOT_PADDED;
+    }
 }
 
 pub fn slot_off(slot_id: u16) -> usize {
@@ -100,6 +120,14 @@ pub fn is_overflow(flags: u16) -> bool {
     flags & SLOT_OVERFLOW != 0
 }
 
+pub fn is_moved(flags: u16) -> bool {
+    flags & SLOT_MOVED != 0
+}
+
+pub fn is_padded(flags: u16) -> bool {
+    flags & SLOT_PADDED != 0
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -145,7 +173,20 @@ mod tests {
     fn test_flags_helpers() {
         assert!(is_redirected(1 << 1));
         assert!(is_overflow(1 << 2));
+        assert!(is_moved(1 << 3));
         assert!(!is_redirected(0));
         assert!(!is_overflow(0));
+        assert!(!is_moved(0));
+
+        let mut s = Slot::new(0, 0, 0);
+        s.mark_flags_redirected();
+        s.mark_flags_moved();
+        assert!(is_redirected(s.flags()) && is_moved(s.flags()));
+        assert!(!is_dead(s.flags()));
+
+        s.mark_flags_padded();
+        assert!(is_padded(s.flags()) && is_moved(s.flags()));
+        s.clear_flags_padded();
+        assert!(!is_padded(s.flags()) && is_moved(s.flags()));
     }
 }
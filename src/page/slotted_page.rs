@@ -1,6 +1,6 @@
 use super::{slot, SLOTTED_HEADER_SIZE, SLOTTED_SLOT_SIZE};
 use crate::page::header::{self};
-use crate::{constants::PAGE_SIZE, DbError, DbResult};
+use crate::{constants::PAGE_SIZE, DbError, DbResult, Lsn};
 
 /// SlottedPage là API cấp cao thao tác trên 1 page bytes theo layout slotted-page.
 /// - Header ở đầu page (fixed 16 bytes)
@@ -29,12 +29,54 @@ impl<'a> SlottedPage<'a> {
         Ok(self)
     }
 
+    /// Khởi tạo page rỗng có special area `special_size` bytes ở cuối page.
+    /// - upper = PAGE_SIZE - special_size
+    pub fn init_with_special(self, page_type: u16, special_size: usize) -> DbResult<Self> {
+        header::init_with_special(self.buf, page_type, special_size)?;
+        Ok(self)
+    }
+
+    /// View chỉ đọc trên cùng buffer.
+    pub fn view(&self) -> SlottedPageRef<'_> {
+        SlottedPageRef { buf: self.buf }
+    }
+
+    /// Special area (metadata riêng của loại page), rỗng nếu page không có.
+    pub fn special(&self) -> DbResult<&[u8]> {
+        self.view().special()
+    }
+
+    pub fn special_mut(&mut self) -> DbResult<&mut [u8]> {
+        let end = header::data_end(self.buf)?;
+        Ok(&mut self.buf[end..])
+    }
+
+    pub fn slot_count(&self) -> DbResult<u16> {
+        self.view().slot_count()
+    }
+
+    /// Đọc slot entry (offset/len/flags) của slot_id.
+    pub fn slot(&self, slot_id: u16) -> DbResult<slot::Slot> {
+        self.view().slot(slot_id)
+    }
+
+    /// Ghi đè flags của slot (giữ offset/len), dùng cho REDIRECTED/MOVED.
+    pub fn set_slot_flags(&mut self, slot_id: u16, flags: u16) -> DbResult<()> {
+        let s = self.slot(slot_id)?;
+        slot::write_slot(
+            self.buf,
+            slot_id,
+            &slot::Slot::new(s.offset(), s.len(), flags),
+        )
+    }
+
     #[cfg(debug_assertions)]
     pub fn validate_full(&self) -> DbResult<()> {
         self.validate_header()?;
 
         let up = header::upper(self.buf)? as usize;
         let sc = header::slot_count(self.buf)? as usize;
+        let data_end = header::data_end(self.buf)?;
 
         for slot_id in 0..sc {
             let s = slot::read_slot(self.buf, slot_id as u16)?;
@@ -44,7 +86,7 @@ impl<'a> SlottedPage<'a> {
                 let end = start
                     .checked_add(len)
                     .ok_or(DbError::Corruption("tuple end overflow"))?;
-                if end > PAGE_SIZE {
+                if end > data_end {
                     return Err(DbError::Corruption("corrupt slot: tuple out of bounds"));
                 }
                 if start < up {
@@ -58,90 +100,60 @@ impl<'a> SlottedPage<'a> {
     }
 
     pub fn validate_header(&self) -> DbResult<()> {
-        // header fields
-        let lo = header::lower(self.buf)? as usize;
-        let up = header::upper(self.buf)? as usize;
-        let sc = header::slot_count(self.buf)? as usize;
-
-        // lower phải >= header size
-        if lo < SLOTTED_HEADER_SIZE {
-            return Err(DbError::Corruption("corrupt header: lower < header size"));
-        }
-
-        // upper không vượt page size
-        if up > PAGE_SIZE {
-            return Err(DbError::Corruption("corrupt header: upper > PAGE_SIZE"));
-        }
-
-        // lower <= upper
-        if lo > up {
-            return Err(DbError::Corruption("corrupt header: lower > upper"));
-        }
-
-        // lower phải đúng công thức slot directory
-        let slot_bytes = sc
-            .checked_mul(SLOTTED_SLOT_SIZE)
-            .ok_or(DbError::Corruption("corrupt header: slot_count overflow"))?;
-
-        let expected_lo = SLOTTED_HEADER_SIZE
-            .checked_add(slot_bytes)
-            .ok_or(DbError::Corruption("corrupt header: lower overflow"))?;
-
-        if expected_lo > PAGE_SIZE {
-            return Err(DbError::Corruption(
-                "corrupt header: slot directory out of page",
-            ));
-        }
-        if lo != expected_lo {
-            return Err(DbError::Corruption(
-                "corrupt header: lower != header_size + slot_count*slot_size",
-            ));
-        }
-        Ok(())
+        self.view().validate_header()
     }
 
     /// Free space hiện tại trong page (upper - lower).
     pub fn free_space(&self) -> DbResult<u16> {
-        let up = header::upper(self.buf)?;
-        let lo = header::lower(self.buf)?;
-        up.checked_sub(lo)
-            .ok_or(DbError::Corruption("corrupt header: lower > upper"))
+        self.view().free_space()
     }
 
-    /// Lấy record bytes theo slot_id.
-    /// Trả None nếu slot DEAD.
-    /// Các check cần có:
-    /// - slot_id < slot_count
-    /// - slot.offset + slot.len <= PAGE_SIZE
-    pub fn get(&self, slot_id: u16) -> DbResult<Option<&[u8]>> {
-        // pub fn get<'b>(&'b self, slot_id: u16) -> DbResult<Option<&'b [u8]>> {
-        self.validate_header()?;
+    /// LSN của log record cuối cùng đã apply lên page.
+    pub fn page_lsn(&self) -> DbResult<Lsn> {
+        self.view().page_lsn()
+    }
 
-        let sc = header::slot_count(self.buf)?;
-        if slot_id >= sc {
-            return Err(DbError::InvalidArgument("invalid slot_id"));
-        }
+    /// Stamp LSN lên page (dùng khi redo/recovery set thẳng LSN).
+    /// LSN không được đi lùi: record có LSN nhỏ hơn page LSN nghĩa là đã được apply.
+    pub fn set_page_lsn(&mut self, lsn: Lsn) -> DbResult<()> {
+        self.check_lsn(lsn)?;
+        header::set_page_lsn(self.buf, lsn)
+    }
 
-        let slot = slot::read_slot(self.buf, slot_id)?;
-        if slot::is_dead(slot.flags()) {
-            return Ok(None);
-        }
+    /// Giống `insert` nhưng stamp `lsn` lên page sau khi insert thành công.
+    pub fn insert_with_lsn(&mut self, data: &[u8], lsn: Lsn) -> DbResult<u16> {
+        // check trước khi sửa page để fail thì page vẫn nguyên
+        self.check_lsn(lsn)?;
+        let slot_id = self.insert(data)?;
+        header::set_page_lsn(self.buf, lsn)?;
+        Ok(slot_id)
+    }
 
-        let start = slot.offset() as usize;
-        let up = header::upper(self.buf)? as usize;
-        if start < up {
-            return Err(DbError::Corruption("tuple overlaps free space"));
-        }
+    /// Giống `update` nhưng stamp `lsn` lên page sau khi update thành công.
+    pub fn update_with_lsn(&mut self, slot_id: u16, data: &[u8], lsn: Lsn) -> DbResult<bool> {
+        self.check_lsn(lsn)?;
+        let moved = self.update(slot_id, data)?;
+        header::set_page_lsn(self.buf, lsn)?;
+        Ok(moved)
+    }
 
-        let len = slot.len() as usize;
-        let end = start
-            .checked_add(len)
-            .ok_or(DbError::Corruption("tuple end overflow"))?;
-        if end > PAGE_SIZE {
-            return Err(DbError::Corruption("tuple end must be <= PAGE_SIZE"));
+    /// Giống `delete` nhưng stamp `lsn` lên page sau khi delete thành công.
+    pub fn delete_with_lsn(&mut self, slot_id: u16, lsn: Lsn) -> DbResult<()> {
+        self.check_lsn(lsn)?;
+        self.delete(slot_id)?;
+        header::set_page_lsn(self.buf, lsn)
+    }
+
+    fn check_lsn(&self, lsn: Lsn) -> DbResult<()> {
+        if lsn < header::page_lsn(self.buf)? {
+            return Err(DbError::InvalidArgument("page lsn must not go backwards"));
         }
+        Ok(())
+    }
 
-        Ok(Some(&self.buf[start..end]))
+    /// Lấy record bytes theo slot_id, trả None nếu slot DEAD (xem `SlottedPageRef::get`).
+    pub fn get(&self, slot_id: u16) -> DbResult<Option<&[u8]>> {
+        self.view().get(slot_id)
     }
 
     /// Insert record bytes vào page.
@@ -323,6 +335,62 @@ impl<'a> SlottedPage<'a> {
         Ok(())
     }
 
+    /// Dồn toàn bộ tuple còn sống về cuối vùng data để gom garbage
+    /// (data cũ sau update moved / delete) thành 1 vùng free liền mạch.
+    /// - slot_id và flags giữ nguyên (RID không đổi)
+    /// - slot DEAD được set offset = data_end, len = 0
+    ///
+    /// Return: số bytes free tăng thêm.
+    pub fn compact(&mut self) -> DbResult<u16> {
+        self.validate_header()?;
+
+        let before = self.free_space()?;
+        let sc = header::slot_count(self.buf)?;
+        let data_end = header::data_end(self.buf)?;
+
+        // copy tuple còn sống ra ngoài trước, vì vùng mới có thể đè vùng cũ
+        let mut live: Vec<(u16, slot::Slot, Vec<u8>)> = Vec::new();
+        for slot_id in 0..sc {
+            let s = slot::read_slot(self.buf, slot_id)?;
+            if slot::is_dead(s.flags()) {
+                continue;
+            }
+            let data = self
+                .get(slot_id)?
+                .ok_or(DbError::Corruption("live slot has no data"))?;
+            live.push((slot_id, s, data.to_vec()));
+        }
+
+        let mut up = data_end;
+        for (slot_id, s, data) in &live {
+            up -= data.len();
+            self.buf[up..up + data.len()].copy_from_slice(data);
+            slot::write_slot(
+                self.buf,
+                *slot_id,
+                &slot::Slot::new(up as u16, s.len(), s.flags()),
+            )?;
+        }
+
+        for slot_id in 0..sc {
+            let s = slot::read_slot(self.buf, slot_id)?;
+            if slot::is_dead(s.flags()) {
+                slot::write_slot(
+                    self.buf,
+                    slot_id,
+                    &slot::Slot::new(data_end as u16, 0, s.flags()),
+                )?;
+            }
+        }
+
+        // zero vùng free để dễ debug
+        let lo = header::lower(self.buf)? as usize;
+        self.buf[lo..up].fill(0);
+        header::set_upper(self.buf, up as u16)?;
+
+        Ok(self.free_space()? - before)
+    }
+
     /// Tìm slot tombstone để reuse.
     /// Nếu page header có HAS_FREE_SLOTS thì scan slot directory, return slot_id đầu tiên DEAD.
     fn find_free_slot(&mut self) -> DbResult<Option<u16>> {
@@ -346,6 +414,136 @@ impl<'a> SlottedPage<'a> {
     }
 }
 
+/// View chỉ đọc của slotted page (vd page đang được giữ bởi shared latch).
+/// Cùng layout và cùng các check như `SlottedPage`.
+#[derive(Clone, Copy)]
+pub struct SlottedPageRef<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> SlottedPageRef<'a> {
+    pub fn new(buf: &'a [u8]) -> DbResult<Self> {
+        if buf.len() != PAGE_SIZE {
+            return Err(DbError::Corruption("buffer length must equal PAGE_SIZE"));
+        }
+        Ok(SlottedPageRef { buf })
+    }
+
+    /// Special area (metadata riêng của loại page), rỗng nếu page không có.
+    pub fn special(&self) -> DbResult<&'a [u8]> {
+        let end = header::data_end(self.buf)?;
+        Ok(&self.buf[end..])
+    }
+
+    pub fn slot_count(&self) -> DbResult<u16> {
+        header::slot_count(self.buf)
+    }
+
+    /// Đọc slot entry (offset/len/flags) của slot_id.
+    pub fn slot(&self, slot_id: u16) -> DbResult<slot::Slot> {
+        if slot_id >= header::slot_count(self.buf)? {
+            return Err(DbError::InvalidArgument("invalid slot_id"));
+        }
+        slot::read_slot(self.buf, slot_id)
+    }
+
+    pub fn validate_header(&self) -> DbResult<()> {
+        // header fields
+        let lo = header::lower(self.buf)? as usize;
+        let up = header::upper(self.buf)? as usize;
+        let sc = header::slot_count(self.buf)? as usize;
+
+        // lower phải >= header size
+        if lo < SLOTTED_HEADER_SIZE {
+            return Err(DbError::Corruption("corrupt header: lower < header size"));
+        }
+
+        // upper không vượt page size (và không lấn special area)
+        if up > PAGE_SIZE {
+            return Err(DbError::Corruption("corrupt header: upper > PAGE_SIZE"));
+        }
+        if up > header::data_end(self.buf)? {
+            return Err(DbError::Corruption(
+                "corrupt header: upper overlaps special area",
+            ));
+        }
+
+        // lower <= upper
+        if lo > up {
+            return Err(DbError::Corruption("corrupt header: lower > upper"));
+        }
+
+        // lower phải đúng công thức slot directory
+        let slot_bytes = sc
+            .checked_mul(SLOTTED_SLOT_SIZE)
+            .ok_or(DbError::Corruption("corrupt header: slot_count overflow"))?;
+
+        let expected_lo = SLOTTED_HEADER_SIZE
+            .checked_add(slot_bytes)
+            .ok_or(DbError::Corruption("corrupt header: lower overflow"))?;
+
+        if expected_lo > PAGE_SIZE {
+            return Err(DbError::Corruption(
+                "corrupt header: slot directory out of page",
+            ));
+        }
+        if lo != expected_lo {
+            return Err(DbError::Corruption(
+                "corrupt header: lower != header_size + slot_count*slot_size",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Free space hiện tại trong page (upper - lower).
+    pub fn free_space(&self) -> DbResult<u16> {
+        let up = header::upper(self.buf)?;
+        let lo = header::lower(self.buf)?;
+        up.checked_sub(lo)
+            .ok_or(DbError::Corruption("corrupt header: lower > upper"))
+    }
+
+    /// LSN của log record cuối cùng đã apply lên page.
+    pub fn page_lsn(&self) -> DbResult<Lsn> {
+        header::page_lsn(self.buf)
+    }
+
+    /// Lấy record bytes theo slot_id.
+    /// Trả None nếu slot DEAD.
+    /// Các check cần có:
+    /// - slot_id < slot_count
+    /// - slot.offset + slot.len <= PAGE_SIZE
+    pub fn get(&self, slot_id: u16) -> DbResult<Option<&'a [u8]>> {
+        self.validate_header()?;
+
+        let sc = header::slot_count(self.buf)?;
+        if slot_id >= sc {
+            return Err(DbError::InvalidArgument("invalid slot_id"));
+        }
+
+        let slot = slot::read_slot(self.buf, slot_id)?;
+        if slot::is_dead(slot.flags()) {
+            return Ok(None);
+        }
+
+        let start = slot.offset() as usize;
+        let up = header::upper(self.buf)? as usize;
+        if start < up {
+            return Err(DbError::Corruption("tuple overlaps free space"));
+        }
+
+        let len = slot.len() as usize;
+        let end = start
+            .checked_add(len)
+            .ok_or(DbError::Corruption("tuple end overflow"))?;
+        if end > header::data_end(self.buf)? {
+            return Err(DbError::Corruption("tuple end must be <= PAGE_SIZE"));
+        }
+
+        Ok(Some(&self.buf[start..end]))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -362,6 +560,22 @@ mod tests {
         assert!(got.is_err(), "new() must reject non-PAGE_SIZE buffers");
     }
 
+    #[test]
+    fn test_read_only_view() {
+        let mut buf = vec![0u8; PAGE_SIZE];
+        let mut page = make_page(&mut buf);
+        let id = page.insert(b"hello").unwrap();
+        let gone = page.insert(b"gone").unwrap();
+        page.delete(gone).unwrap();
+
+        let view = SlottedPageRef::new(&buf).unwrap();
+        assert_eq!(view.slot_count().unwrap(), 2);
+        assert_eq!(view.get(id).unwrap(), Some(&b"hello"[..]));
+        assert_eq!(view.get(gone).unwrap(), None);
+        assert!(view.get(2).is_err());
+        assert!(SlottedPageRef::new(&buf[..15]).is_err());
+    }
+
     #[test]
     fn test_new_accepts_page_size() {
         let mut buf = vec![0u8; PAGE_SIZE];
@@ -530,7 +744,7 @@ mod tests {
 
         // Case 2: in-place (new <= old)
         let moved = p.update(id, b"hi").unwrap();
-        assert_eq!(moved, false);
+        assert!(!moved);
 
         let got = p.get(id).unwrap().unwrap();
         assert_eq!(got, b"hi");
@@ -541,7 +755,7 @@ mod tests {
         // Case 3: moved (new > old)
         let big = b"this is a longer string than before";
         let moved2 = p.update(id, big).unwrap();
-        assert_eq!(moved2, true);
+        assert!(moved2);
 
         let got2 = p.get(id).unwrap().unwrap();
         assert_eq!(got2, big);
@@ -608,6 +822,105 @@ mod tests {
         p.validate_full().unwrap();
     }
 
+    #[test]
+    fn test_slotted_page_special_area() {
+        let mut buf = vec![0u8; PAGE_SIZE];
+        let mut p = SlottedPage::new(&mut buf)
+            .unwrap()
+            .init_with_special(PAGE_TYPE_HEAP, 8)
+            .unwrap();
+
+        assert_eq!(
+            p.free_space().unwrap() as usize,
+            PAGE_SIZE - SLOTTED_HEADER_SIZE - 8
+        );
+        p.special_mut().unwrap().copy_from_slice(b"12345678");
+
+        // fill page -> tuple không được lấn special area
+        while p.insert(&[0xEE; 100]).is_ok() {}
+        assert_eq!(p.special().unwrap(), b"12345678");
+        p.validate_header().unwrap();
+        #[cfg(debug_assertions)]
+        p.validate_full().unwrap();
+
+        // page thường: special rỗng
+        let mut buf2 = vec![0u8; PAGE_SIZE];
+        let p2 = make_page(&mut buf2);
+        assert!(p2.special().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_slotted_page_compact() {
+        let mut buf = vec![0u8; PAGE_SIZE];
+        let mut p = SlottedPage::new(&mut buf)
+            .unwrap()
+            .init_with_special(PAGE_TYPE_HEAP, 4)
+            .unwrap();
+
+        let a = p.insert(b"aaaa").unwrap();
+        let b = p.insert(b"bbbbbbbb").unwrap();
+        let c = p.insert(b"cc").unwrap();
+        p.update(a, b"aaaaaaaaaaaa").unwrap(); // moved -> 4 bytes garbage
+        p.delete(b).unwrap(); // 8 bytes garbage
+        p.set_slot_flags(c, 0x0008).unwrap();
+
+        let free_before = p.free_space().unwrap();
+        let gained = p.compact().unwrap();
+        assert_eq!(gained, 4 + 8);
+        assert_eq!(p.free_space().unwrap(), free_before + 12);
+
+        // RID + flags giữ nguyên
+        assert_eq!(p.get(a).unwrap().unwrap(), b"aaaaaaaaaaaa");
+        assert!(p.get(b).unwrap().is_none());
+        assert_eq!(p.get(c).unwrap().unwrap(), b"cc");
+        assert_eq!(p.slot(c).unwrap().flags(), 0x0008);
+        assert_eq!(
+            header::upper(p.buf).unwrap() as usize,
+            PAGE_SIZE - 4 - 12 - 2
+        );
+
+        // compact lần 2 không gom thêm gì
+        assert_eq!(p.compact().unwrap(), 0);
+        // tombstone vẫn reuse được
+        assert_eq!(p.insert(b"new").unwrap(), b);
+
+        p.validate_header().unwrap();
+        #[cfg(debug_assertions)]
+        p.validate_full().unwrap();
+    }
+
+    #[test]
+    fn test_slotted_page_lsn_stamp() {
+        let mut buf = vec![0u8; PAGE_SIZE];
+        let mut p = make_page(&mut buf);
+        assert_eq!(p.page_lsn().unwrap(), Lsn::ZERO);
+
+        let id = p.insert_with_lsn(b"abc", Lsn(10)).unwrap();
+        assert_eq!(p.page_lsn().unwrap(), Lsn(10));
+
+        p.update_with_lsn(id, b"abcdef", Lsn(11)).unwrap();
+        assert_eq!(p.page_lsn().unwrap(), Lsn(11));
+
+        // LSN đi lùi -> reject, page không bị sửa
+        let err = p.delete_with_lsn(id, Lsn(5)).unwrap_err();
+        match err {
+            DbError::InvalidArgument(_) => {}
+            other => panic!("expected InvalidArgument, got: {:?}", other),
+        }
+        assert_eq!(p.get(id).unwrap().unwrap(), b"abcdef");
+        assert_eq!(p.page_lsn().unwrap(), Lsn(11));
+
+        // mutation fail (slot không tồn tại) -> LSN giữ nguyên
+        assert!(p.update_with_lsn(99, b"x", Lsn(12)).is_err());
+        assert_eq!(p.page_lsn().unwrap(), Lsn(11));
+
+        p.delete_with_lsn(id, Lsn(12)).unwrap();
+        assert!(p.get(id).unwrap().is_none());
+        assert_eq!(p.page_lsn().unwrap(), Lsn(12));
+
+        p.validate_header().unwrap();
+    }
+
     #[test]
     fn test_slotted_page_roundtrip() {
         let mut buf = vec![0u8; PAGE_SIZE];
@@ -625,12 +938,12 @@ mod tests {
         assert_eq!(id3, 3);
 
         // update: in-place
-        assert_eq!(p.update(id1, b"X").unwrap(), false);
+        assert!(!p.update(id1, b"X").unwrap());
         assert_eq!(p.get(id1).unwrap().unwrap(), b"X");
 
         // update: moved
         let big = b"this update will move because it's longer than before";
-        assert_eq!(p.update(id0, big).unwrap(), true);
+        assert!(p.update(id0, big).unwrap());
         assert_eq!(p.get(id0).unwrap().unwrap(), big);
 
         // delete 2 slots
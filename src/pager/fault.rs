@@ -0,0 +1,134 @@
+use crate::constants::PAGE_SIZE;
+use crate::{DbError, DbResult, PageId};
+
+use super::pager::Pager;
+
+/// Đơn vị ghi atomic nhỏ nhất của disk: mất điện chỉ làm rách page ở ranh giới sector.
+pub const SECTOR_SIZE: usize = 512;
+pub const SECTORS_PER_PAGE: usize = PAGE_SIZE / SECTOR_SIZE;
+
+/// Pager bọc pager khác để giả lập mất điện giữa lúc ghi page (cho test recovery).
+///
+/// Sau `tear_after(writes, sectors)`: `writes` lần ghi đầu đi qua bình thường, lần ghi
+/// kế tiếp chỉ ghi `sectors` sector đầu của page mới (phần còn lại giữ data cũ) rồi
+/// pager "crash": lần ghi đó và mọi thao tác ghi sau đều trả lỗi.
+pub struct TornWritePager<P: Pager> {
+    inner: P,
+    /// Số lần ghi còn được đi qua trước khi rách, `None` = không giả lập lỗi.
+    writes_left: Option<usize>,
+    torn_sectors: usize,
+    crashed: bool,
+}
+
+impl<P: Pager> TornWritePager<P> {
+    pub fn new(inner: P) -> Self {
+        TornWritePager {
+            inner,
+            writes_left: None,
+            torn_sectors: 0,
+            crashed: false,
+        }
+    }
+
+    pub fn tear_after(&mut self, writes: usize, sectors: usize) {
+        assert!(
+            sectors < SECTORS_PER_PAGE,
+            "torn write must leave old sectors"
+        );
+        self.writes_left = Some(writes);
+        self.torn_sectors = sectors;
+    }
+
+    pub fn is_crashed(&self) -> bool {
+        self.crashed
+    }
+
+    pub fn into_inner(self) -> P {
+        self.inner
+    }
+
+    fn check_alive(&self) -> DbResult<()> {
+        if self.crashed {
+            return Err(crash_error());
+        }
+        Ok(())
+    }
+}
+
+fn crash_error() -> DbError {
+    DbError::Io(std::io::Error::other("simulated power loss"))
+}
+
+impl<P: Pager> Pager for TornWritePager<P> {
+    fn read_page(&mut self, pid: PageId, out: &mut [u8]) -> DbResult<()> {
+        self.inner.read_page(pid, out)
+    }
+
+    fn write_page(&mut self, pid: PageId, buf: &[u8]) -> DbResult<()> {
+        self.check_alive()?;
+        match self.writes_left {
+            Some(0) => {
+                let mut torn = vec![0u8; PAGE_SIZE];
+                self.inner.read_page(pid, &mut torn)?;
+                let cut = self.torn_sectors * SECTOR_SIZE;
+                torn[..cut].copy_from_slice(&buf[..cut]);
+                self.inner.write_page(pid, &torn)?;
+                // phần đã rách phải thật sự nằm trên disk trước khi "mất điện"
+                self.inner.flush()?;
+                self.crashed = true;
+                Err(crash_error())
+            }
+            Some(ref mut n) => {
+                *n -= 1;
+                self.inner.write_page(pid, buf)
+            }
+            None => self.inner.write_page(pid, buf),
+        }
+    }
+
+    fn alloc_page(&mut self) -> DbResult<PageId> {
+        self.check_alive()?;
+        self.inner.alloc_page()
+    }
+
+    fn free_page(&mut self, pid: PageId) -> DbResult<()> {
+        self.check_alive()?;
+        self.inner.free_page(pid)
+    }
+
+    fn flush(&mut self) -> DbResult<()> {
+        self.check_alive()?;
+        self.inner.flush()
+    }
+
+    fn num_pages(&mut self) -> DbResult<u64> {
+        self.inner.num_pages()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pager::mem::MemPager;
+
+    #[test]
+    fn test_tears_at_sector_boundary() {
+        let mut p = TornWritePager::new(MemPager::new());
+        let a = p.alloc_page().unwrap();
+        let b = p.alloc_page().unwrap();
+        p.write_page(b, &vec![1u8; PAGE_SIZE]).unwrap();
+
+        p.tear_after(1, 3);
+        p.write_page(a, &vec![2u8; PAGE_SIZE]).unwrap();
+        assert!(p.write_page(b, &vec![3u8; PAGE_SIZE]).is_err());
+        assert!(p.is_crashed());
+        assert!(p.flush().is_err());
+
+        let mut out = vec![0u8; PAGE_SIZE];
+        p.read_page(b, &mut out).unwrap();
+        assert!(out[..3 * SECTOR_SIZE].iter().all(|&x| x == 3));
+        assert!(out[3 * SECTOR_SIZE..].iter().all(|&x| x == 1));
+        p.read_page(a, &mut out).unwrap();
+        assert_eq!(out[0], 2);
+    }
+}
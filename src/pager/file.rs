@@ -1,15 +1,34 @@
 use std::fs::{File, OpenOptions};
-use std::io::{Seek, SeekFrom, Write};
+use std::io::{Read, Seek, SeekFrom, Write};
 
 use crate::constants::PAGE_SIZE;
+use crate::page::header::verify_checksum;
+use crate::wal::format::WAL_HEADER_SIZE;
+use crate::wal::wal_path;
 use crate::{DbError, DbResult, PageId};
 
-use super::pager::Pager;
+use super::freelist;
+use super::meta;
+use super::pager::{check_multi_page_buf, nth_page, Pager};
+use super::sync::{sync_data_if, sync_parent_dir, SyncMode};
 
+/// Pager ghi page tại chỗ trong file database, không có journal.
+///
+/// `alloc_page`/`free_page` cập nhật freelist (page free + head trong page meta) bằng
+/// nhiều lần ghi tại chỗ, có sync giữa các lần ghi theo thứ tự: page free luôn có link
+/// trước khi meta trỏ tới nó, và rời khỏi chuỗi trong meta trước khi bị ghi đè. Crash
+/// giữa chừng có thể làm rò page, không làm hỏng chuỗi. Cần atomic thì dùng
+/// `JournalPager`/`WalPager`: freelist được commit cùng transaction.
 pub struct FilePager {
     f: File,
+    /// Page đã free, page cấp phát lại trước nằm cuối (= head của chuỗi trong meta).
+    /// Chỉ `open` đọc freelist: pager WAL/journal tự quản lý freelist của transaction.
     freelist: Vec<PageId>,
     next_pid: PageId, // nếu freelist trống, lấy id page kế tiếp
+    path: String,
+    sync: SyncMode,
+    /// File vừa được tạo, entry trong thư mục chưa được fsync.
+    created: bool,
 }
 
 impl Pager for FilePager {
@@ -19,35 +38,118 @@ impl Pager for FilePager {
     }
 
     fn read_page(&mut self, pid: PageId, out: &mut [u8]) -> DbResult<()> {
-        todo!()
+        if out.len() != PAGE_SIZE {
+            return Err(DbError::InvalidArgument(
+                "buffer length must equal PAGE_SIZE",
+            ));
+        }
+        self.check_pid(pid)?;
+        self.seek_to(pid)?;
+        self.f.read_exact(out)?;
+        Ok(())
+    }
+
+    fn read_pages(&mut self, start: PageId, out: &mut [u8]) -> DbResult<()> {
+        check_multi_page_buf(out)?;
+        // check page cuối là đủ: các page liên tiếp
+        self.check_pid(nth_page(start, out.len() / PAGE_SIZE - 1)?)?;
+        self.seek_to(start)?;
+        self.f.read_exact(out)?;
+        Ok(())
     }
 
     fn write_page(&mut self, pid: PageId, buf: &[u8]) -> DbResult<()> {
-        todo!()
+        if buf.len() != PAGE_SIZE {
+            return Err(DbError::InvalidArgument(
+                "buffer length must equal PAGE_SIZE",
+            ));
+        }
+        self.check_pid(pid)?;
+        self.seek_to(pid)?;
+        self.f.write_all(buf)?;
+        Ok(())
     }
 
     fn alloc_page(&mut self) -> DbResult<PageId> {
-        todo!()
+        // ưu tiên reuse page đã free; page trả về đọc ra toàn 0 như page mới append.
+        // Gỡ khỏi chuỗi trong meta trước, sync, rồi mới zero page: crash ở giữa chỉ
+        // làm rò page, zero trước thì chuỗi trong meta trỏ qua link đã mất.
+        if let Some(pid) = self.freelist.pop() {
+            self.write_freelist_meta()?;
+            self.sync_barrier()?;
+            self.write_page(pid, &[0u8; PAGE_SIZE])?;
+            return Ok(pid);
+        }
+
+        // append 1 page zero vào cuối file
+        let pid = self.next_pid;
+        self.seek_to(pid)?;
+        self.f.write_all(&[0u8; PAGE_SIZE])?;
+        self.next_pid = PageId(pid.as_u32() + 1);
+        Ok(pid)
     }
 
     fn free_page(&mut self, pid: PageId) -> DbResult<()> {
-        todo!()
+        // page 0 là meta, không bao giờ free
+        if pid == PageId(0) {
+            return Err(DbError::InvalidArgument("cannot free meta page"));
+        }
+        self.check_pid(pid)?;
+        if self.freelist.contains(&pid) {
+            return Err(DbError::InvalidArgument("page is already free"));
+        }
+        // link trước, sync, rồi mới đưa vào chuỗi trong meta: crash ở giữa chỉ làm rò
+        // page (data đã bị caller bỏ), không bao giờ để meta trỏ tới page chưa có link
+        self.write_free_link(pid, freelist::head(&self.freelist))?;
+        self.sync_barrier()?;
+        self.freelist.push(pid);
+        self.write_freelist_meta()
     }
 
     fn flush(&mut self) -> DbResult<()> {
-        // gọi fsync xuống disk
-        self.f.sync_data()?;
+        // gọi fsync xuống disk (theo sync mode)
+        sync_data_if(&self.f, self.sync.syncs_files())?;
+        if self.created && self.sync.syncs_dir_on_create() {
+            sync_parent_dir(&self.path)?;
+            self.created = false;
+        }
         Ok(())
     }
 }
 
 impl FilePager {
+    /// Mở file database để đọc/ghi page tại chỗ.
+    /// Từ chối nếu cạnh file còn WAL có frame: page đã commit trong WAL chưa được
+    /// checkpoint về file, đọc file trực tiếp sẽ thấy data cũ (dùng `WalPager`).
     pub fn open(path: String) -> DbResult<Self> {
+        let mut p = Self::open_unjournaled(path)?;
+        let pages = p.next_pid.as_u32();
+        p.freelist = freelist::load(pages, |pid, out| p.read_page(pid, out))?;
+        Ok(p)
+    }
+
+    /// Như `open` nhưng không đọc freelist: pager rollback journal đọc freelist sau khi
+    /// đã rollback hot journal (page meta/page free có thể đang ghi dở).
+    pub(crate) fn open_unjournaled(path: String) -> DbResult<Self> {
+        if let Ok(m) = std::fs::metadata(wal_path(&path)) {
+            if m.len() > WAL_HEADER_SIZE as u64 {
+                return Err(DbError::InvalidArgument(
+                    "database has an uncheckpointed wal; open it with WalPager",
+                ));
+            }
+        }
+        Self::open_main(path)
+    }
+
+    /// Mở file database, không kiểm tra WAL, không đọc freelist (pager của chế độ WAL
+    /// dùng).
+    pub(crate) fn open_main(path: String) -> DbResult<Self> {
         let mut file = OpenOptions::new()
             .read(true)
             .write(true)
             .create(true)
-            .open(path)?;
+            .truncate(false)
+            .open(&path)?;
 
         let len = file.metadata()?.len();
         if len % (PAGE_SIZE as u64) != 0 {
@@ -57,10 +159,11 @@ impl FilePager {
         let pages = (len / PAGE_SIZE as u64) as u32;
 
         // Reserve page 0 cho meta
-        // Nếu chưa tồn tại file, chắc chắn page meta (0) tồn tại
+        // Nếu chưa tồn tại file, khởi tạo page meta (0) để decode được ngay
         let next_pid = if pages == 0 {
-            let zero = [0u8; PAGE_SIZE];
-            file.write_all(&zero)?;
+            let mut page = vec![0u8; PAGE_SIZE];
+            meta::encode(&meta::init_default(), &mut page)?;
+            file.write_all(&page)?;
             file.flush()?;
             PageId(1)
         } else {
@@ -71,9 +174,21 @@ impl FilePager {
             f: file,
             freelist: Vec::new(),
             next_pid,
+            path,
+            sync: SyncMode::default(),
+            created: pages == 0,
         })
     }
 
+    pub fn sync_mode(&self) -> SyncMode {
+        self.sync
+    }
+
+    /// Mức fsync của `flush`.
+    pub fn set_sync_mode(&mut self, mode: SyncMode) {
+        self.sync = mode;
+    }
+
     #[inline]
     pub fn seek_to(&mut self, pid: PageId) -> DbResult<()> {
         // move pointer đến page tương ứng -> pid * PAGE_SIZE
@@ -83,7 +198,291 @@ impl FilePager {
         self.f.seek(SeekFrom::Start(off))?;
         Ok(())
     }
+
+    /// Nới file tới ít nhất `pages` page (page mới đọc ra toàn 0). Dùng khi checkpoint
+    /// copy page chỉ có trong WAL về file.
+    pub(crate) fn grow_to(&mut self, pages: u32) -> DbResult<()> {
+        if pages <= self.next_pid.as_u32() {
+            return Ok(());
+        }
+        self.f.set_len(pages as u64 * PAGE_SIZE as u64)?;
+        self.next_pid = PageId(pages);
+        Ok(())
+    }
+
+    /// Cắt file về `pages` page (rollback transaction đã nới file).
+    pub(crate) fn truncate_to(&mut self, pages: u32) -> DbResult<()> {
+        self.f.set_len(pages as u64 * PAGE_SIZE as u64)?;
+        self.next_pid = PageId(pages);
+        let before = self.freelist.len();
+        self.freelist.retain(|&pid| pid.as_u32() < pages);
+        if self.freelist.len() == before {
+            return Ok(());
+        }
+        // page bị cắt có thể nằm giữa chuỗi: nối lại các page còn lại
+        for i in 0..self.freelist.len() {
+            let next = match i {
+                0 => PageId(0),
+                _ => self.freelist[i - 1],
+            };
+            self.write_free_link(self.freelist[i], next)?;
+        }
+        self.sync_barrier()?;
+        self.write_freelist_meta()
+    }
+
+    /// Đọc page và kiểm checksum của page (nếu page bật checksum): page bị ghi rách
+    /// khi mất điện trả về lỗi thay vì data nửa cũ nửa mới. Page 0 là meta, không có
+    /// header slotted nên không kiểm.
+    pub fn read_verified(&mut self, pid: PageId, out: &mut [u8]) -> DbResult<()> {
+        self.read_page(pid, out)?;
+        if pid != PageId(0) && !verify_checksum(out)? {
+            return Err(DbError::Corruption("page checksum mismatch"));
+        }
+        Ok(())
+    }
+
+    /// Ghi page free `pid` (data cũ bị xoá) trỏ tới page free kế tiếp `next`.
+    fn write_free_link(&mut self, pid: PageId, next: PageId) -> DbResult<()> {
+        let page = freelist::link_page(next)?;
+        self.write_page(pid, &page)
+    }
+
+    /// Thứ tự ghi giữa page free và page meta (page được ghi tại chỗ, không qua
+    /// journal): ghi trước barrier phải xuống disk trước ghi sau barrier.
+    fn sync_barrier(&mut self) -> DbResult<()> {
+        sync_data_if(&self.f, self.sync.syncs_files())
+    }
+
+    /// Cập nhật head + độ dài freelist trong page meta (giữ các field khác).
+    fn write_freelist_meta(&mut self) -> DbResult<()> {
+        let mut page = vec![0u8; PAGE_SIZE];
+        self.read_page(PageId(0), &mut page)?;
+        freelist::update_meta(&mut page, &self.freelist)?;
+        self.write_page(PageId(0), &page)
+    }
+
+    /// pid phải nằm trong vùng page đã cấp phát.
+    fn check_pid(&self, pid: PageId) -> DbResult<()> {
+        if pid >= self.next_pid {
+            return Err(DbError::InvalidArgument("page id out of range"));
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
-mod tests {}
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> String {
+        let mut p = std::env::temp_dir();
+        p.push(format!("novadb-{}-{}.db", name, std::process::id()));
+        let _ = std::fs::remove_file(&p);
+        p.to_string_lossy().into_owned()
+    }
+
+    fn read_meta(p: &mut FilePager) -> meta::Meta {
+        let mut page = vec![0u8; PAGE_SIZE];
+        p.read_page(PageId(0), &mut page).unwrap();
+        meta::decode(&page).unwrap()
+    }
+
+    #[test]
+    fn test_open_reserves_meta_page() {
+        let path = temp_path("pager-open");
+        let mut p = FilePager::open(path.clone()).unwrap();
+        assert_eq!(p.num_pages().unwrap(), 1);
+
+        // page đầu tiên cấp phát phải là 1 (0 là meta)
+        assert_eq!(p.alloc_page().unwrap(), PageId(1));
+        assert_eq!(p.alloc_page().unwrap(), PageId(2));
+        assert_eq!(p.num_pages().unwrap(), 3);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_write_read_roundtrip() {
+        let path = temp_path("pager-rw");
+        let mut p = FilePager::open(path.clone()).unwrap();
+        let pid = p.alloc_page().unwrap();
+
+        let mut buf = vec![0u8; PAGE_SIZE];
+        buf[0] = 0xAB;
+        buf[PAGE_SIZE - 1] = 0xCD;
+        p.write_page(pid, &buf).unwrap();
+        p.flush().unwrap();
+        drop(p);
+
+        // reopen -> data vẫn còn
+        let mut p = FilePager::open(path.clone()).unwrap();
+        let mut out = vec![0u8; PAGE_SIZE];
+        p.read_page(pid, &mut out).unwrap();
+        assert_eq!(out, buf);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_free_then_alloc_reuses_pid() {
+        let path = temp_path("pager-free");
+        let mut p = FilePager::open(path.clone()).unwrap();
+        let a = p.alloc_page().unwrap();
+        let _b = p.alloc_page().unwrap();
+
+        p.free_page(a).unwrap();
+        assert!(p.free_page(a).is_err(), "double free must be rejected");
+        assert!(
+            p.free_page(PageId(0)).is_err(),
+            "meta page must not be freed"
+        );
+
+        assert_eq!(p.alloc_page().unwrap(), a);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_open_initializes_meta_page() {
+        let path = temp_path("pager-meta");
+        let mut p = FilePager::open(path.clone()).unwrap();
+        let mut page = vec![0u8; PAGE_SIZE];
+        p.read_page(PageId(0), &mut page).unwrap();
+        assert_eq!(meta::decode(&page).unwrap(), meta::init_default());
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_freelist_survives_reopen() {
+        let path = temp_path("pager-freelist");
+        let mut p = FilePager::open(path.clone()).unwrap();
+        let pids: Vec<PageId> = (0..4).map(|_| p.alloc_page().unwrap()).collect();
+        p.write_page(pids[1], &vec![9u8; PAGE_SIZE]).unwrap();
+        p.free_page(pids[1]).unwrap();
+        p.free_page(pids[3]).unwrap();
+        p.flush().unwrap();
+        drop(p);
+
+        let mut p = FilePager::open(path.clone()).unwrap();
+        assert_eq!(read_meta(&mut p).freelist_len(), 2);
+        assert!(p.free_page(pids[3]).is_err(), "double free after reopen");
+        assert_eq!(p.alloc_page().unwrap(), pids[3]);
+        let reused = p.alloc_page().unwrap();
+        assert_eq!(reused, pids[1]);
+        let mut out = vec![0u8; PAGE_SIZE];
+        p.read_page(reused, &mut out).unwrap();
+        assert!(out.iter().all(|&b| b == 0));
+        assert_eq!(p.alloc_page().unwrap(), PageId(5));
+        drop(p);
+
+        let mut p = FilePager::open(path.clone()).unwrap();
+        assert_eq!(read_meta(&mut p).freelist_len(), 0);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_crash_between_link_and_meta_only_leaks() {
+        let path = temp_path("pager-free-crash");
+        let mut p = FilePager::open(path.clone()).unwrap();
+        let pids: Vec<PageId> = (0..3).map(|_| p.alloc_page().unwrap()).collect();
+        p.free_page(pids[0]).unwrap();
+
+        // crash trong free_page(pids[1]): link đã ghi, meta chưa
+        let head = freelist::head(&p.freelist);
+        p.write_free_link(pids[1], head).unwrap();
+        drop(p);
+        let mut p = FilePager::open(path.clone()).unwrap();
+        assert_eq!(p.freelist, vec![pids[0]]);
+
+        // crash trong alloc_page: meta đã gỡ head, page chưa bị zero
+        p.freelist.pop();
+        p.write_freelist_meta().unwrap();
+        drop(p);
+        let mut p = FilePager::open(path.clone()).unwrap();
+        assert!(p.freelist.is_empty());
+        assert_eq!(p.alloc_page().unwrap(), PageId(4));
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_truncate_relinks_freelist() {
+        let path = temp_path("pager-free-trunc");
+        let mut p = FilePager::open(path.clone()).unwrap();
+        let pids: Vec<PageId> = (0..4).map(|_| p.alloc_page().unwrap()).collect();
+        for pid in [pids[0], pids[3], pids[1]] {
+            p.free_page(pid).unwrap();
+        }
+        // cắt page 4 nằm giữa chuỗi 2 -> 4 -> 1
+        p.truncate_to(4).unwrap();
+        drop(p);
+
+        let mut p = FilePager::open(path.clone()).unwrap();
+        assert_eq!(p.alloc_page().unwrap(), pids[1]);
+        assert_eq!(p.alloc_page().unwrap(), pids[0]);
+        assert_eq!(p.alloc_page().unwrap(), PageId(4));
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_read_pages_contiguous() {
+        let path = temp_path("pager-multi");
+        let mut p = FilePager::open(path.clone()).unwrap();
+        let pids: Vec<PageId> = (0..3).map(|_| p.alloc_page().unwrap()).collect();
+        for (i, pid) in pids.iter().enumerate() {
+            p.write_page(*pid, &vec![i as u8 + 1; PAGE_SIZE]).unwrap();
+        }
+
+        let mut out = vec![0u8; 3 * PAGE_SIZE];
+        p.read_pages(pids[0], &mut out).unwrap();
+        for (i, chunk) in out.chunks(PAGE_SIZE).enumerate() {
+            assert!(chunk.iter().all(|&b| b == i as u8 + 1));
+        }
+
+        // vượt quá page cuối hoặc buffer lẻ -> lỗi
+        let mut out = vec![0u8; 4 * PAGE_SIZE];
+        assert!(p.read_pages(pids[0], &mut out).is_err());
+        let mut odd = vec![0u8; PAGE_SIZE + 1];
+        assert!(p.read_pages(pids[0], &mut odd).is_err());
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_open_refuses_uncheckpointed_wal() {
+        let path = temp_path("pager-wal");
+        let wal = wal_path(&path);
+        std::fs::write(&wal, vec![0u8; WAL_HEADER_SIZE + 1]).unwrap();
+        assert!(FilePager::open(path.clone()).is_err());
+
+        // WAL chỉ có header (đã checkpoint hết) -> mở được
+        std::fs::write(&wal, vec![0u8; WAL_HEADER_SIZE]).unwrap();
+        FilePager::open(path.clone()).unwrap();
+
+        std::fs::remove_file(wal).unwrap();
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_pid_out_of_range() {
+        let path = temp_path("pager-oob");
+        let mut p = FilePager::open(path.clone()).unwrap();
+
+        let mut out = vec![0u8; PAGE_SIZE];
+        match p.read_page(PageId(5), &mut out).unwrap_err() {
+            DbError::InvalidArgument(_) => {}
+            other => panic!("expected InvalidArgument, got: {:?}", other),
+        }
+        assert!(p.write_page(PageId(5), &out).is_err());
+
+        // sai size buffer
+        let mut small = vec![0u8; 10];
+        assert!(p.read_page(PageId(0), &mut small).is_err());
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,101 @@
+//! Freelist lưu trên disk, dùng chung cho mọi pager.
+//!
+//! Page meta giữ head + độ dài của chuỗi; mỗi page free giữ id page free kế tiếp ở
+//! đầu page (data cũ của page bị bỏ). Trong RAM freelist là `Vec<PageId>`: page cấp
+//! phát lại trước (= head của chuỗi) nằm cuối, nên page thứ `i` trỏ tới page `i - 1`.
+
+use crate::constants::PAGE_SIZE;
+use crate::page::raw::{read_u32_le, write_u32_le};
+use crate::{DbError, DbResult, PageId};
+
+use super::meta::{self, Meta};
+
+/// Page free giữ id page free kế tiếp của chuỗi freelist ở đầu page.
+const OFF_FREE_NEXT: usize = 0;
+
+/// Head của chuỗi (page cấp phát lại trước tiên), `PageId(0)` nếu freelist rỗng.
+pub(crate) fn head(freelist: &[PageId]) -> PageId {
+    freelist.last().copied().unwrap_or(PageId(0))
+}
+
+/// Nội dung page free trỏ tới page free kế tiếp `next`.
+pub(crate) fn link_page(next: PageId) -> DbResult<Vec<u8>> {
+    let mut page = vec![0u8; PAGE_SIZE];
+    write_u32_le(&mut page, OFF_FREE_NEXT, next.as_u32())?;
+    Ok(page)
+}
+
+/// Page meta đã decode; page meta còn trống (file tạo khi page 0 chưa được khởi
+/// tạo) coi như meta mặc định.
+pub(crate) fn decode_meta(page: &[u8]) -> DbResult<Meta> {
+    if page.iter().all(|&b| b == 0) {
+        return Ok(meta::init_default());
+    }
+    meta::decode(page)
+}
+
+/// Đọc chuỗi freelist từ page meta qua `read`; `pages` là số page của database.
+pub(crate) fn load(
+    pages: u32,
+    mut read: impl FnMut(PageId, &mut [u8]) -> DbResult<()>,
+) -> DbResult<Vec<PageId>> {
+    let mut page = vec![0u8; PAGE_SIZE];
+    read(PageId(0), &mut page)?;
+    let m = decode_meta(&page)?;
+
+    let mut chain = Vec::with_capacity(m.freelist_len() as usize);
+    let mut pid = m.freelist_head();
+    for _ in 0..m.freelist_len() {
+        if pid == PageId(0) || pid.as_u32() >= pages || chain.contains(&pid) {
+            return Err(DbError::Corruption("bad freelist page id"));
+        }
+        chain.push(pid);
+        read(pid, &mut page)?;
+        pid = PageId(read_u32_le(&page, OFF_FREE_NEXT)?);
+    }
+    // head được cấp phát lại trước -> nằm cuối
+    chain.reverse();
+    Ok(chain)
+}
+
+/// Ghi head + độ dài của `freelist` vào page meta `page` (giữ các field khác).
+pub(crate) fn update_meta(page: &mut [u8], freelist: &[PageId]) -> DbResult<()> {
+    let mut m = decode_meta(page)?;
+    m.set_freelist(head(freelist), freelist.len() as u32);
+    meta::encode(&m, page)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_follows_chain() {
+        // chuỗi 3 -> 1 -> 2, database 4 page
+        let mut pages = vec![vec![0u8; PAGE_SIZE]; 4];
+        update_meta(&mut pages[0], &[PageId(2), PageId(1), PageId(3)]).unwrap();
+        pages[3] = link_page(PageId(1)).unwrap();
+        pages[1] = link_page(PageId(2)).unwrap();
+        pages[2] = link_page(PageId(0)).unwrap();
+        let read = |pid: PageId, out: &mut [u8]| {
+            out.copy_from_slice(&pages[pid.as_usize()]);
+            Ok(())
+        };
+        assert_eq!(
+            load(4, read).unwrap(),
+            vec![PageId(2), PageId(1), PageId(3)]
+        );
+
+        // page ngoài database -> corruption
+        assert!(load(3, read).is_err());
+
+        // meta ghi độ dài 4, chuỗi 3 -> 1 -> 2 -> 3 quay vòng -> corruption
+        pages[2] = link_page(PageId(3)).unwrap();
+        update_meta(&mut pages[0], &[PageId(0), PageId(2), PageId(1), PageId(3)]).unwrap();
+        let read = |pid: PageId, out: &mut [u8]| {
+            out.copy_from_slice(&pages[pid.as_usize()]);
+            Ok(())
+        };
+        assert!(matches!(load(4, read), Err(DbError::Corruption(_))));
+    }
+}
@@ -0,0 +1,110 @@
+use crate::constants::PAGE_SIZE;
+use crate::{DbError, DbResult, PageId};
+
+use super::pager::Pager;
+
+/// Pager giữ toàn bộ page trong RAM (không persist).
+/// Dùng cho test và cho DB tạm; contract giống `FilePager` (page 0 là meta).
+pub struct MemPager {
+    pages: Vec<Vec<u8>>,
+    freelist: Vec<PageId>,
+}
+
+impl MemPager {
+    pub fn new() -> Self {
+        MemPager {
+            pages: vec![vec![0u8; PAGE_SIZE]],
+            freelist: Vec::new(),
+        }
+    }
+
+    fn check_pid(&self, pid: PageId) -> DbResult<()> {
+        if pid.as_usize() >= self.pages.len() {
+            return Err(DbError::InvalidArgument("page id out of range"));
+        }
+        Ok(())
+    }
+}
+
+impl Default for MemPager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Pager for MemPager {
+    fn read_page(&mut self, pid: PageId, out: &mut [u8]) -> DbResult<()> {
+        if out.len() != PAGE_SIZE {
+            return Err(DbError::InvalidArgument(
+                "buffer length must equal PAGE_SIZE",
+            ));
+        }
+        self.check_pid(pid)?;
+        out.copy_from_slice(&self.pages[pid.as_usize()]);
+        Ok(())
+    }
+
+    fn write_page(&mut self, pid: PageId, buf: &[u8]) -> DbResult<()> {
+        if buf.len() != PAGE_SIZE {
+            return Err(DbError::InvalidArgument(
+                "buffer length must equal PAGE_SIZE",
+            ));
+        }
+        self.check_pid(pid)?;
+        self.pages[pid.as_usize()].copy_from_slice(buf);
+        Ok(())
+    }
+
+    fn alloc_page(&mut self) -> DbResult<PageId> {
+        if let Some(pid) = self.freelist.pop() {
+            return Ok(pid);
+        }
+        let pid = PageId(self.pages.len() as u32);
+        self.pages.push(vec![0u8; PAGE_SIZE]);
+        Ok(pid)
+    }
+
+    fn free_page(&mut self, pid: PageId) -> DbResult<()> {
+        if pid == PageId(0) {
+            return Err(DbError::InvalidArgument("cannot free meta page"));
+        }
+        self.check_pid(pid)?;
+        if self.freelist.contains(&pid) {
+            return Err(DbError::InvalidArgument("page is already free"));
+        }
+        self.freelist.push(pid);
+        Ok(())
+    }
+
+    fn flush(&mut self) -> DbResult<()> {
+        Ok(())
+    }
+
+    fn num_pages(&mut self) -> DbResult<u64> {
+        Ok(self.pages.len() as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mem_pager_basic() {
+        let mut p = MemPager::new();
+        assert_eq!(p.num_pages().unwrap(), 1);
+
+        let pid = p.alloc_page().unwrap();
+        assert_eq!(pid, PageId(1));
+
+        let buf = vec![7u8; PAGE_SIZE];
+        p.write_page(pid, &buf).unwrap();
+        let mut out = vec![0u8; PAGE_SIZE];
+        p.read_page(pid, &mut out).unwrap();
+        assert_eq!(out, buf);
+
+        p.free_page(pid).unwrap();
+        assert_eq!(p.alloc_page().unwrap(), pid);
+        assert!(p.read_page(PageId(9), &mut out).is_err());
+    }
+}
@@ -1,20 +1,191 @@
-use crate::{DbResult, PageId};
+use crate::constants::{DB_MAGIC, DB_VERSION, PAGE_SIZE};
+use crate::page::raw::{read_u16_le, read_u32_le, read_u8, write_u16_le, write_u32_le, write_u8};
+use crate::{DbError, DbResult, PageId};
 
+// layout page 0 (meta), little-endian. Không lưu số page: số page suy ra từ độ dài
+// file (hoặc db size của commit cuối trong WAL).
+const OFF_MAGIC: usize = 0;
+const OFF_VERSION: usize = 12;
+const OFF_PAGE_SIZE: usize = 14;
+const OFF_FREELIST_LEN: usize = 18;
+const OFF_FREELIST_HEAD: usize = 22;
+const OFF_JOURNAL_MODE: usize = 26;
+
+const JOURNAL_MODE_DELETE: u8 = 0;
+const JOURNAL_MODE_PERSIST: u8 = 1;
+const JOURNAL_MODE_WAL: u8 = 2;
+
+/// Cách database ghi commit atomic; chọn theo từng database, lưu trong page meta.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum JournalMode {
+    /// Rollback journal, file `-journal` bị xoá khi commit xong.
+    #[default]
+    Delete,
+    /// Rollback journal, header của file `-journal` bị zero khi commit xong (giữ file,
+    /// tránh tạo/xoá file mỗi commit).
+    Persist,
+    /// Write-ahead log (`-wal`).
+    Wal,
+}
+
+impl JournalMode {
+    fn to_u8(self) -> u8 {
+        match self {
+            JournalMode::Delete => JOURNAL_MODE_DELETE,
+            JournalMode::Persist => JOURNAL_MODE_PERSIST,
+            JournalMode::Wal => JOURNAL_MODE_WAL,
+        }
+    }
+
+    fn from_u8(v: u8) -> DbResult<Self> {
+        match v {
+            JOURNAL_MODE_DELETE => Ok(JournalMode::Delete),
+            JOURNAL_MODE_PERSIST => Ok(JournalMode::Persist),
+            JOURNAL_MODE_WAL => Ok(JournalMode::Wal),
+            _ => Err(DbError::Corruption("unknown journal mode")),
+        }
+    }
+
+    /// Mode dùng rollback journal.
+    pub fn is_rollback(self) -> bool {
+        self != JournalMode::Wal
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Meta {
     page_size: u32,
-    next_pid: PageId,
     freelist_len: u32,
+    /// Page free cấp phát lại trước tiên; mỗi page free giữ id page free kế tiếp.
+    /// Chỉ có nghĩa khi `freelist_len > 0`.
+    freelist_head: PageId,
+    journal_mode: JournalMode,
     //   ... others
 }
 
-pub fn encode(meta: &Meta, buf: &mut [u8]) {
-    todo!()
+impl Meta {
+    pub fn page_size(&self) -> u32 {
+        self.page_size
+    }
+
+    pub fn freelist_len(&self) -> u32 {
+        self.freelist_len
+    }
+
+    pub fn freelist_head(&self) -> PageId {
+        self.freelist_head
+    }
+
+    pub fn set_freelist(&mut self, head: PageId, len: u32) {
+        self.freelist_head = head;
+        self.freelist_len = len;
+    }
+
+    pub fn journal_mode(&self) -> JournalMode {
+        self.journal_mode
+    }
+
+    pub fn set_journal_mode(&mut self, mode: JournalMode) {
+        self.journal_mode = mode;
+    }
+}
+
+pub fn encode(meta: &Meta, buf: &mut [u8]) -> DbResult<()> {
+    if buf.len() != PAGE_SIZE {
+        return Err(DbError::InvalidArgument(
+            "buffer length must equal PAGE_SIZE",
+        ));
+    }
+    buf[OFF_MAGIC..OFF_MAGIC + DB_MAGIC.len()].copy_from_slice(&DB_MAGIC);
+    write_u16_le(buf, OFF_VERSION, DB_VERSION)?;
+    write_u32_le(buf, OFF_PAGE_SIZE, meta.page_size)?;
+    write_u32_le(buf, OFF_FREELIST_LEN, meta.freelist_len)?;
+    write_u8(buf, OFF_JOURNAL_MODE, meta.journal_mode.to_u8())?;
+    write_u32_le(buf, OFF_FREELIST_HEAD, meta.freelist_head.as_u32())?;
+    Ok(())
 }
 
 pub fn decode(buf: &[u8]) -> DbResult<Meta> {
-    todo!()
+    if buf.len() != PAGE_SIZE {
+        return Err(DbError::Corruption("buffer length must equal PAGE_SIZE"));
+    }
+    if buf[OFF_MAGIC..OFF_MAGIC + DB_MAGIC.len()] != DB_MAGIC {
+        return Err(DbError::Corruption("bad meta magic"));
+    }
+    if read_u16_le(buf, OFF_VERSION)? != DB_VERSION {
+        return Err(DbError::Corruption("unsupported db version"));
+    }
+    let page_size = read_u32_le(buf, OFF_PAGE_SIZE)?;
+    if page_size as usize != PAGE_SIZE {
+        return Err(DbError::Corruption("page size mismatch"));
+    }
+
+    Ok(Meta {
+        page_size,
+        freelist_len: read_u32_le(buf, OFF_FREELIST_LEN)?,
+        freelist_head: PageId(read_u32_le(buf, OFF_FREELIST_HEAD)?),
+        journal_mode: JournalMode::from_u8(read_u8(buf, OFF_JOURNAL_MODE)?)?,
+    })
 }
 
 pub fn init_default() -> Meta {
-    todo!()
+    Meta {
+        page_size: PAGE_SIZE as u32,
+        freelist_len: 0,
+        freelist_head: PageId(0),
+        journal_mode: JournalMode::default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_meta_roundtrip() {
+        let mut buf = vec![0u8; PAGE_SIZE];
+        let meta = init_default();
+        encode(&meta, &mut buf).unwrap();
+        assert_eq!(decode(&buf).unwrap(), meta);
+    }
+
+    #[test]
+    fn test_meta_journal_mode() {
+        let mut buf = vec![0u8; PAGE_SIZE];
+        let mut meta = init_default();
+        meta.set_journal_mode(JournalMode::Wal);
+        encode(&meta, &mut buf).unwrap();
+        assert_eq!(decode(&buf).unwrap().journal_mode(), JournalMode::Wal);
+
+        buf[OFF_JOURNAL_MODE] = 9;
+        assert!(decode(&buf).is_err());
+    }
+
+    #[test]
+    fn test_meta_freelist_roundtrip() {
+        let mut buf = vec![0u8; PAGE_SIZE];
+        let mut meta = init_default();
+        meta.set_freelist(PageId(7), 3);
+        meta.set_journal_mode(JournalMode::Persist);
+        encode(&meta, &mut buf).unwrap();
+
+        let back = decode(&buf).unwrap();
+        assert_eq!(back.freelist_head(), PageId(7));
+        assert_eq!(back.freelist_len(), 3);
+        assert_eq!(back.journal_mode(), JournalMode::Persist);
+    }
+
+    #[test]
+    fn test_meta_old_version_rejected() {
+        let mut buf = vec![0u8; PAGE_SIZE];
+        encode(&init_default(), &mut buf).unwrap();
+        write_u16_le(&mut buf, OFF_VERSION, DB_VERSION - 1).unwrap();
+        assert!(matches!(decode(&buf), Err(DbError::Corruption(_))));
+    }
+
+    #[test]
+    fn test_meta_bad_magic() {
+        let buf = vec![0u8; PAGE_SIZE];
+        assert!(decode(&buf).is_err());
+    }
 }
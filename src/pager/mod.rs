@@ -1,4 +1,10 @@
+pub mod fault;
 pub mod file;
-// pub mod freelist;
+pub(crate) mod freelist;
+pub mod mem;
 pub mod meta;
+pub mod open;
+#[allow(clippy::module_inception)]
 pub mod pager;
+pub(crate) mod pending;
+pub mod sync;
@@ -0,0 +1,148 @@
+//! Mở database theo journal mode lưu trong page meta.
+//!
+//! Mode được đọc/ghi thẳng trên page 0 của file database (không qua WAL/journal) để
+//! biết phải mở bằng pager nào trước khi có pager.
+
+use std::fs::File;
+use std::io::Read;
+
+use crate::constants::PAGE_SIZE;
+use crate::journal::{journal_path, JournalPager};
+use crate::wal::{wal_path, CheckpointMode, WalPager};
+use crate::{DbResult, PageId};
+
+use super::file::FilePager;
+use super::meta::{self, JournalMode};
+use super::pager::Pager;
+use super::sync::SyncMode;
+
+/// Journal mode lưu trong page meta của `path`; `None` nếu database chưa tồn tại hoặc
+/// chưa có page meta.
+pub fn read_journal_mode(path: &str) -> DbResult<Option<JournalMode>> {
+    let mut f = match File::open(path) {
+        Ok(f) => f,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    if f.metadata()?.len() < PAGE_SIZE as u64 {
+        return Ok(None);
+    }
+    let mut page = vec![0u8; PAGE_SIZE];
+    f.read_exact(&mut page)?;
+    // page meta còn trống: file tạo khi FilePager chưa khởi tạo page meta
+    if page.iter().all(|&b| b == 0) {
+        return Ok(None);
+    }
+    Ok(Some(meta::decode(&page)?.journal_mode()))
+}
+
+/// Mở database với pager theo mode đã lưu trong page meta. Database mới được khởi tạo
+/// meta với `mode`; database đã có thì mode đã lưu được dùng, `mode` bị bỏ qua.
+/// `sync` không được lưu: mỗi lần mở tự chọn mức fsync.
+pub fn open_pager(
+    path: String,
+    mode: JournalMode,
+    sync: SyncMode,
+) -> DbResult<Box<dyn Pager + Send>> {
+    let mode = match read_journal_mode(&path)? {
+        Some(stored) => stored,
+        None => {
+            write_journal_mode(&path, mode)?;
+            mode
+        }
+    };
+    Ok(match mode {
+        JournalMode::Wal => {
+            let mut p = WalPager::open(path)?;
+            p.set_sync_mode(sync);
+            Box::new(p)
+        }
+        JournalMode::Delete | JournalMode::Persist => {
+            let mut p = JournalPager::open(path, mode)?;
+            p.set_sync_mode(sync);
+            Box::new(p)
+        }
+    })
+}
+
+/// Đổi journal mode của database. Trạng thái của mode cũ được dọn trước: WAL được
+/// checkpoint hết về file database, hot journal được rollback; file phụ bị xoá.
+pub fn set_journal_mode(path: String, mode: JournalMode) -> DbResult<()> {
+    match read_journal_mode(&path)? {
+        Some(JournalMode::Wal) => {
+            WalPager::open(path.clone())?.checkpoint(CheckpointMode::Truncate)?;
+            std::fs::remove_file(wal_path(&path))?;
+        }
+        Some(current) => {
+            drop(JournalPager::open(path.clone(), current)?);
+            if let Err(e) = std::fs::remove_file(journal_path(&path)) {
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    return Err(e.into());
+                }
+            }
+        }
+        None => {}
+    }
+    write_journal_mode(&path, mode)
+}
+
+/// Ghi mode vào page meta trực tiếp trên file database (khởi tạo meta nếu chưa có).
+fn write_journal_mode(path: &str, mode: JournalMode) -> DbResult<()> {
+    let mut db = FilePager::open(path.to_string())?;
+    let mut page = vec![0u8; PAGE_SIZE];
+    db.read_page(PageId(0), &mut page)?;
+    let mut m = if page.iter().all(|&b| b == 0) {
+        meta::init_default()
+    } else {
+        meta::decode(&page)?
+    };
+    m.set_journal_mode(mode);
+    meta::encode(&m, &mut page)?;
+    db.write_page(PageId(0), &page)?;
+    db.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::journal::{remove_db_files, temp_db_path};
+
+    fn commit_page(p: &mut dyn Pager, fill: u8) -> PageId {
+        let pid = p.alloc_page().unwrap();
+        p.write_page(pid, &vec![fill; PAGE_SIZE]).unwrap();
+        p.flush().unwrap();
+        pid
+    }
+
+    #[test]
+    fn test_mode_is_stored_per_database() {
+        let path = temp_db_path("open-mode");
+        let mut p = open_pager(path.clone(), JournalMode::Wal, SyncMode::Full).unwrap();
+        let pid = commit_page(p.as_mut(), 1);
+        drop(p);
+        assert_eq!(read_journal_mode(&path).unwrap(), Some(JournalMode::Wal));
+        assert!(std::fs::metadata(wal_path(&path)).unwrap().len() > 0);
+
+        // mode đã lưu thắng mode truyền vào
+        let mut p = open_pager(path.clone(), JournalMode::Delete, SyncMode::Off).unwrap();
+        let mut out = vec![0u8; PAGE_SIZE];
+        p.read_page(pid, &mut out).unwrap();
+        assert_eq!(out[0], 1);
+        drop(p);
+
+        set_journal_mode(path.clone(), JournalMode::Persist).unwrap();
+        assert!(std::fs::metadata(wal_path(&path)).is_err());
+        let mut p = open_pager(path.clone(), JournalMode::Wal, SyncMode::Normal).unwrap();
+        p.read_page(pid, &mut out).unwrap();
+        assert_eq!(out[0], 1);
+        commit_page(p.as_mut(), 2);
+        drop(p);
+        assert_eq!(
+            read_journal_mode(&path).unwrap(),
+            Some(JournalMode::Persist)
+        );
+        assert!(std::fs::metadata(journal_path(&path)).is_ok());
+
+        remove_db_files(&path);
+    }
+}
@@ -1,10 +1,97 @@
+use crate::constants::PAGE_SIZE;
+use crate::wal::{CheckpointInfo, CheckpointMode, CommitTicket};
 use crate::{DbError, DbResult, PageId};
 
+/// Snapshot đọc của pager ghi theo transaction: WAL cố định commit cuối lúc bắt đầu
+/// đọc; rollback journal chỉ bỏ qua page chưa commit. Phải trả lại bằng
+/// `Pager::end_snapshot`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Snapshot {
+    pub(crate) id: u64,
+    /// Thế hệ log (checkpoint seq) lúc lấy snapshot (chỉ WAL).
+    pub(crate) log_seq: u32,
+    /// Số frame đã commit lúc lấy snapshot: chỉ frame < mark thuộc snapshot.
+    pub(crate) mark: u32,
+    /// Số page của database lúc lấy snapshot.
+    pub(crate) db_pages: u32,
+}
+
+impl Snapshot {
+    pub fn db_pages(&self) -> u32 {
+        self.db_pages
+    }
+}
+
 pub trait Pager {
     fn read_page(&mut self, pid: PageId, out: &mut [u8]) -> DbResult<()>;
+
+    /// Đọc `out.len() / PAGE_SIZE` page liên tiếp bắt đầu từ `start` (dùng cho readahead).
+    /// Mặc định đọc từng page; pager có thể override để đọc 1 lần.
+    fn read_pages(&mut self, start: PageId, out: &mut [u8]) -> DbResult<()> {
+        check_multi_page_buf(out)?;
+        for (i, chunk) in out.chunks_exact_mut(PAGE_SIZE).enumerate() {
+            self.read_page(nth_page(start, i)?, chunk)?;
+        }
+        Ok(())
+    }
     fn write_page(&mut self, pid: PageId, buf: &[u8]) -> DbResult<()>;
     fn alloc_page(&mut self) -> DbResult<PageId>;
     fn free_page(&mut self, pid: PageId) -> DbResult<()>;
     fn flush(&mut self) -> DbResult<()>;
     fn num_pages(&mut self) -> DbResult<u64>;
+
+    /// Như `flush` nhưng chưa chờ commit bền vững: caller nhả lock rồi mới `wait`
+    /// ticket, để các commit đồng thời dùng chung 1 lần fsync (group commit). Mặc định
+    /// `flush` luôn, ticket không cần chờ.
+    fn flush_deferred(&mut self) -> DbResult<CommitTicket> {
+        self.flush()?;
+        Ok(CommitTicket::done())
+    }
+
+    /// Bỏ mọi thay đổi (ghi, cấp phát, free) từ lần `flush` trước. Chỉ pager ghi theo
+    /// transaction (WAL, rollback journal) hỗ trợ; pager ghi thẳng trả lỗi.
+    fn rollback(&mut self) -> DbResult<()> {
+        Err(DbError::InvalidArgument("pager does not support rollback"))
+    }
+
+    /// Lấy snapshot đọc. `None`: pager không giữ version cũ, đọc luôn thấy commit mới nhất.
+    fn begin_snapshot(&mut self) -> DbResult<Option<Snapshot>> {
+        Ok(None)
+    }
+
+    /// Đọc page như tại thời điểm `snap`, bỏ qua commit sau đó.
+    fn read_page_at(&mut self, snap: &Snapshot, pid: PageId, out: &mut [u8]) -> DbResult<()> {
+        let _ = (snap, pid, out);
+        Err(DbError::InvalidArgument("pager does not support snapshots"))
+    }
+
+    /// Trả snapshot: checkpoint không còn phải giữ version cho nó.
+    fn end_snapshot(&mut self, snap: Snapshot) {
+        let _ = snap;
+    }
+
+    /// Checkpoint log về file database. `None`: pager không có log để checkpoint.
+    fn checkpoint(&mut self, mode: CheckpointMode) -> DbResult<Option<CheckpointInfo>> {
+        let _ = mode;
+        Ok(None)
+    }
+}
+
+/// Buffer của `read_pages` phải là bội số (khác 0) của PAGE_SIZE.
+pub(crate) fn check_multi_page_buf(buf: &[u8]) -> DbResult<()> {
+    if buf.is_empty() || !buf.len().is_multiple_of(PAGE_SIZE) {
+        return Err(DbError::InvalidArgument(
+            "buffer length must be a non-zero multiple of PAGE_SIZE",
+        ));
+    }
+    Ok(())
+}
+
+/// Page id thứ `n` tính từ `start`.
+pub(crate) fn nth_page(start: PageId, n: usize) -> DbResult<PageId> {
+    u32::try_from(n)
+        .ok()
+        .and_then(|n| start.as_u32().checked_add(n))
+        .map(PageId)
+        .ok_or(DbError::InvalidArgument("page id out of range"))
 }
@@ -0,0 +1,260 @@
+use std::collections::BTreeMap;
+
+use crate::constants::PAGE_SIZE;
+use crate::page::header::{is_slotted, stamp_checksum};
+use crate::{DbError, DbResult, PageId};
+
+use super::freelist;
+
+/// Page đã ghi nhưng chưa commit + cấp phát page id, dùng chung cho các pager ghi
+/// theo transaction (WAL, rollback journal): mọi thay đổi nằm trong RAM tới lúc commit.
+///
+/// Freelist có cùng format trên disk với `FilePager` (xem `freelist`): `free` ghi page
+/// free trỏ tới head cũ vào `pages`, `stage_freelist` ghi head + độ dài mới vào page
+/// meta, nên freelist được commit (hoặc rollback) cùng transaction.
+pub(crate) struct PendingPages {
+    /// Page đã ghi từ lần commit trước, theo thứ tự page id.
+    pages: BTreeMap<PageId, Box<[u8]>>,
+    freelist: Vec<PageId>,
+    /// `freelist` tại lần commit trước.
+    committed_freelist: Vec<PageId>,
+    /// Số page logic của database (kể cả page mới cấp phát chưa commit).
+    next_pid: PageId,
+    /// `next_pid` tại lần commit trước.
+    committed_pid: PageId,
+}
+
+impl PendingPages {
+    /// `pages`: số page của database đã commit, `freelist`: freelist đã commit (đọc
+    /// bằng `freelist::load`).
+    pub(crate) fn new(pages: u32, freelist: Vec<PageId>) -> Self {
+        PendingPages {
+            pages: BTreeMap::new(),
+            committed_freelist: freelist.clone(),
+            freelist,
+            next_pid: PageId(pages),
+            committed_pid: PageId(pages),
+        }
+    }
+
+    pub(crate) fn next_pid(&self) -> PageId {
+        self.next_pid
+    }
+
+    /// Số page database có tại lần commit trước.
+    pub(crate) fn committed_pages(&self) -> u32 {
+        self.committed_pid.as_u32()
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.pages.is_empty()
+    }
+
+    /// Các page pending theo thứ tự page id.
+    pub(crate) fn pages(&self) -> Vec<(PageId, &[u8])> {
+        self.pages
+            .iter()
+            .map(|(pid, page)| (*pid, &page[..]))
+            .collect()
+    }
+
+    pub(crate) fn check_pid(&self, pid: PageId) -> DbResult<()> {
+        if pid >= self.next_pid {
+            return Err(DbError::InvalidArgument("page id out of range"));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn get(&self, pid: PageId) -> Option<&[u8]> {
+        self.pages.get(&pid).map(|p| &p[..])
+    }
+
+    pub(crate) fn write(&mut self, pid: PageId, buf: &[u8]) -> DbResult<()> {
+        if buf.len() != PAGE_SIZE {
+            return Err(DbError::InvalidArgument(
+                "buffer length must equal PAGE_SIZE",
+            ));
+        }
+        self.check_pid(pid)?;
+        let mut page: Box<[u8]> = buf.into();
+        // mọi page đi xuống file (frame WAL, backfill, ghi tại chỗ, journal) đều copy từ
+        // đây nên stamp checksum một lần ở đây. Chỉ page slotted có chỗ cho checksum
+        // trong header; page khác giữ nguyên byte caller ghi. Page 0 là meta.
+        if pid != PageId(0) && is_slotted(&page)? {
+            stamp_checksum(&mut page)?;
+        }
+        self.pages.insert(pid, page);
+        Ok(())
+    }
+
+    pub(crate) fn alloc(&mut self) -> DbResult<PageId> {
+        // page reuse từ freelist đọc ra toàn 0 như page mới cấp phát
+        if let Some(pid) = self.freelist.pop() {
+            self.pages
+                .insert(pid, vec![0u8; PAGE_SIZE].into_boxed_slice());
+            return Ok(pid);
+        }
+        let pid = self.next_pid;
+        self.next_pid = PageId(
+            pid.as_u32()
+                .checked_add(1)
+                .ok_or(DbError::NoSpace("page id space exhausted"))?,
+        );
+        self.pages
+            .insert(pid, vec![0u8; PAGE_SIZE].into_boxed_slice());
+        Ok(pid)
+    }
+
+    pub(crate) fn free(&mut self, pid: PageId) -> DbResult<()> {
+        // page 0 là meta, không bao giờ free
+        if pid == PageId(0) {
+            return Err(DbError::InvalidArgument("cannot free meta page"));
+        }
+        self.check_pid(pid)?;
+        if self.freelist.contains(&pid) {
+            return Err(DbError::InvalidArgument("page is already free"));
+        }
+        let link = freelist::link_page(freelist::head(&self.freelist))?;
+        self.pages.insert(pid, link.into_boxed_slice());
+        self.freelist.push(pid);
+        Ok(())
+    }
+
+    /// Trước commit: nếu freelist đổi từ lần commit trước, ghi head + độ dài mới vào
+    /// page meta pending. `read_meta` đọc page meta đã commit (khi page meta chưa
+    /// pending).
+    pub(crate) fn stage_freelist(
+        &mut self,
+        read_meta: impl FnOnce(&mut [u8]) -> DbResult<()>,
+    ) -> DbResult<()> {
+        if self.freelist == self.committed_freelist {
+            return Ok(());
+        }
+        let mut page = match self.pages.get(&PageId(0)) {
+            Some(p) => p.to_vec(),
+            None => {
+                let mut p = vec![0u8; PAGE_SIZE];
+                read_meta(&mut p)?;
+                p
+            }
+        };
+        freelist::update_meta(&mut page, &self.freelist)?;
+        self.pages.insert(PageId(0), page.into_boxed_slice());
+        Ok(())
+    }
+
+    /// Các page pending (kể cả page meta từ `stage_freelist`) đã được ghi bền vững.
+    pub(crate) fn committed(&mut self) {
+        self.pages.clear();
+        self.committed_pid = self.next_pid;
+        self.committed_freelist.clone_from(&self.freelist);
+    }
+
+    /// Bỏ mọi thay đổi từ lần commit trước: page free trả lại freelist và page cấp
+    /// phát từ freelist đều quay về freelist đã commit.
+    pub(crate) fn discard(&mut self) {
+        self.pages.clear();
+        self.next_pid = self.committed_pid;
+        self.freelist.clone_from(&self.committed_freelist);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_discard_restores_committed_size() {
+        let mut p = PendingPages::new(1, Vec::new());
+        let a = p.alloc().unwrap();
+        p.write(a, &vec![1u8; PAGE_SIZE]).unwrap();
+        p.committed();
+
+        let b = p.alloc().unwrap();
+        p.write(a, &vec![2u8; PAGE_SIZE]).unwrap();
+        assert_eq!(p.pages().len(), 2);
+        p.discard();
+        assert!(p.is_empty());
+        assert_eq!(p.next_pid(), PageId(2));
+        assert!(p.check_pid(b).is_err());
+        assert!(p.free(PageId(0)).is_err());
+    }
+
+    #[test]
+    fn test_only_slotted_pages_are_stamped() {
+        use crate::page::header::{init_empty, verify_checksum, PAGE_TYPE_HEAP};
+
+        let mut p = PendingPages::new(1, Vec::new());
+        let raw = p.alloc().unwrap();
+        let mut buf = vec![0u8; PAGE_SIZE];
+        buf[100] = 9;
+        p.write(raw, &buf).unwrap();
+        assert_eq!(p.get(raw).unwrap(), &buf[..]);
+
+        let slotted = p.alloc().unwrap();
+        init_empty(&mut buf, PAGE_TYPE_HEAP).unwrap();
+        p.write(slotted, &buf).unwrap();
+        assert_ne!(p.get(slotted).unwrap(), &buf[..]);
+        assert!(verify_checksum(p.get(slotted).unwrap()).unwrap());
+    }
+
+    /// Commit như pager: stage freelist vào page meta `meta` rồi "ghi" page pending.
+    fn commit(p: &mut PendingPages, meta: &mut [u8]) {
+        p.stage_freelist(|out| {
+            out.copy_from_slice(meta);
+            Ok(())
+        })
+        .unwrap();
+        if let Some(page) = p.get(PageId(0)) {
+            meta.copy_from_slice(page);
+        }
+        p.committed();
+    }
+
+    #[test]
+    fn test_discard_restores_freelist() {
+        let mut meta = vec![0u8; PAGE_SIZE];
+        let mut p = PendingPages::new(1, Vec::new());
+        let a = p.alloc().unwrap();
+        let b = p.alloc().unwrap();
+        commit(&mut p, &mut meta);
+
+        // free -> rollback: page vẫn đang dùng, không được cấp phát lại
+        p.free(a).unwrap();
+        p.discard();
+        assert_eq!(p.alloc().unwrap(), PageId(3));
+        p.discard();
+
+        // page cấp phát từ freelist -> rollback: page quay lại freelist
+        p.free(b).unwrap();
+        commit(&mut p, &mut meta);
+        assert_eq!(freelist::decode_meta(&meta).unwrap().freelist_len(), 1);
+        assert_eq!(p.alloc().unwrap(), b);
+        assert!(p.get(b).unwrap().iter().all(|&x| x == 0));
+        p.discard();
+        assert!(p.is_empty());
+        assert_eq!(p.alloc().unwrap(), b);
+        assert!(p.free(b).is_ok());
+    }
+
+    #[test]
+    fn test_freelist_staged_in_meta_page() {
+        let mut meta = vec![0u8; PAGE_SIZE];
+        let mut p = PendingPages::new(1, Vec::new());
+        let pids: Vec<PageId> = (0..3).map(|_| p.alloc().unwrap()).collect();
+        commit(&mut p, &mut meta);
+
+        // commit chỉ free page vẫn có page để ghi (page free + page meta)
+        p.free(pids[0]).unwrap();
+        p.free(pids[2]).unwrap();
+        commit(&mut p, &mut meta);
+        let m = freelist::decode_meta(&meta).unwrap();
+        assert_eq!((m.freelist_head(), m.freelist_len()), (pids[2], 2));
+
+        // free rồi cấp phát lại cùng page: freelist không đổi, page meta không bị ghi
+        p.free(pids[1]).unwrap();
+        assert_eq!(p.alloc().unwrap(), pids[1]);
+        p.stage_freelist(|_| panic!("meta page read")).unwrap();
+        assert!(p.get(PageId(0)).is_none());
+    }
+}
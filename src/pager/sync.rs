@@ -0,0 +1,102 @@
+//! Mức fsync khi commit/checkpoint (đổi độ bền lấy tốc độ).
+
+use std::fs::File;
+use std::path::Path;
+
+use crate::DbResult;
+
+/// Mức đồng bộ xuống disk. Crash của process không bao giờ làm mất commit ở mọi mức;
+/// các mức chỉ khác nhau khi mất điện / OS crash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum SyncMode {
+    /// Không bao giờ fsync. Mất điện có thể làm hỏng database.
+    Off,
+    /// fsync đủ để database không bị hỏng: journal + file database mỗi commit ở chế độ
+    /// rollback journal; ở chế độ WAL chỉ fsync lúc checkpoint (commit gần nhất có
+    /// thể mất khi mất điện).
+    Normal,
+    /// Normal + fsync WAL mỗi commit, fsync thư mục sau khi tạo file (database, WAL,
+    /// journal) và sau khi xoá journal (điểm commit của chế độ Delete): commit đã trả
+    /// về là bền vững ở mọi journal mode.
+    #[default]
+    Full,
+    /// Full + header journal chỉ được ghi (và fsync) sau khi record đã bền, để
+    /// filesystem đổi thứ tự ghi trong file cũng không để lại header trỏ tới record
+    /// chưa có trên disk.
+    Extra,
+}
+
+impl SyncMode {
+    /// fsync file (journal, file database) để giữ database nhất quán.
+    pub fn syncs_files(self) -> bool {
+        self >= SyncMode::Normal
+    }
+
+    /// fsync bước cuối của commit (frame WAL, journal bị vô hiệu) trước khi trả về.
+    pub fn syncs_commit(self) -> bool {
+        self >= SyncMode::Full
+    }
+
+    /// fsync thư mục sau khi tạo file phụ / file database.
+    pub fn syncs_dir_on_create(self) -> bool {
+        self >= SyncMode::Full
+    }
+
+    /// fsync thư mục sau khi xoá file phụ (xoá journal là điểm commit của chế độ Delete).
+    pub fn syncs_dir_on_delete(self) -> bool {
+        self >= SyncMode::Full
+    }
+
+    /// fsync record journal trước khi ghi header journal.
+    pub fn syncs_journal_header(self) -> bool {
+        self >= SyncMode::Extra
+    }
+}
+
+/// fsync data của `f` nếu `enabled`.
+pub(crate) fn sync_data_if(f: &File, enabled: bool) -> DbResult<()> {
+    if enabled {
+        f.sync_data()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+thread_local! {
+    /// Số lần `sync_parent_dir` được gọi trên thread hiện tại.
+    pub(crate) static DIR_SYNCS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+/// fsync thư mục chứa `path` để entry vừa tạo/xoá bền vững.
+pub(crate) fn sync_parent_dir(path: &str) -> DbResult<()> {
+    #[cfg(test)]
+    DIR_SYNCS.with(|n| n.set(n.get() + 1));
+    let dir = match Path::new(path).parent() {
+        Some(d) if !d.as_os_str().is_empty() => d,
+        _ => Path::new("."),
+    };
+    // chỉ Unix cho mở + fsync thư mục
+    if cfg!(unix) {
+        File::open(dir)?.sync_all()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_levels_are_cumulative() {
+        assert!(!SyncMode::Off.syncs_files());
+        assert!(SyncMode::Normal.syncs_files());
+        assert!(!SyncMode::Normal.syncs_commit());
+        assert!(SyncMode::Full.syncs_commit() && SyncMode::Full.syncs_dir_on_create());
+        assert!(!SyncMode::Normal.syncs_dir_on_delete());
+        assert!(SyncMode::Full.syncs_dir_on_delete());
+        assert!(!SyncMode::Full.syncs_journal_header());
+        assert!(SyncMode::Extra.syncs_dir_on_delete() && SyncMode::Extra.syncs_journal_header());
+        assert_eq!(SyncMode::default(), SyncMode::Full);
+        sync_parent_dir("relative-file").unwrap();
+    }
+}
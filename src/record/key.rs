@@ -0,0 +1,473 @@
+//! Key encoding giữ thứ tự (memcmp-comparable).
+//!
+//! Encode 1 tuple value thành bytes sao cho so sánh bytes (lexicographic) cho
+//! cùng kết quả với so sánh logic. B-tree chỉ cần so sánh `&[u8]`, không cần
+//! comparator riêng cho từng type.
+//!
+//! Mỗi component = `[tag][body]`:
+//!
+//! - tag quyết định thứ tự giữa các type: NULL < số < TEXT < BLOB. INTEGER và REAL
+//!   dùng chung 1 tag và so sánh theo giá trị số (`Real(-1.0) < Integer(100)`).
+//! - số: `[f64 8 bytes][phần dư u16][type u8]`
+//!   - f64 = REAL, hoặc f64 lớn nhất <= INTEGER (làm tròn xuống); 8 bytes big-endian,
+//!     số dương lật bit dấu, số âm lật toàn bộ bit
+//!   - phần dư = INTEGER - f64 (0 với REAL, < 1024 vì i64 cần tối đa 63 bit)
+//!   - type phân định INTEGER với REAL cùng giá trị: `Integer(1) < Real(1.0)`
+//! - TEXT/BLOB: byte 0x00 escape thành `0x00 0xFF`, kết thúc bằng `0x00 0x00`
+//!
+//! Component DESC = đảo (NOT) toàn bộ bytes của component đó (kể cả tag).
+
+use super::Value;
+use crate::{DbError, DbResult};
+
+const KEY_TAG_NULL: u8 = 0x01;
+const KEY_TAG_NUMBER: u8 = 0x02;
+const KEY_TAG_TEXT: u8 = 0x03;
+const KEY_TAG_BLOB: u8 = 0x04;
+
+/// Byte cuối của component số: INTEGER trước REAL khi cùng giá trị.
+const NUMBER_INTEGER: u8 = 0x00;
+const NUMBER_REAL: u8 = 0x01;
+
+const SIGN_BIT: u64 = 1 << 63;
+const NUM_SIZE: usize = 8;
+/// 2^63: f64 của mọi INTEGER nằm trong [-2^63, 2^63).
+const I64_BOUND: f64 = 9_223_372_036_854_775_808.0;
+
+/// byte 0x00 trong TEXT/BLOB được ghi thành [ESCAPE, ESCAPED_ZERO]
+const ESCAPE: u8 = 0x00;
+const ESCAPED_ZERO: u8 = 0xFF;
+/// kết thúc TEXT/BLOB: [ESCAPE, TERMINATOR]
+const TERMINATOR: u8 = 0x00;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortOrder {
+    Asc,
+    Desc,
+}
+
+impl SortOrder {
+    /// Mask XOR lên từng byte: DESC đảo toàn bộ bit.
+    fn mask(self) -> u8 {
+        match self {
+            SortOrder::Asc => 0x00,
+            SortOrder::Desc => 0xFF,
+        }
+    }
+}
+
+/// Tách INTEGER thành (f64 lớn nhất <= v, phần dư >= 0): so sánh cặp này theo thứ tự
+/// cho đúng thứ tự số học giữa INTEGER và REAL.
+fn split_i64(v: i64) -> (f64, u16) {
+    let mut f = v as f64;
+    // `as` làm tròn gần nhất, có thể lên trên v
+    if f as i128 > v as i128 {
+        f = f.next_down();
+    }
+    (f, (v as i128 - f as i128) as u16)
+}
+
+fn join_i64(f: f64, rem: u16) -> DbResult<i64> {
+    if f.fract() != 0.0 || !(-I64_BOUND..I64_BOUND).contains(&f) {
+        return Err(DbError::Corruption("key integer out of range"));
+    }
+    i64::try_from(f as i64 as i128 + rem as i128)
+        .map_err(|_| DbError::Corruption("key integer out of range"))
+}
+
+fn push_number(out: &mut Vec<u8>, f: f64, rem: u16, kind: u8, mask: u8) {
+    out.push(KEY_TAG_NUMBER ^ mask);
+    out.extend(encode_f64(f).to_be_bytes().iter().map(|b| b ^ mask));
+    out.extend(rem.to_be_bytes().iter().map(|b| b ^ mask));
+    out.push(kind ^ mask);
+}
+
+fn encode_f64(v: f64) -> u64 {
+    // -0.0 và 0.0 bằng nhau về logic -> encode giống nhau
+    // NaN chuẩn hoá về 1 bit pattern (lớn hơn +inf)
+    let v = if v == 0.0 {
+        0.0
+    } else if v.is_nan() {
+        f64::NAN
+    } else {
+        v
+    };
+    let bits = v.to_bits();
+    if bits & SIGN_BIT != 0 {
+        !bits
+    } else {
+        bits ^ SIGN_BIT
+    }
+}
+
+fn decode_f64(v: u64) -> f64 {
+    let bits = if v & SIGN_BIT != 0 { v ^ SIGN_BIT } else { !v };
+    f64::from_bits(bits)
+}
+
+fn push_escaped(out: &mut Vec<u8>, data: &[u8], mask: u8) {
+    for &b in data {
+        if b == ESCAPE {
+            out.push(ESCAPE ^ mask);
+            out.push(ESCAPED_ZERO ^ mask);
+        } else {
+            out.push(b ^ mask);
+        }
+    }
+    out.push(ESCAPE ^ mask);
+    out.push(TERMINATOR ^ mask);
+}
+
+/// Encode 1 component, append vào `out`.
+pub fn encode_component(out: &mut Vec<u8>, v: &Value, order: SortOrder) {
+    let mask = order.mask();
+    match v {
+        Value::Null => out.push(KEY_TAG_NULL ^ mask),
+        Value::Integer(n) => {
+            let (f, rem) = split_i64(*n);
+            push_number(out, f, rem, NUMBER_INTEGER, mask);
+        }
+        Value::Real(f) => push_number(out, *f, 0, NUMBER_REAL, mask),
+        Value::Text(s) => {
+            out.push(KEY_TAG_TEXT ^ mask);
+            push_escaped(out, s.as_bytes(), mask);
+        }
+        Value::Blob(b) => {
+            out.push(KEY_TAG_BLOB ^ mask);
+            push_escaped(out, b, mask);
+        }
+    }
+}
+
+/// Encode tuple key. `orders[i]` là thứ tự của component i.
+pub fn encode_key(values: &[Value], orders: &[SortOrder]) -> DbResult<Vec<u8>> {
+    if values.len() != orders.len() {
+        return Err(DbError::InvalidArgument(
+            "key values and sort orders length mismatch",
+        ));
+    }
+    let mut out = Vec::new();
+    for (v, order) in values.iter().zip(orders) {
+        encode_component(&mut out, v, *order);
+    }
+    Ok(out)
+}
+
+/// Cursor đọc bytes của key, tự XOR mask của component hiện tại.
+struct KeyReader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl KeyReader<'_> {
+    fn next(&mut self, mask: u8) -> DbResult<u8> {
+        let b = *self
+            .buf
+            .get(self.pos)
+            .ok_or(DbError::Corruption("key truncated"))?;
+        self.pos += 1;
+        Ok(b ^ mask)
+    }
+
+    fn next_u64(&mut self, mask: u8) -> DbResult<u64> {
+        let mut bytes = [0u8; NUM_SIZE];
+        for b in bytes.iter_mut() {
+            *b = self.next(mask)?;
+        }
+        Ok(u64::from_be_bytes(bytes))
+    }
+
+    fn next_escaped(&mut self, mask: u8) -> DbResult<Vec<u8>> {
+        let mut data = Vec::new();
+        loop {
+            let b = self.next(mask)?;
+            if b != ESCAPE {
+                data.push(b);
+                continue;
+            }
+            match self.next(mask)? {
+                TERMINATOR => return Ok(data),
+                ESCAPED_ZERO => data.push(0),
+                _ => return Err(DbError::Corruption("key has bad escape sequence")),
+            }
+        }
+    }
+}
+
+/// Decode tuple key đã encode bằng `encode_key` với cùng `orders`.
+pub fn decode_key(buf: &[u8], orders: &[SortOrder]) -> DbResult<Vec<Value>> {
+    let mut r = KeyReader { buf, pos: 0 };
+    let mut values = Vec::with_capacity(orders.len());
+
+    for order in orders {
+        let mask = order.mask();
+        let v = match r.next(mask)? {
+            KEY_TAG_NULL => Value::Null,
+            KEY_TAG_NUMBER => {
+                let f = decode_f64(r.next_u64(mask)?);
+                let rem = u16::from_be_bytes([r.next(mask)?, r.next(mask)?]);
+                match (r.next(mask)?, rem) {
+                    (NUMBER_INTEGER, _) => Value::Integer(join_i64(f, rem)?),
+                    (NUMBER_REAL, 0) => Value::Real(f),
+                    _ => return Err(DbError::Corruption("key has bad number component")),
+                }
+            }
+            KEY_TAG_TEXT => {
+                let data = r.next_escaped(mask)?;
+                let s = String::from_utf8(data)
+                    .map_err(|_| DbError::Corruption("key text is not utf-8"))?;
+                Value::Text(s)
+            }
+            KEY_TAG_BLOB => Value::Blob(r.next_escaped(mask)?),
+            _ => return Err(DbError::Corruption("key has unknown component tag")),
+        };
+        values.push(v);
+    }
+
+    if r.pos != buf.len() {
+        return Err(DbError::Corruption("key has trailing bytes"));
+    }
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cmp::Ordering;
+
+    fn type_rank(v: &Value) -> u8 {
+        match v {
+            Value::Null => 0,
+            Value::Integer(_) | Value::Real(_) => 1,
+            Value::Text(_) => 2,
+            Value::Blob(_) => 3,
+        }
+    }
+
+    /// So sánh chính xác INTEGER với REAL (không qua `as f64`, vốn làm tròn); cùng giá
+    /// trị thì INTEGER đứng trước.
+    fn int_real_cmp(x: i64, y: f64) -> Ordering {
+        if y.is_nan() || y >= I64_BOUND {
+            return Ordering::Less;
+        }
+        if y < -I64_BOUND {
+            return Ordering::Greater;
+        }
+        let floor = y.floor() as i128;
+        match (x as i128).cmp(&floor) {
+            Ordering::Equal => Ordering::Less,
+            ord => ord,
+        }
+    }
+
+    /// Thứ tự logic mà encoding phải giữ: số so sánh theo giá trị số (kể cả INTEGER với
+    /// REAL), type khác còn lại so sánh theo type.
+    fn logical_cmp(a: &Value, b: &Value) -> Ordering {
+        match (a, b) {
+            (Value::Integer(x), Value::Integer(y)) => x.cmp(y),
+            (Value::Real(x), Value::Real(y)) => x.total_cmp(y),
+            (Value::Integer(x), Value::Real(y)) => int_real_cmp(*x, *y),
+            (Value::Real(x), Value::Integer(y)) => int_real_cmp(*y, *x).reverse(),
+            (Value::Text(x), Value::Text(y)) => x.as_bytes().cmp(y.as_bytes()),
+            (Value::Blob(x), Value::Blob(y)) => x.cmp(y),
+            _ => type_rank(a).cmp(&type_rank(b)),
+        }
+    }
+
+    fn samples() -> Vec<Value> {
+        vec![
+            Value::Null,
+            Value::Integer(i64::MIN),
+            Value::Integer(-1),
+            Value::Integer(0),
+            Value::Integer(1),
+            Value::Integer(i64::MAX),
+            Value::Integer(i64::MAX - 1),
+            Value::Integer((1 << 53) + 1),
+            Value::Integer(100),
+            Value::Integer(2),
+            Value::Real(f64::NEG_INFINITY),
+            Value::Real(-9_223_372_036_854_775_808.0),
+            Value::Real(-2.5),
+            Value::Real(-1.0),
+            Value::Real(-1e-300),
+            Value::Real(0.0),
+            Value::Real(1e-300),
+            Value::Real(1.0),
+            Value::Real(2.5),
+            Value::Real(9_007_199_254_740_992.0),
+            Value::Real(9_223_372_036_854_775_808.0),
+            Value::Real(f64::INFINITY),
+            Value::Text(String::new()),
+            Value::Text("a".to_string()),
+            Value::Text("a\0".to_string()),
+            Value::Text("a\0b".to_string()),
+            Value::Text("ab".to_string()),
+            Value::Text("b".to_string()),
+            Value::Blob(vec![]),
+            Value::Blob(vec![0]),
+            Value::Blob(vec![0, 0]),
+            Value::Blob(vec![0, 1]),
+            Value::Blob(vec![0xFF]),
+        ]
+    }
+
+    #[test]
+    fn test_roundtrip_single() {
+        for order in [SortOrder::Asc, SortOrder::Desc] {
+            for v in samples() {
+                let k = encode_key(std::slice::from_ref(&v), &[order]).unwrap();
+                assert_eq!(decode_key(&k, &[order]).unwrap(), vec![v]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_order_single_component() {
+        let vals = samples();
+        for a in &vals {
+            for b in &vals {
+                let ka = encode_key(std::slice::from_ref(a), &[SortOrder::Asc]).unwrap();
+                let kb = encode_key(std::slice::from_ref(b), &[SortOrder::Asc]).unwrap();
+                assert_eq!(ka.cmp(&kb), logical_cmp(a, b), "asc {:?} vs {:?}", a, b);
+
+                let ka = encode_key(std::slice::from_ref(a), &[SortOrder::Desc]).unwrap();
+                let kb = encode_key(std::slice::from_ref(b), &[SortOrder::Desc]).unwrap();
+                assert_eq!(
+                    ka.cmp(&kb),
+                    logical_cmp(a, b).reverse(),
+                    "desc {:?} vs {:?}",
+                    a,
+                    b
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_order_composite() {
+        // (text ASC, int DESC): prefix của text không được "lấn" sang component sau
+        let orders = [SortOrder::Asc, SortOrder::Desc];
+        let rows = [
+            vec![Value::Text("a".into()), Value::Integer(9)],
+            vec![Value::Text("a".into()), Value::Integer(1)],
+            vec![Value::Text("a".into()), Value::Null],
+            vec![Value::Text("a\0".into()), Value::Integer(5)],
+            vec![Value::Text("ab".into()), Value::Integer(100)],
+            vec![Value::Text("ab".into()), Value::Integer(-100)],
+            vec![Value::Text("b".into()), Value::Integer(0)],
+        ];
+        let keys: Vec<Vec<u8>> = rows
+            .iter()
+            .map(|r| encode_key(r, &orders).unwrap())
+            .collect();
+        for w in keys.windows(2) {
+            assert!(w[0] < w[1], "{:?} must sort before {:?}", w[0], w[1]);
+        }
+        for (row, k) in rows.iter().zip(&keys) {
+            assert_eq!(&decode_key(k, &orders).unwrap(), row);
+        }
+    }
+
+    #[test]
+    fn test_mixed_numeric_orders_by_value() {
+        let asc = [SortOrder::Asc];
+        let key = |v: Value| encode_key(&[v], &asc).unwrap();
+        assert!(key(Value::Real(-1.0)) < key(Value::Integer(100)));
+        assert!(key(Value::Integer(1)) < key(Value::Real(1.5)));
+        assert!(key(Value::Real(1.5)) < key(Value::Integer(2)));
+        // cùng giá trị: khác key, INTEGER trước
+        assert!(key(Value::Integer(1)) < key(Value::Real(1.0)));
+        assert!(key(Value::Real(1.0)) < key(Value::Integer(2)));
+        // vượt độ chính xác của f64: 2^53 + 1 không biểu diễn được bằng f64
+        assert!(key(Value::Real(9_007_199_254_740_992.0)) < key(Value::Integer((1 << 53) + 1)));
+        assert!(key(Value::Integer(i64::MAX)) < key(Value::Real(9_223_372_036_854_775_808.0)));
+        assert!(key(Value::Integer(i64::MAX - 1)) < key(Value::Integer(i64::MAX)));
+
+        // tuple (số ASC, int ASC): component đầu so sánh theo giá trị số
+        let orders = [SortOrder::Asc, SortOrder::Asc];
+        let rows = [
+            vec![Value::Real(-10.5), Value::Integer(0)],
+            vec![Value::Integer(-5), Value::Integer(9)],
+            vec![Value::Real(1.5), Value::Integer(0)],
+            vec![Value::Integer(2), Value::Integer(0)],
+            vec![Value::Integer(2), Value::Integer(1)],
+            vec![Value::Real(2.0), Value::Integer(-1)],
+        ];
+        let keys: Vec<Vec<u8>> = rows
+            .iter()
+            .map(|r| encode_key(r, &orders).unwrap())
+            .collect();
+        for w in keys.windows(2) {
+            assert!(w[0] < w[1], "{:?} must sort before {:?}", w[0], w[1]);
+        }
+        for (row, k) in rows.iter().zip(&keys) {
+            assert_eq!(&decode_key(k, &orders).unwrap(), row);
+        }
+    }
+
+    #[test]
+    fn test_real_zero_and_nan() {
+        let pos = encode_key(&[Value::Real(0.0)], &[SortOrder::Asc]).unwrap();
+        let neg = encode_key(&[Value::Real(-0.0)], &[SortOrder::Asc]).unwrap();
+        assert_eq!(pos, neg);
+
+        let nan = encode_key(&[Value::Real(f64::NAN)], &[SortOrder::Asc]).unwrap();
+        let inf = encode_key(&[Value::Real(f64::INFINITY)], &[SortOrder::Asc]).unwrap();
+        assert!(nan > inf);
+        match &decode_key(&nan, &[SortOrder::Asc]).unwrap()[0] {
+            Value::Real(f) => assert!(f.is_nan()),
+            other => panic!("expected Real, got: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_length_mismatch() {
+        match encode_key(&[Value::Null], &[]).unwrap_err() {
+            DbError::InvalidArgument(_) => {}
+            other => panic!("expected InvalidArgument, got: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_decode_corrupt() {
+        let asc = [SortOrder::Asc];
+        let k = encode_key(&[Value::Text("abc".into())], &asc).unwrap();
+
+        // thiếu terminator
+        assert!(matches!(
+            decode_key(&k[..k.len() - 1], &asc),
+            Err(DbError::Corruption(_))
+        ));
+        // thừa byte
+        let mut extra = k.clone();
+        extra.push(1);
+        assert!(matches!(
+            decode_key(&extra, &asc),
+            Err(DbError::Corruption(_))
+        ));
+        // escape sai
+        let bad = [KEY_TAG_BLOB, 0x00, 0x07];
+        assert!(matches!(
+            decode_key(&bad, &asc),
+            Err(DbError::Corruption(_))
+        ));
+        // tag lạ
+        assert!(matches!(
+            decode_key(&[0x7F], &asc),
+            Err(DbError::Corruption(_))
+        ));
+        // số thiếu bytes
+        assert!(matches!(
+            decode_key(&[KEY_TAG_NUMBER, 0, 0], &asc),
+            Err(DbError::Corruption(_))
+        ));
+        // REAL có phần dư, type số lạ
+        let mut k = encode_key(&[Value::Real(1.0)], &asc).unwrap();
+        k[NUM_SIZE + 2] = 1;
+        assert!(matches!(decode_key(&k, &asc), Err(DbError::Corruption(_))));
+        let mut k = encode_key(&[Value::Integer(1)], &asc).unwrap();
+        k[NUM_SIZE + 3] = 9;
+        assert!(matches!(decode_key(&k, &asc), Err(DbError::Corruption(_))));
+    }
+}
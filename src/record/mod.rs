@@ -0,0 +1,346 @@
+//! Record (row) format: encode/decode 1 row gồm nhiều column có type.
+//!
+//! Layout (little-endian):
+//!
+//! ```text
+//! +-----------+-------------+-----------+-------------+---------+
+//! | col_count | null bitmap | type tags | end offsets | payload |
+//! | u16       | ceil(n/8) B | n x u8    | n x u16     |         |
+//! +-----------+-------------+-----------+-------------+---------+
+//! ```
+//!
+//! - bit i của null bitmap = 1 <=> column i là NULL (type tag phải là TYPE_NULL)
+//! - end offset tính từ đầu payload, column i chiếm `[end[i-1]..end[i])` (end[-1] = 0)
+//! - INTEGER/REAL chiếm đúng 8 bytes, TEXT là utf-8, BLOB là raw bytes, NULL chiếm 0 byte
+//!
+//! Nhờ end offsets, `read_column` đọc được 1 column mà không decode cả row.
+
+pub mod key;
+pub mod value;
+
+pub use value::Value;
+
+use crate::page::raw::{
+    read_bytes, read_f64_le, read_i64_le, read_u16_le, read_u8, write_bytes, write_f64_le,
+    write_i64_le, write_u16_le, write_u8,
+};
+use crate::{DbError, DbResult};
+use value::{TYPE_BLOB, TYPE_INTEGER, TYPE_NULL, TYPE_REAL, TYPE_TEXT};
+
+const OFF_COL_COUNT: usize = 0;
+const COL_COUNT_SIZE: usize = 2;
+const TYPE_TAG_SIZE: usize = 1;
+const END_OFFSET_SIZE: usize = 2;
+/// INTEGER (i64) và REAL (f64) đều lưu fixed 8 bytes.
+const FIXED_NUM_SIZE: usize = 8;
+
+/// Vị trí các vùng header, chỉ phụ thuộc vào col_count.
+struct Layout {
+    col_count: usize,
+    bitmap_off: usize,
+    types_off: usize,
+    ends_off: usize,
+    payload_off: usize,
+}
+
+impl Layout {
+    fn new(col_count: usize) -> Self {
+        let bitmap_off = OFF_COL_COUNT + COL_COUNT_SIZE;
+        let types_off = bitmap_off + col_count.div_ceil(8);
+        let ends_off = types_off + col_count * TYPE_TAG_SIZE;
+        let payload_off = ends_off + col_count * END_OFFSET_SIZE;
+        Layout {
+            col_count,
+            bitmap_off,
+            types_off,
+            ends_off,
+            payload_off,
+        }
+    }
+
+    fn end_off(&self, col: usize) -> usize {
+        self.ends_off + col * END_OFFSET_SIZE
+    }
+}
+
+fn payload_len(v: &Value) -> usize {
+    match v {
+        Value::Null => 0,
+        Value::Integer(_) | Value::Real(_) => FIXED_NUM_SIZE,
+        Value::Text(s) => s.len(),
+        Value::Blob(b) => b.len(),
+    }
+}
+
+/// Encode 1 row thành record bytes.
+pub fn encode(values: &[Value]) -> DbResult<Vec<u8>> {
+    if values.len() > u16::MAX as usize {
+        return Err(DbError::InvalidArgument("too many columns"));
+    }
+    let layout = Layout::new(values.len());
+
+    let total_payload: usize = values.iter().map(payload_len).sum();
+    if total_payload > u16::MAX as usize {
+        return Err(DbError::InvalidArgument("record is too large"));
+    }
+
+    let mut buf = vec![0u8; layout.payload_off + total_payload];
+    write_u16_le(&mut buf, OFF_COL_COUNT, values.len() as u16)?;
+
+    let mut end = 0usize;
+    for (i, v) in values.iter().enumerate() {
+        if v.is_null() {
+            let byte_off = layout.bitmap_off + i / 8;
+            let cur = read_u8(&buf, byte_off)?;
+            write_u8(&mut buf, byte_off, cur | (1 << (i % 8)))?;
+        }
+        write_u8(&mut buf, layout.types_off + i, v.type_tag())?;
+
+        let off = layout.payload_off + end;
+        match v {
+            Value::Null => {}
+            Value::Integer(n) => write_i64_le(&mut buf, off, *n)?,
+            Value::Real(f) => write_f64_le(&mut buf, off, *f)?,
+            Value::Text(s) => write_bytes(&mut buf, off, s.as_bytes())?,
+            Value::Blob(b) => write_bytes(&mut buf, off, b)?,
+        }
+
+        // total_payload <= u16::MAX nên end luôn fit u16
+        end += payload_len(v);
+        write_u16_le(&mut buf, layout.end_off(i), end as u16)?;
+    }
+
+    Ok(buf)
+}
+
+/// Số column của record.
+pub fn column_count(buf: &[u8]) -> DbResult<u16> {
+    read_u16_le(buf, OFF_COL_COUNT).map_err(|_| DbError::Corruption("record header truncated"))
+}
+
+fn read_layout(buf: &[u8]) -> DbResult<Layout> {
+    let layout = Layout::new(column_count(buf)? as usize);
+    if layout.payload_off > buf.len() {
+        return Err(DbError::Corruption("record header truncated"));
+    }
+    Ok(layout)
+}
+
+/// Decode column `col` khi đã biết layout; chỉ đọc header của column đó.
+fn decode_column(buf: &[u8], layout: &Layout, col: usize) -> DbResult<Value> {
+    let null_byte = read_u8(buf, layout.bitmap_off + col / 8)?;
+    let is_null = null_byte & (1 << (col % 8)) != 0;
+    let tag = read_u8(buf, layout.types_off + col)?;
+
+    let start = if col == 0 {
+        0
+    } else {
+        read_u16_le(buf, layout.end_off(col - 1))? as usize
+    };
+    let end = read_u16_le(buf, layout.end_off(col))? as usize;
+    if start > end {
+        return Err(DbError::Corruption("record end offsets not monotonic"));
+    }
+
+    let data = read_bytes(buf, layout.payload_off + start, end - start)
+        .map_err(|_| DbError::Corruption("record column out of bounds"))?;
+
+    if is_null != (tag == TYPE_NULL) {
+        return Err(DbError::Corruption("record null bitmap mismatch"));
+    }
+
+    match tag {
+        TYPE_NULL => {
+            if !data.is_empty() {
+                return Err(DbError::Corruption("record null column has payload"));
+            }
+            Ok(Value::Null)
+        }
+        TYPE_INTEGER | TYPE_REAL => {
+            if data.len() != FIXED_NUM_SIZE {
+                return Err(DbError::Corruption("record numeric column has bad length"));
+            }
+            if tag == TYPE_INTEGER {
+                Ok(Value::Integer(read_i64_le(data, 0)?))
+            } else {
+                Ok(Value::Real(read_f64_le(data, 0)?))
+            }
+        }
+        TYPE_TEXT => {
+            let s = std::str::from_utf8(data)
+                .map_err(|_| DbError::Corruption("record text is not utf-8"))?;
+            Ok(Value::Text(s.to_owned()))
+        }
+        TYPE_BLOB => Ok(Value::Blob(data.to_vec())),
+        _ => Err(DbError::Corruption("record has unknown column type")),
+    }
+}
+
+/// Đọc 1 column mà không decode toàn bộ row.
+pub fn read_column(buf: &[u8], col: usize) -> DbResult<Value> {
+    let layout = read_layout(buf)?;
+    if col >= layout.col_count {
+        return Err(DbError::InvalidArgument("column index out of range"));
+    }
+    decode_column(buf, &layout, col)
+}
+
+/// Decode toàn bộ row.
+pub fn decode(buf: &[u8]) -> DbResult<Vec<Value>> {
+    let layout = read_layout(buf)?;
+
+    let mut values = Vec::with_capacity(layout.col_count);
+    for col in 0..layout.col_count {
+        values.push(decode_column(buf, &layout, col)?);
+    }
+
+    // payload phải kết thúc đúng ở end offset cuối cùng (không có byte rác)
+    let last_end = if layout.col_count == 0 {
+        0
+    } else {
+        read_u16_le(buf, layout.end_off(layout.col_count - 1))? as usize
+    };
+    if layout.payload_off + last_end != buf.len() {
+        return Err(DbError::Corruption("record has trailing bytes"));
+    }
+
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_row() -> Vec<Value> {
+        vec![
+            Value::Integer(-42),
+            Value::Null,
+            Value::Real(3.5),
+            Value::Text("xin chào".to_string()),
+            Value::Blob(vec![0, 1, 2, 255]),
+        ]
+    }
+
+    fn expect_corruption(r: DbResult<impl std::fmt::Debug>) {
+        match r {
+            Err(DbError::Corruption(_)) => {}
+            other => panic!("expected Corruption, got: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let row = sample_row();
+        let buf = encode(&row).unwrap();
+        assert_eq!(column_count(&buf).unwrap(), 5);
+        assert_eq!(decode(&buf).unwrap(), row);
+    }
+
+    #[test]
+    fn test_empty_row() {
+        let buf = encode(&[]).unwrap();
+        assert_eq!(buf.len(), COL_COUNT_SIZE);
+        assert!(decode(&buf).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_null_bitmap_spans_bytes() {
+        // 10 column -> bitmap 2 bytes
+        let row: Vec<Value> = (0..10)
+            .map(|i| {
+                if i % 3 == 0 {
+                    Value::Null
+                } else {
+                    Value::Integer(i)
+                }
+            })
+            .collect();
+        let buf = encode(&row).unwrap();
+        assert_eq!(decode(&buf).unwrap(), row);
+        assert_eq!(read_column(&buf, 9).unwrap(), Value::Null);
+        assert_eq!(read_column(&buf, 8).unwrap(), Value::Integer(8));
+    }
+
+    #[test]
+    fn test_read_single_column() {
+        let row = sample_row();
+        let buf = encode(&row).unwrap();
+        for (i, v) in row.iter().enumerate() {
+            assert_eq!(&read_column(&buf, i).unwrap(), v);
+        }
+
+        match read_column(&buf, 5).unwrap_err() {
+            DbError::InvalidArgument(_) => {}
+            other => panic!("expected InvalidArgument, got: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_read_column_ignores_other_columns() {
+        let row = sample_row();
+        let mut buf = encode(&row).unwrap();
+        // làm hỏng type tag của column 4 -> column 0 vẫn đọc được
+        let layout = Layout::new(row.len());
+        buf[layout.types_off + 4] = 0xEE;
+        assert_eq!(read_column(&buf, 0).unwrap(), Value::Integer(-42));
+        expect_corruption(read_column(&buf, 4));
+        expect_corruption(decode(&buf));
+    }
+
+    #[test]
+    fn test_too_large() {
+        let row = vec![Value::Blob(vec![0u8; u16::MAX as usize + 1])];
+        match encode(&row).unwrap_err() {
+            DbError::InvalidArgument(_) => {}
+            other => panic!("expected InvalidArgument, got: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_corrupt_truncated() {
+        let buf = encode(&sample_row()).unwrap();
+        expect_corruption(decode(&buf[..1]));
+        expect_corruption(decode(&buf[..buf.len() - 1]));
+        expect_corruption(read_column(&buf[..buf.len() - 1], 4));
+    }
+
+    #[test]
+    fn test_corrupt_null_bitmap_mismatch() {
+        let row = sample_row();
+        let mut buf = encode(&row).unwrap();
+        let layout = Layout::new(row.len());
+        // bật bit NULL cho column 0 (INTEGER)
+        buf[layout.bitmap_off] |= 1;
+        expect_corruption(read_column(&buf, 0));
+    }
+
+    #[test]
+    fn test_corrupt_bad_lengths() {
+        let row = sample_row();
+        let layout = Layout::new(row.len());
+
+        // INTEGER 7 bytes
+        let mut buf = encode(&row).unwrap();
+        buf[layout.end_off(0)] = 7;
+        expect_corruption(read_column(&buf, 0));
+
+        // end offsets giảm dần
+        let mut buf = encode(&row).unwrap();
+        write_u16_le(&mut buf, layout.end_off(3), u16::MAX).unwrap();
+        expect_corruption(read_column(&buf, 4));
+
+        // trailing garbage
+        let mut buf = encode(&row).unwrap();
+        buf.push(0);
+        expect_corruption(decode(&buf));
+    }
+
+    #[test]
+    fn test_corrupt_text_not_utf8() {
+        let row = vec![Value::Text("ab".to_string())];
+        let mut buf = encode(&row).unwrap();
+        let n = buf.len();
+        buf[n - 1] = 0xFF;
+        expect_corruption(decode(&buf));
+    }
+}
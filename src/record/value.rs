@@ -0,0 +1,32 @@
+/// Type tag lưu trong record header (1 byte / column).
+pub const TYPE_NULL: u8 = 0;
+pub const TYPE_INTEGER: u8 = 1;
+pub const TYPE_REAL: u8 = 2;
+pub const TYPE_TEXT: u8 = 3;
+pub const TYPE_BLOB: u8 = 4;
+
+/// Giá trị của 1 column trong row.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Integer(i64),
+    Real(f64),
+    Text(String),
+    Blob(Vec<u8>),
+}
+
+impl Value {
+    pub fn type_tag(&self) -> u8 {
+        match self {
+            Value::Null => TYPE_NULL,
+            Value::Integer(_) => TYPE_INTEGER,
+            Value::Real(_) => TYPE_REAL,
+            Value::Text(_) => TYPE_TEXT,
+            Value::Blob(_) => TYPE_BLOB,
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, Value::Null)
+    }
+}
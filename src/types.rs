@@ -1,3 +1,6 @@
+use crate::page::raw::{read_u16_le, read_u32_le, write_u16_le, write_u32_le};
+use crate::DbResult;
+
 /// Page identifier inside a single database file.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct PageId(pub u32);
@@ -21,3 +24,67 @@ impl PageId {
         self.0 as usize
     }
 }
+
+/// Log sequence number: vị trí của một log record trong WAL.
+/// LSN tăng đơn điệu; page header lưu LSN của record cuối đã apply lên page.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Lsn(pub u32);
+
+impl Lsn {
+    /// Page chưa từng được log (page mới init).
+    pub const ZERO: Lsn = Lsn(0);
+
+    #[inline]
+    pub fn as_u32(self) -> u32 {
+        self.0
+    }
+
+    #[inline]
+    pub fn as_u64(self) -> u64 {
+        self.0 as u64
+    }
+}
+
+/// Địa chỉ của 1 tuple: (page, slot).
+///
+/// On-disk (6 bytes, little-endian): `[page u32][slot u16]`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RecordId {
+    pub page: PageId,
+    pub slot: u16,
+}
+
+impl RecordId {
+    pub const ENCODED_SIZE: usize = 6;
+
+    pub fn new(page: PageId, slot: u16) -> Self {
+        RecordId { page, slot }
+    }
+
+    pub fn encode(&self, buf: &mut [u8], off: usize) -> DbResult<()> {
+        write_u32_le(buf, off, self.page.as_u32())?;
+        write_u16_le(buf, off + 4, self.slot)
+    }
+
+    pub fn decode(buf: &[u8], off: usize) -> DbResult<Self> {
+        Ok(RecordId {
+            page: PageId(read_u32_le(buf, off)?),
+            slot: read_u16_le(buf, off + 4)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_id_roundtrip() {
+        let rid = RecordId::new(PageId(0x0102_0304), 0x0506);
+        let mut buf = [0u8; 8];
+        rid.encode(&mut buf, 1).unwrap();
+        assert_eq!(&buf[1..7], &[0x04, 0x03, 0x02, 0x01, 0x06, 0x05]);
+        assert_eq!(RecordId::decode(&buf, 1).unwrap(), rid);
+        assert!(RecordId::decode(&buf, 3).is_err());
+    }
+}
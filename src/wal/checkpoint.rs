@@ -0,0 +1,55 @@
+//! Checkpoint: copy version đã commit mới nhất của mỗi page từ WAL về file database,
+//! sync file database rồi (tuỳ mode) bắt đầu lại log.
+
+use crate::constants::PAGE_SIZE;
+use crate::pager::pager::Pager;
+use crate::{DbResult, PageId};
+
+use super::file::WalFile;
+
+/// Mode checkpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CheckpointMode {
+    /// Copy các frame đã commit chưa được copy, không reset log và không chờ ai:
+    /// reader vẫn đọc tiếp trong lúc checkpoint. Writer tự bắt đầu lại log ở commit sau
+    /// nếu mọi frame đã được copy.
+    #[default]
+    Passive,
+    /// Như `Passive`, nhưng mọi frame đã commit phải được copy hết: reader đang chặn
+    /// frame -> `DbError::Busy`.
+    Full,
+    /// `Full`, rồi bắt đầu lại log ngay (header mới, file WAL chỉ còn header).
+    Restart,
+    /// `Restart`, rồi cắt file WAL về 0 byte.
+    Truncate,
+}
+
+/// Kết quả 1 lần checkpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CheckpointInfo {
+    /// Số frame trong log lúc bắt đầu checkpoint.
+    pub log_frames: u32,
+    /// Số frame đầu log đã nằm trong file database sau checkpoint.
+    pub backfilled: u32,
+    /// Số page được ghi về file database.
+    pub pages_written: u32,
+}
+
+/// Checkpoint tự động khi log có từ chừng này frame chưa được copy (0 = tắt).
+pub const DEFAULT_AUTOCHECKPOINT_FRAMES: u32 = 1000;
+
+/// Copy page của các frame `(pid, frame)` từ WAL về `db` (chưa sync). File database
+/// phải đã đủ lớn. Page bị ghi rách nếu mất điện giữa chừng: frame vẫn còn trong WAL
+/// nên recovery lúc mở khôi phục lại được.
+pub(super) fn backfill(
+    wal: &mut WalFile,
+    db: &mut dyn Pager,
+    pages: &[(PageId, u32)],
+) -> DbResult<()> {
+    let mut buf = vec![0u8; PAGE_SIZE];
+    for &(pid, idx) in pages {
+        wal.read_frame_page(idx, &mut buf)?;
+        db.write_page(pid, &buf)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,399 @@
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::sync::Arc;
+
+use crate::constants::PAGE_SIZE;
+use crate::pager::sync::{sync_data_if, sync_parent_dir, SyncMode};
+use crate::{DbError, DbResult, PageId};
+
+use super::format::{
+    frame_offset, new_salt, Checksum, FrameHeader, WalHeader, FRAME_HEADER_SIZE, FRAME_SIZE,
+    WAL_HEADER_SIZE,
+};
+use super::group::{CommitTicket, SyncGroup};
+use super::recovery::{recover, RecoveryInfo};
+
+/// File WAL đang mở: append frame ở cuối, đọc lại page theo frame.
+pub struct WalFile {
+    f: File,
+    header: WalHeader,
+    /// false sau khi log bị truncate về 0 byte: header được ghi lại ở lần append sau.
+    header_on_disk: bool,
+    /// Page id của từng frame hợp lệ trong log (frame i -> `pids[i]`).
+    pids: Vec<PageId>,
+    /// Số frame đầu log đã được checkpoint về file database.
+    backfilled: u32,
+    /// Checksum của frame cuối (log rỗng: checksum của header), seed cho frame kế tiếp.
+    last_checksum: Checksum,
+    /// `db_size` của frame commit cuối, 0 nếu log chưa có commit.
+    db_size: u32,
+    /// page -> các frame chứa page theo thứ tự tăng dần (chỉ frame đã commit); reader
+    /// snapshot cần cả version cũ.
+    index: HashMap<PageId, Vec<u32>>,
+    /// Kết quả recovery lúc mở.
+    recovery: RecoveryInfo,
+    path: String,
+    sync: SyncMode,
+    /// File WAL vừa được tạo, entry trong thư mục chưa được fsync.
+    created: bool,
+    /// fsync của commit, chạy ngoài write lock (group commit).
+    group: Arc<SyncGroup>,
+}
+
+impl WalFile {
+    /// Mở (tạo nếu chưa có) file WAL và chạy recovery: giữ các frame đã commit,
+    /// bỏ phần đuôi chưa commit. File rỗng hoặc header hỏng (crash lúc đang tạo log)
+    /// -> bắt đầu log mới.
+    pub fn open(path: &str) -> DbResult<Self> {
+        let created = !std::path::Path::new(path).exists();
+        let mut f = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+        let group = SyncGroup::new(f.try_clone()?);
+
+        let mut buf = [0u8; WAL_HEADER_SIZE];
+        let existing = if f.metadata()?.len() >= WAL_HEADER_SIZE as u64 {
+            f.read_exact(&mut buf)?;
+            WalHeader::decode(&buf).ok()
+        } else {
+            None
+        };
+
+        let mut wal = WalFile {
+            f,
+            header: WalHeader {
+                checkpoint_seq: 0,
+                salt: (0, 0),
+            },
+            header_on_disk: true,
+            pids: Vec::new(),
+            backfilled: 0,
+            last_checksum: (0, 0),
+            db_size: 0,
+            index: HashMap::new(),
+            recovery: RecoveryInfo::default(),
+            path: path.to_string(),
+            sync: SyncMode::default(),
+            created,
+            group,
+        };
+        match existing {
+            Some((header, sum)) => {
+                let r = recover(&mut wal.f, &header, sum)?;
+                wal.header = header;
+                wal.pids = r.pids;
+                wal.last_checksum = r.last_checksum;
+                wal.db_size = r.db_size;
+                wal.index = r.index;
+                wal.recovery = r.info;
+            }
+            None => wal.reset(0, true)?,
+        }
+        Ok(wal)
+    }
+
+    pub fn sync_mode(&self) -> SyncMode {
+        self.sync
+    }
+
+    pub fn set_sync_mode(&mut self, mode: SyncMode) {
+        self.sync = mode;
+    }
+
+    /// fsync log (theo sync mode), trước khi checkpoint copy frame về file database.
+    pub fn sync(&mut self) -> DbResult<()> {
+        if self.sync.syncs_files() {
+            self.group.sync_all()?;
+        }
+        self.sync_created()
+    }
+
+    /// Số lần fsync commit đã chạy (mỗi lần có thể gom nhiều commit).
+    pub fn commit_syncs(&self) -> u64 {
+        self.group.syncs()
+    }
+
+    pub fn header(&self) -> WalHeader {
+        self.header
+    }
+
+    pub fn recovery(&self) -> RecoveryInfo {
+        self.recovery
+    }
+
+    pub fn frame_count(&self) -> u32 {
+        self.pids.len() as u32
+    }
+
+    pub fn backfilled(&self) -> u32 {
+        self.backfilled
+    }
+
+    /// `frames` frame đầu log đã nằm trong file database (đã sync).
+    pub fn set_backfilled(&mut self, frames: u32) {
+        debug_assert!(frames <= self.frame_count(), "backfill past end of wal");
+        self.backfilled = frames;
+    }
+
+    /// Mọi frame đều đã được checkpoint: log có thể bắt đầu lại từ đầu.
+    pub fn is_fully_backfilled(&self) -> bool {
+        !self.pids.is_empty() && self.backfilled == self.frame_count()
+    }
+
+    /// Frame mới nhất của mỗi page trong các frame `[from, to)`, theo thứ tự page id.
+    pub fn latest_frames(&self, from: u32, to: u32) -> Vec<(PageId, u32)> {
+        let mut latest = std::collections::BTreeMap::new();
+        for idx in from..to.min(self.frame_count()) {
+            latest.insert(self.pids[idx as usize], idx);
+        }
+        latest.into_iter().collect()
+    }
+
+    /// Số page của database theo commit cuối trong log, 0 nếu log chưa có commit.
+    pub fn db_size(&self) -> u32 {
+        self.db_size
+    }
+
+    /// Log đang có ảnh đầy đủ của `pid`. Frame WAL luôn là ảnh đầy đủ, và log chỉ được
+    /// bắt đầu lại sau khi mọi page đã nằm bền vững trong file database, nên lần sửa
+    /// đầu tiên của 1 page sau checkpoint luôn ghi ảnh đầy đủ: recovery dùng ảnh này để
+    /// sửa page bị ghi rách lúc checkpoint.
+    pub fn has_full_image(&self, pid: PageId) -> bool {
+        self.index.contains_key(&pid)
+    }
+
+    /// Frame mới nhất chứa `pid`.
+    pub fn find_frame(&self, pid: PageId) -> Option<u32> {
+        self.index
+            .get(&pid)
+            .and_then(|frames| frames.last().copied())
+    }
+
+    /// Frame mới nhất chứa `pid` trong `mark` frame đầu log (snapshot của reader).
+    pub fn find_frame_before(&self, pid: PageId, mark: u32) -> Option<u32> {
+        let frames = self.index.get(&pid)?;
+        let n = frames.partition_point(|&idx| idx < mark);
+        n.checked_sub(1).map(|i| frames[i])
+    }
+
+    /// Đọc page data của frame `idx`.
+    pub fn read_frame_page(&mut self, idx: u32, out: &mut [u8]) -> DbResult<()> {
+        if out.len() != PAGE_SIZE {
+            return Err(DbError::InvalidArgument(
+                "buffer length must equal PAGE_SIZE",
+            ));
+        }
+        if idx >= self.frame_count() {
+            return Err(DbError::InvalidArgument("wal frame out of range"));
+        }
+        self.f.seek(SeekFrom::Start(
+            frame_offset(idx) + FRAME_HEADER_SIZE as u64,
+        ))?;
+        self.f.read_exact(out)?;
+        Ok(())
+    }
+
+    /// Append các page của 1 transaction, frame cuối mang commit marker `db_size`.
+    /// Commit chỉ bền vững sau khi ticket trả về được `wait` (fsync theo sync mode,
+    /// gom với các commit khác). Ghi lỗi thì log giữ nguyên trạng thái trước đó.
+    pub fn append_commit(
+        &mut self,
+        pages: &[(PageId, &[u8])],
+        db_size: u32,
+    ) -> DbResult<CommitTicket> {
+        if pages.is_empty() {
+            return Err(DbError::InvalidArgument("commit needs at least one page"));
+        }
+        if db_size == 0 {
+            return Err(DbError::InvalidArgument("commit db size must be non-zero"));
+        }
+
+        let mut buf = vec![0u8; pages.len() * FRAME_SIZE];
+        let mut sum = self.last_checksum;
+        for (i, ((pid, page), frame)) in pages
+            .iter()
+            .zip(buf.chunks_exact_mut(FRAME_SIZE))
+            .enumerate()
+        {
+            let last = i + 1 == pages.len();
+            let header = FrameHeader {
+                pid: *pid,
+                db_size: if last { db_size } else { 0 },
+            };
+            let (head, data) = frame.split_at_mut(FRAME_HEADER_SIZE);
+            data.copy_from_slice(page);
+            sum = header.encode(head, self.header.salt, sum, data)?;
+        }
+
+        if !self.header_on_disk {
+            self.write_header()?;
+        }
+        self.f
+            .seek(SeekFrom::Start(frame_offset(self.frame_count())))?;
+        self.f.write_all(&buf)?;
+        let seq = self.group.written();
+        let ticket = if self.sync.syncs_commit() {
+            self.sync_created()?;
+            CommitTicket::new(self.group.clone(), seq)
+        } else {
+            CommitTicket::done()
+        };
+
+        for (pid, _) in pages {
+            let idx = self.frame_count();
+            self.index.entry(*pid).or_default().push(idx);
+            self.pids.push(*pid);
+        }
+        self.last_checksum = sum;
+        self.db_size = db_size;
+        Ok(ticket)
+    }
+
+    /// Bắt đầu lại log (header mới, `checkpoint_seq` + 1). Caller đảm bảo mọi frame
+    /// đã được checkpoint.
+    pub fn restart(&mut self) -> DbResult<()> {
+        self.reset(self.header.checkpoint_seq.wrapping_add(1), true)
+    }
+
+    /// Như `restart` nhưng cắt file WAL về 0 byte; header chỉ được ghi lại khi có
+    /// commit tiếp theo.
+    pub fn truncate(&mut self) -> DbResult<()> {
+        self.reset(self.header.checkpoint_seq.wrapping_add(1), false)
+    }
+
+    /// Log mới (salt mới) với `checkpoint_seq`, bỏ mọi frame cũ.
+    fn reset(&mut self, checkpoint_seq: u32, write_header: bool) -> DbResult<()> {
+        let header = WalHeader {
+            checkpoint_seq,
+            salt: new_salt(),
+        };
+        let mut buf = [0u8; WAL_HEADER_SIZE];
+        let sum = header.encode(&mut buf)?;
+        self.f.set_len(0)?;
+        self.header = header;
+        self.header_on_disk = false;
+        if write_header {
+            self.write_header()?;
+        } else {
+            sync_data_if(&self.f, self.sync.syncs_files())?;
+        }
+
+        // frame cũ đã được checkpoint hết, commit đang chờ sync không còn gì để chờ
+        self.group.mark_synced();
+        self.pids.clear();
+        self.backfilled = 0;
+        self.last_checksum = sum;
+        self.db_size = 0;
+        self.index.clear();
+        Ok(())
+    }
+
+    fn write_header(&mut self) -> DbResult<()> {
+        let mut buf = [0u8; WAL_HEADER_SIZE];
+        self.header.encode(&mut buf)?;
+        self.f.seek(SeekFrom::Start(0))?;
+        self.f.write_all(&buf)?;
+        sync_data_if(&self.f, self.sync.syncs_files())?;
+        self.header_on_disk = true;
+        Ok(())
+    }
+
+    /// fsync thư mục một lần sau khi file WAL được tạo.
+    fn sync_created(&mut self) -> DbResult<()> {
+        if self.created && self.sync.syncs_dir_on_create() {
+            sync_parent_dir(&self.path)?;
+            self.created = false;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wal::temp_db_path;
+
+    fn page(fill: u8) -> Vec<u8> {
+        vec![fill; PAGE_SIZE]
+    }
+
+    #[test]
+    fn test_append_and_reopen() {
+        let path = temp_db_path("wal-append");
+        let (a, b) = (page(1), page(2));
+        let salt = {
+            let mut wal = WalFile::open(&path).unwrap();
+            assert_eq!(wal.frame_count(), 0);
+            wal.append_commit(&[(PageId(1), &a), (PageId(2), &b)], 3)
+                .unwrap()
+                .wait()
+                .unwrap();
+            wal.append_commit(&[(PageId(1), &b)], 3)
+                .unwrap()
+                .wait()
+                .unwrap();
+            wal.header().salt
+        };
+
+        let mut wal = WalFile::open(&path).unwrap();
+        assert_eq!(wal.header().salt, salt);
+        assert_eq!(wal.frame_count(), 3);
+        assert_eq!(wal.db_size(), 3);
+        assert_eq!(wal.find_frame(PageId(1)), Some(2));
+        assert_eq!(wal.find_frame(PageId(3)), None);
+        assert_eq!(wal.find_frame_before(PageId(1), 2), Some(0));
+        assert_eq!(wal.find_frame_before(PageId(1), 0), None);
+        let mut out = page(0);
+        wal.read_frame_page(1, &mut out).unwrap();
+        assert_eq!(out, b);
+
+        assert!(wal.append_commit(&[], 3).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_torn_and_stale_frames_end_the_log() {
+        let path = temp_db_path("wal-torn");
+        {
+            let mut wal = WalFile::open(&path).unwrap();
+            for i in 0..3 {
+                wal.append_commit(&[(PageId(i + 1), &page(i as u8))], 4)
+                    .unwrap()
+                    .wait()
+                    .unwrap();
+            }
+        }
+        // frame cuối bị ghi dở
+        let f = OpenOptions::new().write(true).open(&path).unwrap();
+        f.set_len(frame_offset(2) + FRAME_SIZE as u64 - 1).unwrap();
+        assert_eq!(WalFile::open(&path).unwrap().frame_count(), 2);
+
+        // hỏng 1 byte ở frame 0 -> các frame sau cũng đứt chuỗi checksum
+        let mut f = OpenOptions::new().write(true).open(&path).unwrap();
+        f.seek(SeekFrom::Start(frame_offset(0) + FRAME_HEADER_SIZE as u64))
+            .unwrap();
+        f.write_all(&[0xFF]).unwrap();
+        let wal = WalFile::open(&path).unwrap();
+        assert_eq!(wal.frame_count(), 0);
+        assert_eq!(wal.db_size(), 0);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_bad_header_starts_new_log() {
+        let path = temp_db_path("wal-header");
+        std::fs::write(&path, [0xAAu8; WAL_HEADER_SIZE + 3]).unwrap();
+        let wal = WalFile::open(&path).unwrap();
+        assert_eq!(wal.frame_count(), 0);
+        assert_eq!(
+            std::fs::metadata(&path).unwrap().len(),
+            WAL_HEADER_SIZE as u64
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,274 @@
+//! Layout on-disk của file WAL (little-endian).
+//!
+//! ```text
+//! [header 32 bytes][frame 0][frame 1]...
+//! header: [magic u32][version u32][page_size u32][checkpoint_seq u32]
+//!         [salt1 u32][salt2 u32][checksum1 u32][checksum2 u32]
+//! frame:  [page_id u32][db_size u32][salt1 u32][salt2 u32][checksum1 u32][checksum2 u32]
+//!         [page data PAGE_SIZE]
+//! ```
+//!
+//! - `db_size` != 0 đánh dấu frame commit: số page của database sau commit.
+//! - Checksum là running checksum: checksum của frame = checksum(8 byte đầu của frame
+//!   header + page data) với seed là checksum của frame trước (frame đầu: checksum của
+//!   header). Frame bị ghi dở, hoặc frame cũ còn sót lại sau frame cuối, đều lệch
+//!   checksum và được coi là cuối log.
+//! - Salt đổi mỗi lần log được reset: frame của log cũ có salt khác nên không bao giờ
+//!   được đọc nhầm.
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+
+use crate::constants::PAGE_SIZE;
+use crate::page::raw::{read_u32_le, write_u32_le};
+use crate::{DbError, DbResult, PageId};
+
+/// "NVWL"
+pub const WAL_MAGIC: u32 = 0x4C57_564E;
+pub const WAL_VERSION: u32 = 1;
+
+pub const WAL_HEADER_SIZE: usize = 32;
+pub const FRAME_HEADER_SIZE: usize = 24;
+pub const FRAME_SIZE: usize = FRAME_HEADER_SIZE + PAGE_SIZE;
+
+// header
+const OFF_MAGIC: usize = 0;
+const OFF_VERSION: usize = 4;
+const OFF_PAGE_SIZE: usize = 8;
+const OFF_CHECKPOINT_SEQ: usize = 12;
+const OFF_SALT1: usize = 16;
+const OFF_SALT2: usize = 20;
+const OFF_CHECKSUM1: usize = 24;
+const OFF_CHECKSUM2: usize = 28;
+/// Phần header được checksum (trước 2 field checksum).
+const HEADER_CHECKSUMMED: usize = OFF_CHECKSUM1;
+
+// frame header
+const OFF_FRAME_PID: usize = 0;
+const OFF_FRAME_DB_SIZE: usize = 4;
+const OFF_FRAME_SALT1: usize = 8;
+const OFF_FRAME_SALT2: usize = 12;
+const OFF_FRAME_CHECKSUM1: usize = 16;
+const OFF_FRAME_CHECKSUM2: usize = 20;
+/// Phần frame header được checksum: page id + db size.
+const FRAME_CHECKSUMMED: usize = OFF_FRAME_SALT1;
+
+/// Checksum tính theo từng cặp word u32.
+const CHECKSUM_CHUNK: usize = 8;
+
+/// Cặp checksum (s1, s2).
+pub type Checksum = (u32, u32);
+
+/// Running checksum kiểu Fletcher trên các word u32 little-endian, nối tiếp từ `seed`.
+/// `data.len()` phải là bội của 8.
+pub fn checksum(data: &[u8], seed: Checksum) -> Checksum {
+    debug_assert!(data.len().is_multiple_of(CHECKSUM_CHUNK));
+    let (mut s1, mut s2) = seed;
+    for chunk in data.chunks_exact(CHECKSUM_CHUNK) {
+        let a = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+        let b = u32::from_le_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]);
+        s1 = s1.wrapping_add(a).wrapping_add(s2);
+        s2 = s2.wrapping_add(b).wrapping_add(s1);
+    }
+    (s1, s2)
+}
+
+/// Salt ngẫu nhiên cho log mới (không cần crypto, chỉ cần khác log trước).
+pub fn new_salt() -> (u32, u32) {
+    let mut h = RandomState::new().build_hasher();
+    h.write_u128(
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default(),
+    );
+    let v = h.finish();
+    (v as u32, (v >> 32) as u32)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WalHeader {
+    /// Tăng mỗi lần log được reset sau checkpoint.
+    pub checkpoint_seq: u32,
+    pub salt: (u32, u32),
+}
+
+impl WalHeader {
+    /// Ghi header, trả về checksum của header (seed cho frame đầu tiên).
+    pub fn encode(&self, buf: &mut [u8]) -> DbResult<Checksum> {
+        if buf.len() != WAL_HEADER_SIZE {
+            return Err(DbError::InvalidArgument(
+                "buffer length must equal WAL_HEADER_SIZE",
+            ));
+        }
+        write_u32_le(buf, OFF_MAGIC, WAL_MAGIC)?;
+        write_u32_le(buf, OFF_VERSION, WAL_VERSION)?;
+        write_u32_le(buf, OFF_PAGE_SIZE, PAGE_SIZE as u32)?;
+        write_u32_le(buf, OFF_CHECKPOINT_SEQ, self.checkpoint_seq)?;
+        write_u32_le(buf, OFF_SALT1, self.salt.0)?;
+        write_u32_le(buf, OFF_SALT2, self.salt.1)?;
+        let sum = checksum(&buf[..HEADER_CHECKSUMMED], (0, 0));
+        write_u32_le(buf, OFF_CHECKSUM1, sum.0)?;
+        write_u32_le(buf, OFF_CHECKSUM2, sum.1)?;
+        Ok(sum)
+    }
+
+    /// Đọc + validate header, trả về kèm checksum của header.
+    pub fn decode(buf: &[u8]) -> DbResult<(WalHeader, Checksum)> {
+        if buf.len() != WAL_HEADER_SIZE {
+            return Err(DbError::Corruption(
+                "buffer length must equal WAL_HEADER_SIZE",
+            ));
+        }
+        if read_u32_le(buf, OFF_MAGIC)? != WAL_MAGIC {
+            return Err(DbError::Corruption("bad wal magic"));
+        }
+        if read_u32_le(buf, OFF_VERSION)? != WAL_VERSION {
+            return Err(DbError::Corruption("unsupported wal version"));
+        }
+        if read_u32_le(buf, OFF_PAGE_SIZE)? as usize != PAGE_SIZE {
+            return Err(DbError::Corruption("wal page size mismatch"));
+        }
+        let sum = checksum(&buf[..HEADER_CHECKSUMMED], (0, 0));
+        if (
+            read_u32_le(buf, OFF_CHECKSUM1)?,
+            read_u32_le(buf, OFF_CHECKSUM2)?,
+        ) != sum
+        {
+            return Err(DbError::Corruption("wal header checksum mismatch"));
+        }
+        let header = WalHeader {
+            checkpoint_seq: read_u32_le(buf, OFF_CHECKPOINT_SEQ)?,
+            salt: (read_u32_le(buf, OFF_SALT1)?, read_u32_le(buf, OFF_SALT2)?),
+        };
+        Ok((header, sum))
+    }
+}
+
+/// Phần có nghĩa của frame header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameHeader {
+    pub pid: PageId,
+    /// Số page của database sau commit; 0 = frame không phải commit.
+    pub db_size: u32,
+}
+
+impl FrameHeader {
+    pub fn is_commit(&self) -> bool {
+        self.db_size != 0
+    }
+
+    /// Ghi frame header cho `page`, nối checksum từ `prev`. Trả về checksum của frame.
+    pub fn encode(
+        &self,
+        buf: &mut [u8],
+        salt: (u32, u32),
+        prev: Checksum,
+        page: &[u8],
+    ) -> DbResult<Checksum> {
+        check_frame_bufs(buf.len(), page.len())?;
+        write_u32_le(buf, OFF_FRAME_PID, self.pid.as_u32())?;
+        write_u32_le(buf, OFF_FRAME_DB_SIZE, self.db_size)?;
+        write_u32_le(buf, OFF_FRAME_SALT1, salt.0)?;
+        write_u32_le(buf, OFF_FRAME_SALT2, salt.1)?;
+        let sum = checksum(page, checksum(&buf[..FRAME_CHECKSUMMED], prev));
+        write_u32_le(buf, OFF_FRAME_CHECKSUM1, sum.0)?;
+        write_u32_le(buf, OFF_FRAME_CHECKSUM2, sum.1)?;
+        Ok(sum)
+    }
+
+    /// Validate frame theo salt của log và checksum của frame trước.
+    /// `None`: frame bị ghi dở hoặc không thuộc chuỗi hiện tại (= cuối log).
+    pub fn decode(
+        buf: &[u8],
+        salt: (u32, u32),
+        prev: Checksum,
+        page: &[u8],
+    ) -> DbResult<Option<(FrameHeader, Checksum)>> {
+        check_frame_bufs(buf.len(), page.len())?;
+        if (
+            read_u32_le(buf, OFF_FRAME_SALT1)?,
+            read_u32_le(buf, OFF_FRAME_SALT2)?,
+        ) != salt
+        {
+            return Ok(None);
+        }
+        let sum = checksum(page, checksum(&buf[..FRAME_CHECKSUMMED], prev));
+        if (
+            read_u32_le(buf, OFF_FRAME_CHECKSUM1)?,
+            read_u32_le(buf, OFF_FRAME_CHECKSUM2)?,
+        ) != sum
+        {
+            return Ok(None);
+        }
+        let header = FrameHeader {
+            pid: PageId(read_u32_le(buf, OFF_FRAME_PID)?),
+            db_size: read_u32_le(buf, OFF_FRAME_DB_SIZE)?,
+        };
+        Ok(Some((header, sum)))
+    }
+}
+
+fn check_frame_bufs(header_len: usize, page_len: usize) -> DbResult<()> {
+    if header_len != FRAME_HEADER_SIZE || page_len != PAGE_SIZE {
+        return Err(DbError::InvalidArgument(
+            "frame buffers must be FRAME_HEADER_SIZE and PAGE_SIZE",
+        ));
+    }
+    Ok(())
+}
+
+/// Offset của frame thứ `idx` (0-based) trong file WAL.
+pub fn frame_offset(idx: u32) -> u64 {
+    WAL_HEADER_SIZE as u64 + idx as u64 * FRAME_SIZE as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_header_roundtrip_and_checksum() {
+        let header = WalHeader {
+            checkpoint_seq: 3,
+            salt: new_salt(),
+        };
+        let mut buf = [0u8; WAL_HEADER_SIZE];
+        let sum = header.encode(&mut buf).unwrap();
+        assert_eq!(WalHeader::decode(&buf).unwrap(), (header, sum));
+
+        buf[OFF_CHECKPOINT_SEQ] ^= 1;
+        assert!(WalHeader::decode(&buf).is_err());
+    }
+
+    #[test]
+    fn test_frame_chain_detects_damage() {
+        let salt = (7, 9);
+        let seed = (1, 2);
+        let page = vec![0x5Au8; PAGE_SIZE];
+        let header = FrameHeader {
+            pid: PageId(4),
+            db_size: 10,
+        };
+        let mut buf = [0u8; FRAME_HEADER_SIZE];
+        let sum = header.encode(&mut buf, salt, seed, &page).unwrap();
+        assert!(header.is_commit());
+        assert_eq!(
+            FrameHeader::decode(&buf, salt, seed, &page).unwrap(),
+            Some((header, sum))
+        );
+
+        // sai seed (frame không nối tiếp frame trước), sai salt, page bị ghi dở
+        assert_eq!(
+            FrameHeader::decode(&buf, salt, (1, 3), &page).unwrap(),
+            None
+        );
+        assert_eq!(
+            FrameHeader::decode(&buf, (7, 8), seed, &page).unwrap(),
+            None
+        );
+        let mut torn = page.clone();
+        torn[PAGE_SIZE - 1] = 0;
+        assert_eq!(FrameHeader::decode(&buf, salt, seed, &torn).unwrap(), None);
+    }
+}
@@ -0,0 +1,163 @@
+//! Group commit: gom fsync của các commit WAL đến gần nhau thành 1 lần sync.
+//!
+//! Commit được append vào WAL (dưới write lock) mà chưa fsync, nhận một `CommitTicket`
+//! mang số thứ tự của commit. Sau khi nhả write lock, caller `wait` ticket:
+//! - commit đã nằm trong lần sync trước -> trả về ngay;
+//! - chưa có ai sync -> caller làm leader, fsync mọi commit đã append tới lúc đó;
+//! - đang có sync -> chờ; commit append trong lúc chờ được gom vào lần sync kế tiếp.
+
+use std::fs::File;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+
+use crate::DbResult;
+
+struct GroupState {
+    /// Số thứ tự của commit append gần nhất.
+    written: u64,
+    /// Mọi commit có số thứ tự <= `synced` đã bền vững.
+    synced: u64,
+    /// Đang có leader fsync.
+    syncing: bool,
+    /// Số lần fsync đã chạy.
+    syncs: u64,
+}
+
+/// Lock bị poison (thread khác panic khi giữ lock) vẫn dùng tiếp được: `GroupState`
+/// luôn nhất quán giữa các câu lệnh.
+fn lock<T>(m: &Mutex<T>) -> MutexGuard<'_, T> {
+    m.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Điều phối fsync dùng chung cho các commit trên 1 file WAL.
+pub(super) struct SyncGroup {
+    /// Handle riêng tới file WAL, fsync không cần giữ write lock.
+    f: File,
+    state: Mutex<GroupState>,
+    synced_cv: Condvar,
+}
+
+impl SyncGroup {
+    pub(super) fn new(f: File) -> Arc<Self> {
+        Arc::new(SyncGroup {
+            f,
+            state: Mutex::new(GroupState {
+                written: 0,
+                synced: 0,
+                syncing: false,
+                syncs: 0,
+            }),
+            synced_cv: Condvar::new(),
+        })
+    }
+
+    /// Ghi nhận 1 commit vừa append (chưa sync), trả về số thứ tự của nó.
+    pub(super) fn written(&self) -> u64 {
+        let mut st = lock(&self.state);
+        st.written += 1;
+        st.written
+    }
+
+    /// Mọi commit đã append được coi là bền vững (log đã được fsync theo đường khác).
+    pub(super) fn mark_synced(&self) {
+        let mut st = lock(&self.state);
+        st.synced = st.written;
+        self.synced_cv.notify_all();
+    }
+
+    pub(super) fn syncs(&self) -> u64 {
+        lock(&self.state).syncs
+    }
+
+    /// fsync mọi commit đã append.
+    pub(super) fn sync_all(&self) -> DbResult<()> {
+        let seq = lock(&self.state).written;
+        self.wait(seq)
+    }
+
+    /// Chờ tới khi commit `seq` bền vững, tự làm leader fsync nếu chưa ai sync.
+    fn wait(&self, seq: u64) -> DbResult<()> {
+        let mut st = lock(&self.state);
+        loop {
+            if st.synced >= seq {
+                return Ok(());
+            }
+            if st.syncing {
+                st = self.synced_cv.wait(st).unwrap_or_else(PoisonError::into_inner);
+                continue;
+            }
+            // leader: gom mọi commit đã append tới thời điểm này
+            st.syncing = true;
+            let target = st.written;
+            drop(st);
+            let res = self.f.sync_data();
+            st = lock(&self.state);
+            st.syncing = false;
+            st.syncs += 1;
+            if res.is_ok() {
+                st.synced = st.synced.max(target);
+            }
+            // sync lỗi: waiter khác thức dậy và thử làm leader lại
+            self.synced_cv.notify_all();
+            res?;
+        }
+    }
+}
+
+/// Commit đã được append vào WAL; `wait` trả về khi commit đã bền vững theo sync mode.
+#[must_use = "a commit is not durable until its ticket is waited on"]
+pub struct CommitTicket {
+    /// `None`: không cần chờ (không có gì để commit, hoặc sync mode không fsync commit).
+    group: Option<Arc<SyncGroup>>,
+    seq: u64,
+}
+
+impl CommitTicket {
+    pub(super) fn new(group: Arc<SyncGroup>, seq: u64) -> Self {
+        CommitTicket {
+            group: Some(group),
+            seq,
+        }
+    }
+
+    /// Ticket không cần chờ.
+    pub(crate) fn done() -> Self {
+        CommitTicket {
+            group: None,
+            seq: 0,
+        }
+    }
+
+    pub fn wait(self) -> DbResult<()> {
+        match self.group {
+            Some(group) => group.wait(self.seq),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wal::temp_db_path;
+
+    #[test]
+    fn test_waits_share_one_sync() {
+        let path = temp_db_path("wal-group");
+        let group = SyncGroup::new(File::create(&path).unwrap());
+        let a = CommitTicket::new(group.clone(), group.written());
+        let b = CommitTicket::new(group.clone(), group.written());
+
+        // sync của commit sau bao luôn commit trước
+        b.wait().unwrap();
+        a.wait().unwrap();
+        assert_eq!(group.syncs(), 1);
+
+        let c = CommitTicket::new(group.clone(), group.written());
+        group.mark_synced();
+        c.wait().unwrap();
+        assert_eq!(group.syncs(), 1);
+        CommitTicket::done().wait().unwrap();
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
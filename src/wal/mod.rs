@@ -0,0 +1,50 @@
+//! Write-ahead log.
+//!
+//! Ở chế độ WAL, page đã commit được append vào file `<db>-wal` nằm cạnh file database
+//! thay vì ghi đè tại chỗ. Page mới nhất của 1 page id luôn nằm ở frame cuối cùng
+//! chứa page đó; page không có trong WAL thì đọc từ file database.
+//!
+//! - `format`: layout header/frame và checksum.
+//! - `WalFile`: append/scan frame trên file WAL.
+//! - `group`: group commit, gom fsync của các commit đồng thời.
+//! - `recovery`: scan log lúc mở, chỉ giữ frame đã commit. Page trong file database
+//!   sai checksum (ghi rách lúc checkpoint) được ghi lại từ ảnh đầy đủ trong log.
+//! - `checkpoint`: copy page từ WAL về file database, reset/truncate log.
+//! - `WalPager`: `Pager` ghi qua WAL.
+
+mod checkpoint;
+mod file;
+pub mod format;
+mod group;
+mod pager;
+mod recovery;
+
+pub use checkpoint::{CheckpointInfo, CheckpointMode, DEFAULT_AUTOCHECKPOINT_FRAMES};
+pub use file::WalFile;
+pub use group::CommitTicket;
+pub use pager::WalPager;
+pub use recovery::RecoveryInfo;
+
+/// Hậu tố tên file WAL.
+pub const WAL_SUFFIX: &str = "-wal";
+
+/// Đường dẫn file WAL của database `db_path`.
+pub fn wal_path(db_path: &str) -> String {
+    format!("{}{}", db_path, WAL_SUFFIX)
+}
+
+/// Path tạm cho test, đã xoá file database và WAL cũ (nếu có).
+#[cfg(test)]
+pub(crate) fn temp_db_path(name: &str) -> String {
+    let mut p = std::env::temp_dir();
+    p.push(format!("novadb-{}-{}.db", name, std::process::id()));
+    let path = p.to_string_lossy().into_owned();
+    remove_db_files(&path);
+    path
+}
+
+#[cfg(test)]
+pub(crate) fn remove_db_files(path: &str) {
+    let _ = std::fs::remove_file(path);
+    let _ = std::fs::remove_file(wal_path(path));
+}
@@ -0,0 +1,735 @@
+use std::collections::HashMap;
+
+use crate::constants::PAGE_SIZE;
+use crate::page::header::verify_checksum;
+use crate::pager::file::FilePager;
+use crate::pager::freelist;
+use crate::pager::pager::{Pager, Snapshot};
+use crate::pager::pending::PendingPages;
+use crate::pager::sync::SyncMode;
+use crate::{DbError, DbResult, PageId};
+
+use super::checkpoint::{backfill, CheckpointInfo, CheckpointMode, DEFAULT_AUTOCHECKPOINT_FRAMES};
+use super::file::WalFile;
+use super::group::CommitTicket;
+use super::{wal_path, RecoveryInfo};
+
+/// Pager ở chế độ WAL: page không bao giờ được ghi đè trực tiếp lên file database.
+///
+/// - `write_page`/`alloc_page` chỉ giữ page trong `pending` (transaction hiện tại).
+/// - `flush` = commit: append mọi page pending vào WAL (frame cuối là commit), fsync
+///   theo `SyncMode`.
+/// - `read_page`: pending -> frame mới nhất trong WAL -> file database.
+/// - `checkpoint` copy page trong WAL về file database; tự chạy (passive) khi log có
+///   từ `autocheckpoint` frame chưa được copy.
+/// - Reader lấy `Snapshot` (số frame đã commit lúc bắt đầu đọc) và chỉ đọc frame trước
+///   mốc đó, nên đọc nhất quán trong khi writer tiếp tục commit. Checkpoint không copy
+///   frame từ mốc của reader cũ nhất trở đi, và không bắt đầu lại log khi còn reader
+///   cần frame trong log.
+pub struct WalPager {
+    db: FilePager,
+    wal: WalFile,
+    /// Page của transaction hiện tại; số page logic gồm cả page chỉ có trong WAL.
+    pending: PendingPages,
+    autocheckpoint: u32,
+    /// Số page bị ghi rách trong file database được khôi phục từ WAL lúc mở.
+    restored_pages: u32,
+    /// Snapshot đang mở: id -> (thế hệ log, mốc frame).
+    readers: HashMap<u64, (u32, u32)>,
+    next_reader: u64,
+}
+
+impl WalPager {
+    /// Mở database `path` cùng file WAL `<path>-wal`; recovery của WAL làm các page
+    /// đã commit nhưng chưa checkpoint đọc được ngay, và page bị ghi rách trong file
+    /// database (crash giữa checkpoint) được ghi lại từ ảnh đầy đủ trong WAL.
+    pub fn open(path: String) -> DbResult<Self> {
+        let wal = WalFile::open(&wal_path(&path))?;
+        let mut db = FilePager::open_main(path)?;
+        let pages = (db.num_pages()? as u32).max(wal.db_size());
+        let mut p = WalPager {
+            db,
+            wal,
+            pending: PendingPages::new(pages, Vec::new()),
+            autocheckpoint: DEFAULT_AUTOCHECKPOINT_FRAMES,
+            restored_pages: 0,
+            readers: HashMap::new(),
+            next_reader: 0,
+        };
+        p.restore_torn_pages()?;
+        // page meta và page free mới nhất có thể chỉ nằm trong WAL
+        let freelist = freelist::load(pages, |pid, out| p.read_page(pid, out))?;
+        p.pending = PendingPages::new(pages, freelist);
+        Ok(p)
+    }
+
+    /// Ghi lại từ WAL các page trong file database sai checksum mà log có ảnh đầy đủ.
+    fn restore_torn_pages(&mut self) -> DbResult<()> {
+        let db_pages = self.db.num_pages()?;
+        let mut torn = Vec::new();
+        let mut buf = vec![0u8; PAGE_SIZE];
+        // frame trong log đã qua validate chuỗi checksum lúc recovery: ảnh page đầy đủ
+        for (pid, idx) in self.wal.latest_frames(0, self.wal.frame_count()) {
+            if pid == PageId(0) || pid.as_u64() >= db_pages {
+                continue;
+            }
+            self.db.read_page(pid, &mut buf)?;
+            if !verify_checksum(&buf)? {
+                torn.push((pid, idx));
+            }
+        }
+        if !torn.is_empty() {
+            backfill(&mut self.wal, &mut self.db, &torn)?;
+            self.db.flush()?;
+        }
+        self.restored_pages = torn.len() as u32;
+        Ok(())
+    }
+
+    /// Số page bị ghi rách được khôi phục từ WAL lúc mở.
+    pub fn restored_pages(&self) -> u32 {
+        self.restored_pages
+    }
+
+    /// Ngưỡng checkpoint tự động (số frame chưa được copy), 0 = tắt.
+    pub fn set_autocheckpoint(&mut self, frames: u32) {
+        self.autocheckpoint = frames;
+    }
+
+    /// Mức fsync của commit (WAL) và checkpoint (WAL + file database).
+    pub fn set_sync_mode(&mut self, mode: SyncMode) {
+        self.db.set_sync_mode(mode);
+        self.wal.set_sync_mode(mode);
+    }
+
+    /// Số frame đầu log được phép copy về file database: frame từ mốc của reader cũ
+    /// nhất trở đi có thể ghi đè version reader đó còn đọc từ file database. Reader của
+    /// thế hệ log trước đọc thẳng file database nên chặn mọi frame.
+    fn backfill_limit(&self) -> u32 {
+        let seq = self.wal.header().checkpoint_seq;
+        self.readers
+            .values()
+            .map(|&(log_seq, mark)| if log_seq == seq { mark } else { 0 })
+            .fold(self.wal.frame_count(), u32::min)
+    }
+
+    /// Số snapshot đang mở.
+    pub fn active_readers(&self) -> usize {
+        self.readers.len()
+    }
+
+    /// Copy các frame đã commit chưa được copy về file database rồi sync file database.
+    /// Page pending (chưa commit) không bị động tới. Reader đang mở giới hạn số frame
+    /// được copy: `Passive` trả về `CheckpointInfo::backfilled` < `log_frames`, mode
+    /// khác copy phần được phép rồi trả `DbError::Busy` (log không được bắt đầu lại).
+    pub fn checkpoint(&mut self, mode: CheckpointMode) -> DbResult<CheckpointInfo> {
+        let log_frames = self.wal.frame_count();
+        let limit = self.backfill_limit().max(self.wal.backfilled());
+        let pages = self.wal.latest_frames(self.wal.backfilled(), limit);
+        if !pages.is_empty() {
+            // commit chưa được fsync (Normal) phải bền trước khi file database đổi
+            self.wal.sync()?;
+            self.db.grow_to(self.wal.db_size())?;
+            backfill(&mut self.wal, &mut self.db, &pages)?;
+            self.db.flush()?;
+        }
+        self.wal.set_backfilled(limit);
+
+        if limit < log_frames {
+            if mode != CheckpointMode::Passive {
+                return Err(DbError::Busy("checkpoint blocked by active readers"));
+            }
+        } else {
+            match mode {
+                CheckpointMode::Passive | CheckpointMode::Full => {}
+                CheckpointMode::Restart => {
+                    if log_frames > 0 {
+                        self.wal.restart()?;
+                    }
+                }
+                CheckpointMode::Truncate => self.wal.truncate()?,
+            }
+        }
+        Ok(CheckpointInfo {
+            log_frames,
+            backfilled: limit,
+            pages_written: pages.len() as u32,
+        })
+    }
+
+    /// Kết quả recovery của WAL lúc mở.
+    pub fn recovery(&self) -> RecoveryInfo {
+        self.wal.recovery()
+    }
+
+    /// Số frame đang nằm trong WAL.
+    pub fn wal_frames(&self) -> u32 {
+        self.wal.frame_count()
+    }
+
+    /// Số lần fsync commit đã chạy; nhỏ hơn số commit khi có group commit.
+    pub fn commit_syncs(&self) -> u64 {
+        self.wal.commit_syncs()
+    }
+
+    /// Append các page pending vào WAL thành 1 commit, chưa chờ fsync. Caller nhả
+    /// write lock rồi `wait` ticket, để các commit đồng thời dùng chung 1 lần fsync.
+    pub fn commit(&mut self) -> DbResult<CommitTicket> {
+        let (wal, db) = (&mut self.wal, &mut self.db);
+        self.pending.stage_freelist(|out| match wal.find_frame(PageId(0)) {
+            Some(idx) => wal.read_frame_page(idx, out),
+            None => db.read_page(PageId(0), out),
+        })?;
+        if self.pending.is_empty() {
+            return Ok(CommitTicket::done());
+        }
+        // log đã được checkpoint hết -> ghi lại từ đầu file WAL thay vì nối dài
+        if self.wal.is_fully_backfilled() {
+            self.wal.restart()?;
+        }
+        let db_size = self.pending.next_pid().as_u32();
+        let ticket = self.wal.append_commit(&self.pending.pages(), db_size)?;
+        self.pending.committed();
+
+        let unbackfilled = self.wal.frame_count() - self.wal.backfilled();
+        if self.autocheckpoint > 0 && unbackfilled >= self.autocheckpoint {
+            self.checkpoint(CheckpointMode::Passive)?;
+        }
+        Ok(ticket)
+    }
+}
+
+impl Pager for WalPager {
+    fn read_page(&mut self, pid: PageId, out: &mut [u8]) -> DbResult<()> {
+        if out.len() != PAGE_SIZE {
+            return Err(DbError::InvalidArgument(
+                "buffer length must equal PAGE_SIZE",
+            ));
+        }
+        self.pending.check_pid(pid)?;
+        if let Some(page) = self.pending.get(pid) {
+            out.copy_from_slice(page);
+            return Ok(());
+        }
+        if let Some(idx) = self.wal.find_frame(pid) {
+            return self.wal.read_frame_page(idx, out);
+        }
+        if pid.as_u64() < self.db.num_pages()? {
+            return self.db.read_verified(pid, out);
+        }
+        // page đã cấp phát nhưng chưa từng được ghi
+        out.fill(0);
+        Ok(())
+    }
+
+    fn write_page(&mut self, pid: PageId, buf: &[u8]) -> DbResult<()> {
+        self.pending.write(pid, buf)
+    }
+
+    fn alloc_page(&mut self) -> DbResult<PageId> {
+        self.pending.alloc()
+    }
+
+    fn free_page(&mut self, pid: PageId) -> DbResult<()> {
+        self.pending.free(pid)
+    }
+
+    /// Commit các page pending vào WAL và chờ commit bền vững.
+    fn flush(&mut self) -> DbResult<()> {
+        self.commit()?.wait()
+    }
+
+    fn num_pages(&mut self) -> DbResult<u64> {
+        Ok(self.pending.next_pid().as_u64())
+    }
+
+    fn flush_deferred(&mut self) -> DbResult<CommitTicket> {
+        self.commit()
+    }
+
+    fn rollback(&mut self) -> DbResult<()> {
+        self.pending.discard();
+        Ok(())
+    }
+
+    fn begin_snapshot(&mut self) -> DbResult<Option<Snapshot>> {
+        let snap = Snapshot {
+            id: self.next_reader,
+            log_seq: self.wal.header().checkpoint_seq,
+            mark: self.wal.frame_count(),
+            db_pages: self.pending.committed_pages(),
+        };
+        self.next_reader += 1;
+        self.readers.insert(snap.id, (snap.log_seq, snap.mark));
+        Ok(Some(snap))
+    }
+
+    /// Đọc version của page tại snapshot: frame mới nhất trước mốc (cùng thế hệ log),
+    /// không có thì file database.
+    fn read_page_at(&mut self, snap: &Snapshot, pid: PageId, out: &mut [u8]) -> DbResult<()> {
+        if out.len() != PAGE_SIZE {
+            return Err(DbError::InvalidArgument(
+                "buffer length must equal PAGE_SIZE",
+            ));
+        }
+        if pid.as_u32() >= snap.db_pages {
+            return Err(DbError::InvalidArgument("page id out of range"));
+        }
+        if snap.log_seq == self.wal.header().checkpoint_seq {
+            if let Some(idx) = self.wal.find_frame_before(pid, snap.mark) {
+                return self.wal.read_frame_page(idx, out);
+            }
+        }
+        if pid.as_u64() < self.db.num_pages()? {
+            return self.db.read_verified(pid, out);
+        }
+        out.fill(0);
+        Ok(())
+    }
+
+    fn end_snapshot(&mut self, snap: Snapshot) {
+        self.readers.remove(&snap.id);
+    }
+
+    fn checkpoint(&mut self, mode: CheckpointMode) -> DbResult<Option<CheckpointInfo>> {
+        WalPager::checkpoint(self, mode).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::page::header::{flags, has_flag, FLAG_IS_CHECKSUMMED};
+    use crate::wal::format::WAL_HEADER_SIZE;
+    use crate::wal::{remove_db_files, temp_db_path};
+    use crate::RecordId;
+
+    #[test]
+    fn test_commit_goes_to_wal_not_db_file() {
+        let path = temp_db_path("walpager-commit");
+        let mut p = WalPager::open(path.clone()).unwrap();
+        let pid = p.alloc_page().unwrap();
+        assert_eq!(pid, PageId(1));
+        p.write_page(pid, &vec![7u8; PAGE_SIZE]).unwrap();
+
+        let mut out = vec![0u8; PAGE_SIZE];
+        p.read_page(pid, &mut out).unwrap();
+        assert_eq!(out[0], 7);
+        assert_eq!(p.wal_frames(), 0);
+
+        p.flush().unwrap();
+        assert_eq!(p.wal_frames(), 1);
+        // file database chỉ có page meta
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), PAGE_SIZE as u64);
+        drop(p);
+
+        let mut p = WalPager::open(path.clone()).unwrap();
+        assert_eq!(p.num_pages().unwrap(), 2);
+        p.read_page(pid, &mut out).unwrap();
+        assert!(out.iter().all(|&b| b == 7));
+        assert!(p.read_page(PageId(2), &mut out).is_err());
+
+        remove_db_files(&path);
+    }
+
+    #[test]
+    fn test_non_slotted_page_read_back_unchanged() {
+        let path = temp_db_path("walpager-raw");
+        let mut p = WalPager::open(path.clone()).unwrap();
+        let pid = p.alloc_page().unwrap();
+        let mut buf = vec![0u8; PAGE_SIZE];
+        buf[100] = 9;
+        p.write_page(pid, &buf).unwrap();
+        let mut out = vec![0u8; PAGE_SIZE];
+        p.read_page(pid, &mut out).unwrap();
+        assert_eq!(out, buf);
+        p.flush().unwrap();
+        p.checkpoint(CheckpointMode::Truncate).unwrap();
+        drop(p);
+
+        let mut p = WalPager::open(path.clone()).unwrap();
+        p.read_page(pid, &mut out).unwrap();
+        assert_eq!(out, buf);
+
+        remove_db_files(&path);
+    }
+
+    fn commit_page(p: &mut WalPager, pid: PageId, fill: u8) {
+        while p.num_pages().unwrap() <= pid.as_u64() {
+            p.alloc_page().unwrap();
+        }
+        p.write_page(pid, &vec![fill; PAGE_SIZE]).unwrap();
+        p.flush().unwrap();
+    }
+
+    fn db_file_page(path: &str, pid: PageId) -> Vec<u8> {
+        let bytes = std::fs::read(path).unwrap();
+        bytes[pid.as_usize() * PAGE_SIZE..(pid.as_usize() + 1) * PAGE_SIZE].to_vec()
+    }
+
+    #[test]
+    fn test_freelist_rollback_and_reopen() {
+        let path = temp_db_path("walpager-freelist");
+        let mut p = WalPager::open(path.clone()).unwrap();
+        commit_page(&mut p, PageId(1), 1);
+        commit_page(&mut p, PageId(2), 2);
+
+        // free -> rollback: page 1 vẫn đang dùng, không được cấp phát lại
+        p.free_page(PageId(1)).unwrap();
+        p.rollback().unwrap();
+        assert_eq!(p.alloc_page().unwrap(), PageId(3));
+        p.rollback().unwrap();
+        let mut out = vec![0u8; PAGE_SIZE];
+        p.read_page(PageId(1), &mut out).unwrap();
+        assert_eq!(out[100], 1);
+
+        // commit chỉ free page -> freelist còn sau khi mở lại
+        p.free_page(PageId(1)).unwrap();
+        p.flush().unwrap();
+        assert!(p.wal_frames() > 2);
+        drop(p);
+
+        let mut p = WalPager::open(path.clone()).unwrap();
+        assert!(p.free_page(PageId(1)).is_err(), "double free after reopen");
+        assert_eq!(p.alloc_page().unwrap(), PageId(1));
+        p.read_page(PageId(1), &mut out).unwrap();
+        assert!(out.iter().all(|&b| b == 0));
+        // page cấp phát từ freelist -> rollback: page quay lại freelist
+        p.rollback().unwrap();
+        assert_eq!(p.num_pages().unwrap(), 3);
+        p.checkpoint(CheckpointMode::Truncate).unwrap();
+        drop(p);
+
+        let mut p = WalPager::open(path.clone()).unwrap();
+        assert_eq!(p.alloc_page().unwrap(), PageId(1));
+        assert_eq!(p.alloc_page().unwrap(), PageId(3));
+
+        remove_db_files(&path);
+    }
+
+    #[test]
+    fn test_passive_checkpoint_copies_latest_versions() {
+        let path = temp_db_path("walpager-passive");
+        let mut p = WalPager::open(path.clone()).unwrap();
+        commit_page(&mut p, PageId(1), 1);
+        commit_page(&mut p, PageId(2), 2);
+        commit_page(&mut p, PageId(1), 3);
+
+        let info = p.checkpoint(CheckpointMode::Passive).unwrap();
+        assert_eq!(
+            info,
+            CheckpointInfo {
+                log_frames: 3,
+                backfilled: 3,
+                pages_written: 2,
+            }
+        );
+        assert_eq!(db_file_page(&path, PageId(1))[0], 3);
+        assert_eq!(db_file_page(&path, PageId(2))[0], 2);
+        // passive không reset log; lần checkpoint sau không còn gì để copy
+        assert_eq!(p.wal_frames(), 3);
+        assert_eq!(
+            p.checkpoint(CheckpointMode::Passive).unwrap().pages_written,
+            0
+        );
+
+        // commit kế tiếp bắt đầu lại log
+        commit_page(&mut p, PageId(2), 4);
+        assert_eq!(p.wal_frames(), 1);
+        drop(p);
+
+        let mut p = WalPager::open(path.clone()).unwrap();
+        let mut out = vec![0u8; PAGE_SIZE];
+        p.read_page(PageId(1), &mut out).unwrap();
+        assert_eq!(out[0], 3);
+        p.read_page(PageId(2), &mut out).unwrap();
+        assert_eq!(out[0], 4);
+
+        remove_db_files(&path);
+    }
+
+    #[test]
+    fn test_restart_and_truncate_checkpoints() {
+        let path = temp_db_path("walpager-restart");
+        let wal = wal_path(&path);
+        let mut p = WalPager::open(path.clone()).unwrap();
+        commit_page(&mut p, PageId(1), 1);
+        let seq = p.wal.header().checkpoint_seq;
+
+        p.checkpoint(CheckpointMode::Restart).unwrap();
+        assert_eq!(p.wal_frames(), 0);
+        assert_eq!(p.wal.header().checkpoint_seq, seq + 1);
+        assert_eq!(
+            std::fs::metadata(&wal).unwrap().len(),
+            WAL_HEADER_SIZE as u64
+        );
+
+        commit_page(&mut p, PageId(2), 2);
+        p.checkpoint(CheckpointMode::Truncate).unwrap();
+        assert_eq!(std::fs::metadata(&wal).unwrap().len(), 0);
+        // WAL trống: file database tự đủ, FilePager mở được
+        let mut out = vec![0u8; PAGE_SIZE];
+        FilePager::open(path.clone())
+            .unwrap()
+            .read_page(PageId(2), &mut out)
+            .unwrap();
+        assert_eq!(out[0], 2);
+
+        // commit sau truncate ghi lại header
+        commit_page(&mut p, PageId(1), 5);
+        drop(p);
+        let mut p = WalPager::open(path.clone()).unwrap();
+        assert_eq!(p.wal_frames(), 1);
+        p.read_page(PageId(1), &mut out).unwrap();
+        assert_eq!(out[0], 5);
+
+        remove_db_files(&path);
+    }
+
+    #[test]
+    fn test_autocheckpoint_bounds_wal() {
+        let path = temp_db_path("walpager-auto");
+        let mut p = WalPager::open(path.clone()).unwrap();
+        p.set_autocheckpoint(3);
+        for i in 0..10u8 {
+            commit_page(&mut p, PageId(1 + (i % 2) as u32), i);
+            assert!(p.wal_frames() <= 3);
+        }
+        assert_eq!(db_file_page(&path, PageId(1))[0], 8);
+
+        remove_db_files(&path);
+    }
+
+    #[test]
+    fn test_sync_off_still_commits() {
+        let path = temp_db_path("walpager-sync-off");
+        let mut p = WalPager::open(path.clone()).unwrap();
+        p.set_sync_mode(SyncMode::Off);
+        commit_page(&mut p, PageId(1), 1);
+        p.set_sync_mode(SyncMode::Normal);
+        commit_page(&mut p, PageId(2), 2);
+        p.checkpoint(CheckpointMode::Passive).unwrap();
+        assert_eq!(db_file_page(&path, PageId(1))[0], 1);
+        drop(p);
+
+        let mut p = WalPager::open(path.clone()).unwrap();
+        let mut out = vec![0u8; PAGE_SIZE];
+        p.read_page(PageId(2), &mut out).unwrap();
+        assert_eq!(out[0], 2);
+
+        remove_db_files(&path);
+    }
+
+    #[test]
+    fn test_concurrent_commits_share_syncs() {
+        use std::sync::{Arc, Barrier, Mutex};
+
+        const THREADS: u32 = 8;
+        const COMMITS: u32 = 20;
+        let path = temp_db_path("walpager-group");
+        let p = Arc::new(Mutex::new(WalPager::open(path.clone()).unwrap()));
+        let pids: Vec<PageId> = {
+            let mut p = p.lock().unwrap();
+            let pids = (0..THREADS).map(|_| p.alloc_page().unwrap()).collect();
+            p.flush().unwrap();
+            pids
+        };
+
+        let syncs_before = p.lock().unwrap().commit_syncs();
+
+        // mỗi vòng mọi thread append commit xong mới wait, nên cả vòng chung 1 fsync
+        let appended = Arc::new(Barrier::new(THREADS as usize));
+        let handles: Vec<_> = pids
+            .iter()
+            .map(|&pid| {
+                let p = p.clone();
+                let appended = appended.clone();
+                std::thread::spawn(move || {
+                    for i in 0..COMMITS {
+                        let ticket = {
+                            let mut p = p.lock().unwrap();
+                            p.write_page(pid, &vec![i as u8; PAGE_SIZE]).unwrap();
+                            p.commit().unwrap()
+                        };
+                        appended.wait();
+                        ticket.wait().unwrap();
+                    }
+                })
+            })
+            .collect();
+        for h in handles {
+            h.join().unwrap();
+        }
+        let syncs = p.lock().unwrap().commit_syncs() - syncs_before;
+        assert_eq!(syncs, COMMITS as u64);
+        drop(p);
+
+        let mut p = WalPager::open(path.clone()).unwrap();
+        let mut out = vec![0u8; PAGE_SIZE];
+        for pid in pids {
+            p.read_page(pid, &mut out).unwrap();
+            assert_eq!(out[0], (COMMITS - 1) as u8);
+        }
+
+        remove_db_files(&path);
+    }
+
+    /// Page heap có checksum, mọi byte data = `fill`.
+    #[test]
+    fn test_torn_checkpoint_write_restored_from_wal() {
+        use crate::heap::HeapFile;
+        use crate::pager::fault::TornWritePager;
+
+        let path = temp_db_path("walpager-torn");
+        let mut p = WalPager::open(path.clone()).unwrap();
+        p.set_autocheckpoint(0);
+        let heap = HeapFile::create(&mut p).unwrap();
+        let rids: Vec<RecordId> = (0..4)
+            .map(|_| heap.insert(&mut p, &[1u8; 600]).unwrap())
+            .collect();
+        p.flush().unwrap();
+        p.checkpoint(CheckpointMode::Passive).unwrap();
+        let heap_pid = heap.first_page();
+        let mut out = vec![0u8; PAGE_SIZE];
+        let mut db = FilePager::open_main(path.clone()).unwrap();
+        db.read_verified(heap_pid, &mut out).unwrap();
+        assert!(has_flag(flags(&out).unwrap(), FLAG_IS_CHECKSUMMED));
+        drop(db);
+
+        // commit đầu tiên sau checkpoint bắt đầu lại log với ảnh đầy đủ của heap page
+        for rid in &rids {
+            heap.update(&mut p, *rid, &[2u8; 600]).unwrap();
+        }
+        p.flush().unwrap();
+        assert!(p.wal.has_full_image(heap_pid));
+
+        // mất điện giữa checkpoint: heap page chỉ kịp ghi 3 sector đầu (header + slot),
+        // tuple cũ ở cuối page vẫn còn
+        let frames = p.wal.latest_frames(0, p.wal.frame_count());
+        let before = frames.iter().position(|&(pid, _)| pid == heap_pid).unwrap();
+        let mut torn = TornWritePager::new(FilePager::open_main(path.clone()).unwrap());
+        torn.tear_after(before, 3);
+        assert!(backfill(&mut p.wal, &mut torn, &frames).is_err());
+        drop(p);
+        let mut db = FilePager::open_main(path.clone()).unwrap();
+        assert!(db.read_verified(heap_pid, &mut out).is_err());
+        drop(db);
+
+        let mut p = WalPager::open(path.clone()).unwrap();
+        assert_eq!(p.restored_pages(), 1);
+        let mut db = FilePager::open_main(path.clone()).unwrap();
+        db.read_verified(heap_pid, &mut out).unwrap();
+        drop(db);
+        for rid in &rids {
+            assert_eq!(heap.get(&mut p, *rid).unwrap().unwrap(), vec![2u8; 600]);
+        }
+
+        // page rách không còn ảnh trong WAL: đọc báo lỗi thay vì trả data hỏng
+        p.checkpoint(CheckpointMode::Truncate).unwrap();
+        drop(p);
+        let mut bytes = std::fs::read(&path).unwrap();
+        bytes[heap_pid.as_usize() * PAGE_SIZE + PAGE_SIZE / 2] ^= 0xFF;
+        std::fs::write(&path, &bytes).unwrap();
+        let mut p = WalPager::open(path.clone()).unwrap();
+        assert_eq!(p.restored_pages(), 0);
+        assert!(matches!(
+            heap.get(&mut p, rids[0]),
+            Err(DbError::Corruption(_))
+        ));
+
+        remove_db_files(&path);
+    }
+
+    #[test]
+    fn test_uncommitted_writes_are_lost() {
+        let path = temp_db_path("walpager-lost");
+        let mut p = WalPager::open(path.clone()).unwrap();
+        let pid = p.alloc_page().unwrap();
+        p.write_page(pid, &vec![1u8; PAGE_SIZE]).unwrap();
+        p.flush().unwrap();
+        p.write_page(pid, &vec![2u8; PAGE_SIZE]).unwrap();
+        drop(p);
+
+        let mut p = WalPager::open(path.clone()).unwrap();
+        let mut out = vec![0u8; PAGE_SIZE];
+        p.read_page(pid, &mut out).unwrap();
+        assert_eq!(out[0], 1);
+
+        remove_db_files(&path);
+    }
+
+    fn read_at(p: &mut WalPager, snap: &Snapshot, pid: PageId) -> u8 {
+        let mut out = vec![0u8; PAGE_SIZE];
+        p.read_page_at(snap, pid, &mut out).unwrap();
+        out[0]
+    }
+
+    #[test]
+    fn test_snapshot_survives_commits_and_checkpoints() {
+        let path = temp_db_path("walpager-snapshot");
+        let mut p = WalPager::open(path.clone()).unwrap();
+        commit_page(&mut p, PageId(1), 1);
+        p.checkpoint(CheckpointMode::Truncate).unwrap();
+        commit_page(&mut p, PageId(2), 2);
+
+        let snap = p.begin_snapshot().unwrap().unwrap();
+        assert_eq!(snap.db_pages(), 3);
+        commit_page(&mut p, PageId(1), 3);
+        commit_page(&mut p, PageId(2), 4);
+        commit_page(&mut p, PageId(3), 5);
+        assert_eq!(read_at(&mut p, &snap, PageId(1)), 1);
+        assert_eq!(read_at(&mut p, &snap, PageId(2)), 2);
+        let mut out = vec![0u8; PAGE_SIZE];
+        assert!(p.read_page_at(&snap, PageId(3), &mut out).is_err());
+
+        // reader chặn frame từ mốc của nó: page 1 trong file database giữ version cũ,
+        // Restart không bắt đầu lại log được
+        assert!(matches!(
+            p.checkpoint(CheckpointMode::Restart),
+            Err(DbError::Busy(_))
+        ));
+        assert_eq!(p.wal_frames(), 4);
+        let info = p.checkpoint(CheckpointMode::Passive).unwrap();
+        assert_eq!(info.log_frames, 4);
+        assert_eq!(info.backfilled, 1);
+        assert_eq!(db_file_page(&path, PageId(1))[0], 1);
+        assert_eq!(db_file_page(&path, PageId(2))[0], 2);
+        assert_eq!(read_at(&mut p, &snap, PageId(1)), 1);
+        assert_eq!(read_at(&mut p, &snap, PageId(2)), 2);
+
+        p.end_snapshot(snap);
+        assert_eq!(p.active_readers(), 0);
+        let info = p.checkpoint(CheckpointMode::Restart).unwrap();
+        assert_eq!(info.backfilled, info.log_frames);
+        assert_eq!(p.wal_frames(), 0);
+        assert_eq!(db_file_page(&path, PageId(1))[0], 3);
+        assert_eq!(db_file_page(&path, PageId(3))[0], 5);
+
+        remove_db_files(&path);
+    }
+
+    #[test]
+    fn test_snapshot_from_older_log_generation() {
+        let path = temp_db_path("walpager-snapshot-gen");
+        let mut p = WalPager::open(path.clone()).unwrap();
+        commit_page(&mut p, PageId(1), 1);
+        p.checkpoint(CheckpointMode::Passive).unwrap();
+
+        // log đã checkpoint hết: commit sau bắt đầu thế hệ log mới
+        let snap = p.begin_snapshot().unwrap().unwrap();
+        commit_page(&mut p, PageId(1), 2);
+        assert_eq!(read_at(&mut p, &snap, PageId(1)), 1);
+
+        // reader thế hệ cũ đọc file database nên không frame nào được copy
+        let info = p.checkpoint(CheckpointMode::Passive).unwrap();
+        assert_eq!(info.backfilled, 0);
+        assert_eq!(db_file_page(&path, PageId(1))[0], 1);
+        assert_eq!(read_at(&mut p, &snap, PageId(1)), 1);
+
+        p.end_snapshot(snap);
+        assert_eq!(p.checkpoint(CheckpointMode::Passive).unwrap().backfilled, 1);
+        assert_eq!(db_file_page(&path, PageId(1))[0], 2);
+
+        remove_db_files(&path);
+    }
+}
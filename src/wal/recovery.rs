@@ -0,0 +1,230 @@
+//! Recovery khi mở WAL sau crash.
+//!
+//! Scan frame từ đầu log, validate salt + chuỗi checksum, dừng ở frame hỏng đầu tiên.
+//! Chỉ frame thuộc transaction đã có frame commit mới được đưa vào index; các frame
+//! hợp lệ phía sau commit cuối (transaction đang ghi dở lúc crash) bị bỏ và file được
+//! cắt về ngay sau commit cuối, để lần append sau nối tiếp chuỗi checksum từ đó.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+
+use crate::constants::PAGE_SIZE;
+use crate::{DbResult, PageId};
+
+use super::format::{
+    frame_offset, Checksum, FrameHeader, WalHeader, FRAME_HEADER_SIZE, FRAME_SIZE,
+};
+
+/// Thống kê của lần recovery.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RecoveryInfo {
+    /// Frame đã commit còn nằm trong log.
+    pub frames: u32,
+    /// Số transaction đã commit.
+    pub commits: u32,
+    /// Frame hợp lệ nhưng thuộc transaction chưa commit, bị bỏ.
+    pub discarded_frames: u32,
+    /// Số byte bị cắt khỏi cuối file (frame chưa commit, frame ghi dở, rác).
+    pub truncated_bytes: u64,
+}
+
+/// Trạng thái log sau recovery.
+pub(super) struct Recovered {
+    /// Page id của từng frame đã commit.
+    pub(super) pids: Vec<PageId>,
+    pub(super) last_checksum: Checksum,
+    pub(super) db_size: u32,
+    pub(super) index: HashMap<PageId, Vec<u32>>,
+    pub(super) info: RecoveryInfo,
+}
+
+/// Scan log đã có header hợp lệ (`seed` = checksum của header) và cắt phần đuôi
+/// không thuộc commit nào.
+pub(super) fn recover(f: &mut File, header: &WalHeader, seed: Checksum) -> DbResult<Recovered> {
+    let len = f.metadata()?.len();
+    let mut head = [0u8; FRAME_HEADER_SIZE];
+    let mut page = vec![0u8; PAGE_SIZE];
+
+    let mut out = Recovered {
+        pids: Vec::new(),
+        last_checksum: seed,
+        db_size: 0,
+        index: HashMap::new(),
+        info: RecoveryInfo::default(),
+    };
+    // frame hợp lệ của transaction chưa thấy commit
+    let mut txn: Vec<PageId> = Vec::new();
+    let mut sum = seed;
+    loop {
+        let idx = (out.pids.len() + txn.len()) as u32;
+        if frame_offset(idx) + FRAME_SIZE as u64 > len {
+            break;
+        }
+        f.seek(SeekFrom::Start(frame_offset(idx)))?;
+        f.read_exact(&mut head)?;
+        f.read_exact(&mut page)?;
+        let Some((frame, next)) = FrameHeader::decode(&head, header.salt, sum, &page)? else {
+            break;
+        };
+        sum = next;
+        txn.push(frame.pid);
+        if frame.is_commit() {
+            for pid in txn.drain(..) {
+                out.index
+                    .entry(pid)
+                    .or_default()
+                    .push(out.pids.len() as u32);
+                out.pids.push(pid);
+            }
+            out.last_checksum = sum;
+            out.db_size = frame.db_size;
+            out.info.commits += 1;
+        }
+    }
+
+    out.info.frames = out.pids.len() as u32;
+    out.info.discarded_frames = txn.len() as u32;
+    let end = frame_offset(out.info.frames);
+    if len > end {
+        f.set_len(end)?;
+        f.sync_data()?;
+        out.info.truncated_bytes = len - end;
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pager::pager::Pager;
+    use crate::wal::{remove_db_files, temp_db_path, wal_path, WalFile, WalPager};
+
+    /// Các transaction của kịch bản crash: (page, byte fill) ghi trong mỗi commit.
+    const TXNS: [&[(u32, u8)]; 4] = [
+        &[(1, 10), (2, 20)],
+        &[(2, 21)],
+        &[(3, 30), (1, 11), (4, 40)],
+        &[(4, 41)],
+    ];
+
+    /// Trạng thái database sau `commits` commit đầu tiên: fill của page 1..=4
+    /// (`None` = page chưa tồn tại).
+    fn expected(commits: usize) -> [Option<u8>; 4] {
+        let mut state = [None; 4];
+        for txn in &TXNS[..commits] {
+            for &(pid, fill) in *txn {
+                state[pid as usize - 1] = Some(fill);
+            }
+        }
+        state
+    }
+
+    /// Ghi các transaction qua WalPager, trả về bytes của WAL và offset cuối mỗi commit.
+    fn build_wal(path: &str) -> (Vec<u8>, Vec<u64>) {
+        let mut p = WalPager::open(path.to_string()).unwrap();
+        let mut ends = Vec::new();
+        for txn in TXNS {
+            for &(pid, fill) in txn {
+                while p.num_pages().unwrap() <= pid as u64 {
+                    p.alloc_page().unwrap();
+                }
+                p.write_page(PageId(pid), &vec![fill; PAGE_SIZE]).unwrap();
+            }
+            p.flush().unwrap();
+            ends.push(frame_offset(p.wal_frames()));
+        }
+        drop(p);
+        (std::fs::read(wal_path(path)).unwrap(), ends)
+    }
+
+    fn check_state(p: &mut WalPager, want: [Option<u8>; 4]) {
+        let mut out = vec![0u8; PAGE_SIZE];
+        for (i, fill) in want.iter().enumerate() {
+            let pid = PageId(i as u32 + 1);
+            match fill {
+                Some(fill) => {
+                    p.read_page(pid, &mut out).unwrap();
+                    assert!(
+                        out.iter().all(|b| b == fill),
+                        "page {} at fill {}",
+                        pid.0,
+                        fill
+                    );
+                }
+                None => assert!(p.num_pages().unwrap() <= pid.as_u64()),
+            }
+        }
+    }
+
+    #[test]
+    fn test_crash_at_every_wal_offset() {
+        let path = temp_db_path("recovery-every-offset");
+        let (bytes, ends) = build_wal(&path);
+
+        for cut in 0..=bytes.len() {
+            std::fs::write(wal_path(&path), &bytes[..cut]).unwrap();
+            let commits = ends.iter().filter(|&&end| end <= cut as u64).count();
+
+            let mut p = WalPager::open(path.clone()).unwrap();
+            check_state(&mut p, expected(commits));
+            let info = p.recovery();
+            assert_eq!(info.commits as usize, commits, "cut at {}", cut);
+            let kept = if commits == 0 {
+                frame_offset(0)
+            } else {
+                ends[commits - 1]
+            };
+            // phần sau commit cuối đã bị cắt khỏi file
+            if cut as u64 >= kept {
+                assert_eq!(info.truncated_bytes, cut as u64 - kept, "cut at {}", cut);
+                assert_eq!(std::fs::metadata(wal_path(&path)).unwrap().len(), kept);
+            }
+        }
+
+        remove_db_files(&path);
+    }
+
+    #[test]
+    fn test_append_after_discarded_tail() {
+        let path = temp_db_path("recovery-append");
+        let (bytes, ends) = build_wal(&path);
+        // crash giữa transaction thứ 3 (đã ghi 2/3 frame)
+        let cut = ends[1] as usize + 2 * FRAME_SIZE;
+        std::fs::write(wal_path(&path), &bytes[..cut]).unwrap();
+
+        let mut p = WalPager::open(path.clone()).unwrap();
+        assert_eq!(p.recovery().discarded_frames, 2);
+        p.write_page(PageId(1), &vec![99u8; PAGE_SIZE]).unwrap();
+        p.flush().unwrap();
+        drop(p);
+
+        let mut p = WalPager::open(path.clone()).unwrap();
+        let mut want = expected(2);
+        want[0] = Some(99);
+        check_state(&mut p, want);
+        assert_eq!(p.recovery().commits, 3);
+        assert_eq!(p.recovery().discarded_frames, 0);
+
+        remove_db_files(&path);
+    }
+
+    #[test]
+    fn test_wal_file_reports_recovery() {
+        let path = temp_db_path("recovery-info");
+        let (bytes, ends) = build_wal(&path);
+        std::fs::write(wal_path(&path), &bytes[..ends[2] as usize + 7]).unwrap();
+
+        let wal = WalFile::open(&wal_path(&path)).unwrap();
+        assert_eq!(
+            wal.recovery(),
+            RecoveryInfo {
+                frames: 6,
+                commits: 3,
+                discarded_frames: 0,
+                truncated_bytes: 7,
+            }
+        );
+        remove_db_files(&path);
+    }
+}